@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use super::error::Error;
-use super::serviceerror::{ServiceError, ServiceErrorKind};
+use super::serviceerror::ServiceError;
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct DataSource {
@@ -25,10 +25,7 @@ pub struct DataSource {
 )]
 #[get("/data-sources")]
 pub(super) async fn get_data_sources(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let data_sources: Vec<DataSource> = client
         .query(
@@ -69,10 +66,7 @@ pub(super) async fn get_data_source(
 ) -> Result<HttpResponse, ServiceError> {
     let ds_id = id.into_inner();
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let data_source = client
         .query_one(
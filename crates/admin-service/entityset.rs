@@ -9,7 +9,7 @@ use chrono::{DateTime, Utc};
 use minerva::change::Change;
 use minerva::entity_set::{load_entity_sets, ChangeEntitySet, CreateEntitySet, EntitySet};
 
-use super::serviceerror::{ServiceError, ServiceErrorKind};
+use super::serviceerror::ServiceError;
 use crate::error::{Error, Success};
 
 type PostgresName = String;
@@ -63,10 +63,7 @@ impl EntitySetData {
 )]
 #[get("/entitysets")]
 pub(super) async fn get_entity_sets(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
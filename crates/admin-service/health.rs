@@ -0,0 +1,79 @@
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use actix_web::{get, web::Data, HttpResponse};
+
+use minerva::schema::schema_exists;
+
+use crate::metrics::record_database_error;
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct HealthData {
+    pub status: String,
+    pub message: Option<String>,
+}
+
+impl HealthData {
+    fn ok() -> HealthData {
+        HealthData {
+            status: "ok".to_string(),
+            message: None,
+        }
+    }
+
+    fn unavailable(message: String) -> HealthData {
+        HealthData {
+            status: "unavailable".to_string(),
+            message: Some(message),
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path="/health/live",
+    responses(
+    (status = 200, description = "The service is running", body = HealthData),
+    )
+)]
+#[get("/health/live")]
+pub(super) async fn get_health_live() -> HttpResponse {
+    HttpResponse::Ok().json(HealthData::ok())
+}
+
+#[utoipa::path(
+    get,
+    path="/health/ready",
+    responses(
+    (status = 200, description = "The service can handle requests", body = HealthData),
+    (status = 503, description = "The database is unreachable or not initialized", body = HealthData),
+    )
+)]
+#[get("/health/ready")]
+pub(super) async fn get_health_ready(pool: Data<Pool>) -> HttpResponse {
+    let manager = match pool.get().await {
+        Ok(manager) => manager,
+        Err(e) => {
+            record_database_error();
+            return HttpResponse::ServiceUnavailable().json(HealthData::unavailable(format!(
+                "Could not get database connection: {e}"
+            )));
+        }
+    };
+
+    let client: &tokio_postgres::Client = &manager;
+
+    match schema_exists(client).await {
+        Ok(true) => HttpResponse::Ok().json(HealthData::ok()),
+        Ok(false) => HttpResponse::ServiceUnavailable().json(HealthData::unavailable(
+            "Minerva schema does not exist".to_string(),
+        )),
+        Err(e) => {
+            record_database_error();
+            HttpResponse::ServiceUnavailable().json(HealthData::unavailable(format!(
+                "Could not check Minerva schema: {e}"
+            )))
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use std::ops::DerefMut;
+use std::path::{Path, PathBuf};
+
+use deadpool_postgres::Pool;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tempfile::TempDir;
+use utoipa::ToSchema;
+
+use actix_web::{post, web, web::Bytes, web::Data, HttpResponse};
+
+use minerva::change::Change;
+use minerva::instance::MinervaInstance;
+use minerva::job::{append_job_log, set_job_progress};
+
+use super::serviceerror::{ServiceError, ServiceErrorKind};
+use crate::job::{spawn_job, JobStarted};
+
+/// Maximum size of an uploaded instance archive
+pub const MAX_INSTANCE_ARCHIVE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Maximum size of a file in an unpacked instance archive
+const MAX_INSTANCE_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Maximum total size of the files in an unpacked instance archive
+const MAX_INSTANCE_UNPACKED_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct InstanceDiffData {
+    pub changes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ChangeResultData {
+    pub change: String,
+    pub success: bool,
+    pub message: String,
+}
+
+/// An instance definition unpacked from an uploaded archive. The temporary
+/// directory is removed when this is dropped.
+struct UnpackedInstance {
+    _directory: TempDir,
+    instance: MinervaInstance,
+}
+
+/// Return the directory that holds the instance definition: the archive may
+/// contain the instance files directly, or a single top-level directory that
+/// contains them.
+fn instance_root(directory: &Path) -> std::io::Result<PathBuf> {
+    let entries: Vec<std::fs::DirEntry> =
+        std::fs::read_dir(directory)?.collect::<Result<Vec<_>, _>>()?;
+
+    match entries.as_slice() {
+        [entry] if entry.file_type()?.is_dir() => Ok(entry.path()),
+        _ => Ok(directory.to_path_buf()),
+    }
+}
+
+fn bad_request(message: String) -> ServiceError {
+    ServiceError {
+        kind: ServiceErrorKind::BadRequest,
+        message,
+    }
+}
+
+fn payload_too_large(message: String) -> ServiceError {
+    ServiceError {
+        kind: ServiceErrorKind::PayloadTooLarge,
+        message,
+    }
+}
+
+/// Unpack the archive entry by entry, so that the size of each file and the
+/// total unpacked size are checked before anything is written. The size of the
+/// compressed archive alone does not limit the unpacked size.
+fn unpack_instance(archive: &[u8]) -> Result<UnpackedInstance, ServiceError> {
+    let directory = tempfile::tempdir().map_err(|e| ServiceError {
+        kind: ServiceErrorKind::InternalError,
+        message: format!("Could not create temporary directory: {e}"),
+    })?;
+
+    let unpack_error =
+        |e: std::io::Error| bad_request(format!("Could not unpack instance archive: {e}"));
+
+    let mut archive = tar::Archive::new(GzDecoder::new(archive));
+    let mut unpacked_size: u64 = 0;
+
+    for entry in archive.entries().map_err(unpack_error)? {
+        let mut entry = entry.map_err(unpack_error)?;
+
+        if entry.header().entry_type().is_gnu_sparse() {
+            return Err(bad_request(format!(
+                "Instance archive contains sparse file '{}'",
+                entry.path().map_err(unpack_error)?.display()
+            )));
+        }
+
+        if entry.size() > MAX_INSTANCE_FILE_SIZE {
+            return Err(payload_too_large(format!(
+                "File '{}' in instance archive is larger than {MAX_INSTANCE_FILE_SIZE} bytes",
+                entry.path().map_err(unpack_error)?.display()
+            )));
+        }
+
+        unpacked_size += entry.size();
+
+        if unpacked_size > MAX_INSTANCE_UNPACKED_SIZE {
+            return Err(payload_too_large(format!(
+                "Unpacked instance archive is larger than {MAX_INSTANCE_UNPACKED_SIZE} bytes"
+            )));
+        }
+
+        entry.unpack_in(directory.path()).map_err(unpack_error)?;
+    }
+
+    let root = instance_root(directory.path())
+        .map_err(|e| bad_request(format!("Could not read unpacked instance archive: {e}")))?;
+
+    let instance = MinervaInstance::load_from(&root)
+        .map_err(|e| bad_request(format!("Could not load instance definition: {e}")))?;
+
+    Ok(UnpackedInstance {
+        _directory: directory,
+        instance,
+    })
+}
+
+async fn load_uploaded_instance(archive: Bytes) -> Result<UnpackedInstance, ServiceError> {
+    web::block(move || unpack_instance(&archive))
+        .await
+        .map_err(|e| bad_request(format!("Could not load instance definition: {e}")))?
+}
+
+async fn try_apply_change(
+    client: &mut tokio_postgres::Client,
+    change: &(dyn Change + Send),
+) -> Result<Result<String, String>, tokio_postgres::Error> {
+    let mut tx = client.transaction().await?;
+
+    tx.execute(
+        "SET LOCAL citus.multi_shard_modify_mode TO 'sequential'",
+        &[],
+    )
+    .await?;
+
+    match change.apply(&mut tx).await {
+        Ok(message) => {
+            tx.commit().await?;
+            Ok(Ok(message))
+        }
+        Err(e) => {
+            tx.rollback().await?;
+            Ok(Err(e.to_string()))
+        }
+    }
+}
+
+/// Apply a change in its own transaction and report the outcome
+async fn apply_change(
+    client: &mut tokio_postgres::Client,
+    change: &(dyn Change + Send),
+) -> ChangeResultData {
+    let (success, message) = match try_apply_change(client, change).await {
+        Ok(Ok(message)) => (true, message),
+        Ok(Err(message)) => (false, message),
+        Err(e) => (false, e.to_string()),
+    };
+
+    ChangeResultData {
+        change: change.to_string(),
+        success,
+        message,
+    }
+}
+
+// curl -H "Content-Type: application/gzip" -X POST --data-binary @instance.tar.gz localhost:8000/instance/diff
+#[utoipa::path(
+    post,
+    path="/instance/diff",
+    request_body(content = Vec<u8>, description = "Instance directory as tar.gz archive", content_type = "application/gzip"),
+    responses(
+    (status = 200, description = "Changes required to bring the database in line with the instance definition", body = InstanceDiffData),
+    (status = 400, description = "Archive could not be read", body = Error),
+    (status = 413, description = "Archive or unpacked archive is too large", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/diff")]
+pub(super) async fn post_instance_diff(
+    pool: Data<Pool>,
+    body: Bytes,
+) -> Result<HttpResponse, ServiceError> {
+    let unpacked = load_uploaded_instance(body).await?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let instance_db = MinervaInstance::load_from_db(client).await?;
+
+    let changes = instance_db
+        .diff(&unpacked.instance)
+        .iter()
+        .map(|change| change.to_string())
+        .collect();
+
+    Ok(HttpResponse::Ok().json(InstanceDiffData { changes }))
+}
+
+// curl -H "Content-Type: application/gzip" -X POST --data-binary @instance.tar.gz localhost:8000/instance/apply
+#[utoipa::path(
+    post,
+    path="/instance/apply",
+    request_body(content = Vec<u8>, description = "Instance directory as tar.gz archive", content_type = "application/gzip"),
+    responses(
+    (status = 200, description = "Result of each applied change", body = [ChangeResultData]),
+    (status = 400, description = "Archive could not be read", body = Error),
+    (status = 413, description = "Archive or unpacked archive is too large", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/apply")]
+pub(super) async fn post_instance_apply(
+    pool: Data<Pool>,
+    body: Bytes,
+) -> Result<HttpResponse, ServiceError> {
+    let unpacked = load_uploaded_instance(body).await?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let instance_db = MinervaInstance::load_from_db(client).await?;
+
+    let mut results: Vec<ChangeResultData> = Vec::new();
+
+    for change in instance_db.diff(&unpacked.instance) {
+        results.push(apply_change(client, change.as_ref()).await);
+    }
+
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// curl -H "Content-Type: application/gzip" -X POST --data-binary @instance.tar.gz localhost:8000/instance/update
+#[utoipa::path(
+    post,
+    path="/instance/update",
+    request_body(content = Vec<u8>, description = "Instance directory as tar.gz archive", content_type = "application/gzip"),
+    responses(
+    (status = 202, description = "Instance update started as a job", body = JobStarted),
+    (status = 400, description = "Archive could not be read", body = Error),
+    (status = 413, description = "Archive or unpacked archive is too large", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/update")]
+pub(super) async fn post_instance_update(
+    pool: Data<Pool>,
+    body: Bytes,
+) -> Result<HttpResponse, ServiceError> {
+    let unpacked = load_uploaded_instance(body).await?;
+
+    let action = json!({"update_instance": null});
+
+    let job_id = spawn_job(&pool, action, move |pool, job_id| async move {
+        let mut manager = pool.get().await.map_err(|e| e.to_string())?;
+
+        let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+        let instance_db = MinervaInstance::load_from_db(client)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let changes = instance_db.diff(&unpacked.instance);
+        let total = changes.len();
+        let mut failed = 0;
+
+        for (index, change) in changes.iter().enumerate() {
+            let result = apply_change(client, change.as_ref()).await;
+
+            let line = if result.success {
+                format!("{}: {}", result.change, result.message)
+            } else {
+                failed += 1;
+                format!("{}: error: {}", result.change, result.message)
+            };
+
+            append_job_log(client, job_id, &line)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            set_job_progress(client, job_id, (index + 1) as f32 / total as f32)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        match failed {
+            0 => Ok(format!("Applied {total} changes")),
+            _ => Err(format!("{failed} of {total} changes failed")),
+        }
+    })
+    .await?;
+
+    Ok(JobStarted::response(job_id))
+}
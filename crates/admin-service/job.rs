@@ -0,0 +1,179 @@
+use std::future::Future;
+
+use chrono::{DateTime, Utc};
+use deadpool_postgres::Pool;
+use log::error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use actix_web::{get, web::Data, web::Path, web::Query, HttpResponse};
+
+use minerva::job::{append_job_log, end_job, fail_job, load_job, load_jobs, start_job, Job};
+
+use super::serviceerror::ServiceError;
+use crate::error::Error;
+
+const DEFAULT_JOB_LIST_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JobData {
+    pub id: i64,
+    pub action: Value,
+    pub status: String,
+    pub progress: Option<f32>,
+    pub log: Vec<String>,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+impl From<Job> for JobData {
+    fn from(job: Job) -> JobData {
+        JobData {
+            id: job.id,
+            action: job.action,
+            status: job.status.to_string(),
+            progress: job.progress,
+            log: job.log,
+            started: job.started,
+            finished: job.finished,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct JobStarted {
+    pub code: i32,
+    pub message: String,
+    pub job_id: i64,
+}
+
+impl JobStarted {
+    pub fn response(job_id: i64) -> HttpResponse {
+        HttpResponse::Accepted()
+            .insert_header(("Location", format!("/jobs/{job_id}")))
+            .json(JobStarted {
+                code: 202,
+                message: format!("Started job {job_id}"),
+                job_id,
+            })
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct JobListParams {
+    /// Maximum number of jobs to return, newest first
+    pub limit: Option<i64>,
+}
+
+/// Register a job for `action` and run `work` in a background task.
+///
+/// The id of the job is returned as soon as it is registered. The outcome of
+/// `work` is recorded in `logging.job`: the returned message is appended to the
+/// job log and the job is marked finished, or the error is logged and the job
+/// is marked failed.
+pub(super) async fn spawn_job<F, Fut>(
+    pool: &Pool,
+    action: Value,
+    work: F,
+) -> Result<i64, ServiceError>
+where
+    F: FnOnce(Pool, i64) -> Fut + 'static,
+    Fut: Future<Output = Result<String, String>> + 'static,
+{
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let job_id = start_job(client, &action).await?;
+
+    drop(manager);
+
+    let pool = pool.clone();
+
+    actix_web::rt::spawn(async move {
+        let result = work(pool.clone(), job_id).await;
+
+        let manager = match pool.get().await {
+            Ok(manager) => manager,
+            Err(e) => {
+                error!("Could not record outcome of job {job_id}: {e}");
+                return;
+            }
+        };
+
+        let client: &tokio_postgres::Client = &manager;
+
+        let recorded = match result {
+            Ok(message) => match append_job_log(client, job_id, &message).await {
+                Ok(_) => end_job(client, job_id).await,
+                Err(e) => Err(e),
+            },
+            Err(message) => {
+                error!("Job {job_id} failed: {message}");
+                fail_job(client, job_id, &message).await
+            }
+        };
+
+        if let Err(e) = recorded {
+            error!("Could not record outcome of job {job_id}: {e}");
+        }
+    });
+
+    Ok(job_id)
+}
+
+#[utoipa::path(
+    get,
+    path="/jobs",
+    params(JobListParams),
+    responses(
+    (status = 200, description = "List the most recent jobs", body = [JobData]),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/jobs")]
+pub(super) async fn get_jobs(
+    pool: Data<Pool>,
+    params: Query<JobListParams>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let limit = params.limit.unwrap_or(DEFAULT_JOB_LIST_LIMIT);
+
+    let jobs: Vec<JobData> = load_jobs(client, limit)
+        .await?
+        .into_iter()
+        .map(JobData::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(jobs))
+}
+
+#[utoipa::path(
+    get,
+    path="/jobs/{id}",
+    responses(
+    (status = 200, description = "Status, progress and log of a job", body = JobData),
+    (status = 404, description = "Job not found", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/jobs/{id}")]
+pub(super) async fn get_job(pool: Data<Pool>, id: Path<i64>) -> Result<HttpResponse, ServiceError> {
+    let job_id = id.into_inner();
+
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    match load_job(client, job_id).await? {
+        Some(job) => Ok(HttpResponse::Ok().json(JobData::from(job))),
+        None => Ok(HttpResponse::NotFound().json(Error {
+            code: 404,
+            message: format!("Job with id {job_id} not found"),
+        })),
+    }
+}
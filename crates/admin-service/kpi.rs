@@ -24,6 +24,7 @@ use crate::trendstore::{TrendData, TrendStorePartCompleteData};
 
 use super::serviceerror::{ServiceError, ServiceErrorKind};
 use crate::error::{Error, Success};
+use crate::job::{spawn_job, JobStarted};
 
 lazy_static! {
     static ref DATASOURCE: String = "kpi".to_string();
@@ -494,10 +495,7 @@ impl KpiImplementedData {
 )]
 #[get("/kpis")]
 pub(super) async fn get_kpis(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let sources: Vec<TrendMaterializationSourceIdentifier> = client
         .query(
@@ -595,10 +593,7 @@ pub(super) async fn get_kpi(
 ) -> Result<HttpResponse, ServiceError> {
     let kpiname = name.into_inner().replace('_', " ");
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
     let kpi = client
         .query_one(
             concat!(
@@ -664,9 +659,8 @@ pub(super) async fn get_kpi(
     post,
     path="/kpis",
     responses(
-    (status = 200, description = "Create a new KPI", body = Success),
+    (status = 202, description = "KPI creation started as a job", body = JobStarted),
     (status = 400, description = "Incorrect data format", body = Error),
-    (status = 409, description = "KPI creation failed", body = Error),
     (status = 500, description = "Database unreachable", body = Error),
     )
 )]
@@ -677,36 +671,32 @@ pub(super) async fn post_kpi(pool: Data<Pool>, post: String) -> Result<HttpRespo
         message: format!("Unable to parse input JSON data: {e}"),
     })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let action = json!({"create_kpi": {"tsp_name": &data.tsp_name, "kpi_name": &data.kpi_name}});
 
-    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+    let job_id = spawn_job(&pool, action, |pool, _job_id| async move {
+        let mut manager = pool.get().await.map_err(|e| e.to_string())?;
 
-    let mut transaction = client.transaction().await.map_err(|e| Error {
-        code: 500,
-        message: e.to_string(),
-    })?;
+        let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
-    transaction
-        .execute(
-            "SET LOCAL citus.multi_shard_modify_mode TO 'sequential';",
-            &[],
-        )
-        .await?;
+        let mut transaction = client.transaction().await.map_err(|e| e.to_string())?;
+
+        transaction
+            .execute(
+                "SET LOCAL citus.multi_shard_modify_mode TO 'sequential';",
+                &[],
+            )
+            .await
+            .map_err(|e| e.to_string())?;
 
-    data.create(&mut transaction).await?;
+        data.create(&mut transaction).await.map_err(|e| e.message)?;
 
-    transaction.commit().await.map_err(|e| Error {
-        code: 500,
-        message: e.to_string(),
-    })?;
+        transaction.commit().await.map_err(|e| e.to_string())?;
 
-    Ok(HttpResponse::Ok().json(Success {
-        code: 200,
-        message: "Successfully created KPI".to_string(),
-    }))
+        Ok("Successfully created KPI".to_string())
+    })
+    .await?;
+
+    Ok(JobStarted::response(job_id))
 }
 
 // curl -H "Content-Type: application/json" -X PUT -d '{"name":"average-output","entity_type":"Cell","data_type":"numeric","enabled":true,"source_trends":["L.Thrp.bits.UL.NsaDc"],"definition":"public.safe_division(SUM(\"L.Thrp.bits.UL.NsaDc\"),1000::numeric)","description":{"type": "ratio", "numerator": [{"type": "trend", "value": "L.Thrp.bits.UL.NsaDC"}], "denominator": [{"type": "constant", "value": "1000"}]}}' localhost:8000/kpis
@@ -731,10 +721,7 @@ pub(super) async fn update_kpi(
         message: e.to_string(),
     })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -784,10 +771,7 @@ pub(super) async fn delete_kpi(
 ) -> Result<HttpResponse, ServiceError> {
     let kpiname = &args.1;
     let entitytype = &args.0;
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
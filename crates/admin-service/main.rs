@@ -17,20 +17,25 @@ use minerva::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
 
 mod trendmaterialization;
 use trendmaterialization::{
-    delete_trend_function_materialization, delete_trend_view_materialization,
-    get_trend_function_materialization, get_trend_function_materializations,
+    backfill_trend_materialization, delete_trend_function_materialization,
+    delete_trend_view_materialization, get_trend_function_materialization,
+    get_trend_function_materializations, get_trend_materialization_state,
     get_trend_materializations, get_trend_view_materialization, get_trend_view_materializations,
+    materialize_trend_materialization, populate_trend_materialization_fingerprints,
     post_trend_function_materialization, post_trend_view_materialization,
-    update_trend_function_materialization, update_trend_view_materialization,
-    TrendFunctionMaterializationData, TrendFunctionMaterializationFull, TrendMaterializationDef,
-    TrendMaterializationSourceData, TrendViewMaterializationData, TrendViewMaterializationFull,
+    reset_trend_materialization_fingerprints, update_trend_function_materialization,
+    update_trend_view_materialization, MaterializationBackfillData, MaterializationStateData,
+    MaterializeData, MaterializeResultData, TrendFunctionMaterializationData,
+    TrendFunctionMaterializationFull, TrendMaterializationDef, TrendMaterializationSourceData,
+    TrendViewMaterializationData, TrendViewMaterializationFull,
 };
 
 mod trendstore;
 use trendstore::{
-    find_trend_store_part, get_trend_store, get_trend_store_part, get_trend_store_parts,
-    get_trend_stores, get_trends, get_trends_by_entity_type, post_trend_store_part,
-    GeneratedTrendFull, TrendFull, TrendStoreFull, TrendStorePartFull,
+    find_trend_store_part, get_trend_store, get_trend_store_part, get_trend_store_part_stats,
+    get_trend_store_parts, get_trend_stores, get_trends, get_trends_by_entity_type,
+    post_partitions, post_trend_store_part, GeneratedTrendFull, PartitionCreationData, TrendFull,
+    TrendStatData, TrendStoreFull, TrendStorePartFull,
 };
 
 mod datasource;
@@ -43,7 +48,18 @@ mod kpi;
 use kpi::{delete_kpi, get_kpi, get_kpis, post_kpi, update_kpi, KpiImplementedData, KpiRawData};
 
 mod trigger;
-use trigger::{change_thresholds, get_triggers, TriggerData};
+use trigger::{
+    change_thresholds, delete_trigger_exception, get_trigger_exceptions, get_trigger_flapping,
+    get_triggers, post_threshold_exception, post_weight_exception, FlappingEntityData,
+    ThresholdExceptionData, TriggerData, TriggerExceptionsData, WeightExceptionData,
+};
+
+mod notification;
+use notification::{
+    delete_maintenance_window, get_maintenance_windows, get_notifications,
+    post_acknowledge_notifications, post_clear_notifications, post_maintenance_window,
+    MaintenanceWindowData, NotificationData, NotificationStateChangeData,
+};
 
 mod entityset;
 use entityset::{change_entity_set, create_entity_set, get_entity_sets, EntitySetData};
@@ -51,15 +67,44 @@ use entityset::{change_entity_set, create_entity_set, get_entity_sets, EntitySet
 mod header;
 use header::get_header;
 
+mod job;
+use job::{get_job, get_jobs, JobData, JobStarted};
+
+mod instance;
+use instance::{
+    post_instance_apply, post_instance_diff, post_instance_update, ChangeResultData,
+    InstanceDiffData, MAX_INSTANCE_ARCHIVE_SIZE,
+};
+
+mod relation;
+use relation::{
+    get_relation_members, get_relations, post_relation, refresh_relation_table, RelationData,
+    RelationMembersData, RelationRefreshData,
+};
+
+mod virtualentity;
+use virtualentity::{
+    get_virtual_entities, get_virtual_entity_members, post_virtual_entity, refresh_virtual_entity,
+    VirtualEntityData, VirtualEntityRefreshData,
+};
+
+mod health;
+use health::{get_health_live, get_health_ready, HealthData};
+
+mod metrics;
+use metrics::{get_metrics, register_metrics, track_request};
+
 mod error;
 mod serviceerror;
 
 static ENV_DB_CONN: &str = "MINERVA_DB_CONN";
 static ENV_PORT: &str = "SERVICE_PORT";
 static ENV_ADDRESS: &str = "SERVICE_ADDRESS";
+static ENV_DB_POOL_SIZE: &str = "SERVICE_DB_POOL_SIZE";
 
 static DEFAULT_ADDRESS: &str = "0.0.0.0";
 static DEFAULT_PORT: &str = "8000";
+static DEFAULT_DB_POOL_SIZE: &str = "16";
 
 #[actix_web::main]
 async fn main() -> Result<(), serviceerror::ServiceError> {
@@ -82,12 +127,19 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
             trendmaterialization::delete_trend_function_materialization,
             trendmaterialization::update_trend_function_materialization,
             trendmaterialization::update_trend_view_materialization,
+            trendmaterialization::backfill_trend_materialization,
+            trendmaterialization::get_trend_materialization_state,
+            trendmaterialization::materialize_trend_materialization,
+            trendmaterialization::populate_trend_materialization_fingerprints,
+            trendmaterialization::reset_trend_materialization_fingerprints,
             trendstore::get_trend_store_parts,
             trendstore::get_trend_store_part,
+            trendstore::get_trend_store_part_stats,
             trendstore::find_trend_store_part,
             trendstore::get_trend_stores,
             trendstore::get_trend_store,
             trendstore::post_trend_store_part,
+            trendstore::post_partitions,
             trendstore::get_trends,
             trendstore::get_trends_by_entity_type,
             datasource::get_data_sources,
@@ -101,19 +153,53 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
             kpi::delete_kpi,
             trigger::get_triggers,
             trigger::change_thresholds,
+            trigger::get_trigger_exceptions,
+            trigger::post_threshold_exception,
+            trigger::post_weight_exception,
+            trigger::delete_trigger_exception,
+            trigger::get_trigger_flapping,
+            notification::get_notifications,
+            notification::post_acknowledge_notifications,
+            notification::post_clear_notifications,
+            notification::get_maintenance_windows,
+            notification::post_maintenance_window,
+            notification::delete_maintenance_window,
             entityset::get_entity_sets,
             entityset::change_entity_set,
             entityset::create_entity_set,
-            header::get_header
+            header::get_header,
+            job::get_jobs,
+            job::get_job,
+            instance::post_instance_diff,
+            instance::post_instance_apply,
+            instance::post_instance_update,
+            relation::get_relations,
+            relation::post_relation,
+            relation::refresh_relation_table,
+            relation::get_relation_members,
+            virtualentity::get_virtual_entities,
+            virtualentity::post_virtual_entity,
+            virtualentity::refresh_virtual_entity,
+            virtualentity::get_virtual_entity_members,
+            health::get_health_live,
+            health::get_health_ready,
+            metrics::get_metrics
         ),
         components(
             schemas(
                 TrendMaterializationSourceData, TrendMaterializationDef,
                 TrendViewMaterializationFull, TrendFunctionMaterializationFull,
                 TrendViewMaterializationData, TrendFunctionMaterializationData,
-                TrendFull, GeneratedTrendFull, TrendStorePartFull, TrendStoreFull,
+                TrendFull, GeneratedTrendFull, TrendStorePartFull, TrendStoreFull, TrendStatData,
                 DataSource, EntityType, KpiRawData, KpiImplementedData,
-                TriggerData, EntitySetData,
+                TriggerData, ThresholdExceptionData, WeightExceptionData,
+                TriggerExceptionsData, FlappingEntityData, NotificationData, NotificationStateChangeData,
+                MaintenanceWindowData, EntitySetData, JobData, JobStarted,
+                MaterializationBackfillData, PartitionCreationData,
+                MaterializationStateData, MaterializeData, MaterializeResultData,
+                InstanceDiffData, ChangeResultData, HealthData,
+                RelationData, RelationRefreshData, RelationMembersData,
+                VirtualEntityData, VirtualEntityRefreshData,
             )
         ),
         tags(
@@ -135,7 +221,22 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
         Ok(value) => value,
     };
 
-    let pool = connect_db().await.map_err(|e| {
+    let pool_size: usize = match env::var(ENV_DB_POOL_SIZE)
+        .unwrap_or(DEFAULT_DB_POOL_SIZE.to_string())
+        .parse()
+    {
+        Err(e) => {
+            println!("Could not parse database pool size value '{ENV_DB_POOL_SIZE}': {e}");
+            exit(-1);
+        }
+        Ok(value) => value,
+    };
+
+    register_metrics().map_err(|e| {
+        serviceerror::ServiceError::from(format!("Could not register metrics: {e}"))
+    })?;
+
+    let pool = connect_db(pool_size).await.map_err(|e| {
         serviceerror::ServiceError::from(format!("Could not connect to database: {e}"))
     })?;
 
@@ -148,6 +249,7 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
             .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
             .max_age(3600);
         App::new()
+            .wrap_fn(track_request)
             .wrap(cors)
             .wrap(Logger::default())
             .app_data(web::Data::new(pool.clone()))
@@ -165,10 +267,17 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
             .service(delete_trend_function_materialization)
             .service(update_trend_function_materialization)
             .service(update_trend_view_materialization)
+            .service(backfill_trend_materialization)
+            .service(get_trend_materialization_state)
+            .service(materialize_trend_materialization)
+            .service(populate_trend_materialization_fingerprints)
+            .service(reset_trend_materialization_fingerprints)
             .service(get_trend_store_parts)
             .service(get_trend_store_part)
+            .service(get_trend_store_part_stats)
             .service(find_trend_store_part)
             .service(post_trend_store_part)
+            .service(post_partitions)
             .service(get_trend_stores)
             .service(get_trend_store)
             .service(get_trends)
@@ -184,10 +293,41 @@ async fn main() -> Result<(), serviceerror::ServiceError> {
             .service(delete_kpi)
             .service(get_triggers)
             .service(change_thresholds)
+            .service(get_trigger_exceptions)
+            .service(post_threshold_exception)
+            .service(post_weight_exception)
+            .service(delete_trigger_exception)
+            .service(get_trigger_flapping)
+            .service(get_notifications)
+            .service(post_acknowledge_notifications)
+            .service(post_clear_notifications)
+            .service(get_maintenance_windows)
+            .service(post_maintenance_window)
+            .service(delete_maintenance_window)
             .service(get_entity_sets)
             .service(change_entity_set)
             .service(create_entity_set)
             .service(get_header)
+            .service(get_jobs)
+            .service(get_job)
+            .service(
+                web::scope("/instance")
+                    .app_data(web::PayloadConfig::new(MAX_INSTANCE_ARCHIVE_SIZE))
+                    .service(post_instance_diff)
+                    .service(post_instance_apply)
+                    .service(post_instance_update),
+            )
+            .service(get_relations)
+            .service(post_relation)
+            .service(refresh_relation_table)
+            .service(get_relation_members)
+            .service(get_virtual_entities)
+            .service(post_virtual_entity)
+            .service(refresh_virtual_entity)
+            .service(get_virtual_entity_members)
+            .service(get_health_live)
+            .service(get_health_ready)
+            .service(get_metrics)
     })
     .bind((service_address, service_port))
     .map_err(|e| serviceerror::ServiceError::from(format!("Could not bind to port: {e}")))?
@@ -267,17 +407,17 @@ fn show_config(config: &Config) -> String {
     )
 }
 
-async fn connect_db() -> Result<Pool, Error> {
+async fn connect_db(pool_size: usize) -> Result<Pool, Error> {
     let config = get_db_config()?;
 
     let config_repr = show_config(&config);
 
     info!("Connecting to database: {}", &config_repr);
 
-    make_db_pool(&config).await
+    make_db_pool(&config, pool_size).await
 }
 
-async fn make_db_pool(config: &Config) -> Result<Pool, Error> {
+async fn make_db_pool(config: &Config, pool_size: usize) -> Result<Pool, Error> {
     let mgr_config = ManagerConfig {
         recycling_method: RecyclingMethod::Fast,
     };
@@ -302,8 +442,9 @@ async fn make_db_pool(config: &Config) -> Result<Pool, Error> {
         Manager::from_config(config.clone(), tls, mgr_config)
     };
 
-    Pool::builder(mgr)
-        .max_size(16)
-        .build()
-        .map_err(|_| Error::Database(DatabaseError::from_msg("test".to_string())))
+    Pool::builder(mgr).max_size(pool_size).build().map_err(|e| {
+        Error::Database(DatabaseError::from_msg(format!(
+            "Could not create database pool: {e}"
+        )))
+    })
 }
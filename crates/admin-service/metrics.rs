@@ -0,0 +1,133 @@
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::{get, web::Data, Error as ActixError, HttpResponse};
+use deadpool_postgres::Pool;
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+
+use super::serviceerror::ServiceError;
+
+lazy_static! {
+    static ref REGISTRY: Registry = Registry::new();
+    static ref HTTP_REQUESTS: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "minerva_service_http_requests_total",
+            "Number of HTTP requests handled"
+        ),
+        &["method", "route", "status"]
+    )
+    .unwrap();
+    static ref HTTP_REQUEST_DURATION: HistogramVec = HistogramVec::new(
+        HistogramOpts::new(
+            "minerva_service_http_request_duration_seconds",
+            "Time spent handling HTTP requests"
+        ),
+        &["method", "route"]
+    )
+    .unwrap();
+    static ref DATABASE_ERRORS: IntCounter = IntCounter::new(
+        "minerva_service_database_errors_total",
+        "Number of errors returned by the database or the connection pool"
+    )
+    .unwrap();
+    static ref POOL_MAX_SIZE: IntGauge = IntGauge::new(
+        "minerva_service_db_pool_max_size",
+        "Maximum number of connections in the database pool"
+    )
+    .unwrap();
+    static ref POOL_SIZE: IntGauge = IntGauge::new(
+        "minerva_service_db_pool_size",
+        "Number of connections currently in the database pool"
+    )
+    .unwrap();
+    static ref POOL_AVAILABLE: IntGauge = IntGauge::new(
+        "minerva_service_db_pool_available",
+        "Number of idle connections in the database pool"
+    )
+    .unwrap();
+    static ref POOL_WAITING: IntGauge = IntGauge::new(
+        "minerva_service_db_pool_waiting",
+        "Number of requests waiting for a database connection"
+    )
+    .unwrap();
+}
+
+/// Register all metrics with the registry, must be called once at startup
+pub fn register_metrics() -> Result<(), prometheus::Error> {
+    REGISTRY.register(Box::new(HTTP_REQUESTS.clone()))?;
+    REGISTRY.register(Box::new(HTTP_REQUEST_DURATION.clone()))?;
+    REGISTRY.register(Box::new(DATABASE_ERRORS.clone()))?;
+    REGISTRY.register(Box::new(POOL_MAX_SIZE.clone()))?;
+    REGISTRY.register(Box::new(POOL_SIZE.clone()))?;
+    REGISTRY.register(Box::new(POOL_AVAILABLE.clone()))?;
+    REGISTRY.register(Box::new(POOL_WAITING.clone()))?;
+
+    Ok(())
+}
+
+pub fn record_database_error() {
+    DATABASE_ERRORS.inc();
+}
+
+/// Middleware function for `App::wrap_fn` that records the count and latency
+/// of requests per route.
+pub fn track_request<S, B>(
+    req: ServiceRequest,
+    srv: &S,
+) -> impl std::future::Future<Output = Result<ServiceResponse<B>, ActixError>>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = ActixError>,
+{
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = srv.call(req);
+
+    async move {
+        let response = response.await?;
+
+        HTTP_REQUESTS
+            .with_label_values(&[&method, &route, response.status().as_str()])
+            .inc();
+        HTTP_REQUEST_DURATION
+            .with_label_values(&[&method, &route])
+            .observe(start.elapsed().as_secs_f64());
+
+        Ok(response)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path="/metrics",
+    responses(
+    (status = 200, description = "Service metrics in Prometheus text format", body = String),
+    )
+)]
+#[get("/metrics")]
+pub(super) async fn get_metrics(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
+    let status = pool.status();
+
+    POOL_MAX_SIZE.set(status.max_size as i64);
+    POOL_SIZE.set(status.size as i64);
+    POOL_AVAILABLE.set(status.available as i64);
+    POOL_WAITING.set(status.waiting as i64);
+
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+
+    encoder
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .map_err(|e| ServiceError::from(format!("Could not encode metrics: {e}")))?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(encoder.format_type())
+        .body(buffer))
+}
@@ -0,0 +1,344 @@
+use deadpool_postgres::Pool;
+use std::ops::DerefMut;
+
+use actix_web::{delete, get, post, web::Data, web::Path, web::Query, HttpResponse};
+use chrono::{DateTime, Utc};
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use minerva::notification_store::{
+    add_maintenance_window, load_maintenance_windows, load_notification_states,
+    remove_maintenance_window, set_notification_state, MaintenanceTarget, MaintenanceWindow,
+    NotificationState, NotificationStatus,
+};
+
+use super::serviceerror::{ServiceError, ServiceErrorKind};
+use crate::error::{Error, Success};
+
+const DEFAULT_NOTIFICATION_LIST_LIMIT: i64 = 100;
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct NotificationData {
+    pub id: i32,
+    pub entity_id: i32,
+    pub timestamp: DateTime<Utc>,
+    pub state: String,
+    pub modified: Option<DateTime<Utc>>,
+    pub changed_by: Option<String>,
+    pub remark: Option<String>,
+}
+
+impl From<NotificationStatus> for NotificationData {
+    fn from(status: NotificationStatus) -> NotificationData {
+        NotificationData {
+            id: status.notification_id,
+            entity_id: status.entity_id,
+            timestamp: status.timestamp,
+            state: status.state.to_string(),
+            modified: status.modified,
+            changed_by: status.changed_by,
+            remark: status.remark,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct NotificationListParams {
+    /// Only return notifications in this state: new, acknowledged or cleared
+    pub state: Option<String>,
+    /// Maximum number of notifications to return, newest first
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct NotificationStateChangeData {
+    pub ids: Vec<i32>,
+    pub user: Option<String>,
+    pub remark: Option<String>,
+}
+
+/// Maintenance window for either an entity, identified by `entity_type` and
+/// `entity`, or an entity set, identified by `entity_set_owner` and
+/// `entity_set_name`
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaintenanceWindowData {
+    pub id: Option<i32>,
+    pub entity_type: Option<String>,
+    pub entity: Option<String>,
+    pub entity_set_owner: Option<String>,
+    pub entity_set_name: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub remark: Option<String>,
+}
+
+impl From<MaintenanceWindow> for MaintenanceWindowData {
+    fn from(window: MaintenanceWindow) -> MaintenanceWindowData {
+        let (entity_type, entity, entity_set_owner, entity_set_name) = match window.target {
+            MaintenanceTarget::Entity { entity_type, name } => {
+                (Some(entity_type), Some(name), None, None)
+            }
+            MaintenanceTarget::EntitySet { owner, name } => (None, None, Some(owner), Some(name)),
+        };
+
+        MaintenanceWindowData {
+            id: window.id,
+            entity_type,
+            entity,
+            entity_set_owner,
+            entity_set_name,
+            start: window.start,
+            end: window.end,
+            remark: window.remark,
+        }
+    }
+}
+
+impl TryFrom<MaintenanceWindowData> for MaintenanceWindow {
+    type Error = ServiceError;
+
+    fn try_from(data: MaintenanceWindowData) -> Result<MaintenanceWindow, ServiceError> {
+        let target = match (
+            data.entity_type,
+            data.entity,
+            data.entity_set_owner,
+            data.entity_set_name,
+        ) {
+            (Some(entity_type), Some(name), None, None) => {
+                MaintenanceTarget::Entity { entity_type, name }
+            }
+            (None, None, Some(owner), Some(name)) => MaintenanceTarget::EntitySet { owner, name },
+            _ => return Err(ServiceError {
+                kind: ServiceErrorKind::BadRequest,
+                message:
+                    "Specify either entity_type and entity, or entity_set_owner and entity_set_name"
+                        .to_string(),
+            }),
+        };
+
+        Ok(MaintenanceWindow {
+            id: data.id,
+            target,
+            start: data.start,
+            end: data.end,
+            remark: data.remark,
+        })
+    }
+}
+
+#[utoipa::path(
+    get,
+    path="/notification-stores/{name}/notifications",
+    params(NotificationListParams),
+    responses(
+    (status = 200, description = "Most recent notifications with their state", body = [NotificationData]),
+    (status = 400, description = "Unsupported notification state", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/notification-stores/{name}/notifications")]
+pub(super) async fn get_notifications(
+    pool: Data<Pool>,
+    name: Path<String>,
+    params: Query<NotificationListParams>,
+) -> Result<HttpResponse, ServiceError> {
+    let state: Option<NotificationState> = params
+        .state
+        .as_deref()
+        .map(str::parse)
+        .transpose()
+        .map_err(|e: minerva::error::Error| ServiceError {
+            kind: ServiceErrorKind::BadRequest,
+            message: e.to_string(),
+        })?;
+
+    let limit = params.limit.unwrap_or(DEFAULT_NOTIFICATION_LIST_LIMIT);
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let notifications: Vec<NotificationData> =
+        load_notification_states(client, &name, state, limit)
+            .await?
+            .into_iter()
+            .map(NotificationData::from)
+            .collect();
+
+    Ok(HttpResponse::Ok().json(notifications))
+}
+
+async fn change_notification_state(
+    pool: Data<Pool>,
+    name: &str,
+    post: &str,
+    state: NotificationState,
+) -> Result<HttpResponse, ServiceError> {
+    let data: NotificationStateChangeData =
+        serde_json::from_str(post).map_err(|e| ServiceError {
+            kind: ServiceErrorKind::BadRequest,
+            message: format!("{e}"),
+        })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let count = set_notification_state(
+        client,
+        name,
+        &data.ids,
+        state,
+        data.user.as_deref(),
+        data.remark.as_deref(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(Success {
+        code: 200,
+        message: format!("Set {count} notification(s) to {state}"),
+    }))
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"ids":[1,2],"user":"operator","remark":"Investigating"}' localhost:8000/notification-stores/trigger-notification/notifications/acknowledge
+#[utoipa::path(
+    post,
+    path="/notification-stores/{name}/notifications/acknowledge",
+    responses(
+    (status = 200, description = "Acknowledged notifications", body = Success),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/notification-stores/{name}/notifications/acknowledge")]
+pub(super) async fn post_acknowledge_notifications(
+    pool: Data<Pool>,
+    name: Path<String>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    change_notification_state(pool, &name, &post, NotificationState::Acknowledged).await
+}
+
+#[utoipa::path(
+    post,
+    path="/notification-stores/{name}/notifications/clear",
+    responses(
+    (status = 200, description = "Cleared notifications", body = Success),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/notification-stores/{name}/notifications/clear")]
+pub(super) async fn post_clear_notifications(
+    pool: Data<Pool>,
+    name: Path<String>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    change_notification_state(pool, &name, &post, NotificationState::Cleared).await
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MaintenanceWindowListParams {
+    /// Only return windows that are active at this time
+    pub active_at: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path="/maintenance-windows",
+    params(MaintenanceWindowListParams),
+    responses(
+    (status = 200, description = "Maintenance windows", body = [MaintenanceWindowData]),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/maintenance-windows")]
+pub(super) async fn get_maintenance_windows(
+    pool: Data<Pool>,
+    params: Query<MaintenanceWindowListParams>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let windows: Vec<MaintenanceWindowData> = load_maintenance_windows(client, params.active_at)
+        .await?
+        .into_iter()
+        .map(MaintenanceWindowData::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(windows))
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"entity_type":"node","entity":"node_1","start":"2024-09-01T22:00:00Z","end":"2024-09-02T04:00:00Z"}' localhost:8000/maintenance-windows
+#[utoipa::path(
+    post,
+    path="/maintenance-windows",
+    responses(
+    (status = 200, description = "Added maintenance window", body = MaintenanceWindowData),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 409, description = "Maintenance window could not be added", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/maintenance-windows")]
+pub(super) async fn post_maintenance_window(
+    pool: Data<Pool>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let mut data: MaintenanceWindowData =
+        serde_json::from_str(&post).map_err(|e| ServiceError {
+            kind: ServiceErrorKind::BadRequest,
+            message: format!("{e}"),
+        })?;
+
+    let window = MaintenanceWindow::try_from(data.clone())?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let id = add_maintenance_window(client, &window)
+        .await
+        .map_err(|e| Error {
+            code: 409,
+            message: e.to_string(),
+        })?;
+
+    data.id = Some(id);
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[utoipa::path(
+    delete,
+    path="/maintenance-windows/{id}",
+    responses(
+    (status = 200, description = "Removed maintenance window", body = Success),
+    (status = 404, description = "Maintenance window not found", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[delete("/maintenance-windows/{id}")]
+pub(super) async fn delete_maintenance_window(
+    pool: Data<Pool>,
+    id: Path<i32>,
+) -> Result<HttpResponse, ServiceError> {
+    let id = id.into_inner();
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    match remove_maintenance_window(client, id).await? {
+        true => Ok(HttpResponse::Ok().json(Success {
+            code: 200,
+            message: format!("Removed maintenance window {id}"),
+        })),
+        false => Err(ServiceError {
+            kind: ServiceErrorKind::NotFound,
+            message: format!("No maintenance window with Id {id}"),
+        }),
+    }
+}
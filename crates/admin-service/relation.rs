@@ -0,0 +1,156 @@
+use std::ops::DerefMut;
+
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use actix_web::{get, post, web::Data, web::Path, HttpResponse};
+
+use minerva::change::Change;
+use minerva::relation::{
+    load_relation_targets, load_relations, refresh_relation, AddRelation, Relation,
+};
+
+use super::serviceerror::{ServiceError, ServiceErrorKind};
+use crate::error::{Error, Success};
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct RelationData {
+    pub name: String,
+    pub query: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct RelationRefreshData {
+    pub added: u64,
+    pub removed: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct RelationMembersData {
+    pub source_id: i32,
+    pub target_ids: Vec<i32>,
+}
+
+#[utoipa::path(
+    get,
+    path="/relations",
+    responses(
+    (status = 200, description = "List all relations", body = [RelationData]),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[get("/relations")]
+pub(super) async fn get_relations(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let relations: Vec<RelationData> = load_relations(client)
+        .await?
+        .into_iter()
+        .map(|relation| RelationData {
+            name: relation.name,
+            query: relation.query,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(relations))
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"name":"node->v-network","query":"SELECT n.id AS source_id, v.id AS target_id FROM entity.node n, entity.\"v-network\" v"}' localhost:8000/relations
+#[utoipa::path(
+    post,
+    path="/relations",
+    responses(
+    (status = 200, description = "Created relation", body = Success),
+    (status = 400, description = "Incorrect data format", body = Error),
+    (status = 409, description = "Relation could not be created", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/relations")]
+pub(super) async fn post_relation(
+    pool: Data<Pool>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let data: RelationData = serde_json::from_str(&post).map_err(|e| ServiceError {
+        kind: ServiceErrorKind::BadRequest,
+        message: format!("{e}"),
+    })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let mut transaction = client.transaction().await?;
+
+    let change = AddRelation {
+        relation: Relation {
+            name: data.name,
+            query: data.query,
+        },
+    };
+
+    let message = change.apply(&mut transaction).await.map_err(|e| Error {
+        code: 409,
+        message: e.to_string(),
+    })?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(Success { code: 200, message }))
+}
+
+#[utoipa::path(
+    post,
+    path="/relations/{name}/refresh",
+    responses(
+    (status = 200, description = "Synchronized the relation table with its definition", body = RelationRefreshData),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/relations/{name}/refresh")]
+pub(super) async fn refresh_relation_table(
+    pool: Data<Pool>,
+    name: Path<String>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let mut transaction = client.transaction().await?;
+
+    let (added, removed) = refresh_relation(&mut transaction, &name).await?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(RelationRefreshData { added, removed }))
+}
+
+#[utoipa::path(
+    get,
+    path="/relations/{name}/members/{source_id}",
+    responses(
+    (status = 200, description = "Target entities related to the source entity", body = RelationMembersData),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[get("/relations/{name}/members/{source_id}")]
+pub(super) async fn get_relation_members(
+    pool: Data<Pool>,
+    path: Path<(String, i32)>,
+) -> Result<HttpResponse, ServiceError> {
+    let (name, source_id) = path.into_inner();
+
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let target_ids = load_relation_targets(client, &name, source_id).await?;
+
+    Ok(HttpResponse::Ok().json(RelationMembersData {
+        source_id,
+        target_ids,
+    }))
+}
@@ -7,12 +7,14 @@ use serde::Serialize;
 use serde_json::{Map, Value};
 
 use super::error::{Error, ExtendedError};
+use super::metrics::record_database_error;
 
 #[derive(Display, From, Debug, Serialize)]
 pub enum ServiceErrorKind {
     NotFound,
     PoolError,
     BadRequest,
+    PayloadTooLarge,
     InternalError,
 }
 
@@ -63,6 +65,7 @@ impl ResponseError for ServiceError {
             ServiceErrorKind::NotFound => StatusCode::NOT_FOUND,
             ServiceErrorKind::PoolError => StatusCode::INTERNAL_SERVER_ERROR,
             ServiceErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+            ServiceErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             ServiceErrorKind::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -83,6 +86,7 @@ impl ResponseError for ExtendedServiceError {
             ServiceErrorKind::NotFound => StatusCode::NOT_FOUND,
             ServiceErrorKind::PoolError => StatusCode::INTERNAL_SERVER_ERROR,
             ServiceErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+            ServiceErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             ServiceErrorKind::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -97,9 +101,22 @@ impl ResponseError for ExtendedServiceError {
     }
 }
 
+impl From<deadpool_postgres::PoolError> for ServiceError {
+    fn from(value: deadpool_postgres::PoolError) -> ServiceError {
+        error!("{value:?}");
+        record_database_error();
+
+        ServiceError {
+            kind: ServiceErrorKind::PoolError,
+            message: value.to_string(),
+        }
+    }
+}
+
 impl From<tokio_postgres::error::Error> for ServiceError {
     fn from(value: tokio_postgres::error::Error) -> ServiceError {
         error!("{value:?}");
+        record_database_error();
 
         ServiceError {
             kind: ServiceErrorKind::InternalError,
@@ -111,6 +128,7 @@ impl From<tokio_postgres::error::Error> for ServiceError {
 impl From<tokio_postgres::error::Error> for ExtendedServiceError {
     fn from(value: tokio_postgres::error::Error) -> ExtendedServiceError {
         error!("{value:?}");
+        record_database_error();
 
         let mut map = Map::new();
         map.insert("general".to_string(), format!("{value:?}").into());
@@ -122,6 +140,21 @@ impl From<tokio_postgres::error::Error> for ExtendedServiceError {
     }
 }
 
+impl From<minerva::error::Error> for ServiceError {
+    fn from(value: minerva::error::Error) -> ServiceError {
+        error!("{value}");
+
+        if let minerva::error::Error::Database(_) = value {
+            record_database_error();
+        }
+
+        ServiceError {
+            kind: ServiceErrorKind::InternalError,
+            message: value.to_string(),
+        }
+    }
+}
+
 impl From<Error> for ServiceError {
     fn from(value: Error) -> ServiceError {
         error!("{value:?}");
@@ -1,20 +1,26 @@
-use serde_json::Value;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
 use std::ops::DerefMut;
 use std::time::Duration;
 
 use deadpool_postgres::Pool;
 
-use actix_web::{delete, get, post, put, web::Data, web::Path, HttpResponse, Responder};
+use actix_web::{
+    delete, get, post, put, web::Data, web::Path, web::Query, HttpResponse, Responder,
+};
 
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
 
 use minerva::change::Change;
+use minerva::granularity::configured_timezone;
 use minerva::interval::parse_interval;
+use minerva::job::set_job_progress;
 use minerva::trend_materialization::{
-    AddTrendMaterialization, TrendFunctionMaterialization, TrendMaterialization,
-    TrendMaterializationFunction, TrendMaterializationSource, TrendViewMaterialization,
-    UpdateTrendMaterialization,
+    load_materialization_state, materialization_timestamps, materialize,
+    populate_source_fingerprint, reset_source_fingerprint, AddTrendMaterialization,
+    TrendFunctionMaterialization, TrendMaterialization, TrendMaterializationFunction,
+    TrendMaterializationSource, TrendViewMaterialization, UpdateTrendMaterialization,
 };
 use tokio_postgres::Transaction;
 
@@ -22,6 +28,7 @@ use log::error;
 
 use super::serviceerror::ServiceError;
 use crate::error::{Error, Success};
+use crate::job::{spawn_job, JobStarted};
 use crate::serviceerror::ServiceErrorKind;
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
@@ -403,10 +410,7 @@ impl TrendFunctionMaterializationData {
 pub(super) async fn get_trend_view_materializations(
     pool: Data<Pool>,
 ) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let sources: Vec<TrendMaterializationSourceIdentifier> = client
         .query(
@@ -497,10 +501,7 @@ pub(super) async fn get_trend_view_materialization(
 ) -> Result<HttpResponse, ServiceError> {
     let vm_id = id.into_inner();
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let sources: Vec<TrendMaterializationSourceData> = client
         .query(
@@ -894,10 +895,7 @@ pub(super) async fn post_trend_view_materialization(
         message: e.to_string(),
     })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -936,10 +934,7 @@ pub(super) async fn post_trend_function_materialization(
             message: e.to_string(),
         })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -1119,10 +1114,7 @@ pub(super) async fn update_trend_view_materialization(
             message: e.to_string(),
         })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -1157,10 +1149,7 @@ pub(super) async fn update_trend_function_materialization(
             message: e.to_string(),
         })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -1173,3 +1162,249 @@ pub(super) async fn update_trend_function_materialization(
         .await
         .map(|success| Ok(HttpResponse::Ok().json(success)))?
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaterializationBackfillData {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"start":"2024-06-01T00:00:00Z","end":"2024-06-02T00:00:00Z"}' localhost:8000/trend-materializations/1/backfill
+#[utoipa::path(
+    post,
+    path="/trend-materializations/{id}/backfill",
+    responses(
+    (status = 202, description = "Backfill started as a job", body = JobStarted),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/trend-materializations/{id}/backfill")]
+pub(super) async fn backfill_trend_materialization(
+    pool: Data<Pool>,
+    id: Path<i32>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let materialization_id = id.into_inner();
+
+    let data: MaterializationBackfillData = serde_json::from_str(&post).map_err(|e| Error {
+        code: 400,
+        message: e.to_string(),
+    })?;
+
+    let timezone = configured_timezone()?;
+
+    let action = json!({
+        "backfill_materialization": materialization_id,
+        "start": data.start,
+        "end": data.end,
+    });
+
+    let job_id = spawn_job(&pool, action, move |pool, job_id| async move {
+        let manager = pool.get().await.map_err(|e| e.to_string())?;
+
+        let client: &tokio_postgres::Client = &manager;
+
+        let timestamps =
+            materialization_timestamps(client, materialization_id, data.start, data.end, &timezone)
+                .await
+                .map_err(|e| e.to_string())?;
+
+        let total = timestamps.len();
+        let mut row_count: i64 = 0;
+
+        for (index, timestamp) in timestamps.iter().enumerate() {
+            row_count += i64::from(
+                materialize(client, materialization_id, *timestamp)
+                    .await
+                    .map_err(|e| e.to_string())?,
+            );
+
+            set_job_progress(client, job_id, (index + 1) as f32 / total as f32)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(format!(
+            "Materialized {total} timestamps with {row_count} records"
+        ))
+    })
+    .await?;
+
+    Ok(JobStarted::response(job_id))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaterializationStateData {
+    pub timestamp: DateTime<Utc>,
+    pub source_fingerprint: Option<Value>,
+    pub processed_fingerprint: Option<Value>,
+    pub stale: bool,
+    pub max_modified: Option<DateTime<Utc>>,
+    pub last_processed: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct MaterializationStateParams {
+    /// Only return state from this timestamp onward
+    pub start: Option<DateTime<Utc>>,
+    /// Only return state up to and including this timestamp
+    pub end: Option<DateTime<Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path="/trend-materializations/{id}/state",
+    params(MaterializationStateParams),
+    responses(
+    (status = 200, description = "Materialization state per timestamp", body = [MaterializationStateData]),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[get("/trend-materializations/{id}/state")]
+pub(super) async fn get_trend_materialization_state(
+    pool: Data<Pool>,
+    id: Path<i32>,
+    params: Query<MaterializationStateParams>,
+) -> Result<HttpResponse, ServiceError> {
+    let materialization_id = id.into_inner();
+
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let state: Vec<MaterializationStateData> =
+        load_materialization_state(client, materialization_id, params.start, params.end)
+            .await?
+            .into_iter()
+            .map(|state| MaterializationStateData {
+                timestamp: state.timestamp,
+                source_fingerprint: state.source_fingerprint,
+                processed_fingerprint: state.processed_fingerprint,
+                stale: state.stale,
+                max_modified: state.max_modified,
+                last_processed: state.last_processed,
+            })
+            .collect();
+
+    Ok(HttpResponse::Ok().json(state))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaterializeData {
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaterializeResultData {
+    pub timestamp: DateTime<Utc>,
+    pub row_count: i32,
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"timestamp":"2024-06-01T00:00:00Z"}' localhost:8000/trend-materializations/1/materialize
+#[utoipa::path(
+    post,
+    path="/trend-materializations/{id}/materialize",
+    responses(
+    (status = 200, description = "Materialized the timestamp", body = MaterializeResultData),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 500, description = "Materialization failed", body = Error)
+    )
+)]
+#[post("/trend-materializations/{id}/materialize")]
+pub(super) async fn materialize_trend_materialization(
+    pool: Data<Pool>,
+    id: Path<i32>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let materialization_id = id.into_inner();
+
+    let data: MaterializeData = serde_json::from_str(&post).map_err(|e| Error {
+        code: 400,
+        message: e.to_string(),
+    })?;
+
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let row_count = materialize(client, materialization_id, data.timestamp).await?;
+
+    Ok(HttpResponse::Ok().json(MaterializeResultData {
+        timestamp: data.timestamp,
+        row_count,
+    }))
+}
+
+async fn materialization_name(
+    client: &tokio_postgres::Client,
+    materialization_id: i32,
+) -> Result<String, ServiceError> {
+    let row = client
+        .query_opt(
+            "SELECT m::text FROM trend_directory.materialization m WHERE m.id = $1",
+            &[&materialization_id],
+        )
+        .await?;
+
+    row.map(|row| row.get(0)).ok_or_else(|| ServiceError {
+        kind: ServiceErrorKind::NotFound,
+        message: format!("Materialization with id {materialization_id} not found"),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path="/trend-materializations/{id}/fingerprints/populate",
+    responses(
+    (status = 200, description = "Populated source fingerprints", body = Success),
+    (status = 404, description = "Materialization not found", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/trend-materializations/{id}/fingerprints/populate")]
+pub(super) async fn populate_trend_materialization_fingerprints(
+    pool: Data<Pool>,
+    id: Path<i32>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let name = materialization_name(client, id.into_inner()).await?;
+
+    populate_source_fingerprint(client, &name).await?;
+
+    Ok(HttpResponse::Ok().json(Success {
+        code: 200,
+        message: format!("Populated source fingerprints of '{name}'"),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path="/trend-materializations/{id}/fingerprints/reset",
+    responses(
+    (status = 200, description = "Reset source fingerprints", body = Success),
+    (status = 404, description = "Materialization not found", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/trend-materializations/{id}/fingerprints/reset")]
+pub(super) async fn reset_trend_materialization_fingerprints(
+    pool: Data<Pool>,
+    id: Path<i32>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let name = materialization_name(client, id.into_inner()).await?;
+
+    reset_source_fingerprint(client, &name).await?;
+
+    Ok(HttpResponse::Ok().json(Success {
+        code: 200,
+        message: format!("Reset source fingerprints of '{name}'"),
+    }))
+}
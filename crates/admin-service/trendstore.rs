@@ -3,22 +3,29 @@ use std::collections::HashMap;
 use std::ops::DerefMut;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
+
 use deadpool_postgres::Pool;
 use tokio_postgres::{GenericClient, Transaction};
 
 use actix_web::{get, post, web::Data, web::Path, web::Query, HttpResponse};
 
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use utoipa::{IntoParams, ToSchema};
 
 use minerva::change::Change;
 use minerva::changes::trend_store::{AddTrendStore, AddTrendStorePart, AddTrends};
 use minerva::interval::parse_interval;
-use minerva::trend_store::{load_trend_store, GeneratedTrend, Trend, TrendStore, TrendStorePart};
+use minerva::trend_store::{
+    create_partitions, default_retention_period, load_trend_stats, load_trend_store,
+    GeneratedTrend, StoredTrendStat, Trend, TrendStore, TrendStorePart,
+};
 
 use minerva::meas_value::DataType;
 
 use super::error::Error;
+use super::job::{spawn_job, JobStarted};
 use super::serviceerror::{ServiceError, ServiceErrorKind};
 
 lazy_static! {
@@ -77,6 +84,7 @@ impl TrendData {
             time_aggregation: self.time_aggregation.clone(),
             entity_aggregation: self.entity_aggregation.clone(),
             extra_data: self.extra_data.clone(),
+            old_names: vec![],
         }
     }
 }
@@ -156,6 +164,7 @@ impl TrendStorePartData {
             name: self.name.clone(),
             trends,
             generated_trends,
+            old_names: vec![],
         }
     }
 }
@@ -200,6 +209,7 @@ impl TrendStoreBasicData {
                     entity_type: self.entity_type.clone(),
                     granularity: self.granularity,
                     partition_size: *PARTITION_SIZE.get(&self.granularity.clone()).unwrap(),
+                    retention_period: default_retention_period(),
                     parts: vec![],
                 };
                 let result = AddTrendStore {
@@ -383,10 +393,7 @@ impl TrendStorePartCompleteData {
 )]
 #[get("/trend-store-parts")]
 pub(super) async fn get_trend_store_parts(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let trends: Vec<TrendFull> = client
         .query(
@@ -500,10 +507,7 @@ pub(super) async fn get_trend_store_part(
 ) -> Result<HttpResponse, ServiceError> {
     let tsp_id = id.into_inner();
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let trends: Vec<TrendFull> = client
         .query(
@@ -582,6 +586,69 @@ pub(super) async fn get_trend_store_part(
     Ok(HttpResponse::Ok().json(trendstorepart))
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TrendStatData {
+    pub name: String,
+    pub analyzed: DateTime<Utc>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub sample_percentage: Option<f64>,
+    pub row_count: i64,
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+    pub null_count: i64,
+    pub distinct_count: i64,
+    pub mean: Option<f64>,
+    pub first_timestamp: Option<DateTime<Utc>>,
+    pub last_timestamp: Option<DateTime<Utc>>,
+}
+
+impl From<StoredTrendStat> for TrendStatData {
+    fn from(stored: StoredTrendStat) -> TrendStatData {
+        TrendStatData {
+            name: stored.stat.name,
+            analyzed: stored.analyzed,
+            start: stored.start,
+            end: stored.end,
+            sample_percentage: stored.sample_percentage,
+            row_count: stored.row_count,
+            min_value: stored.stat.min_value,
+            max_value: stored.stat.max_value,
+            null_count: stored.stat.null_count,
+            distinct_count: stored.stat.distinct_count,
+            mean: stored.stat.mean,
+            first_timestamp: stored.stat.first_timestamp,
+            last_timestamp: stored.stat.last_timestamp,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path="/trend-store-part/{id}/stats",
+    responses(
+    (status = 200, description = "Stored analysis results of the trends of a trend store part", body = [TrendStatData]),
+    (status = 500, description = "Failure to interact with database", body = Error)
+    )
+)]
+#[get("/trend-store-part/{id}/stats")]
+pub(super) async fn get_trend_store_part_stats(
+    pool: Data<Pool>,
+    id: Path<i32>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let stats: Vec<TrendStatData> = load_trend_stats(client, id.into_inner())
+        .await?
+        .into_iter()
+        .map(TrendStatData::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(stats))
+}
+
 #[utoipa::path(
     get,
     path="/trend-store-parts/find",
@@ -598,10 +665,7 @@ pub(super) async fn find_trend_store_part(
 ) -> Result<HttpResponse, ServiceError> {
     let name = &info.name;
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let (trend_store_part_id, trend_store_id): (i32, i32) = client
         .query_one(
@@ -692,10 +756,7 @@ pub(super) async fn find_trend_store_part(
 )]
 #[get("/trend-stores")]
 pub(super) async fn get_trend_stores(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let trends: Vec<TrendFull> = client
         .query(
@@ -842,10 +903,7 @@ pub(super) async fn get_trend_store(
 ) -> Result<HttpResponse, ServiceError> {
     let tsid = id.into_inner();
 
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let trends: Vec<TrendFull> = client
         .query(
@@ -996,10 +1054,7 @@ pub(super) async fn post_trend_store_part(
             message: format!("{e}"),
         })?;
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -1012,6 +1067,58 @@ pub(super) async fn post_trend_store_part(
     Ok(HttpResponse::Ok().json(tsp))
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema, Default)]
+pub struct PartitionCreationData {
+    /// How far ahead of the current time partitions should be created, defaults to 3 days
+    #[serde(default, with = "humantime_serde")]
+    #[schema(value_type = Option<String>)]
+    pub ahead_interval: Option<Duration>,
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"ahead_interval":"7days"}' localhost:8000/trend-stores/partitions
+#[utoipa::path(
+    post,
+    path="/trend-stores/partitions",
+    responses(
+    (status = 202, description = "Partition creation started as a job", body = JobStarted),
+    (status = 400, description = "Incorrect data format", body = Error),
+    (status = 500, description = "Problems interacting with database", body = Error),
+    )
+)]
+#[post("/trend-stores/partitions")]
+pub(super) async fn post_partitions(
+    pool: Data<Pool>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let data: PartitionCreationData = if post.trim().is_empty() {
+        PartitionCreationData::default()
+    } else {
+        serde_json::from_str(&post).map_err(|e| ServiceError {
+            kind: ServiceErrorKind::BadRequest,
+            message: format!("{e}"),
+        })?
+    };
+
+    let action = json!({
+        "create_partitions": data.ahead_interval.map(|i| humantime::format_duration(i).to_string()),
+    });
+
+    let job_id = spawn_job(&pool, action, move |pool, _job_id| async move {
+        let mut manager = pool.get().await.map_err(|e| e.to_string())?;
+
+        let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+        create_partitions(client, data.ahead_interval)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok("Created partitions".to_string())
+    })
+    .await?;
+
+    Ok(JobStarted::response(job_id))
+}
+
 #[utoipa::path(
     get,
     path="/trends",
@@ -1022,10 +1129,7 @@ pub(super) async fn post_trend_store_part(
 )]
 #[get("/trends")]
 pub(super) async fn get_trends(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let client = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let client = pool.get().await.map_err(ServiceError::from)?;
 
     let table_trends: Vec<TrendDataWithTrendStorePart> = client
         .query(
@@ -1104,10 +1208,7 @@ pub(super) async fn get_trends_by_entity_type(
 ) -> Result<HttpResponse, ServiceError> {
     let entity_type = et.into_inner();
 
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
 
@@ -1,15 +1,22 @@
 use deadpool_postgres::Pool;
+use std::collections::BTreeMap;
 use std::ops::DerefMut;
+use std::str::FromStr;
 
-use actix_web::{get, put, web::Data, HttpResponse};
+use actix_web::{delete, get, post, put, web::Data, web::Path, HttpResponse};
+use chrono::{DateTime, Utc};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Map;
 use utoipa::ToSchema;
 
 use minerva::trigger::{
-    list_triggers, load_thresholds_with_client, load_trigger, set_enabled, set_thresholds,
-    Threshold,
+    list_triggers, load_flapping_entities, load_thresholds_with_client, load_trigger, set_enabled,
+    set_thresholds, FlappingEntity, Threshold,
+};
+use minerva::trigger_exception::{
+    add_threshold_exception, add_weight_exception, load_trigger_exceptions, remove_exception,
+    ExceptionKind, ThresholdException, WeightException,
 };
 
 use super::serviceerror::{ExtendedServiceError, ServiceError, ServiceErrorKind};
@@ -23,6 +30,98 @@ pub struct TriggerData {
     thresholds: Vec<Threshold>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ThresholdExceptionData {
+    pub id: Option<i32>,
+    pub entity: String,
+    pub start: Option<DateTime<Utc>>,
+    pub expires: Option<DateTime<Utc>>,
+    pub remark: Option<String>,
+    pub thresholds: BTreeMap<String, String>,
+}
+
+impl From<ThresholdException> for ThresholdExceptionData {
+    fn from(exception: ThresholdException) -> ThresholdExceptionData {
+        ThresholdExceptionData {
+            id: exception.id,
+            entity: exception.entity,
+            start: exception.start,
+            expires: exception.expires,
+            remark: exception.remark,
+            thresholds: exception.thresholds,
+        }
+    }
+}
+
+impl From<ThresholdExceptionData> for ThresholdException {
+    fn from(data: ThresholdExceptionData) -> ThresholdException {
+        ThresholdException {
+            id: data.id,
+            entity: data.entity,
+            start: data.start,
+            expires: data.expires,
+            remark: data.remark,
+            thresholds: data.thresholds,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct WeightExceptionData {
+    pub id: Option<i32>,
+    pub entity: String,
+    pub start: Option<DateTime<Utc>>,
+    pub expires: Option<DateTime<Utc>>,
+    pub weight: i32,
+}
+
+impl From<WeightException> for WeightExceptionData {
+    fn from(exception: WeightException) -> WeightExceptionData {
+        WeightExceptionData {
+            id: exception.id,
+            entity: exception.entity,
+            start: exception.start,
+            expires: exception.expires,
+            weight: exception.weight,
+        }
+    }
+}
+
+impl From<WeightExceptionData> for WeightException {
+    fn from(data: WeightExceptionData) -> WeightException {
+        WeightException {
+            id: data.id,
+            entity: data.entity,
+            start: data.start,
+            expires: data.expires,
+            weight: data.weight,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct FlappingEntityData {
+    pub entity: String,
+    pub since: DateTime<Utc>,
+    pub changes: i32,
+}
+
+impl From<FlappingEntity> for FlappingEntityData {
+    fn from(flapping_entity: FlappingEntity) -> FlappingEntityData {
+        FlappingEntityData {
+            entity: flapping_entity.entity,
+            since: flapping_entity.since,
+            changes: flapping_entity.changes,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TriggerExceptionsData {
+    pub threshold: Vec<ThresholdExceptionData>,
+    pub weight: Vec<WeightExceptionData>,
+}
+
 #[utoipa::path(
     get,
     path="/triggers",
@@ -33,10 +132,7 @@ pub struct TriggerData {
 )]
 #[get("/triggers")]
 pub(super) async fn get_triggers(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
-    let mut manager = pool.get().await.map_err(|_| ServiceError {
-        kind: ServiceErrorKind::PoolError,
-        message: "".to_string(),
-    })?;
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
 
     let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
     let triggerdata = list_triggers(client).await.map_err(|e| Error {
@@ -164,3 +260,185 @@ pub(super) async fn change_thresholds(
         }))
     }
 }
+
+#[utoipa::path(
+    get,
+    path="/triggers/{name}/exceptions",
+    responses(
+    (status = 200, description = "Entity specific exceptions of a trigger", body = TriggerExceptionsData),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/triggers/{name}/exceptions")]
+pub(super) async fn get_trigger_exceptions(
+    pool: Data<Pool>,
+    name: Path<String>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let exceptions = load_trigger_exceptions(client, &name).await?;
+
+    Ok(HttpResponse::Ok().json(TriggerExceptionsData {
+        threshold: exceptions
+            .threshold
+            .into_iter()
+            .map(ThresholdExceptionData::from)
+            .collect(),
+        weight: exceptions
+            .weight
+            .into_iter()
+            .map(WeightExceptionData::from)
+            .collect(),
+    }))
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"entity":"cell_1","expires":"2024-09-01T00:00:00Z","remark":"Known noisy cell","thresholds":{"max_drop_rate":"0.2"}}' localhost:8000/triggers/drop-rate/exceptions/threshold
+#[utoipa::path(
+    post,
+    path="/triggers/{name}/exceptions/threshold",
+    responses(
+    (status = 200, description = "Added threshold exception", body = ThresholdExceptionData),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 409, description = "Exception could not be added", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/triggers/{name}/exceptions/threshold")]
+pub(super) async fn post_threshold_exception(
+    pool: Data<Pool>,
+    name: Path<String>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let mut data: ThresholdExceptionData =
+        serde_json::from_str(&post).map_err(|e| ServiceError {
+            kind: ServiceErrorKind::BadRequest,
+            message: format!("{e}"),
+        })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let mut transaction = client.transaction().await?;
+
+    let id = add_threshold_exception(&mut transaction, &name, &data.clone().into())
+        .await
+        .map_err(|e| Error {
+            code: 409,
+            message: e.to_string(),
+        })?;
+
+    transaction.commit().await?;
+
+    data.id = Some(id);
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+// curl -H "Content-Type: application/json" -X POST -d '{"entity":"cell_1","weight":10}' localhost:8000/triggers/drop-rate/exceptions/weight
+#[utoipa::path(
+    post,
+    path="/triggers/{name}/exceptions/weight",
+    responses(
+    (status = 200, description = "Added weight exception", body = WeightExceptionData),
+    (status = 400, description = "Input format incorrect", body = Error),
+    (status = 409, description = "Exception could not be added", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[post("/triggers/{name}/exceptions/weight")]
+pub(super) async fn post_weight_exception(
+    pool: Data<Pool>,
+    name: Path<String>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let mut data: WeightExceptionData = serde_json::from_str(&post).map_err(|e| ServiceError {
+        kind: ServiceErrorKind::BadRequest,
+        message: format!("{e}"),
+    })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let mut transaction = client.transaction().await?;
+
+    let id = add_weight_exception(&mut transaction, &name, &data.clone().into())
+        .await
+        .map_err(|e| Error {
+            code: 409,
+            message: e.to_string(),
+        })?;
+
+    transaction.commit().await?;
+
+    data.id = Some(id);
+
+    Ok(HttpResponse::Ok().json(data))
+}
+
+#[utoipa::path(
+    delete,
+    path="/triggers/{name}/exceptions/{kind}/{id}",
+    responses(
+    (status = 200, description = "Removed exception", body = Success),
+    (status = 400, description = "Unsupported exception kind", body = Error),
+    (status = 404, description = "Exception not found", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[delete("/triggers/{name}/exceptions/{kind}/{id}")]
+pub(super) async fn delete_trigger_exception(
+    pool: Data<Pool>,
+    path: Path<(String, String, i32)>,
+) -> Result<HttpResponse, ServiceError> {
+    let (name, kind, id) = path.into_inner();
+
+    let kind = ExceptionKind::from_str(&kind).map_err(|e| ServiceError {
+        kind: ServiceErrorKind::BadRequest,
+        message: e.to_string(),
+    })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    match remove_exception(client, &name, kind, id).await? {
+        true => Ok(HttpResponse::Ok().json(Success {
+            code: 200,
+            message: format!("Removed {kind} exception {id}"),
+        })),
+        false => Err(ServiceError {
+            kind: ServiceErrorKind::NotFound,
+            message: format!("No {kind} exception with Id {id} for trigger '{name}'"),
+        }),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path="/triggers/{name}/flapping",
+    responses(
+    (status = 200, description = "Entities that alternate between firing and not firing", body = [FlappingEntityData]),
+    (status = 500, description = "Unable to interact with database", body = Error),
+    )
+)]
+#[get("/triggers/{name}/flapping")]
+pub(super) async fn get_trigger_flapping(
+    pool: Data<Pool>,
+    name: Path<String>,
+) -> Result<HttpResponse, ServiceError> {
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let flapping_entities: Vec<FlappingEntityData> = load_flapping_entities(client, &name)
+        .await?
+        .into_iter()
+        .map(FlappingEntityData::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(flapping_entities))
+}
@@ -0,0 +1,140 @@
+use std::ops::DerefMut;
+
+use deadpool_postgres::Pool;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use actix_web::{get, post, web::Data, web::Path, HttpResponse};
+
+use minerva::change::Change;
+use minerva::virtual_entity::{
+    load_virtual_entities, load_virtual_entity_members, update_virtual_entity, AddVirtualEntity,
+    VirtualEntity,
+};
+
+use super::serviceerror::{ServiceError, ServiceErrorKind};
+use crate::error::{Error, Success};
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct VirtualEntityData {
+    pub name: String,
+    pub sql: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct VirtualEntityRefreshData {
+    pub created: i32,
+}
+
+#[utoipa::path(
+    get,
+    path="/virtual-entities",
+    responses(
+    (status = 200, description = "List all virtual entities", body = [VirtualEntityData]),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[get("/virtual-entities")]
+pub(super) async fn get_virtual_entities(pool: Data<Pool>) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let virtual_entities: Vec<VirtualEntityData> = load_virtual_entities(client)
+        .await?
+        .into_iter()
+        .map(|virtual_entity| VirtualEntityData {
+            name: virtual_entity.name,
+            sql: virtual_entity.sql,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(virtual_entities))
+}
+
+#[utoipa::path(
+    post,
+    path="/virtual-entities",
+    responses(
+    (status = 200, description = "Created virtual entity", body = Success),
+    (status = 400, description = "Incorrect data format", body = Error),
+    (status = 409, description = "Virtual entity could not be created", body = Error),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/virtual-entities")]
+pub(super) async fn post_virtual_entity(
+    pool: Data<Pool>,
+    post: String,
+) -> Result<HttpResponse, ServiceError> {
+    let data: VirtualEntityData = serde_json::from_str(&post).map_err(|e| ServiceError {
+        kind: ServiceErrorKind::BadRequest,
+        message: format!("{e}"),
+    })?;
+
+    let mut manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &mut tokio_postgres::Client = manager.deref_mut().deref_mut();
+
+    let mut transaction = client.transaction().await?;
+
+    let change = AddVirtualEntity {
+        virtual_entity: VirtualEntity {
+            name: data.name,
+            sql: data.sql,
+        },
+    };
+
+    let message = change.apply(&mut transaction).await.map_err(|e| Error {
+        code: 409,
+        message: e.to_string(),
+    })?;
+
+    transaction.commit().await?;
+
+    Ok(HttpResponse::Ok().json(Success { code: 200, message }))
+}
+
+#[utoipa::path(
+    post,
+    path="/virtual-entities/{name}/refresh",
+    responses(
+    (status = 200, description = "Created missing entities of the virtual entity type", body = VirtualEntityRefreshData),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[post("/virtual-entities/{name}/refresh")]
+pub(super) async fn refresh_virtual_entity(
+    pool: Data<Pool>,
+    name: Path<String>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let created = update_virtual_entity(client, &name).await?;
+
+    Ok(HttpResponse::Ok().json(VirtualEntityRefreshData { created }))
+}
+
+#[utoipa::path(
+    get,
+    path="/virtual-entities/{name}/members",
+    responses(
+    (status = 200, description = "Names of the entities defined by the virtual entity", body = [String]),
+    (status = 500, description = "Unable to interact with database", body = Error)
+    )
+)]
+#[get("/virtual-entities/{name}/members")]
+pub(super) async fn get_virtual_entity_members(
+    pool: Data<Pool>,
+    name: Path<String>,
+) -> Result<HttpResponse, ServiceError> {
+    let manager = pool.get().await.map_err(ServiceError::from)?;
+
+    let client: &tokio_postgres::Client = &manager;
+
+    let members = load_virtual_entity_members(client, &name).await?;
+
+    Ok(HttpResponse::Ok().json(members))
+}
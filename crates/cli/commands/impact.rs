@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use clap::{Parser, ValueEnum};
+
+use comfy_table::Table;
+
+use minerva::dependency::{load_dependency_graph, DependencyNode};
+use minerva::error::{Error, RuntimeError};
+
+use super::common::{connect_db, Cmd, CmdResult};
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum ImpactFormat {
+    Table,
+    Json,
+    Dot,
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct ImpactOpt {
+    #[arg(long, value_enum, default_value_t = ImpactFormat::Table)]
+    format: ImpactFormat,
+    #[arg(
+        help = "object as KIND:NAME with KIND one of trend-store-part, trend, relation or attribute-store, e.g. trend:hub_node_main_15m.power_kwh"
+    )]
+    object: DependencyNode,
+}
+
+#[async_trait]
+impl Cmd for ImpactOpt {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let graph = load_dependency_graph(&mut client).await?;
+
+        if !graph.contains(&self.object) {
+            return Err(Error::Runtime(RuntimeError::from_msg(format!(
+                "No {} '{}' found",
+                self.object.kind(),
+                self.object.name()
+            ))));
+        }
+
+        let impact = graph.downstream(&self.object);
+
+        match self.format {
+            ImpactFormat::Json => {
+                let json = serde_json::to_string_pretty(&impact).map_err(|e| {
+                    RuntimeError::from_msg(format!("Could not serialize dependency graph: {e}"))
+                })?;
+
+                println!("{json}");
+            }
+            ImpactFormat::Dot => {
+                print!("{}", impact.to_dot());
+            }
+            ImpactFormat::Table => {
+                let mut table = Table::new();
+                let style = "     ═╪ ┆          ";
+                table.load_preset(style);
+                table.set_header(vec!["Type", "Name", "Depends On"]);
+
+                for node in impact.nodes.iter().filter(|node| **node != self.object) {
+                    table.add_row(vec![
+                        node.kind().to_string(),
+                        node.name(),
+                        impact
+                            .dependencies(node)
+                            .iter()
+                            .map(|dependency| dependency.to_string())
+                            .collect::<Vec<String>>()
+                            .join("\n"),
+                    ]);
+                }
+
+                println!("{table}");
+            }
+        }
+
+        Ok(())
+    }
+}
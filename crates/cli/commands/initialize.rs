@@ -106,7 +106,7 @@ impl Cmd for InitializeOpt {
         // started during initialization.
         std::env::set_var(ENV_MINERVA_INSTANCE_ROOT, &self.instance_root);
 
-        MinervaInstance::load_from(&self.instance_root)
+        MinervaInstance::load_from(&self.instance_root)?
             .initialize(&mut client)
             .await?;
 
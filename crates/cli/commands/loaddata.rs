@@ -1,9 +1,12 @@
 use async_trait::async_trait;
 use clap::Parser;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use minerva::error::ConfigurationError;
+use minerva::granularity::configured_timezone;
 use minerva::loading::{load_data, ParserConfig, TrendsFrom, TrendsFromHeader};
+use minerva::trend_store::LoadMode;
 
 use super::common::{connect_db, Cmd, CmdResult};
 
@@ -17,7 +20,12 @@ pub struct LoadDataOpt {
     parser_config: Option<PathBuf>,
     #[arg(long, help = "Create partitions for timestamps in data")]
     create_partitions: bool,
-    #[arg(help = "File to load")]
+    #[arg(
+        long,
+        help = "How to handle existing data: insert-only, overwrite, fill-nulls-only or skip-existing (overrides the parser configuration)"
+    )]
+    load_mode: Option<LoadMode>,
+    #[arg(help = "CSV or Parquet (.parquet) file to load")]
     file: PathBuf,
 }
 
@@ -28,7 +36,7 @@ impl Cmd for LoadDataOpt {
 
         let mut client = connect_db().await?;
 
-        let parser_config: ParserConfig = match &self.parser_config {
+        let mut parser_config: ParserConfig = match &self.parser_config {
             None => ParserConfig {
                 entity_type: "node".into(),
                 granularity: "15m".into(),
@@ -38,6 +46,8 @@ impl Cmd for LoadDataOpt {
                 }),
                 extra: None,
                 null_value: NULL_VALUE.to_string(),
+                value_parsers: HashMap::new(),
+                load_mode: LoadMode::default(),
             },
             Some(path) => {
                 let config_file = std::fs::File::open(path)
@@ -46,6 +56,10 @@ impl Cmd for LoadDataOpt {
             }
         };
 
+        if let Some(load_mode) = self.load_mode {
+            parser_config.load_mode = load_mode;
+        }
+
         let data_source = match &self.data_source {
             None => "minerva-cli".to_string(),
             Some(d) => d.to_string(),
@@ -57,13 +71,14 @@ impl Cmd for LoadDataOpt {
             &parser_config,
             &self.file,
             self.create_partitions,
+            &configured_timezone()?,
         )
         .await;
 
         match result {
             Err(e) => {
                 println!(
-                    "Could not load CSV file '{}': {}",
+                    "Could not load file '{}': {}",
                     &self.file.as_path().to_string_lossy(),
                     e
                 );
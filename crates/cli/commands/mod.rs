@@ -2,8 +2,10 @@ pub mod attributestore;
 pub mod common;
 pub mod diff;
 pub mod dump;
+pub mod impact;
 pub mod initialize;
 pub mod loaddata;
+pub mod notification;
 pub mod relation;
 pub mod schema;
 #[cfg(feature = "test-containers")]
@@ -0,0 +1,336 @@
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, Utc};
+use clap::{Parser, Subcommand};
+
+use comfy_table::Table;
+
+use minerva::error::{Error, RuntimeError};
+use minerva::notification_store::{
+    add_maintenance_window, load_maintenance_windows, load_notification_states,
+    remove_maintenance_window, set_notification_state, MaintenanceTarget, MaintenanceWindow,
+    NotificationState,
+};
+
+use super::common::{connect_db, Cmd, CmdResult};
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct NotificationList {
+    #[arg(long, help = "only list notifications in this state")]
+    state: Option<NotificationState>,
+    #[arg(long, help = "maximum number of notifications", default_value_t = 100)]
+    limit: i64,
+    #[arg(help = "notification store name")]
+    notification_store: String,
+}
+
+#[async_trait]
+impl Cmd for NotificationList {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let notifications = load_notification_states(
+            &mut client,
+            &self.notification_store,
+            self.state,
+            self.limit,
+        )
+        .await?;
+
+        let mut table = Table::new();
+        let style = "     ═╪ ┆          ";
+        table.load_preset(style);
+        table.set_header(vec![
+            "Id",
+            "Entity Id",
+            "Timestamp",
+            "State",
+            "Modified",
+            "Changed By",
+            "Remark",
+        ]);
+
+        for notification in notifications {
+            table.add_row(vec![
+                notification.notification_id.to_string(),
+                notification.entity_id.to_string(),
+                notification.timestamp.to_rfc3339(),
+                notification.state.to_string(),
+                notification
+                    .modified
+                    .map(|modified| modified.to_rfc3339())
+                    .unwrap_or_default(),
+                notification.changed_by.unwrap_or_default(),
+                notification.remark.unwrap_or_default(),
+            ]);
+        }
+
+        println!("{table}");
+
+        Ok(())
+    }
+}
+
+async fn change_state(
+    notification_store: &str,
+    ids: &[i32],
+    state: NotificationState,
+    changed_by: Option<&str>,
+    remark: Option<&str>,
+) -> CmdResult {
+    let mut client = connect_db().await?;
+
+    let count = set_notification_state(
+        &mut client,
+        notification_store,
+        ids,
+        state,
+        changed_by,
+        remark,
+    )
+    .await?;
+
+    println!("Set {count} notification(s) to {state}");
+
+    Ok(())
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct NotificationAcknowledge {
+    #[arg(long = "by", help = "user acknowledging the notifications")]
+    changed_by: Option<String>,
+    #[arg(long, help = "remark on the acknowledgement")]
+    remark: Option<String>,
+    #[arg(help = "notification store name")]
+    notification_store: String,
+    #[arg(help = "notification Ids", required = true)]
+    ids: Vec<i32>,
+}
+
+#[async_trait]
+impl Cmd for NotificationAcknowledge {
+    async fn run(&self) -> CmdResult {
+        change_state(
+            &self.notification_store,
+            &self.ids,
+            NotificationState::Acknowledged,
+            self.changed_by.as_deref(),
+            self.remark.as_deref(),
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct NotificationClear {
+    #[arg(long = "by", help = "user clearing the notifications")]
+    changed_by: Option<String>,
+    #[arg(long, help = "remark on clearing")]
+    remark: Option<String>,
+    #[arg(help = "notification store name")]
+    notification_store: String,
+    #[arg(help = "notification Ids", required = true)]
+    ids: Vec<i32>,
+}
+
+#[async_trait]
+impl Cmd for NotificationClear {
+    async fn run(&self) -> CmdResult {
+        change_state(
+            &self.notification_store,
+            &self.ids,
+            NotificationState::Cleared,
+            self.changed_by.as_deref(),
+            self.remark.as_deref(),
+        )
+        .await
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct MaintenanceAdd {
+    #[arg(long, help = "entity type of the entity in maintenance")]
+    entity_type: Option<String>,
+    #[arg(long, help = "entity in maintenance", requires = "entity_type")]
+    entity: Option<String>,
+    #[arg(
+        long,
+        help = "entity set in maintenance as OWNER:NAME",
+        conflicts_with_all = ["entity_type", "entity"]
+    )]
+    entity_set: Option<String>,
+    #[arg(
+        long,
+        help = "start of the maintenance window",
+        value_parser = DateTime::parse_from_rfc3339
+    )]
+    start: DateTime<FixedOffset>,
+    #[arg(
+        long,
+        help = "end of the maintenance window",
+        value_parser = DateTime::parse_from_rfc3339
+    )]
+    end: DateTime<FixedOffset>,
+    #[arg(long, help = "reason for the maintenance")]
+    remark: Option<String>,
+}
+
+#[async_trait]
+impl Cmd for MaintenanceAdd {
+    async fn run(&self) -> CmdResult {
+        let target = match (&self.entity_type, &self.entity, &self.entity_set) {
+            (Some(entity_type), Some(entity), None) => MaintenanceTarget::Entity {
+                entity_type: entity_type.clone(),
+                name: entity.clone(),
+            },
+            (None, None, Some(entity_set)) => {
+                let (owner, name) = entity_set.split_once(':').ok_or_else(|| {
+                    Error::Runtime(RuntimeError::from_msg(format!(
+                        "Expected entity set as OWNER:NAME, got '{entity_set}'"
+                    )))
+                })?;
+
+                MaintenanceTarget::EntitySet {
+                    owner: owner.to_string(),
+                    name: name.to_string(),
+                }
+            }
+            _ => {
+                return Err(Error::Runtime(RuntimeError::from_msg(
+                    "Specify either --entity-type and --entity, or --entity-set".to_string(),
+                )))
+            }
+        };
+
+        let window = MaintenanceWindow {
+            id: None,
+            target,
+            start: self.start.with_timezone(&Utc),
+            end: self.end.with_timezone(&Utc),
+            remark: self.remark.clone(),
+        };
+
+        let mut client = connect_db().await?;
+
+        let id = add_maintenance_window(&mut client, &window).await?;
+
+        println!("Added maintenance window {id} for {}", window.target);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct MaintenanceList {
+    #[arg(long, help = "only list windows that are currently active")]
+    active: bool,
+}
+
+#[async_trait]
+impl Cmd for MaintenanceList {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let active_at = self.active.then(Utc::now);
+
+        let windows = load_maintenance_windows(&mut client, active_at).await?;
+
+        let mut table = Table::new();
+        let style = "     ═╪ ┆          ";
+        table.load_preset(style);
+        table.set_header(vec!["Id", "Target", "Start", "End", "Remark"]);
+
+        for window in windows {
+            table.add_row(vec![
+                window.id.unwrap_or_default().to_string(),
+                window.target.to_string(),
+                window.start.to_rfc3339(),
+                window.end.to_rfc3339(),
+                window.remark.unwrap_or_default(),
+            ]);
+        }
+
+        println!("{table}");
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct MaintenanceRemove {
+    #[arg(help = "maintenance window Id")]
+    id: i32,
+}
+
+#[async_trait]
+impl Cmd for MaintenanceRemove {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        match remove_maintenance_window(&mut client, self.id).await? {
+            true => println!("Removed maintenance window {}", self.id),
+            false => {
+                return Err(Error::Runtime(RuntimeError::from_msg(format!(
+                    "No maintenance window with Id {}",
+                    self.id
+                ))))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct Maintenance {
+    #[command(subcommand)]
+    command: MaintenanceCommands,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum MaintenanceCommands {
+    #[command(about = "add a maintenance window for an entity or entity set")]
+    Add(MaintenanceAdd),
+    #[command(about = "list maintenance windows")]
+    List(MaintenanceList),
+    #[command(about = "remove a maintenance window")]
+    Remove(MaintenanceRemove),
+}
+
+impl Maintenance {
+    async fn run(&self) -> CmdResult {
+        match &self.command {
+            MaintenanceCommands::Add(add) => add.run().await,
+            MaintenanceCommands::List(list) => list.run().await,
+            MaintenanceCommands::Remove(remove) => remove.run().await,
+        }
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct NotificationOpt {
+    #[command(subcommand)]
+    command: NotificationOptCommands,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum NotificationOptCommands {
+    #[command(about = "list notifications with their state")]
+    List(NotificationList),
+    #[command(about = "acknowledge notifications")]
+    Acknowledge(NotificationAcknowledge),
+    #[command(about = "clear notifications")]
+    Clear(NotificationClear),
+    #[command(about = "manage maintenance windows that suppress notifications")]
+    Maintenance(Maintenance),
+}
+
+impl NotificationOpt {
+    pub async fn run(&self) -> CmdResult {
+        match &self.command {
+            NotificationOptCommands::List(list) => list.run().await,
+            NotificationOptCommands::Acknowledge(acknowledge) => acknowledge.run().await,
+            NotificationOptCommands::Clear(clear) => clear.run().await,
+            NotificationOptCommands::Maintenance(maintenance) => maintenance.run().await,
+        }
+    }
+}
@@ -66,7 +66,7 @@ impl Cmd for StartOpt {
                     minerva_instance_root.to_string_lossy()
                 );
 
-                let minerva_instance = MinervaInstance::load_from(&minerva_instance_root);
+                let minerva_instance = MinervaInstance::load_from(&minerva_instance_root)?;
                 minerva_instance.initialize(&mut client).await?;
 
                 if self.create_partitions {
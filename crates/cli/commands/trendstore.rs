@@ -6,7 +6,7 @@ use chrono::FixedOffset;
 
 use async_trait::async_trait;
 use chrono::Utc;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use dialoguer::Confirm;
 
 use clap::Subcommand;
@@ -19,11 +19,20 @@ use term_table::{
 };
 
 use minerva::change::Change;
-use minerva::changes::trend_store::AddTrendStore;
-use minerva::error::{Error, RuntimeError};
+use minerva::changes::trend_store::{AddTrendStore, RenameTrend, RepartitionTrendStore};
+use minerva::entity::lookup_entity_ids;
+use minerva::error::{ConfigurationError, Error, RuntimeError};
+use minerva::granularity::configured_timezone;
+use minerva::trend_completeness::{trend_store_part_completeness, CompletenessOptions};
+use minerva::trend_export::{export_trend_data, ExportFormat, TrendExport};
+use minerva::trend_migration::{
+    data_type_modifications, migrate_data_types, plan_data_type_migration,
+};
 use minerva::trend_store::{
-    analyze_trend_store_part, create_partitions, create_partitions_for_timestamp,
-    delete_trend_store, list_trend_stores, load_trend_store, load_trend_store_from_file,
+    analyze_trend_store_part_with, create_partitions, create_partitions_for_timestamp,
+    delete_trend_store, list_trend_stores, load_trend_store, load_trend_store_for_part,
+    load_trend_store_from_file, store_analyze_result, AnalyzeOptions, TrendDataFilter, TrendStore,
+    TrendStorePart,
 };
 
 use super::common::{connect_db, Cmd, CmdResult};
@@ -289,28 +298,27 @@ impl Cmd for TrendStoreRenameTrend {
     async fn run(&self) -> CmdResult {
         let mut client = connect_db().await?;
 
-        let transaction = client.transaction().await?;
+        let mut transaction = client.transaction().await?;
 
         let query = concat!(
-            "UPDATE trend_directory.table_trend ",
-            "SET name = $3 ",
-            "FROM trend_directory.trend_store_part tsp ",
-            "WHERE tsp.id = trend_store_part_id AND tsp.name = $1 AND table_trend.name = $2"
+            "SELECT 1 FROM trend_directory.table_trend tt ",
+            "JOIN trend_directory.trend_store_part tsp ON tsp.id = tt.trend_store_part_id ",
+            "WHERE tsp.name = $1 AND tt.name = $2"
         );
 
-        let update_count = transaction
-            .execute(query, &[&self.trend_store_part, &self.from, &self.to])
+        let rows = transaction
+            .query(query, &[&self.trend_store_part, &self.from])
             .await
             .map_err(|e| {
                 Error::Runtime(RuntimeError {
                     msg: format!(
-                        "Error renaming trend '{}' of trend store part '{}': {e}",
+                        "Error looking up trend '{}' of trend store part '{}': {e}",
                         &self.from, &self.trend_store_part
                     ),
                 })
             })?;
 
-        if update_count == 0 {
+        if rows.is_empty() {
             return Err(Error::Runtime(RuntimeError {
                 msg: format!(
                     "No trend found matching trend store part name '{}' and name '{}'",
@@ -319,12 +327,17 @@ impl Cmd for TrendStoreRenameTrend {
             }));
         }
 
+        let change = RenameTrend {
+            trend_store_part_name: self.trend_store_part.clone(),
+            from_name: self.from.clone(),
+            to_name: self.to.clone(),
+        };
+
+        let message = change.apply(&mut transaction).await?;
+
         transaction.commit().await?;
 
-        println!(
-            "Renamed {}.{} -> {}.{}",
-            self.trend_store_part, self.from, self.trend_store_part, self.to
-        );
+        println!("{message}");
 
         Ok(())
     }
@@ -334,6 +347,31 @@ impl Cmd for TrendStoreRenameTrend {
 pub struct TrendStorePartAnalyze {
     #[arg(help = "name of trend store part")]
     name: String,
+    #[arg(
+        long,
+        help="start of time range to analyze (inclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    from: Option<DateTime<FixedOffset>>,
+    #[arg(
+        long,
+        help="end of time range to analyze (exclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    to: Option<DateTime<FixedOffset>>,
+    #[arg(long, help = "percentage of the data to sample")]
+    sample: Option<f64>,
+    #[arg(
+        long = "trend",
+        help = "trend to analyze, all trends when not specified"
+    )]
+    trends: Vec<String>,
+    #[arg(long, help = "store the results in the database")]
+    store: bool,
+}
+
+fn format_timestamp(timestamp: Option<DateTime<Utc>>) -> String {
+    timestamp.map_or("N/A".to_string(), |t| t.to_rfc3339())
 }
 
 #[async_trait]
@@ -341,9 +379,23 @@ impl Cmd for TrendStorePartAnalyze {
     async fn run(&self) -> CmdResult {
         let mut client = connect_db().await?;
 
-        let result = analyze_trend_store_part(&mut client, &self.name).await?;
+        let options = AnalyzeOptions {
+            trend_names: match self.trends.is_empty() {
+                true => None,
+                false => Some(self.trends.clone()),
+            },
+            start: self.from.map(|t| t.with_timezone(&Utc)),
+            end: self.to.map(|t| t.with_timezone(&Utc)),
+            sample_percentage: self.sample,
+        };
+
+        let result = analyze_trend_store_part_with(&mut client, &self.name, &options).await?;
+
+        println!("Analyzed {} rows of '{}'", result.row_count, self.name);
 
-        println!("Analyzed '{}'", self.name);
+        if self.store {
+            store_analyze_result(&mut client, &self.name, &options, &result).await?;
+        }
 
         let mut table = Table::new();
         table.style = TableStyle::thin();
@@ -353,19 +405,30 @@ impl Cmd for TrendStorePartAnalyze {
             TableCell::new("Name"),
             TableCell::new("Min"),
             TableCell::new("Max"),
+            TableCell::new("Mean"),
+            TableCell::new("Nulls"),
+            TableCell::new("Distinct"),
+            TableCell::new("First"),
+            TableCell::new("Last"),
         ]));
 
+        let right_aligned = |value: &str| {
+            TableCell::builder(value)
+                .col_span(1)
+                .alignment(Alignment::Right)
+                .build()
+        };
+
         for stat in result.trend_stats {
             table.add_row(Row::new(vec![
                 TableCell::new(&stat.name),
-                TableCell::builder(&stat.min_value.unwrap_or("N/A".into()))
-                    .col_span(1)
-                    .alignment(Alignment::Right)
-                    .build(),
-                TableCell::builder(&stat.max_value.unwrap_or("N/A".into()))
-                    .col_span(1)
-                    .alignment(Alignment::Right)
-                    .build(),
+                right_aligned(&stat.min_value.unwrap_or("N/A".into())),
+                right_aligned(&stat.max_value.unwrap_or("N/A".into())),
+                right_aligned(&stat.mean.map_or("N/A".into(), |mean| mean.to_string())),
+                right_aligned(&stat.null_count.to_string()),
+                right_aligned(&stat.distinct_count.to_string()),
+                TableCell::new(format_timestamp(stat.first_timestamp)),
+                TableCell::new(format_timestamp(stat.last_timestamp)),
             ]));
         }
 
@@ -383,7 +446,7 @@ pub struct TrendStorePartOpt {
 
 #[derive(Debug, Subcommand, PartialEq)]
 pub enum TrendStorePartOptCommands {
-    #[command(about = "analyze range of values for trends in a trend store part")]
+    #[command(about = "analyze the values of trends in a trend store part")]
     Analyze(TrendStorePartAnalyze),
 }
 
@@ -448,6 +511,83 @@ impl Cmd for TrendStoreDeleteTimestamp {
     }
 }
 
+#[derive(Debug, Parser, PartialEq)]
+pub struct TrendStoreRepartition {
+    #[arg(help = "data source of trend store to repartition")]
+    data_source: String,
+    #[arg(help = "entity type of trend store to repartition")]
+    entity_type: String,
+    #[arg(
+        help="granularity of trend store to repartition",
+        value_parser=humantime::parse_duration
+    )]
+    granularity: Duration,
+    #[arg(
+        help="new partition size",
+        value_parser=humantime::parse_duration
+    )]
+    partition_size: Duration,
+    #[arg(short, long, help = "repartition without asking for confirmation")]
+    yes: bool,
+}
+
+#[async_trait]
+impl Cmd for TrendStoreRepartition {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let trend_store = load_trend_store(
+            &client,
+            &self.data_source,
+            &self.entity_type,
+            &self.granularity,
+        )
+        .await?;
+
+        if trend_store.partition_size == self.partition_size {
+            println!(
+                "{} already has partition size {}",
+                &trend_store,
+                humantime::format_duration(self.partition_size)
+            );
+
+            return Ok(());
+        }
+
+        let change = RepartitionTrendStore {
+            trend_store: trend_store.clone(),
+            from_partition_size: trend_store.partition_size,
+            to_partition_size: self.partition_size,
+        };
+
+        println!("{change}");
+        println!("All data is moved in one transaction and is not visible until it completes.");
+
+        if !self.yes
+            && !Confirm::new()
+                .with_prompt("Repartition trend store?")
+                .interact()
+                .map_err(|e| {
+                    Error::Runtime(RuntimeError {
+                        msg: format!("Could not process input: {e}"),
+                    })
+                })?
+        {
+            return Ok(());
+        }
+
+        let mut transaction = client.transaction().await?;
+
+        let message = change.apply(&mut transaction).await?;
+
+        transaction.commit().await?;
+
+        println!("{message}");
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Parser, PartialEq)]
 pub struct TrendStoreDump {
     #[arg(help = "data source of trend store to dump")]
@@ -482,6 +622,372 @@ impl Cmd for TrendStoreDump {
     }
 }
 
+#[derive(Debug, Parser, PartialEq)]
+pub struct TrendStoreExport {
+    #[arg(long, help = "name of trend store part to export")]
+    part: Option<String>,
+    #[arg(
+        long,
+        help = "data source of trend store to export",
+        conflicts_with = "part",
+        requires_all = ["entity_type", "granularity"]
+    )]
+    data_source: Option<String>,
+    #[arg(long, help = "entity type of trend store to export")]
+    entity_type: Option<String>,
+    #[arg(
+        long,
+        help="granularity of trend store to export",
+        value_parser=humantime::parse_duration
+    )]
+    granularity: Option<Duration>,
+    #[arg(
+        long,
+        help="start of time range to export (inclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    from: DateTime<FixedOffset>,
+    #[arg(
+        long,
+        help="end of time range to export (exclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    to: DateTime<FixedOffset>,
+    #[arg(
+        long = "trend",
+        help = "trend to export, all trends when not specified"
+    )]
+    trends: Vec<String>,
+    #[arg(
+        long = "entity",
+        help = "entity to export, all entities when not specified"
+    )]
+    entities: Vec<String>,
+    #[arg(long, help = "name of the entity column, defaults to the entity type")]
+    entity_column: Option<String>,
+    #[arg(
+        long,
+        help = "output format: csv or parquet",
+        default_value = "csv"
+    )]
+    format: ExportFormat,
+    #[arg(long, help = "file to write to, standard output when not specified")]
+    output: Option<PathBuf>,
+}
+
+impl TrendStoreExport {
+    async fn load_trend_store(&self, client: &tokio_postgres::Client) -> Result<TrendStore, Error> {
+        match (
+            &self.part,
+            &self.data_source,
+            &self.entity_type,
+            &self.granularity,
+        ) {
+            (Some(part), _, _, _) => load_trend_store_for_part(client, part).await,
+            (None, Some(data_source), Some(entity_type), Some(granularity)) => {
+                load_trend_store(client, data_source, entity_type, granularity).await
+            }
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(
+                "Specify a trend store part or a data source, entity type and granularity"
+                    .to_string(),
+            ))),
+        }
+    }
+
+    /// Select the part to export from: the part specified by name, or the
+    /// single part of the trend store that contains all specified trends.
+    fn select_part<'a>(&self, trend_store: &'a TrendStore) -> Result<&'a TrendStorePart, Error> {
+        if let Some(part) = &self.part {
+            return trend_store
+                .parts
+                .iter()
+                .find(|p| &p.name == part)
+                .ok_or_else(|| {
+                    Error::Runtime(RuntimeError::from_msg(format!(
+                        "No trend store part '{part}'"
+                    )))
+                });
+        }
+
+        let candidates: Vec<&TrendStorePart> = trend_store
+            .parts
+            .iter()
+            .filter(|part| {
+                self.trends
+                    .iter()
+                    .all(|trend_name| part.trends.iter().any(|t| &t.name == trend_name))
+            })
+            .collect();
+
+        match candidates.as_slice() {
+            [part] => Ok(part),
+            [] => Err(Error::Runtime(RuntimeError::from_msg(
+                "No trend store part contains all specified trends".to_string(),
+            ))),
+            _ => Err(Error::Runtime(RuntimeError::from_msg(format!(
+                "Multiple trend store parts match, specify one with --part: {}",
+                candidates
+                    .iter()
+                    .map(|part| part.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )))),
+        }
+    }
+}
+
+#[async_trait]
+impl Cmd for TrendStoreExport {
+    async fn run(&self) -> CmdResult {
+        let client = connect_db().await?;
+
+        let trend_store = self.load_trend_store(&client).await?;
+        let trend_store_part = self.select_part(&trend_store)?;
+
+        let mut export = TrendExport::new(&trend_store, trend_store_part);
+
+        if !self.trends.is_empty() {
+            export.trend_names.clone_from(&self.trends);
+        }
+
+        if let Some(entity_column) = &self.entity_column {
+            export.entity_column.clone_from(entity_column);
+        }
+
+        let entity_ids = match self.entities.is_empty() {
+            true => None,
+            false => {
+                Some(lookup_entity_ids(&client, &trend_store.entity_type, &self.entities).await?)
+            }
+        };
+
+        let filter = TrendDataFilter {
+            entity_ids: entity_ids.as_deref(),
+            start: self.from.with_timezone(&Utc),
+            end: self.to.with_timezone(&Utc),
+        };
+
+        let row_count = match &self.output {
+            Some(path) => {
+                let file = std::fs::File::create(path).map_err(|e| {
+                    RuntimeError::from_msg(format!(
+                        "Could not create file '{}': {e}",
+                        path.display()
+                    ))
+                })?;
+
+                export_trend_data(
+                    &client,
+                    &export,
+                    &filter,
+                    self.format,
+                    std::io::BufWriter::new(file),
+                )
+                .await?
+            }
+            None => {
+                export_trend_data(
+                    &client,
+                    &export,
+                    &filter,
+                    self.format,
+                    std::io::BufWriter::new(std::io::stdout()),
+                )
+                .await?
+            }
+        };
+
+        eprintln!("Exported {row_count} rows from '{}'", trend_store_part.name);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TrendStoreCompleteness {
+    #[arg(help = "name of trend store part")]
+    part: String,
+    #[arg(
+        long,
+        help="start of time range to check (inclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    from: DateTime<FixedOffset>,
+    #[arg(
+        long,
+        help="end of time range to check (exclusive), defaults to now",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    to: Option<DateTime<FixedOffset>>,
+    #[arg(
+        long,
+        help = "number of days before each timestamp used for the baseline entity count",
+        default_value_t = 7
+    )]
+    baseline_days: u64,
+    #[arg(
+        long,
+        help = "fraction of the baseline below which a timestamp is partial",
+        default_value_t = 0.9
+    )]
+    threshold: f64,
+    #[arg(
+        long,
+        help = "only check for loaded timestamps, without counting entities"
+    )]
+    quick: bool,
+    #[arg(long, help = "only show missing and partial timestamps")]
+    gaps_only: bool,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+}
+
+#[async_trait]
+impl Cmd for TrendStoreCompleteness {
+    async fn run(&self) -> CmdResult {
+        let client = connect_db().await?;
+
+        let trend_store = load_trend_store_for_part(&client, &self.part).await?;
+
+        let options = CompletenessOptions {
+            start: self.from.with_timezone(&Utc),
+            end: self.to.map_or(Utc::now(), |t| t.with_timezone(&Utc)),
+            baseline_period: Duration::from_secs(self.baseline_days * 86400),
+            partial_threshold: self.threshold,
+            quick: self.quick,
+            timezone: configured_timezone()?,
+        };
+
+        let mut report =
+            trend_store_part_completeness(&client, &trend_store, &self.part, &options).await?;
+
+        if self.gaps_only {
+            report.timestamps = report.gaps().cloned().collect();
+        }
+
+        match self.format {
+            ReportFormat::Json => {
+                let json = serde_json::to_string_pretty(&report).map_err(|e| {
+                    RuntimeError::from_msg(format!("Could not serialize report: {e}"))
+                })?;
+
+                println!("{json}");
+            }
+            ReportFormat::Table => {
+                let mut table = comfy_table::Table::new();
+                let style = "     ═╪ ┆          ";
+                table.load_preset(style);
+                table.set_header(vec![
+                    "Timestamp",
+                    "Entities",
+                    "Baseline",
+                    "Modified",
+                    "Status",
+                ]);
+
+                for t in &report.timestamps {
+                    table.add_row(vec![
+                        t.timestamp.to_rfc3339(),
+                        t.entity_count.map_or("".to_string(), |c| c.to_string()),
+                        t.baseline.map_or("".to_string(), |b| b.to_string()),
+                        t.modified.map_or("".to_string(), |m| m.to_rfc3339()),
+                        t.status.to_string(),
+                    ]);
+                }
+
+                println!("{table}");
+
+                println!(
+                    "{} of {} timestamps missing or partial",
+                    report.gaps().count(),
+                    report.timestamps.len()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TrendStoreMigrateDataTypes {
+    #[arg(help = "trend store definition file")]
+    definition: PathBuf,
+    #[arg(long, help = "only show the migration plan")]
+    dry_run: bool,
+}
+
+#[async_trait]
+impl Cmd for TrendStoreMigrateDataTypes {
+    async fn run(&self) -> CmdResult {
+        let trend_store = load_trend_store_from_file(&self.definition)?;
+
+        let mut client = connect_db().await?;
+
+        let trend_store_db = load_trend_store(
+            &client,
+            &trend_store.data_source,
+            &trend_store.entity_type,
+            &trend_store.granularity,
+        )
+        .await
+        .map_err(|e| {
+            Error::Runtime(RuntimeError {
+                msg: format!("Error loading trend store: {e}"),
+            })
+        })?;
+
+        let mut migration_count = 0;
+
+        for part in &trend_store.parts {
+            let part_db = match trend_store_db.parts.iter().find(|p| p.name == part.name) {
+                Some(part_db) => part_db,
+                None => continue,
+            };
+
+            let modifications = data_type_modifications(part_db, part);
+
+            if modifications.is_empty() {
+                continue;
+            }
+
+            migration_count += 1;
+
+            let plan = plan_data_type_migration(&mut client, &part.name, modifications).await?;
+
+            println!("{plan}");
+
+            if self.dry_run {
+                continue;
+            }
+
+            if !plan.is_safe() {
+                println!("Skipping '{}': values do not fit", &part.name);
+                continue;
+            }
+
+            if !plan.dependents.is_empty() {
+                println!("Skipping '{}': trends are used by other objects", &part.name);
+                continue;
+            }
+
+            migrate_data_types(&mut client, &plan, |step| println!("{step}")).await?;
+        }
+
+        if migration_count == 0 {
+            println!("No data type changes");
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Parser, PartialEq)]
 pub struct TrendStoreOpt {
     #[command(subcommand)]
@@ -512,6 +1018,14 @@ pub enum TrendStoreOptCommands {
     DeleteTimestamp(TrendStoreDeleteTimestamp),
     #[command(about = "dump the definition of a trend store")]
     Dump(TrendStoreDump),
+    #[command(about = "change the partition size of a trend store in one transaction")]
+    Repartition(TrendStoreRepartition),
+    #[command(about = "export trend data of a trend store part")]
+    Export(TrendStoreExport),
+    #[command(about = "change trend data types one partition at a time")]
+    MigrateDataTypes(TrendStoreMigrateDataTypes),
+    #[command(about = "report missing and partial timestamps of a trend store part")]
+    Completeness(TrendStoreCompleteness),
 }
 
 impl TrendStoreOpt {
@@ -537,6 +1051,12 @@ impl TrendStoreOpt {
                 delete_timestamp.run().await
             }
             TrendStoreOptCommands::Dump(dump) => dump.run().await,
+            TrendStoreOptCommands::Repartition(repartition) => repartition.run().await,
+            TrendStoreOptCommands::Export(export) => export.run().await,
+            TrendStoreOptCommands::MigrateDataTypes(migrate_data_types) => {
+                migrate_data_types.run().await
+            }
+            TrendStoreOptCommands::Completeness(completeness) => completeness.run().await,
         }
     }
 }
@@ -1,20 +1,32 @@
+use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use async_trait::async_trait;
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use clap::{Parser, Subcommand};
 
 use comfy_table::Table;
 
 use minerva::change::Change;
-use minerva::error::{DatabaseError, Error, RuntimeError};
+use minerva::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
+use minerva::granularity::configured_timezone;
+use minerva::instance::MinervaInstance;
 use minerva::trigger::{
-    dump_trigger, get_notifications, list_triggers, load_trigger, load_trigger_from_file,
-    AddTrigger, CreateNotifications, DeleteTrigger, DisableTrigger, EnableTrigger, RenameTrigger,
-    UpdateTrigger, VerifyTrigger,
+    dump_trigger, get_notifications, list_triggers, load_flapping_entities, load_trigger,
+    load_trigger_from_file, AddTrigger, CreateNotifications, DeleteTrigger, DisableTrigger,
+    EnableTrigger, RenameTrigger, UpdateTrigger, VerifyTrigger,
 };
+use minerva::trigger_backtest::backtest_trigger;
+use minerva::trigger_exception::{
+    add_threshold_exception, add_weight_exception, load_trigger_exceptions, remove_exception,
+    ExceptionKind, ThresholdException, WeightException,
+};
+use minerva::trigger_lint::lint_trigger_file;
+use minerva::trigger_runner::{load_scheduled_triggers, run_trigger, TriggerRunOptions};
 
-use super::common::{connect_db, Cmd, CmdResult};
+use super::common::{connect_db, Cmd, CmdResult, ENV_MINERVA_INSTANCE_ROOT};
+use super::trendstore::ReportFormat;
 
 #[derive(Debug, Parser, PartialEq)]
 pub struct TriggerList {}
@@ -353,6 +365,8 @@ impl Cmd for TriggerCreateNotifications {
 
 #[derive(Debug, Parser, PartialEq)]
 pub struct TriggerDump {
+    #[arg(long = "with-exceptions", help = "include entity specific exceptions")]
+    with_exceptions: bool,
     #[arg(help = "trigger name")]
     name: String,
 }
@@ -362,7 +376,11 @@ impl Cmd for TriggerDump {
     async fn run(&self) -> CmdResult {
         let mut client = connect_db().await?;
 
-        let trigger = load_trigger(&mut client, &self.name).await?;
+        let mut trigger = load_trigger(&mut client, &self.name).await?;
+
+        if self.with_exceptions {
+            trigger.exceptions = Some(load_trigger_exceptions(&mut client, &self.name).await?);
+        }
 
         let trigger_definition = dump_trigger(&trigger);
 
@@ -372,6 +390,491 @@ impl Cmd for TriggerDump {
     }
 }
 
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerBacktest {
+    #[arg(
+        long,
+        help="start of time range to evaluate (inclusive)",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    from: DateTime<FixedOffset>,
+    #[arg(
+        long,
+        help="end of time range to evaluate (exclusive), defaults to now",
+        value_parser=DateTime::parse_from_rfc3339
+    )]
+    to: Option<DateTime<FixedOffset>>,
+    #[arg(long, help = "number of entities to show", default_value_t = 10)]
+    top: usize,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+    #[arg(help = "trigger name or definition file")]
+    trigger: String,
+}
+
+#[async_trait]
+impl Cmd for TriggerBacktest {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let start = self.from.with_timezone(&Utc);
+        let end = self.to.map_or(Utc::now(), |t| t.with_timezone(&Utc));
+
+        // Nothing is committed, so a trigger from a definition file only exists
+        // for the duration of the backtest
+        let mut tx = client.transaction().await?;
+
+        let definition = PathBuf::from(&self.trigger);
+
+        let trigger_name = match definition.is_file() {
+            true => {
+                let trigger = load_trigger_from_file(&definition)?;
+                let trigger_name = trigger.name.clone();
+
+                let change = AddTrigger {
+                    trigger,
+                    verify: false,
+                };
+
+                change.apply(&mut tx).await?;
+
+                trigger_name
+            }
+            false => self.trigger.clone(),
+        };
+
+        let report = backtest_trigger(
+            &tx,
+            &trigger_name,
+            start,
+            end,
+            &configured_timezone()?,
+            self.top,
+        )
+        .await?;
+
+        tx.rollback().await?;
+
+        match self.format {
+            ReportFormat::Json => {
+                let json = serde_json::to_string_pretty(&report).map_err(|e| {
+                    RuntimeError::from_msg(format!("Could not serialize report: {e}"))
+                })?;
+
+                println!("{json}");
+            }
+            ReportFormat::Table => {
+                let style = "     ═╪ ┆          ";
+
+                let mut table = Table::new();
+                table.load_preset(style);
+                table.set_header(vec!["Timestamp", "Notifications", "Total Weight"]);
+
+                for t in &report.timestamps {
+                    table.add_row(vec![
+                        t.timestamp.to_rfc3339(),
+                        t.notification_count.to_string(),
+                        t.total_weight.to_string(),
+                    ]);
+                }
+
+                println!("{table}");
+
+                let mut table = Table::new();
+                table.load_preset(style);
+                table.set_header(vec!["Entity", "Notifications", "Max Weight"]);
+
+                for entity in &report.top_entities {
+                    table.add_row(vec![
+                        entity
+                            .entity
+                            .clone()
+                            .unwrap_or(entity.entity_id.to_string()),
+                        entity.notification_count.to_string(),
+                        entity.max_weight.to_string(),
+                    ]);
+                }
+
+                println!("{table}");
+
+                let mut table = Table::new();
+                table.load_preset(style);
+                table.set_header(vec!["Weight", "Notifications"]);
+
+                for (weight, count) in &report.weight_distribution {
+                    table.add_row(vec![weight.to_string(), count.to_string()]);
+                }
+
+                println!("{table}");
+
+                println!(
+                    "{} notifications over {} timestamps",
+                    report.notification_count,
+                    report.timestamps.len()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerLint {
+    #[arg(
+        long = "instance-root",
+        help = "Minerva instance definition root directory to check trend store links against, defaults to MINERVA_INSTANCE_ROOT"
+    )]
+    instance_root: Option<PathBuf>,
+    #[arg(required = true, help = "trigger definition files")]
+    definitions: Vec<PathBuf>,
+}
+
+#[async_trait]
+impl Cmd for TriggerLint {
+    async fn run(&self) -> CmdResult {
+        let instance_root = self
+            .instance_root
+            .clone()
+            .or_else(|| env::var(ENV_MINERVA_INSTANCE_ROOT).ok().map(PathBuf::from));
+
+        let trend_stores = instance_root
+            .map(|root| MinervaInstance::load_from(&root).map(|instance| instance.trend_stores))
+            .transpose()?;
+
+        let mut issue_count = 0;
+
+        for definition in &self.definitions {
+            let issues = lint_trigger_file(definition, trend_stores.as_deref())?;
+
+            for issue in &issues {
+                println!("{}:{issue}", definition.display());
+            }
+
+            issue_count += issues.len();
+        }
+
+        if issue_count > 0 {
+            return Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Found {issue_count} issue(s) in trigger definitions"
+            ))));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerRun {
+    #[arg(
+        long = "trigger",
+        help = "only run the specified triggers instead of all enabled triggers"
+    )]
+    triggers: Vec<String>,
+    #[arg(
+        long,
+        help = "keep running and check for triggers to evaluate at this interval",
+        value_parser = humantime::parse_duration
+    )]
+    interval: Option<Duration>,
+    #[arg(
+        long,
+        help = "maximum period before now to catch up since the last evaluation of a trigger",
+        value_parser = humantime::parse_duration,
+        default_value = "7days"
+    )]
+    max_catch_up: Duration,
+}
+
+impl TriggerRun {
+    async fn run_once(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let triggers = load_scheduled_triggers(&client, &self.triggers).await?;
+
+        let options = TriggerRunOptions {
+            max_catch_up: self.max_catch_up,
+            timezone: configured_timezone()?,
+        };
+
+        for trigger in triggers {
+            // A failing trigger should not keep the other triggers from running
+            match run_trigger(&mut client, &trigger, Utc::now(), &options).await {
+                Ok(evaluations) => {
+                    for evaluation in evaluations {
+                        println!(
+                            "{} notifications for trigger '{}' at {}{}",
+                            evaluation.notification_count,
+                            evaluation.trigger,
+                            evaluation.timestamp.to_rfc3339(),
+                            match evaluation.reevaluated {
+                                true => " (source data changed)",
+                                false => "",
+                            }
+                        );
+                    }
+                }
+                Err(e) => {
+                    println!("Error running trigger '{}': {e}", trigger.name);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Cmd for TriggerRun {
+    async fn run(&self) -> CmdResult {
+        match self.interval {
+            None => self.run_once().await,
+            Some(interval) => loop {
+                if let Err(e) = self.run_once().await {
+                    println!("Error running triggers: {e}");
+                }
+
+                tokio::time::sleep(interval).await;
+            },
+        }
+    }
+}
+
+fn parse_threshold_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{s}'"))
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerExceptionAdd {
+    #[arg(
+        long = "threshold",
+        help = "threshold override as NAME=VALUE",
+        value_parser = parse_threshold_value
+    )]
+    thresholds: Vec<(String, String)>,
+    #[arg(long, help = "weight override")]
+    weight: Option<i32>,
+    #[arg(
+        long,
+        help = "start of the validity period",
+        value_parser = DateTime::parse_from_rfc3339
+    )]
+    start: Option<DateTime<FixedOffset>>,
+    #[arg(
+        long,
+        help = "end of the validity period",
+        value_parser = DateTime::parse_from_rfc3339
+    )]
+    expires: Option<DateTime<FixedOffset>>,
+    #[arg(long, help = "reason for the threshold exception")]
+    remark: Option<String>,
+    #[arg(help = "trigger name")]
+    trigger: String,
+    #[arg(help = "entity name")]
+    entity: String,
+}
+
+#[async_trait]
+impl Cmd for TriggerExceptionAdd {
+    async fn run(&self) -> CmdResult {
+        if self.thresholds.is_empty() && self.weight.is_none() {
+            return Err(Error::Runtime(RuntimeError::from_msg(
+                "No threshold or weight override specified".to_string(),
+            )));
+        }
+
+        let start = self.start.map(|start| start.with_timezone(&Utc));
+        let expires = self.expires.map(|expires| expires.with_timezone(&Utc));
+
+        let mut client = connect_db().await?;
+
+        let mut tx = client.transaction().await?;
+
+        if !self.thresholds.is_empty() {
+            let exception = ThresholdException {
+                id: None,
+                entity: self.entity.clone(),
+                start,
+                expires,
+                remark: self.remark.clone(),
+                thresholds: self.thresholds.iter().cloned().collect(),
+            };
+
+            let id = add_threshold_exception(&mut tx, &self.trigger, &exception).await?;
+
+            println!("Added threshold exception {id} for '{}'", &self.entity);
+        }
+
+        if let Some(weight) = self.weight {
+            let exception = WeightException {
+                id: None,
+                entity: self.entity.clone(),
+                start,
+                expires,
+                weight,
+            };
+
+            let id = add_weight_exception(&mut tx, &self.trigger, &exception).await?;
+
+            println!("Added weight exception {id} for '{}'", &self.entity);
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerExceptionList {
+    #[arg(help = "trigger name")]
+    trigger: String,
+}
+
+fn format_bound(bound: Option<DateTime<Utc>>) -> String {
+    bound.map(|bound| bound.to_rfc3339()).unwrap_or_default()
+}
+
+#[async_trait]
+impl Cmd for TriggerExceptionList {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let exceptions = load_trigger_exceptions(&mut client, &self.trigger).await?;
+
+        let mut table = Table::new();
+        let style = "     ═╪ ┆          ";
+        table.load_preset(style);
+        table.set_header(vec![
+            "Kind", "Id", "Entity", "Start", "Expires", "Override", "Remark",
+        ]);
+
+        for exception in exceptions.threshold {
+            table.add_row(vec![
+                ExceptionKind::Threshold.to_string(),
+                exception.id.unwrap_or_default().to_string(),
+                exception.entity,
+                format_bound(exception.start),
+                format_bound(exception.expires),
+                exception
+                    .thresholds
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                exception.remark.unwrap_or_default(),
+            ]);
+        }
+
+        for exception in exceptions.weight {
+            table.add_row(vec![
+                ExceptionKind::Weight.to_string(),
+                exception.id.unwrap_or_default().to_string(),
+                exception.entity,
+                format_bound(exception.start),
+                format_bound(exception.expires),
+                exception.weight.to_string(),
+                String::new(),
+            ]);
+        }
+
+        println!("{table}");
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerExceptionRemove {
+    #[arg(help = "trigger name")]
+    trigger: String,
+    #[arg(help = "kind of exception: threshold or weight")]
+    kind: ExceptionKind,
+    #[arg(help = "exception Id")]
+    id: i32,
+}
+
+#[async_trait]
+impl Cmd for TriggerExceptionRemove {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let removed = remove_exception(&mut client, &self.trigger, self.kind, self.id).await?;
+
+        match removed {
+            true => println!("Removed {} exception {}", self.kind, self.id),
+            false => {
+                return Err(Error::Runtime(RuntimeError::from_msg(format!(
+                    "No {} exception with Id {} for trigger '{}'",
+                    self.kind, self.id, &self.trigger
+                ))))
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerFlapping {
+    #[arg(help = "trigger name")]
+    trigger: String,
+}
+
+#[async_trait]
+impl Cmd for TriggerFlapping {
+    async fn run(&self) -> CmdResult {
+        let mut client = connect_db().await?;
+
+        let flapping_entities = load_flapping_entities(&mut client, &self.trigger).await?;
+
+        let mut table = Table::new();
+        let style = "     ═╪ ┆          ";
+        table.load_preset(style);
+        table.set_header(vec!["Entity", "Since", "Changes"]);
+
+        for flapping_entity in flapping_entities {
+            table.add_row(vec![
+                flapping_entity.entity,
+                flapping_entity.since.to_rfc3339(),
+                flapping_entity.changes.to_string(),
+            ]);
+        }
+
+        println!("{table}");
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Parser, PartialEq)]
+pub struct TriggerException {
+    #[command(subcommand)]
+    command: TriggerExceptionCommands,
+}
+
+#[derive(Debug, Subcommand, PartialEq)]
+pub enum TriggerExceptionCommands {
+    #[command(about = "add an entity specific threshold or weight exception")]
+    Add(TriggerExceptionAdd),
+    #[command(about = "list the exceptions of a trigger")]
+    List(TriggerExceptionList),
+    #[command(about = "remove an exception")]
+    Remove(TriggerExceptionRemove),
+}
+
+impl TriggerException {
+    async fn run(&self) -> CmdResult {
+        match &self.command {
+            TriggerExceptionCommands::Add(add) => add.run().await,
+            TriggerExceptionCommands::List(list) => list.run().await,
+            TriggerExceptionCommands::Remove(remove) => remove.run().await,
+        }
+    }
+}
+
 #[derive(Debug, Parser, PartialEq)]
 pub struct TriggerOpt {
     #[command(subcommand)]
@@ -402,6 +905,16 @@ pub enum TriggerOptCommands {
     PreviewNotifications(TriggerPreviewNotifications),
     #[command(about = "create notifications of a trigger")]
     CreateNotifications(TriggerCreateNotifications),
+    #[command(about = "create notifications of enabled triggers for new source data")]
+    Run(TriggerRun),
+    #[command(about = "evaluate a trigger over historical data without creating notifications")]
+    Backtest(TriggerBacktest),
+    #[command(about = "check trigger definitions without a database")]
+    Lint(TriggerLint),
+    #[command(about = "manage entity specific exceptions of a trigger")]
+    Exception(TriggerException),
+    #[command(about = "list entities that alternate between firing and not firing")]
+    Flapping(TriggerFlapping),
 }
 
 impl TriggerOpt {
@@ -422,6 +935,11 @@ impl TriggerOpt {
             TriggerOptCommands::CreateNotifications(create_notifications) => {
                 create_notifications.run().await
             }
+            TriggerOptCommands::Run(run) => run.run().await,
+            TriggerOptCommands::Backtest(backtest) => backtest.run().await,
+            TriggerOptCommands::Lint(lint) => lint.run().await,
+            TriggerOptCommands::Exception(exception) => exception.run().await,
+            TriggerOptCommands::Flapping(flapping) => flapping.run().await,
         }
     }
 }
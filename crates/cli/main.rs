@@ -8,8 +8,10 @@ use crate::commands::attributestore::AttributeStoreOpt;
 use crate::commands::common::Cmd;
 use crate::commands::diff::DiffOpt;
 use crate::commands::dump::DumpOpt;
+use crate::commands::impact::ImpactOpt;
 use crate::commands::initialize::InitializeOpt;
 use crate::commands::loaddata::LoadDataOpt;
+use crate::commands::notification::NotificationOpt;
 use crate::commands::relation::RelationOpt;
 use crate::commands::schema::SchemaOpt;
 #[cfg(feature = "test-containers")]
@@ -52,6 +54,10 @@ enum Commands {
     LoadData(LoadDataOpt),
     #[command(about = "Manage relations")]
     Relation(RelationOpt),
+    #[command(about = "Manage notifications")]
+    Notification(NotificationOpt),
+    #[command(about = "Show everything that depends on a Minerva object")]
+    Impact(ImpactOpt),
     #[cfg(feature = "test-containers")]
     #[command(about = "Start Minerva instance")]
     Start(StartOpt),
@@ -88,6 +94,8 @@ async fn main() {
         }
         Some(Commands::LoadData(load_data)) => load_data.run().await,
         Some(Commands::Relation(relation)) => relation.run().await,
+        Some(Commands::Notification(notification)) => notification.run().await,
+        Some(Commands::Impact(impact)) => impact.run().await,
         #[cfg(feature = "test-containers")]
         Some(Commands::Start(start)) => start.run().await,
         None => return,
@@ -119,3 +119,33 @@ impl Drop for MinervaService {
         }
     }
 }
+
+/// Poll the job endpoint of the service until the job is no longer running and
+/// return the final job state
+pub async fn wait_for_job(
+    client: &reqwest::Client,
+    base_url: &str,
+    job_id: i64,
+) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let interval = Duration::from_millis(200);
+    let max_attempts = 600;
+
+    for _ in 0..max_attempts {
+        let body = client
+            .get(format!("{base_url}/jobs/{job_id}"))
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        let job: serde_json::Value = serde_json::from_str(&body)?;
+
+        if job["status"] != "running" {
+            return Ok(job);
+        }
+
+        tokio::time::sleep(interval).await;
+    }
+
+    Err(format!("Job {job_id} did not finish in time").into())
+}
@@ -12,7 +12,7 @@ mod tests {
     use minerva::schema::create_schema;
     use minerva::trend_store::TrendStore;
 
-    use crate::common::get_available_port;
+    use crate::common::{get_available_port, wait_for_job};
     use crate::common::{MinervaService, MinervaServiceConfig};
 
     const TREND_STORE_DEFINITION_15M: &str = r###"
@@ -146,7 +146,13 @@ mod tests {
 
         let response = client.post(url).body(request_body).send().await?;
 
-        let body = response.text().await?;
+        assert_eq!(response.status(), reqwest::StatusCode::ACCEPTED);
+
+        let body: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+
+        let job_id = body["job_id"].as_i64().expect("job id in response");
+
+        let job = wait_for_job(&client, &service.base_url(), job_id).await?;
 
         let (language, src): (String, String) = {
             let mut client = test_database.connect().await?;
@@ -156,10 +162,8 @@ mod tests {
                 .unwrap()
         };
 
-        assert_eq!(
-            body,
-            "{\"code\":200,\"message\":\"Successfully created KPI\"}"
-        );
+        assert_eq!(job["status"], "finished");
+        assert_eq!(job["log"], serde_json::json!(["Successfully created KPI"]));
 
         assert_eq!(language, "plpgsql");
 
@@ -12,7 +12,7 @@ mod tests {
     use minerva::changes::trend_store::AddTrendStore;
     use minerva::cluster::MinervaCluster;
     use minerva::schema::create_schema;
-    use minerva::trend_store::TrendStore;
+    use minerva::trend_store::{default_retention_period, TrendStore};
 
     use crate::common::{get_available_port, MinervaService, MinervaServiceConfig};
 
@@ -38,6 +38,7 @@ mod tests {
                 entity_type: "pvpanel".to_string(),
                 granularity: Duration::from_secs(300),
                 partition_size: Duration::from_secs(86400),
+                retention_period: default_retention_period(),
                 parts: [].to_vec(),
             };
 
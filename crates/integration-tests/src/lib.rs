@@ -6,6 +6,8 @@ pub mod entity_set;
 pub mod get_entity_types;
 pub mod initialize;
 pub mod load_data;
+pub mod service_health;
+pub mod trigger_notifications;
 
 static INIT: Once = Once::new();
 
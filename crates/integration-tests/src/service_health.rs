@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+    use std::path::PathBuf;
+
+    use log::debug;
+
+    use minerva::cluster::MinervaCluster;
+    use minerva::schema::create_schema;
+
+    use crate::common::{get_available_port, MinervaService, MinervaServiceConfig};
+
+    #[tokio::test]
+    async fn service_health() -> Result<(), Box<dyn std::error::Error>> {
+        crate::setup();
+
+        let config_file = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/postgresql.conf"));
+
+        let cluster = MinervaCluster::start(&config_file, 3).await?;
+
+        let test_database = cluster.create_db().await?;
+
+        debug!("Created database '{}'", test_database.name);
+
+        {
+            let service_address = Ipv4Addr::new(127, 0, 0, 1);
+            let service_port = get_available_port(service_address).unwrap();
+
+            let service_conf = MinervaServiceConfig {
+                pg_host: cluster.controller_host.to_string(),
+                pg_port: cluster.controller_port.to_string(),
+                pg_sslmode: "disable".to_string(),
+                pg_database: test_database.name.to_string(),
+                service_address: service_address.to_string(),
+                service_port,
+            };
+
+            let mut service = MinervaService::start(service_conf)?;
+
+            service.wait_for().await?;
+
+            let base_url = service.base_url();
+
+            let response = reqwest::get(format!("{base_url}/health/live")).await?;
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+            // Without the Minerva schema the service is not ready
+            let response = reqwest::get(format!("{base_url}/health/ready")).await?;
+            assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+
+            {
+                let mut client = test_database.connect().await?;
+
+                create_schema(&mut client).await?;
+            }
+
+            let response = reqwest::get(format!("{base_url}/health/ready")).await?;
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+            let response = reqwest::get(format!("{base_url}/metrics")).await?;
+            assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+            let body = response.text().await?;
+
+            assert!(body.contains(
+                "minerva_service_http_requests_total{method=\"GET\",route=\"/health/ready\",status=\"200\"} 1"
+            ));
+            assert!(body.contains("minerva_service_db_pool_max_size 16"));
+        }
+
+        let mut admin_client = cluster.connect_to_coordinator().await;
+
+        test_database.drop_database(&mut admin_client).await;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,336 @@
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use log::debug;
+
+    use minerva::cluster::MinervaCluster;
+    use minerva::schema::create_schema;
+
+    const RULE_NAME: &str = "test/15m/notifications";
+
+    /// Create a trigger notification store and a rule with a granularity of 15
+    /// minutes that uses it, without any KPI or condition functions.
+    async fn create_rule(
+        client: &mut tokio_postgres::Client,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        client
+            .execute(
+                "SELECT trigger.create_trigger_notification_store('integration_test')",
+                &[],
+            )
+            .await?;
+
+        client
+            .execute("SELECT trigger.define($1::name)", &[&RULE_NAME])
+            .await?;
+
+        client
+            .execute(
+                concat!(
+                    "UPDATE trigger.rule SET granularity = '15m', notification_store_id = ns.id ",
+                    "FROM notification_directory.notification_store ns ",
+                    "JOIN directory.data_source ds ON ds.id = ns.data_source_id ",
+                    "WHERE ds.name = 'integration_test' AND rule.name = $1::name"
+                ),
+                &[&RULE_NAME],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Insert a notification of the rule for each entity and timestamp
+    async fn insert_notifications(
+        client: &mut tokio_postgres::Client,
+        notifications: &[(i32, &str)],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for (entity_id, timestamp) in notifications {
+            client
+                .execute(
+                    concat!(
+                        "INSERT INTO notification.integration_test(entity_id, timestamp, created, rule_id, weight, details) ",
+                        "SELECT $1, $2::text::timestamptz, now(), id, 300, '' FROM trigger.rule WHERE name = $3::name"
+                    ),
+                    &[entity_id, timestamp, &RULE_NAME],
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the entity, timestamp and state of all notifications of the rule
+    async fn notification_states(
+        client: &mut tokio_postgres::Client,
+    ) -> Result<Vec<(i32, String, Option<String>)>, Box<dyn std::error::Error>> {
+        let rows = client
+            .query(
+                concat!(
+                    "SELECT n.entity_id, to_char(n.timestamp AT TIME ZONE 'UTC', 'HH24:MI'), s.state::text ",
+                    "FROM notification.integration_test n ",
+                    "LEFT JOIN notification_directory.notification_state s ON s.notification_id = n.id ",
+                    "ORDER BY n.entity_id, n.timestamp"
+                ),
+                &[],
+            )
+            .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| (row.get(0), row.get(1), row.get(2)))
+            .collect())
+    }
+
+    /// Notifications of the previous evaluation step are cleared for entities
+    /// for which the rule no longer fires, older notifications are kept.
+    #[tokio::test]
+    async fn clear_notifications_of_previous_step() -> Result<(), Box<dyn std::error::Error>> {
+        crate::setup();
+
+        let config_file = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/postgresql.conf"));
+
+        let cluster = MinervaCluster::start(&config_file, 3).await?;
+
+        debug!("Containers started");
+
+        let test_database = cluster.create_db().await?;
+
+        {
+            let mut client = test_database.connect().await?;
+            create_schema(&mut client).await?;
+            create_rule(&mut client).await?;
+
+            insert_notifications(
+                &mut client,
+                &[
+                    (1, "2024-01-01 10:00Z"),
+                    (1, "2024-01-01 10:15Z"),
+                    (2, "2024-01-01 10:15Z"),
+                ],
+            )
+            .await?;
+
+            // Entity 2 still fires at 10:30, entity 1 does not
+            client
+                .execute(
+                    concat!(
+                        "INSERT INTO trigger.rule_firing(rule_id, entity_id, timestamp) ",
+                        "SELECT id, 2, '2024-01-01 10:30Z' FROM trigger.rule WHERE name = $1::name"
+                    ),
+                    &[&RULE_NAME],
+                )
+                .await?;
+
+            let row = client
+                .query_one(
+                    concat!(
+                        "SELECT trigger.clear_notifications(rule, notification_store, '2024-01-01 10:30Z') ",
+                        "FROM trigger.rule ",
+                        "JOIN notification_directory.notification_store ON notification_store.id = rule.notification_store_id ",
+                        "WHERE rule.name = $1::name"
+                    ),
+                    &[&RULE_NAME],
+                )
+                .await?;
+
+            let cleared_count: i32 = row.get(0);
+
+            assert_eq!(cleared_count, 1);
+
+            let states = notification_states(&mut client).await?;
+
+            assert_eq!(
+                states,
+                vec![
+                    (1, "10:00".to_string(), None),
+                    (1, "10:15".to_string(), Some("cleared".to_string())),
+                    (2, "10:15".to_string(), None),
+                ]
+            );
+        }
+
+        let mut admin_client = cluster.connect_to_coordinator().await;
+
+        test_database.drop_database(&mut admin_client).await;
+
+        Ok(())
+    }
+
+    /// Return the entities that remain in the staging table after
+    /// deduplication of the notifications at 10:15 with the given mode. Both
+    /// entities have an uncleared notification with weight 300 at 10:00, and
+    /// the weight of entity 2 increases to 500.
+    async fn deduplicated_entities(
+        client: &mut tokio_postgres::Client,
+        mode: &str,
+    ) -> Result<Vec<i32>, Box<dyn std::error::Error>> {
+        let tx = client.transaction().await?;
+
+        tx.execute(
+            concat!(
+                "UPDATE trigger.rule SET deduplication_window = '1h', ",
+                "deduplication_mode = $1::text::trigger.deduplication_mode_enum ",
+                "WHERE name = $2::name"
+            ),
+            &[&mode, &RULE_NAME],
+        )
+        .await?;
+
+        tx.execute(
+            concat!(
+                "INSERT INTO notification.integration_test(entity_id, timestamp, created, rule_id, weight, details) ",
+                "SELECT e, '2024-01-01 10:00Z', now(), id, 300, '' ",
+                "FROM trigger.rule, (VALUES (1), (2)) v(e) WHERE name = $1::name"
+            ),
+            &[&RULE_NAME],
+        )
+        .await?;
+
+        tx.execute(
+            concat!(
+                "INSERT INTO notification.integration_test_staging(entity_id, timestamp, created, rule_id, weight, details) ",
+                "SELECT e, '2024-01-01 10:15Z', now(), id, w, '' ",
+                "FROM trigger.rule, (VALUES (1, 300), (2, 500)) v(e, w) WHERE name = $1::name"
+            ),
+            &[&RULE_NAME],
+        )
+        .await?;
+
+        tx.execute(
+            concat!(
+                "SELECT trigger.deduplicate_notifications(rule, notification_store, '2024-01-01 10:15Z') ",
+                "FROM trigger.rule ",
+                "JOIN notification_directory.notification_store ON notification_store.id = rule.notification_store_id ",
+                "WHERE rule.name = $1::name"
+            ),
+            &[&RULE_NAME],
+        )
+        .await?;
+
+        let rows = tx
+            .query(
+                "SELECT entity_id FROM notification.integration_test_staging ORDER BY entity_id",
+                &[],
+            )
+            .await?;
+
+        // The transaction is rolled back on drop, so each mode starts from an
+        // empty notification store
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }
+
+    /// In window mode, any uncleared notification within the window
+    /// suppresses a new one. In weight_increase mode, a new notification with
+    /// a higher weight is kept.
+    #[tokio::test]
+    async fn deduplicate_notifications_by_mode() -> Result<(), Box<dyn std::error::Error>> {
+        crate::setup();
+
+        let config_file = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/postgresql.conf"));
+
+        let cluster = MinervaCluster::start(&config_file, 3).await?;
+
+        debug!("Containers started");
+
+        let test_database = cluster.create_db().await?;
+
+        {
+            let mut client = test_database.connect().await?;
+            create_schema(&mut client).await?;
+            create_rule(&mut client).await?;
+
+            let remaining = deduplicated_entities(&mut client, "window").await?;
+
+            assert_eq!(remaining, Vec::<i32>::new());
+
+            let remaining = deduplicated_entities(&mut client, "weight_increase").await?;
+
+            assert_eq!(remaining, vec![2]);
+        }
+
+        let mut admin_client = cluster.connect_to_coordinator().await;
+
+        test_database.drop_database(&mut admin_client).await;
+
+        Ok(())
+    }
+
+    /// Entities are flapping when the number of changes between firing and
+    /// not firing within the window reaches the threshold. The window of 1
+    /// hour before 11:00 contains the evaluations at 10:15, 10:30, 10:45 and
+    /// 11:00, so a firing at 10:00 is not counted.
+    #[tokio::test]
+    async fn detect_flapping_at_threshold() -> Result<(), Box<dyn std::error::Error>> {
+        crate::setup();
+
+        let config_file = PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/postgresql.conf"));
+
+        let cluster = MinervaCluster::start(&config_file, 3).await?;
+
+        debug!("Containers started");
+
+        let test_database = cluster.create_db().await?;
+
+        {
+            let mut client = test_database.connect().await?;
+            create_schema(&mut client).await?;
+            create_rule(&mut client).await?;
+
+            client
+                .execute(
+                    concat!(
+                        "UPDATE trigger.rule SET flapping_window = '1h', flapping_threshold = 3 ",
+                        "WHERE name = $1::name"
+                    ),
+                    &[&RULE_NAME],
+                )
+                .await?;
+
+            // Entity 1 changes twice within the window, the firing at 10:00
+            // is at the edge and outside of it. Entity 2 changes 3 times and
+            // entity 3 twice.
+            client
+                .execute(
+                    concat!(
+                        "INSERT INTO trigger.rule_firing(rule_id, entity_id, timestamp) ",
+                        "SELECT id, e, t::timestamptz FROM trigger.rule, (VALUES ",
+                        "(1, '2024-01-01 10:00Z'), (1, '2024-01-01 10:30Z'), ",
+                        "(2, '2024-01-01 10:15Z'), (2, '2024-01-01 10:45Z'), ",
+                        "(3, '2024-01-01 10:30Z')",
+                        ") v(e, t) WHERE name = $1::name"
+                    ),
+                    &[&RULE_NAME],
+                )
+                .await?;
+
+            client
+                .execute(
+                    concat!(
+                        "SELECT trigger.detect_flapping(rule, '2024-01-01 11:00Z') ",
+                        "FROM trigger.rule WHERE name = $1::name"
+                    ),
+                    &[&RULE_NAME],
+                )
+                .await?;
+
+            let rows = client
+                .query(
+                    "SELECT entity_id, changes FROM trigger.flapping_entity ORDER BY entity_id",
+                    &[],
+                )
+                .await?;
+
+            let flapping: Vec<(i32, i32)> =
+                rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+            assert_eq!(flapping, vec![(2, 3)]);
+        }
+
+        let mut admin_client = cluster.connect_to_coordinator().await;
+
+        test_database.drop_database(&mut admin_client).await;
+
+        Ok(())
+    }
+}
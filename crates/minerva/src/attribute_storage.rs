@@ -10,7 +10,7 @@ use tokio_postgres::Transaction;
 
 use crate::attribute_store::{Attribute, AttributeStore};
 use crate::entity::{EntityMapping, EntityMappingError};
-use crate::meas_value::{parse_meas_value, DataType, MeasValue, INT2_NONE_VALUE, TEXT_NONE_VALUE};
+use crate::meas_value::{parse_meas_value, DataType, MeasValue};
 
 #[derive(Error, Debug)]
 pub enum AttributeStorageError {
@@ -66,19 +66,6 @@ impl<'a> ToSql for AttributeValue<'a> {
     }
 }
 
-fn to_sql_type(data_type: DataType) -> Type {
-    match data_type {
-        DataType::Int2 => Type::INT2,
-        DataType::Integer => Type::INT4,
-        DataType::Int8 => Type::INT8,
-        DataType::Numeric => Type::NUMERIC,
-        DataType::Real => Type::FLOAT4,
-        DataType::Double => Type::FLOAT8,
-        DataType::Timestamp => Type::TIMESTAMPTZ,
-        _ => Type::TEXT,
-    }
-}
-
 #[derive(Debug, Clone)]
 struct NullValue {
     data_type: DataType,
@@ -94,10 +81,7 @@ impl ToSql for NullValue {
         Self: Sized,
     {
         debug!("to_sql: {}", self.data_type);
-        match self.data_type {
-            DataType::Int2 => INT2_NONE_VALUE.to_sql(ty, out),
-            _ => TEXT_NONE_VALUE.to_sql(ty, out),
-        }
+        MeasValue::null_value_of_type(self.data_type).to_sql(ty, out)
     }
 
     fn accepts(_ty: &Type) -> bool
@@ -113,7 +97,7 @@ impl ToSql for NullValue {
         out: &mut BytesMut,
     ) -> Result<IsNull, Box<dyn std::error::Error + Sync + Send>> {
         debug!("to_sql_checked: {}", self.data_type);
-        TEXT_NONE_VALUE.to_sql_checked(ty, out)
+        MeasValue::null_value_of_type(self.data_type).to_sql_checked(ty, out)
     }
 }
 
@@ -190,7 +174,7 @@ impl RawAttributeStore for AttributeStore {
             .chain(
                 matched_attributes
                     .iter()
-                    .map(|(_index, att)| to_sql_type(att.data_type)),
+                    .map(|(_index, att)| att.data_type.sql_type()),
             )
             .collect();
 
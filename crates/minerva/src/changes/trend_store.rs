@@ -1,13 +1,18 @@
 use serde_json::Value;
 use std::fmt;
+use std::time::Duration;
 use tokio_postgres::Transaction;
 
 use async_trait::async_trait;
+use postgres_protocol::escape::escape_identifier;
 
 use crate::change::{Change, ChangeResult};
-use crate::error::DatabaseError;
+use crate::dependency::{load_dependency_graph, DependencyNode};
+use crate::error::{DatabaseError, Error};
 use crate::meas_value::DataType;
-use crate::trend_store::{Trend, TrendStore, TrendStorePart};
+use crate::trend_store::{
+    GeneratedTrend, Trend, TrendDescr, TrendStore, TrendStorePart, TrendStorePartDescr,
+};
 
 pub struct RemoveTrends {
     pub trend_store_part: TrendStorePart,
@@ -115,8 +120,10 @@ impl Change for AddTrends {
             "FROM trend_directory.trend_store_part WHERE name = $2",
         );
 
+        let trends: Vec<TrendDescr> = self.trends.iter().map(Trend::descr).collect();
+
         client
-            .query_one(query, &[&self.trends, &self.trend_store_part.name])
+            .query_one(query, &[&trends, &self.trend_store_part.name])
             .await
             .map_err(|e| {
                 DatabaseError::from_msg(format!("Error adding trends to trend store part: {e}"))
@@ -332,6 +339,166 @@ impl Change for ModifyTrendExtraData {
     }
 }
 
+/////////////////////
+// AddGeneratedTrends
+/////////////////////
+
+pub struct AddGeneratedTrends {
+    pub trend_store_part: TrendStorePart,
+    pub generated_trends: Vec<GeneratedTrend>,
+}
+
+impl fmt::Display for AddGeneratedTrends {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AddGeneratedTrends({}, {})",
+            &self.trend_store_part,
+            self.generated_trends.len()
+        )
+    }
+}
+
+#[async_trait]
+impl Change for AddGeneratedTrends {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT trend_directory.create_generated_table_trends(trend_store_part, $1) ",
+            "FROM trend_directory.trend_store_part WHERE name = $2",
+        );
+
+        client
+            .query_one(
+                query,
+                &[&self.generated_trends, &self.trend_store_part.name],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error adding generated trends to trend store part: {e}"
+                ))
+            })?;
+
+        Ok(format!(
+            "Added {} generated trends to trend store part '{}'",
+            &self.generated_trends.len(),
+            &self.trend_store_part.name
+        ))
+    }
+}
+
+////////////////////////
+// RemoveGeneratedTrends
+////////////////////////
+
+pub struct RemoveGeneratedTrends {
+    pub trend_store_part: TrendStorePart,
+    pub generated_trends: Vec<String>,
+}
+
+impl fmt::Display for RemoveGeneratedTrends {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RemoveGeneratedTrends({}, {})",
+            &self.trend_store_part,
+            self.generated_trends.len()
+        )
+    }
+}
+
+#[async_trait]
+impl Change for RemoveGeneratedTrends {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT trend_directory.remove_generated_table_trend(generated_table_trend) ",
+            "FROM trend_directory.generated_table_trend ",
+            "JOIN trend_directory.trend_store_part ON trend_store_part.id = generated_table_trend.trend_store_part_id ",
+            "WHERE trend_store_part.name = $1 AND generated_table_trend.name = $2",
+        );
+
+        for trend_name in &self.generated_trends {
+            client
+                .query_one(query, &[&self.trend_store_part.name, &trend_name])
+                .await
+                .map_err(|e| {
+                    DatabaseError::from_msg(format!(
+                        "Error removing generated trend '{}' from trend store part: {}",
+                        &trend_name, e
+                    ))
+                })?;
+        }
+
+        Ok(format!(
+            "Removed {} generated trends from trend store part '{}'",
+            &self.generated_trends.len(),
+            &self.trend_store_part.name
+        ))
+    }
+}
+
+/////////////////////////
+// ModifyGeneratedTrend
+/////////////////////////
+
+/// Change of the expression and/or data type of a generated trend. The column
+/// is recreated, so the values are recalculated for all existing data.
+pub struct ModifyGeneratedTrend {
+    pub trend_store_part_name: String,
+    pub from: GeneratedTrend,
+    pub to: GeneratedTrend,
+}
+
+impl fmt::Display for ModifyGeneratedTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ModifyGeneratedTrend({}.{}, {} {}->{} {})",
+            &self.trend_store_part_name,
+            &self.from.name,
+            &self.from.data_type,
+            &self.from.expression,
+            &self.to.data_type,
+            &self.to.expression
+        )
+    }
+}
+
+#[async_trait]
+impl Change for ModifyGeneratedTrend {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT trend_directory.change_generated_table_trend(generated_table_trend, $3, $4) ",
+            "FROM trend_directory.generated_table_trend ",
+            "JOIN trend_directory.trend_store_part ON trend_store_part.id = generated_table_trend.trend_store_part_id ",
+            "WHERE trend_store_part.name = $1 AND generated_table_trend.name = $2",
+        );
+
+        client
+            .query_one(
+                query,
+                &[
+                    &self.trend_store_part_name,
+                    &self.from.name,
+                    &self.to.data_type,
+                    &self.to.expression,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error changing generated trend '{}': {e}",
+                    &self.from.name
+                ))
+            })?;
+
+        Ok(format!(
+            "Changed generated trend '{}'.'{}'",
+            &self.trend_store_part_name, &self.from.name
+        ))
+    }
+}
+
 pub struct AddTrendStorePart {
     pub trend_store: TrendStore,
     pub trend_store_part: TrendStorePart,
@@ -388,6 +555,271 @@ impl fmt::Display for AddTrendStorePart {
     }
 }
 
+////////////////////////
+// RemoveTrendStorePart
+////////////////////////
+
+/// Removal of a trend store part, including its table and all data in it
+pub struct RemoveTrendStorePart {
+    pub trend_store_part: TrendStorePart,
+}
+
+impl fmt::Display for RemoveTrendStorePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RemoveTrendStorePart({})", &self.trend_store_part)
+    }
+}
+
+#[async_trait]
+impl Change for RemoveTrendStorePart {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let row = client
+            .query_one(
+                concat!(
+                    "SELECT trend_directory.staging_table_schema(), trend_directory.staging_table_name(trend_store_part) ",
+                    "FROM trend_directory.trend_store_part WHERE name = $1"
+                ),
+                &[&self.trend_store_part.name],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Could not find trend store part '{}': {e}",
+                    &self.trend_store_part.name
+                ))
+            })?;
+
+        let staging_table_schema: String = row.get(0);
+        let staging_table_name: String = row.get(1);
+
+        client
+            .execute(
+                &format!(
+                    "DROP TABLE IF EXISTS {}.{}",
+                    escape_identifier(&staging_table_schema),
+                    escape_identifier(&staging_table_name)
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| DatabaseError::from_msg(format!("Error dropping staging table: {e}")))?;
+
+        client
+            .execute(
+                concat!(
+                    "SELECT trend_directory.delete_trend_store_part(trend_store_part) ",
+                    "FROM trend_directory.trend_store_part WHERE name = $1"
+                ),
+                &[&self.trend_store_part.name],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error removing trend store part '{}': {e}",
+                    &self.trend_store_part.name
+                ))
+            })?;
+
+        Ok(format!(
+            "Removed trend store part '{}'",
+            &self.trend_store_part.name
+        ))
+    }
+}
+
+////////////////////////
+// RenameTrendStorePart
+////////////////////////
+
+/// Rename of a trend store part, keeping its data. The tables, views and
+/// functions derived from the part name are renamed along with it.
+pub struct RenameTrendStorePart {
+    pub trend_store_part: TrendStorePart,
+    pub new_name: String,
+}
+
+impl fmt::Display for RenameTrendStorePart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RenameTrendStorePart({}->{})",
+            &self.trend_store_part, &self.new_name
+        )
+    }
+}
+
+#[async_trait]
+impl Change for RenameTrendStorePart {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        client
+            .query_one(
+                concat!(
+                    "SELECT trend_directory.rename_trend_store_part_full(trend_store_part, $2::name) ",
+                    "FROM trend_directory.trend_store_part WHERE name = $1"
+                ),
+                &[&self.trend_store_part.name, &self.new_name],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error renaming trend store part '{}': {e}",
+                    &self.trend_store_part.name
+                ))
+            })?;
+
+        Ok(format!(
+            "Renamed trend store part '{}' to '{}'",
+            &self.trend_store_part.name, &self.new_name
+        ))
+    }
+}
+
+//////////////
+// RenameTrend
+//////////////
+
+/// Rename of a trend in a trend store part, keeping its data. Columns of
+/// staging tables and of the source views of view materializations are
+/// renamed along with it. Views that read the trend keep working, but
+/// functions, function materializations and triggers that read it still use
+/// the old name and are reported.
+pub struct RenameTrend {
+    pub trend_store_part_name: String,
+    pub from_name: String,
+    pub to_name: String,
+}
+
+impl fmt::Display for RenameTrend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RenameTrend({}, {}->{})",
+            &self.trend_store_part_name, &self.from_name, &self.to_name
+        )
+    }
+}
+
+impl RenameTrend {
+    /// Objects that refer to the trend by its column name in their SQL, which
+    /// is not updated by renaming the column. Views refer to columns by their
+    /// number and are not included.
+    async fn readers_by_name(&self, client: &mut Transaction<'_>) -> Result<Vec<String>, Error> {
+        let graph = load_dependency_graph(client).await?;
+
+        let rows = client
+            .query(
+                concat!(
+                    "SELECT tsp.name ",
+                    "FROM trend_directory.function_materialization fm ",
+                    "JOIN trend_directory.materialization m ON m.id = fm.materialization_id ",
+                    "JOIN trend_directory.trend_store_part tsp ON tsp.id = m.dst_trend_store_part_id"
+                ),
+                &[],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error checking function materializations: {e}"))
+            })?;
+
+        let function_materializations: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+
+        let trend = DependencyNode::Trend {
+            trend_store_part: self.trend_store_part_name.clone(),
+            name: self.from_name.clone(),
+        };
+
+        let readers = graph
+            .dependents(&trend)
+            .into_iter()
+            .filter(|node| match node {
+                DependencyNode::Function { .. } | DependencyNode::Trigger { .. } => true,
+                DependencyNode::Materialization { name } => {
+                    function_materializations.contains(name)
+                }
+                _ => false,
+            })
+            .map(|node| node.to_string())
+            .collect();
+
+        Ok(readers)
+    }
+}
+
+#[async_trait]
+impl Change for RenameTrend {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        // The readers are determined before renaming, while their definitions
+        // still match the trend name
+        let mut readers = self.readers_by_name(client).await?;
+
+        client
+            .execute(
+                concat!(
+                    "SELECT trend_directory.rename_table_trend(trend_store_part, $2::name, $3::name) ",
+                    "FROM trend_directory.trend_store_part WHERE name = $1"
+                ),
+                &[
+                    &self.trend_store_part_name,
+                    &self.from_name,
+                    &self.to_name,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error renaming trend '{}' of trend store part '{}': {e}",
+                    &self.from_name, &self.trend_store_part_name
+                ))
+            })?;
+
+        // Function materializations into this part cannot be rewritten
+        // automatically either
+        let row = client
+            .query_one(
+                concat!(
+                    "SELECT count(*) ",
+                    "FROM trend_directory.function_materialization fm ",
+                    "JOIN trend_directory.materialization m ON m.id = fm.materialization_id ",
+                    "JOIN trend_directory.trend_store_part tsp ON tsp.id = m.dst_trend_store_part_id ",
+                    "WHERE tsp.name = $1"
+                ),
+                &[&self.trend_store_part_name],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error checking function materializations: {e}"))
+            })?;
+
+        let function_materialization_count: i64 = row.get(0);
+
+        if function_materialization_count > 0 {
+            let materialization = DependencyNode::Materialization {
+                name: self.trend_store_part_name.clone(),
+            }
+            .to_string();
+
+            if !readers.contains(&materialization) {
+                readers.push(materialization);
+            }
+        }
+
+        if readers.is_empty() {
+            Ok(format!(
+                "Renamed trend '{}' of trend store part '{}' to '{}'",
+                &self.from_name, &self.trend_store_part_name, &self.to_name
+            ))
+        } else {
+            Ok(format!(
+                "Renamed trend '{}' of trend store part '{}' to '{}', update these objects that still use the old name: {}",
+                &self.from_name,
+                &self.trend_store_part_name,
+                &self.to_name,
+                readers.join(", ")
+            ))
+        }
+    }
+}
+
 pub struct AddTrendStore {
     pub trend_store: TrendStore,
 }
@@ -412,8 +844,14 @@ impl Change for AddTrendStore {
         let granularity_text = humantime::format_duration(self.trend_store.granularity).to_string();
         let partition_size_text =
             humantime::format_duration(self.trend_store.partition_size).to_string();
+        let parts: Vec<TrendStorePartDescr> = self
+            .trend_store
+            .parts
+            .iter()
+            .map(TrendStorePart::descr)
+            .collect();
 
-        client
+        let row = client
             .query_one(
                 query,
                 &[
@@ -421,12 +859,162 @@ impl Change for AddTrendStore {
                     &self.trend_store.entity_type,
                     &granularity_text,
                     &partition_size_text,
-                    &self.trend_store.parts,
+                    &parts,
                 ],
             )
             .await
             .map_err(|e| DatabaseError::from_msg(format!("Error creating trend store: {e}")))?;
 
+        let trend_store_id: i32 = row.get(0);
+
+        let retention_period_text =
+            humantime::format_duration(self.trend_store.retention_period).to_string();
+
+        client
+            .execute(
+                "UPDATE trend_directory.trend_store SET retention_period = $2::text::interval WHERE id = $1",
+                &[&trend_store_id, &retention_period_text],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error setting trend store retention period: {e}"))
+            })?;
+
         Ok(format!("Added trend store {}", &self.trend_store))
     }
 }
+
+const TREND_STORE_ID_QUERY: &str = concat!(
+    "SELECT trend_store.id ",
+    "FROM trend_directory.trend_store ",
+    "JOIN directory.data_source ON data_source.id = trend_store.data_source_id ",
+    "JOIN directory.entity_type ON entity_type.id = trend_store.entity_type_id ",
+    "WHERE data_source.name = $1 AND entity_type.name = $2 AND granularity = $3::text::interval"
+);
+
+async fn trend_store_id(
+    client: &mut Transaction<'_>,
+    trend_store: &TrendStore,
+) -> Result<i32, Error> {
+    let granularity_text = humantime::format_duration(trend_store.granularity).to_string();
+
+    let row = client
+        .query_one(
+            TREND_STORE_ID_QUERY,
+            &[
+                &trend_store.data_source,
+                &trend_store.entity_type,
+                &granularity_text,
+            ],
+        )
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not find {trend_store}: {e}")))?;
+
+    Ok(row.get(0))
+}
+
+//////////////////////////////////
+// ModifyTrendStoreRetentionPeriod
+//////////////////////////////////
+
+pub struct ModifyTrendStoreRetentionPeriod {
+    pub trend_store: TrendStore,
+    pub from_retention_period: Duration,
+    pub to_retention_period: Duration,
+}
+
+impl fmt::Display for ModifyTrendStoreRetentionPeriod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ModifyTrendStoreRetentionPeriod({}, {}->{})",
+            &self.trend_store,
+            humantime::format_duration(self.from_retention_period),
+            humantime::format_duration(self.to_retention_period)
+        )
+    }
+}
+
+#[async_trait]
+impl Change for ModifyTrendStoreRetentionPeriod {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let trend_store_id = trend_store_id(client, &self.trend_store).await?;
+
+        let retention_period_text =
+            humantime::format_duration(self.to_retention_period).to_string();
+
+        client
+            .execute(
+                "UPDATE trend_directory.trend_store SET retention_period = $2::text::interval WHERE id = $1",
+                &[&trend_store_id, &retention_period_text],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error changing retention period: {e}"))
+            })?;
+
+        Ok(format!(
+            "Changed retention period of {} to {}",
+            &self.trend_store, &retention_period_text
+        ))
+    }
+}
+
+////////////////////////
+// RepartitionTrendStore
+////////////////////////
+
+/// Change of the partition size of a trend store. All existing data is moved
+/// to partitions of the new size in one transaction, during which the data is
+/// not visible, so this is only applied explicitly and never from a diff.
+pub struct RepartitionTrendStore {
+    pub trend_store: TrendStore,
+    pub from_partition_size: Duration,
+    pub to_partition_size: Duration,
+}
+
+impl fmt::Display for RepartitionTrendStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RepartitionTrendStore({}, {}->{})",
+            &self.trend_store,
+            humantime::format_duration(self.from_partition_size),
+            humantime::format_duration(self.to_partition_size)
+        )
+    }
+}
+
+#[async_trait]
+impl Change for RepartitionTrendStore {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let trend_store_id = trend_store_id(client, &self.trend_store).await?;
+
+        let partition_size_text = humantime::format_duration(self.to_partition_size).to_string();
+
+        client
+            .execute("SET LOCAL statement_timeout = 0", &[])
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error setting statement timeout: {e}"))
+            })?;
+
+        client
+            .query_one(
+                concat!(
+                    "SELECT trend_directory.repartition_trend_store(trend_store, $2::text::interval) ",
+                    "FROM trend_directory.trend_store WHERE id = $1"
+                ),
+                &[&trend_store_id, &partition_size_text],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Error repartitioning trend store: {e}"))
+            })?;
+
+        Ok(format!(
+            "Repartitioned {} with partition size {}",
+            &self.trend_store, &partition_size_text
+        ))
+    }
+}
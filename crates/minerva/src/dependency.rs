@@ -0,0 +1,671 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+use tokio_postgres::GenericClient;
+
+use crate::error::{ConfigurationError, DatabaseError, Error};
+
+/// Schemas with the views and functions of an instance that can read trend,
+/// attribute and relation data
+const DEFINITION_SCHEMAS: [&str; 4] = ["trend", "trigger_rule", "relation_def", "virtual_entity"];
+
+/// Schemas with the tables and views of attribute stores
+const ATTRIBUTE_SCHEMAS: [&str; 4] = [
+    "attribute",
+    "attribute_base",
+    "attribute_history",
+    "attribute_staging",
+];
+
+lazy_static! {
+    static ref QUALIFIED_NAME: Regex = Regex::new(
+        r#"("(?:[^"]|"")+"|[A-Za-z_][A-Za-z0-9_$]*)\s*\.\s*("(?:[^"]|"")+"|[A-Za-z_][A-Za-z0-9_$]*)"#
+    )
+    .unwrap();
+    static ref IDENTIFIER: Regex = Regex::new(r#""(?:[^"]|"")+"|[A-Za-z_][A-Za-z0-9_$]*"#).unwrap();
+}
+
+/// Object that other objects in a Minerva instance can depend on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DependencyNode {
+    TrendStorePart {
+        name: String,
+    },
+    Trend {
+        trend_store_part: String,
+        name: String,
+    },
+    Relation {
+        name: String,
+    },
+    AttributeStore {
+        name: String,
+    },
+    View {
+        name: String,
+    },
+    Function {
+        name: String,
+    },
+    Materialization {
+        name: String,
+    },
+    Trigger {
+        name: String,
+    },
+}
+
+impl DependencyNode {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DependencyNode::TrendStorePart { .. } => "trend-store-part",
+            DependencyNode::Trend { .. } => "trend",
+            DependencyNode::Relation { .. } => "relation",
+            DependencyNode::AttributeStore { .. } => "attribute-store",
+            DependencyNode::View { .. } => "view",
+            DependencyNode::Function { .. } => "function",
+            DependencyNode::Materialization { .. } => "materialization",
+            DependencyNode::Trigger { .. } => "trigger",
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            DependencyNode::Trend {
+                trend_store_part,
+                name,
+            } => format!("{trend_store_part}.{name}"),
+            DependencyNode::TrendStorePart { name }
+            | DependencyNode::Relation { name }
+            | DependencyNode::AttributeStore { name }
+            | DependencyNode::View { name }
+            | DependencyNode::Function { name }
+            | DependencyNode::Materialization { name }
+            | DependencyNode::Trigger { name } => name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for DependencyNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.kind(), self.name())
+    }
+}
+
+/// Parses `KIND:NAME`, where the name of a trend is `TREND_STORE_PART.TREND`
+impl FromStr for DependencyNode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = s.split_once(':').ok_or_else(|| {
+            Error::Configuration(ConfigurationError::from_msg(format!(
+                "Expected object as KIND:NAME, got '{s}'"
+            )))
+        })?;
+
+        let name = name.to_string();
+
+        match kind {
+            "trend-store-part" => Ok(DependencyNode::TrendStorePart { name }),
+            "trend" => match name.split_once('.') {
+                Some((trend_store_part, trend)) => Ok(DependencyNode::Trend {
+                    trend_store_part: trend_store_part.to_string(),
+                    name: trend.to_string(),
+                }),
+                None => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                    "Expected trend as TREND_STORE_PART.TREND, got '{name}'"
+                )))),
+            },
+            "relation" => Ok(DependencyNode::Relation { name }),
+            "attribute-store" => Ok(DependencyNode::AttributeStore { name }),
+            "view" => Ok(DependencyNode::View { name }),
+            "function" => Ok(DependencyNode::Function { name }),
+            "materialization" => Ok(DependencyNode::Materialization { name }),
+            "trigger" => Ok(DependencyNode::Trigger { name }),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported object kind '{kind}'"
+            )))),
+        }
+    }
+}
+
+/// Dependency of `to` on `from`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DependencyEdge {
+    pub from: DependencyNode,
+    pub to: DependencyNode,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    pub nodes: BTreeSet<DependencyNode>,
+    pub edges: BTreeSet<DependencyEdge>,
+}
+
+impl DependencyGraph {
+    pub fn add_node(&mut self, node: DependencyNode) {
+        self.nodes.insert(node);
+    }
+
+    /// Register that `to` depends on `from`
+    pub fn add_edge(&mut self, from: DependencyNode, to: DependencyNode) {
+        if from == to {
+            return;
+        }
+
+        self.nodes.insert(from.clone());
+        self.nodes.insert(to.clone());
+        self.edges.insert(DependencyEdge { from, to });
+    }
+
+    pub fn contains(&self, node: &DependencyNode) -> bool {
+        self.nodes.contains(node)
+    }
+
+    /// Objects that `node` directly depends on
+    pub fn dependencies(&self, node: &DependencyNode) -> Vec<&DependencyNode> {
+        self.edges
+            .iter()
+            .filter(|edge| &edge.to == node)
+            .map(|edge| &edge.from)
+            .collect()
+    }
+
+    /// Objects that directly depend on `node`
+    pub fn dependents(&self, node: &DependencyNode) -> Vec<&DependencyNode> {
+        self.edges
+            .iter()
+            .filter(|edge| &edge.from == node)
+            .map(|edge| &edge.to)
+            .collect()
+    }
+
+    /// Subgraph of `node` and everything that depends on it
+    pub fn downstream(&self, node: &DependencyNode) -> DependencyGraph {
+        let mut adjacency: HashMap<&DependencyNode, Vec<&DependencyNode>> = HashMap::new();
+
+        for edge in &self.edges {
+            adjacency.entry(&edge.from).or_default().push(&edge.to);
+        }
+
+        let mut reached: HashSet<&DependencyNode> = HashSet::from([node]);
+        let mut queue: VecDeque<&DependencyNode> = VecDeque::from([node]);
+
+        while let Some(current) = queue.pop_front() {
+            for next in adjacency.get(current).into_iter().flatten() {
+                if reached.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        DependencyGraph {
+            nodes: reached.iter().map(|node| (*node).clone()).collect(),
+            edges: self
+                .edges
+                .iter()
+                .filter(|edge| reached.contains(&edge.from))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Graphviz representation with edges pointing from an object to the
+    /// objects that depend on it
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n  rankdir=LR;\n");
+
+        for node in &self.nodes {
+            dot.push_str(&format!(
+                "  {} [shape={}];\n",
+                dot_id(node),
+                match node {
+                    DependencyNode::View { .. } | DependencyNode::Function { .. } => "ellipse",
+                    DependencyNode::Materialization { .. } | DependencyNode::Trigger { .. } => {
+                        "box"
+                    }
+                    _ => "cylinder",
+                }
+            ));
+        }
+
+        for edge in &self.edges {
+            dot.push_str(&format!(
+                "  {} -> {};\n",
+                dot_id(&edge.from),
+                dot_id(&edge.to)
+            ));
+        }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+fn dot_id(node: &DependencyNode) -> String {
+    format!(
+        "\"{}\"",
+        node.to_string().replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn normalize_identifier(identifier: &str) -> String {
+    match identifier
+        .strip_prefix('"')
+        .and_then(|identifier| identifier.strip_suffix('"'))
+    {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => identifier.to_lowercase(),
+    }
+}
+
+/// Schema qualified names referenced in an SQL definition
+fn referenced_names(definition: &str) -> Vec<(String, String)> {
+    QUALIFIED_NAME
+        .captures_iter(definition)
+        .map(|captures| {
+            (
+                normalize_identifier(&captures[1]),
+                normalize_identifier(&captures[2]),
+            )
+        })
+        .collect()
+}
+
+/// Trigger that owns a function or view in the trigger_rule schema, which are
+/// named after the trigger with an optional suffix like `_kpi`
+fn owning_trigger(name: &str, triggers: &HashSet<String>) -> Option<String> {
+    let mut candidate = name;
+
+    loop {
+        if triggers.contains(candidate) {
+            return Some(candidate.to_string());
+        }
+
+        candidate = &candidate[..candidate.rfind('_')?];
+    }
+}
+
+/// Build the dependency graph of trend store parts, trends, relations,
+/// attribute stores, materializations and triggers from the registered links
+/// and from the names referenced in the definitions of views and functions.
+/// Dynamic SQL in functions is not analyzed.
+pub async fn load_dependency_graph<T: GenericClient + Send + Sync>(
+    client: &mut T,
+) -> Result<DependencyGraph, Error> {
+    let mut graph = DependencyGraph::default();
+
+    // Objects that a schema qualified name in a definition refers to
+    let mut references: HashMap<(String, String), DependencyNode> = HashMap::new();
+    let mut trends: HashMap<String, Vec<String>> = HashMap::new();
+
+    let query = concat!(
+        "SELECT tsp.name, t.name ",
+        "FROM trend_directory.trend_store_part tsp ",
+        "LEFT JOIN (",
+        "SELECT trend_store_part_id, name FROM trend_directory.table_trend ",
+        "UNION ALL ",
+        "SELECT trend_store_part_id, name FROM trend_directory.generated_table_trend",
+        ") t ON t.trend_store_part_id = tsp.id"
+    );
+
+    let rows = client
+        .query(query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading trends: {e}")))?;
+
+    for row in rows {
+        let trend_store_part: String = row.get(0);
+        let trend: Option<String> = row.get(1);
+
+        graph.add_node(DependencyNode::TrendStorePart {
+            name: trend_store_part.clone(),
+        });
+
+        references.insert(
+            ("trend".to_string(), trend_store_part.clone()),
+            DependencyNode::TrendStorePart {
+                name: trend_store_part.clone(),
+            },
+        );
+
+        if let Some(trend) = trend {
+            graph.add_node(DependencyNode::Trend {
+                trend_store_part: trend_store_part.clone(),
+                name: trend.clone(),
+            });
+
+            trends.entry(trend_store_part).or_default().push(trend);
+        }
+    }
+
+    let rows = client
+        .query(
+            "SELECT attribute_store::text FROM attribute_directory.attribute_store",
+            &[],
+        )
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading attribute stores: {e}")))?;
+
+    for row in rows {
+        let name: String = row.get(0);
+
+        for schema in ATTRIBUTE_SCHEMAS {
+            references.insert(
+                (schema.to_string(), name.clone()),
+                DependencyNode::AttributeStore { name: name.clone() },
+            );
+        }
+
+        graph.add_node(DependencyNode::AttributeStore { name });
+    }
+
+    let rows = client
+        .query("SELECT name FROM relation_directory.type", &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading relations: {e}")))?;
+
+    // Objects that are part of the definition of another object, like the
+    // view of a view materialization
+    let mut owners: HashMap<(String, String), DependencyNode> = HashMap::new();
+
+    for row in rows {
+        let name: String = row.get(0);
+
+        references.insert(
+            ("relation".to_string(), name.clone()),
+            DependencyNode::Relation { name: name.clone() },
+        );
+        owners.insert(
+            ("relation_def".to_string(), name.clone()),
+            DependencyNode::Relation { name: name.clone() },
+        );
+
+        graph.add_node(DependencyNode::Relation { name });
+    }
+
+    let query = concat!(
+        "SELECT dst.name, src.name ",
+        "FROM trend_directory.materialization m ",
+        "JOIN trend_directory.trend_store_part dst ON dst.id = m.dst_trend_store_part_id ",
+        "LEFT JOIN trend_directory.materialization_trend_store_link mtsl ON mtsl.materialization_id = m.id ",
+        "LEFT JOIN trend_directory.trend_store_part src ON src.id = mtsl.trend_store_part_id"
+    );
+
+    let rows = client
+        .query(query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading materializations: {e}")))?;
+
+    for row in rows {
+        let target: String = row.get(0);
+        let source: Option<String> = row.get(1);
+
+        let materialization = DependencyNode::Materialization {
+            name: target.clone(),
+        };
+
+        for owned in [
+            format!("_{target}"),
+            target.clone(),
+            format!("{target}_fingerprint"),
+        ] {
+            owners.insert(("trend".to_string(), owned), materialization.clone());
+        }
+
+        if let Some(source) = source {
+            graph.add_edge(
+                DependencyNode::TrendStorePart { name: source },
+                materialization.clone(),
+            );
+        }
+
+        graph.add_edge(
+            materialization,
+            DependencyNode::TrendStorePart { name: target },
+        );
+    }
+
+    let query = concat!(
+        "SELECT rule.name, tsp.name ",
+        "FROM trigger.rule ",
+        "LEFT JOIN trigger.rule_trend_store_link rtsl ON rtsl.rule_id = rule.id ",
+        "LEFT JOIN trend_directory.trend_store_part tsp ON tsp.id = rtsl.trend_store_part_id"
+    );
+
+    let rows = client
+        .query(query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading triggers: {e}")))?;
+
+    let mut triggers: HashSet<String> = HashSet::new();
+
+    for row in rows {
+        let name: String = row.get(0);
+        let trend_store_part: Option<String> = row.get(1);
+
+        let trigger = DependencyNode::Trigger { name: name.clone() };
+
+        match trend_store_part {
+            Some(trend_store_part) => graph.add_edge(
+                DependencyNode::TrendStorePart {
+                    name: trend_store_part,
+                },
+                trigger,
+            ),
+            None => graph.add_node(trigger),
+        }
+
+        triggers.insert(name);
+    }
+
+    let schemas: Vec<&str> = DEFINITION_SCHEMAS.to_vec();
+
+    let query = concat!(
+        "SELECT nspname::text, relname::text, true, pg_get_viewdef(pg_class.oid) ",
+        "FROM pg_class ",
+        "JOIN pg_namespace ON pg_namespace.oid = relnamespace ",
+        "WHERE relkind IN ('v', 'm') AND nspname = ANY($1::name[]) ",
+        "UNION ",
+        "SELECT nspname::text, proname::text, false, prosrc ",
+        "FROM pg_proc ",
+        "JOIN pg_namespace ON pg_namespace.oid = pronamespace ",
+        "WHERE prokind = 'f' AND nspname = ANY($1::name[])"
+    );
+
+    let rows = client
+        .query(query, &[&schemas])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading views and functions: {e}")))?;
+
+    let mut definitions: Vec<(DependencyNode, String)> = Vec::new();
+
+    for row in rows {
+        let schema: String = row.get(0);
+        let name: String = row.get(1);
+        let is_view: bool = row.get(2);
+        let definition: Option<String> = row.get(3);
+
+        let qualified_name = format!("{schema}.{name}");
+
+        let owner = match owners.get(&(schema.clone(), name.clone())) {
+            Some(owner) => owner.clone(),
+            None => match schema.as_str() {
+                "trigger_rule" => match owning_trigger(&name, &triggers) {
+                    Some(trigger) => DependencyNode::Trigger { name: trigger },
+                    None => DependencyNode::Function {
+                        name: qualified_name,
+                    },
+                },
+                _ if is_view => DependencyNode::View {
+                    name: qualified_name,
+                },
+                _ => DependencyNode::Function {
+                    name: qualified_name,
+                },
+            },
+        };
+
+        references
+            .entry((schema, name))
+            .or_insert_with(|| owner.clone());
+
+        if let Some(definition) = definition {
+            definitions.push((owner, definition));
+        }
+    }
+
+    for (owner, definition) in definitions {
+        let mut identifiers: Option<HashSet<String>> = None;
+
+        for reference in referenced_names(&definition) {
+            let Some(dependency) = references.get(&reference) else {
+                continue;
+            };
+
+            if let DependencyNode::TrendStorePart { name } = dependency {
+                let identifiers = identifiers.get_or_insert_with(|| {
+                    IDENTIFIER
+                        .find_iter(&definition)
+                        .map(|identifier| normalize_identifier(identifier.as_str()))
+                        .collect()
+                });
+
+                for trend in trends.get(name).into_iter().flatten() {
+                    if identifiers.contains(trend) {
+                        graph.add_edge(
+                            DependencyNode::Trend {
+                                trend_store_part: name.clone(),
+                                name: trend.clone(),
+                            },
+                            owner.clone(),
+                        );
+                    }
+                }
+            }
+
+            graph.add_edge(dependency.clone(), owner.clone());
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trend_store_part(name: &str) -> DependencyNode {
+        DependencyNode::TrendStorePart {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn node_round_trip() {
+        for object in [
+            "trend-store-part:hub_node_main_15m",
+            "trend:hub_node_main_15m.power_kwh",
+            "relation:node->v-network",
+            "attribute-store:hub_node",
+            "trigger:node/15m/highpowerusage",
+        ] {
+            assert_eq!(
+                object.parse::<DependencyNode>().unwrap().to_string(),
+                object
+            );
+        }
+
+        assert!("trend:power_kwh".parse::<DependencyNode>().is_err());
+        assert!("table:hub_node_main_15m".parse::<DependencyNode>().is_err());
+    }
+
+    #[test]
+    fn referenced_names_in_definition() {
+        let definition = concat!(
+            "SELECT t.entity_id, t.\"power_kwh\" ",
+            "FROM trend.\"hub-node_main_15m\" t ",
+            "JOIN relation.\"node->v-network\" r ON r.source_id = t.entity_id ",
+            "JOIN Attribute_History.hub_node a ON a.entity_id = r.target_id"
+        );
+
+        let names = referenced_names(definition);
+
+        assert!(names.contains(&("trend".to_string(), "hub-node_main_15m".to_string())));
+        assert!(names.contains(&("relation".to_string(), "node->v-network".to_string())));
+        assert!(names.contains(&("attribute_history".to_string(), "hub_node".to_string())));
+    }
+
+    #[test]
+    fn trigger_owning_function() {
+        let triggers: HashSet<String> = HashSet::from([
+            "node/15m/power".to_string(),
+            "node/15m/power_peak".to_string(),
+        ]);
+
+        assert_eq!(
+            owning_trigger("node/15m/power_kpi", &triggers).as_deref(),
+            Some("node/15m/power")
+        );
+        assert_eq!(
+            owning_trigger("node/15m/power_peak_with_threshold", &triggers).as_deref(),
+            Some("node/15m/power_peak")
+        );
+        assert_eq!(owning_trigger("other_kpi", &triggers), None);
+    }
+
+    #[test]
+    fn downstream_of_trend_store_part() {
+        let mut graph = DependencyGraph::default();
+
+        let materialization = DependencyNode::Materialization {
+            name: "hub_node_main_1h".to_string(),
+        };
+        let trigger = DependencyNode::Trigger {
+            name: "node/1h/highpowerusage".to_string(),
+        };
+
+        graph.add_edge(
+            trend_store_part("hub_node_main_15m"),
+            materialization.clone(),
+        );
+        graph.add_edge(
+            materialization.clone(),
+            trend_store_part("hub_node_main_1h"),
+        );
+        graph.add_edge(trend_store_part("hub_node_main_1h"), trigger.clone());
+        graph.add_edge(
+            trend_store_part("hub_cell_main_15m"),
+            DependencyNode::Trigger {
+                name: "cell/15m/highpowerusage".to_string(),
+            },
+        );
+
+        let impact = graph.downstream(&trend_store_part("hub_node_main_15m"));
+
+        assert_eq!(impact.nodes.len(), 4);
+        assert_eq!(impact.edges.len(), 3);
+        assert!(impact.contains(&trigger));
+        assert!(!impact.contains(&trend_store_part("hub_cell_main_15m")));
+        assert_eq!(
+            impact.dependencies(&trigger),
+            vec![&trend_store_part("hub_node_main_1h")]
+        );
+        assert_eq!(
+            impact.dependents(&trend_store_part("hub_node_main_1h")),
+            vec![&trigger]
+        );
+
+        let dot = impact.to_dot();
+
+        assert!(dot.contains(
+            "  \"trend-store-part:hub_node_main_1h\" -> \"trigger:node/1h/highpowerusage\";\n"
+        ));
+    }
+}
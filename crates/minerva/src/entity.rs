@@ -6,6 +6,8 @@ use quick_cache::sync::Cache;
 use thiserror::Error;
 use tokio_postgres::GenericClient;
 
+use crate::error::{DatabaseError, Error, RuntimeError};
+
 #[derive(Error, Debug)]
 pub enum EntityMappingError {
     #[error("Database error: {0}")]
@@ -122,3 +124,70 @@ async fn create_entity<T: GenericClient>(
         None => Err(EntityMappingError::EntityInsertError),
     }
 }
+
+/// Load the names of entities of the specified type, either of all entities or
+/// only of those with the specified Ids.
+pub async fn load_entity_names<T: GenericClient>(
+    client: &T,
+    entity_type: &str,
+    entity_ids: Option<&[i32]>,
+) -> Result<HashMap<i32, String>, Error> {
+    let rows = match entity_ids {
+        Some(entity_ids) => {
+            let query = format!(
+                "SELECT id, name FROM entity.{} WHERE id = ANY($1)",
+                escape_identifier(entity_type)
+            );
+
+            client.query(&query, &[&entity_ids]).await
+        }
+        None => {
+            let query = format!(
+                "SELECT id, name FROM entity.{}",
+                escape_identifier(entity_type)
+            );
+
+            client.query(&query, &[]).await
+        }
+    }
+    .map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not load names of entities of type '{entity_type}': {e}"
+        ))
+    })?;
+
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+}
+
+/// Look up the Ids of existing entities by name, without creating entities that
+/// do not exist.
+pub async fn lookup_entity_ids<T: GenericClient>(
+    client: &T,
+    entity_type: &str,
+    names: &[String],
+) -> Result<Vec<i32>, Error> {
+    let query = format!(
+        "SELECT name, id FROM entity.{} WHERE name = ANY($1)",
+        escape_identifier(entity_type)
+    );
+
+    let rows = client.query(&query, &[&names]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not look up entities of type '{entity_type}': {e}"
+        ))
+    })?;
+
+    let entity_ids: HashMap<String, i32> =
+        rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+
+    names
+        .iter()
+        .map(|name| {
+            entity_ids.get(name).copied().ok_or_else(|| {
+                Error::Runtime(RuntimeError::from_msg(format!(
+                    "No entity '{name}' of type '{entity_type}'"
+                )))
+            })
+        })
+        .collect()
+}
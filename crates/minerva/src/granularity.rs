@@ -0,0 +1,355 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::error::{ConfigurationError, Error, RuntimeError};
+use crate::interval::parse_interval;
+
+const SECONDS_PER_DAY: u64 = 86400;
+
+/// Environment variable with the name of the timezone in which periods are
+/// aligned, such as `Europe/Amsterdam`
+pub static ENV_MINERVA_TIMEZONE: &str = "MINERVA_TIMEZONE";
+
+/// Parse an IANA timezone name
+pub fn parse_timezone(name: &str) -> Result<chrono_tz::Tz, Error> {
+    chrono_tz::Tz::from_str(name).map_err(|e| {
+        Error::Configuration(ConfigurationError::from_msg(format!(
+            "Invalid timezone '{name}': {e}"
+        )))
+    })
+}
+
+/// Timezone in which periods are aligned, from `MINERVA_TIMEZONE` or UTC when
+/// that is not set
+pub fn configured_timezone() -> Result<chrono_tz::Tz, Error> {
+    match std::env::var(ENV_MINERVA_TIMEZONE) {
+        Ok(name) => parse_timezone(&name),
+        Err(_) => Ok(chrono_tz::UTC),
+    }
+}
+
+/// Period of trend data or trigger evaluation, aligned to the calendar of a
+/// timezone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Granularity {
+    /// Period that divides a day evenly, such as 5m, 15m, 30m or 1h, aligned
+    /// to midnight
+    Fixed(Duration),
+    Day,
+    /// ISO week, starting on Monday
+    Week,
+    Month,
+}
+
+impl TryFrom<Duration> for Granularity {
+    type Error = Error;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        match duration.as_secs() {
+            SECONDS_PER_DAY => Ok(Granularity::Day),
+            604800 => Ok(Granularity::Week),
+            // rust and postgres disagree on the number of seconds in a month
+            2500000..=3000000 => Ok(Granularity::Month),
+            secs if secs > 0
+                && duration.subsec_nanos() == 0
+                && SECONDS_PER_DAY.is_multiple_of(secs) =>
+            {
+                Ok(Granularity::Fixed(duration))
+            }
+            _ => Err(Error::Runtime(RuntimeError::from_msg(format!(
+                "Unsupported granularity: {}",
+                humantime::format_duration(duration)
+            )))),
+        }
+    }
+}
+
+impl FromStr for Granularity {
+    type Err = Error;
+
+    /// Parse a granularity in PostgreSQL interval or humantime notation, such
+    /// as `00:15:00`, `1 day`, `1 mon` or `15m`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Granularity::try_from(parse_interval(s)?)
+    }
+}
+
+impl fmt::Display for Granularity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Granularity::Fixed(duration) => write!(f, "{}", humantime::format_duration(*duration)),
+            Granularity::Day => write!(f, "1day"),
+            Granularity::Week => write!(f, "7days"),
+            Granularity::Month => write!(f, "1month"),
+        }
+    }
+}
+
+/// Convert a local time to a timestamp, moving forward out of a DST gap
+fn from_local<Tz: TimeZone>(tz: &Tz, local: NaiveDateTime) -> Result<DateTime<Tz>, Error> {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + chrono::Duration::hours(1)))
+                .earliest()
+        })
+        .ok_or_else(|| {
+            Error::Runtime(RuntimeError::from_msg(format!(
+                "Local time {local} does not exist"
+            )))
+        })
+}
+
+fn start_of_day(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+impl Granularity {
+    /// Duration as stored in trend store and trigger definitions
+    pub fn duration(&self) -> Duration {
+        match self {
+            Granularity::Fixed(duration) => *duration,
+            Granularity::Day => Duration::from_secs(SECONDS_PER_DAY),
+            Granularity::Week => Duration::from_secs(7 * SECONDS_PER_DAY),
+            Granularity::Month => humantime::parse_duration("1month").unwrap(),
+        }
+    }
+
+    /// Start of the period containing `timestamp` in its timezone
+    pub fn truncate<Tz: TimeZone>(&self, timestamp: &DateTime<Tz>) -> Result<DateTime<Tz>, Error> {
+        let local = timestamp.naive_local();
+        let date = local.date();
+
+        let truncated = match self {
+            Granularity::Fixed(duration) => {
+                // Fixed periods are measured in absolute time from the start
+                // of the local day, so a day with a DST change has more or
+                // fewer periods instead of repeating or skipping local times
+                let midnight = from_local(&timestamp.timezone(), start_of_day(date))?;
+                let secs = duration.as_secs() as i64;
+                let elapsed = (timestamp.clone() - midnight.clone()).num_seconds();
+
+                return Ok(midnight + chrono::Duration::seconds(elapsed - elapsed % secs));
+            }
+            Granularity::Day => start_of_day(date),
+            Granularity::Week => {
+                start_of_day(date - Days::new(date.weekday().num_days_from_monday() as u64))
+            }
+            Granularity::Month => start_of_day(date.with_day(1).unwrap()),
+        };
+
+        from_local(&timestamp.timezone(), truncated)
+    }
+
+    /// Start of the period following the one containing `timestamp`
+    pub fn next<Tz: TimeZone>(&self, timestamp: &DateTime<Tz>) -> Result<DateTime<Tz>, Error> {
+        let start = self.truncate(timestamp)?;
+        let date = start.naive_local().date();
+
+        match self {
+            // The last period of a day is cut short at midnight when the
+            // day is not a multiple of the period, e.g. 2h on a 25 hour day
+            Granularity::Fixed(duration) => self.truncate(&(start + *duration)),
+            Granularity::Day => from_local(&start.timezone(), start_of_day(date + Days::new(1))),
+            Granularity::Week => from_local(&start.timezone(), start_of_day(date + Days::new(7))),
+            Granularity::Month => {
+                from_local(&start.timezone(), start_of_day(date + Months::new(1)))
+            }
+        }
+    }
+
+    /// First period start at or after `timestamp`
+    pub fn ceil<Tz: TimeZone>(&self, timestamp: &DateTime<Tz>) -> Result<DateTime<Tz>, Error> {
+        let truncated = self.truncate(timestamp)?;
+
+        match truncated < *timestamp {
+            true => self.next(timestamp),
+            false => Ok(truncated),
+        }
+    }
+
+    /// Period starts from `start` up to and including `end`
+    pub fn timestamps<Tz: TimeZone>(
+        &self,
+        start: &DateTime<Tz>,
+        end: &DateTime<Tz>,
+    ) -> Result<Vec<DateTime<Tz>>, Error> {
+        let mut timestamps = Vec::new();
+
+        let mut timestamp = self.ceil(start)?;
+
+        while timestamp <= *end {
+            let next = self.next(&timestamp)?;
+            timestamps.push(timestamp);
+            timestamp = next;
+        }
+
+        Ok(timestamps)
+    }
+
+    /// Period starts from `start` up to but not including `end`, aligned in
+    /// `timezone`
+    pub fn timestamps_in_range<Tz: TimeZone>(
+        &self,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+        timezone: &Tz,
+    ) -> Result<Vec<DateTime<Utc>>, Error> {
+        Ok(self
+            .timestamps(&start.with_timezone(timezone), &end.with_timezone(timezone))?
+            .iter()
+            .map(|timestamp| timestamp.with_timezone(&Utc))
+            .filter(|timestamp| timestamp < end)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::FixedOffset;
+
+    use super::*;
+
+    fn format_all<Tz: TimeZone>(timestamps: &[DateTime<Tz>]) -> Vec<String>
+    where
+        Tz::Offset: fmt::Display,
+    {
+        timestamps
+            .iter()
+            .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn parse_granularities() {
+        let parsed: Vec<Granularity> = ["00:05:00", "30m", "1h", "1 day", "7 days", "1 mon"]
+            .iter()
+            .map(|s| Granularity::from_str(s).unwrap())
+            .collect();
+
+        assert_eq!(
+            parsed,
+            vec![
+                Granularity::Fixed(Duration::from_secs(300)),
+                Granularity::Fixed(Duration::from_secs(1800)),
+                Granularity::Fixed(Duration::from_secs(3600)),
+                Granularity::Day,
+                Granularity::Week,
+                Granularity::Month,
+            ]
+        );
+
+        assert!(Granularity::from_str("7m").is_err());
+    }
+
+    #[test]
+    fn truncate_in_timezone() {
+        let tz = FixedOffset::east_opt(2 * 3600).unwrap();
+        // Thursday
+        let timestamp = tz.with_ymd_and_hms(2024, 8, 15, 10, 44, 0).unwrap();
+
+        let truncated: Vec<DateTime<FixedOffset>> = [
+            Granularity::Fixed(Duration::from_secs(300)),
+            Granularity::Fixed(Duration::from_secs(1800)),
+            Granularity::Day,
+            Granularity::Week,
+            Granularity::Month,
+        ]
+        .iter()
+        .map(|g| g.truncate(&timestamp).unwrap())
+        .collect();
+
+        assert_eq!(
+            format_all(&truncated),
+            vec![
+                "2024-08-15 10:40",
+                "2024-08-15 10:30",
+                "2024-08-15 00:00",
+                "2024-08-12 00:00",
+                "2024-08-01 00:00",
+            ]
+        );
+    }
+
+    #[test]
+    fn step_over_calendar_months() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+
+        let timestamps = Granularity::Month.timestamps(&start, &end).unwrap();
+
+        assert_eq!(
+            format_all(&timestamps),
+            vec!["2024-02-01 00:00", "2024-03-01 00:00", "2024-04-01 00:00"]
+        );
+    }
+
+    #[test]
+    fn days_in_named_timezone_over_dst_change() {
+        let timezone = parse_timezone("Europe/Amsterdam").unwrap();
+
+        let start = Utc.with_ymd_and_hms(2024, 3, 29, 12, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 4, 1, 22, 0, 0).unwrap();
+
+        let timestamps = Granularity::Day
+            .timestamps_in_range(&start, &end, &timezone)
+            .unwrap();
+
+        // Midnight in Amsterdam moves from UTC+1 to UTC+2 on March 31st
+        assert_eq!(
+            format_all(&timestamps),
+            vec!["2024-03-29 23:00", "2024-03-30 23:00", "2024-03-31 22:00"]
+        );
+
+        assert!(parse_timezone("Mars/Olympus_Mons").is_err());
+    }
+
+    #[test]
+    fn quarters_in_named_timezone_when_clocks_go_back() {
+        let timezone = parse_timezone("Europe/Amsterdam").unwrap();
+
+        // 03:00 CEST becomes 02:00 CET at 01:00 UTC on October 27th
+        let start = Utc.with_ymd_and_hms(2024, 10, 27, 0, 30, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 10, 27, 1, 30, 0).unwrap();
+
+        let timestamps = Granularity::Fixed(Duration::from_secs(900))
+            .timestamps_in_range(&start, &end, &timezone)
+            .unwrap();
+
+        assert_eq!(
+            format_all(&timestamps),
+            vec![
+                "2024-10-27 00:30",
+                "2024-10-27 00:45",
+                "2024-10-27 01:00",
+                "2024-10-27 01:15"
+            ]
+        );
+
+        let repeated_hour = Utc
+            .with_ymd_and_hms(2024, 10, 27, 1, 15, 0)
+            .unwrap()
+            .with_timezone(&timezone);
+
+        assert_eq!(
+            Granularity::Fixed(Duration::from_secs(900))
+                .truncate(&repeated_hour)
+                .unwrap(),
+            repeated_hour
+        );
+
+        // The day from midnight to midnight in Amsterdam lasts 25 hours
+        let hours = Granularity::Fixed(Duration::from_secs(3600))
+            .timestamps_in_range(
+                &Utc.with_ymd_and_hms(2024, 10, 26, 22, 0, 0).unwrap(),
+                &Utc.with_ymd_and_hms(2024, 10, 27, 23, 0, 0).unwrap(),
+                &timezone,
+            )
+            .unwrap();
+
+        assert_eq!(hours.len(), 25);
+    }
+}
@@ -11,7 +11,7 @@ use super::attribute_store::{load_attribute_stores, AddAttributeStore, Attribute
 use super::change::Change;
 use super::changes::trend_store::AddTrendStore;
 use super::entity_set::{load_entity_sets, EntitySet};
-use super::error::Error;
+use super::error::{ConfigurationError, Error};
 use super::notification_store::{
     load_notification_stores, AddNotificationStore, NotificationStore,
 };
@@ -22,6 +22,7 @@ use super::trend_materialization::{
 };
 use super::trend_store::{load_trend_store_from_file, load_trend_stores, TrendStore};
 use super::trigger::{load_trigger_from_file, load_triggers, AddTrigger, Trigger};
+use super::trigger_template::load_template_triggers_from;
 use super::virtual_entity::{load_virtual_entity_from_file, AddVirtualEntity, VirtualEntity};
 
 pub struct MinervaInstance {
@@ -71,17 +72,33 @@ impl MinervaInstance {
         })
     }
 
-    pub fn load_from(minerva_instance_root: &Path) -> MinervaInstance {
+    pub fn load_from(minerva_instance_root: &Path) -> Result<MinervaInstance, Error> {
         let trend_stores = load_trend_stores_from(minerva_instance_root).collect();
         let notification_stores = load_notification_stores_from(minerva_instance_root).collect();
         let attribute_stores = load_attribute_stores_from(minerva_instance_root).collect();
         let virtual_entities = load_virtual_entities_from(minerva_instance_root).collect();
         let relations = load_relations_from(minerva_instance_root).collect();
         let trend_materializations = load_materializations_from(minerva_instance_root).collect();
-        let triggers = load_triggers_from(minerva_instance_root).collect();
+        let triggers: Vec<Trigger> = load_triggers_from(minerva_instance_root)
+            .chain(load_template_triggers_from(minerva_instance_root))
+            .collect();
         let entity_sets: Vec<EntitySet> = vec![];
 
-        MinervaInstance {
+        // A template can expand to the name of a trigger that is also defined
+        // in the trigger directory.
+        for (index, trigger) in triggers.iter().enumerate() {
+            if triggers[..index]
+                .iter()
+                .any(|other_trigger| other_trigger.name == trigger.name)
+            {
+                return Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                    "Trigger '{}' is defined more than once in the instance definition",
+                    trigger.name
+                ))));
+            }
+        }
+
+        Ok(MinervaInstance {
             instance_root: Some(PathBuf::from(minerva_instance_root)),
             trend_stores,
             attribute_stores,
@@ -91,7 +108,7 @@ impl MinervaInstance {
             trend_materializations,
             triggers,
             entity_sets,
-        }
+        })
     }
 
     pub async fn initialize(&self, client: &mut Client) -> Result<(), Error> {
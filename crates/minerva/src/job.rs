@@ -1,7 +1,12 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio_postgres::GenericClient;
+use tokio_postgres::{GenericClient, Row};
 
-use crate::error::{DatabaseError, Error};
+use crate::error::{DatabaseError, Error, RuntimeError};
 
 pub async fn start_job<T: GenericClient + Send + Sync>(
     client: &T,
@@ -21,10 +26,7 @@ pub async fn start_job<T: GenericClient + Send + Sync>(
     Ok(job_id)
 }
 
-pub async fn end_job<T: GenericClient + Send + Sync>(
-    client: &T,
-    job_id: i64,
-) -> Result<(), Error> {
+pub async fn end_job<T: GenericClient + Send + Sync>(client: &T, job_id: i64) -> Result<(), Error> {
     let query = "SELECT logging.end_job($1)";
 
     client
@@ -34,3 +36,146 @@ pub async fn end_job<T: GenericClient + Send + Sync>(
 
     Ok(())
 }
+
+pub async fn fail_job<T: GenericClient + Send + Sync>(
+    client: &T,
+    job_id: i64,
+    message: &str,
+) -> Result<(), Error> {
+    let query = "SELECT logging.fail_job($1, $2)";
+
+    client
+        .execute(query, &[&job_id, &message])
+        .await
+        .map_err(|e| Error::Database(DatabaseError::from_msg(format!("Error failing job: {e}"))))?;
+
+    Ok(())
+}
+
+pub async fn set_job_progress<T: GenericClient + Send + Sync>(
+    client: &T,
+    job_id: i64,
+    progress: f32,
+) -> Result<(), Error> {
+    let query = "SELECT logging.set_job_progress($1, $2)";
+
+    client
+        .execute(query, &[&job_id, &progress])
+        .await
+        .map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Error setting job progress: {e}"
+            )))
+        })?;
+
+    Ok(())
+}
+
+pub async fn append_job_log<T: GenericClient + Send + Sync>(
+    client: &T,
+    job_id: i64,
+    message: &str,
+) -> Result<(), Error> {
+    let query = "SELECT logging.append_job_log($1, $2)";
+
+    client
+        .execute(query, &[&job_id, &message])
+        .await
+        .map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Error appending to job log: {e}"
+            )))
+        })?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Finished,
+    Failed,
+}
+
+impl FromStr for JobStatus {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "running" => Ok(JobStatus::Running),
+            "finished" => Ok(JobStatus::Finished),
+            "failed" => Ok(JobStatus::Failed),
+            _ => Err(Error::Runtime(RuntimeError::from_msg(format!(
+                "Unknown job status '{s}'"
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JobStatus::Running => write!(f, "running"),
+            JobStatus::Finished => write!(f, "finished"),
+            JobStatus::Failed => write!(f, "failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: i64,
+    pub action: Value,
+    pub status: JobStatus,
+    pub progress: Option<f32>,
+    pub log: Vec<String>,
+    pub started: DateTime<Utc>,
+    pub finished: Option<DateTime<Utc>>,
+}
+
+impl Job {
+    fn from_row(row: &Row) -> Result<Job, Error> {
+        let status: String = row.get(2);
+
+        Ok(Job {
+            id: row.get(0),
+            action: row.get(1),
+            status: JobStatus::from_str(&status)?,
+            progress: row.get(3),
+            log: row.get(4),
+            started: row.get(5),
+            finished: row.get(6),
+        })
+    }
+}
+
+const JOB_COLUMNS: &str = "id, action, status, progress, log, started, finished";
+
+pub async fn load_job<T: GenericClient + Send + Sync>(
+    client: &T,
+    job_id: i64,
+) -> Result<Option<Job>, Error> {
+    let query = format!("SELECT {JOB_COLUMNS} FROM logging.job WHERE id = $1");
+
+    let row = client
+        .query_opt(&query, &[&job_id])
+        .await
+        .map_err(|e| Error::Database(DatabaseError::from_msg(format!("Error loading job: {e}"))))?;
+
+    row.as_ref().map(Job::from_row).transpose()
+}
+
+/// Load the most recently started jobs, newest first
+pub async fn load_jobs<T: GenericClient + Send + Sync>(
+    client: &T,
+    limit: i64,
+) -> Result<Vec<Job>, Error> {
+    let query = format!("SELECT {JOB_COLUMNS} FROM logging.job ORDER BY id DESC LIMIT $1");
+
+    let rows = client.query(&query, &[&limit]).await.map_err(|e| {
+        Error::Database(DatabaseError::from_msg(format!("Error loading jobs: {e}")))
+    })?;
+
+    rows.iter().map(Job::from_row).collect()
+}
@@ -3,9 +3,11 @@ pub mod attribute_store;
 pub mod change;
 pub mod changes;
 pub mod database;
+pub mod dependency;
 pub mod entity;
 pub mod entity_set;
 pub mod error;
+pub mod granularity;
 pub mod instance;
 pub mod interval;
 pub mod job;
@@ -14,9 +16,17 @@ pub mod meas_value;
 pub mod notification_store;
 pub mod relation;
 pub mod schema;
+pub mod trend_completeness;
+pub mod trend_export;
 pub mod trend_materialization;
+pub mod trend_migration;
 pub mod trend_store;
 pub mod trigger;
+pub mod trigger_backtest;
+pub mod trigger_exception;
+pub mod trigger_lint;
+pub mod trigger_runner;
+pub mod trigger_template;
 pub mod virtual_entity;
 
 #[cfg(feature = "test-containers")]
@@ -1,21 +1,28 @@
+use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, Read};
 use std::path::Path;
+use std::str::FromStr;
 
+use arrow::array::{Array, ArrayRef, AsArray, ListArray};
+use arrow::util::display::{ArrayFormatter, FormatOptions};
 use log::debug;
 use chrono::DateTime;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::file::reader::ChunkReader;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use tokio_postgres::Client;
 
 use crate::entity::CachingEntityMapping;
 use crate::error::{Error, RuntimeError};
-use crate::interval::parse_interval;
+use crate::granularity::Granularity;
 use crate::job::{end_job, start_job};
+use crate::meas_value::{array_literal, ValueParserConfig};
 use crate::trend_store::get_trend_store_id;
 use crate::trend_store::{
     create_partitions_for_trend_store_and_timestamp, load_trend_store, RawMeasurementStore,
-    TrendStore,
+    LoadMode, RawLoadOptions, TrendStore,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -37,36 +44,48 @@ pub struct ParserConfig {
     pub trends: TrendsFrom,
     pub extra: Option<Value>,
     pub null_value: String,
+    /// Format of array values per trend, trends without an entry use the
+    /// default `{1,2,3}` format
+    #[serde(default)]
+    pub value_parsers: HashMap<String, ValueParserConfig>,
+    /// How loaded data is combined with existing data
+    #[serde(default)]
+    pub load_mode: LoadMode,
 }
 
+/// Load trend data from a CSV or Parquet file. Timestamps must be aligned to
+/// the granularity of the trend store in `timezone`.
 pub async fn load_data<P: AsRef<Path>>(
     client: &mut Client,
     data_source: &str,
     parser_config: &ParserConfig,
     file_path: P,
     create_partitions: bool,
+    timezone: &chrono_tz::Tz,
 ) -> Result<(), Error> {
-    let description = json!({"csv-load": file_path.as_ref().to_string_lossy()});
+    let is_parquet = file_path
+        .as_ref()
+        .extension()
+        .is_some_and(|extension| extension == "parquet");
 
-    let f = File::open(file_path).map_err(|e| format!("{}", e))?;
+    let description = match is_parquet {
+        true => json!({"parquet-load": file_path.as_ref().to_string_lossy()}),
+        false => json!({"csv-load": file_path.as_ref().to_string_lossy()}),
+    };
 
-    let reader = BufReader::new(f);
+    let f = File::open(file_path).map_err(|e| format!("{}", e))?;
 
-    let mut csv_reader = csv::Reader::from_reader(reader);
+    let (headers, records) = match is_parquet {
+        true => read_parquet_records(f, &parser_config.null_value)?,
+        false => read_csv_records(BufReader::new(f))?,
+    };
 
     let (trends, entity_column, timestamp_column) = match &parser_config.trends {
-        TrendsFrom::Header(from_header) => {
-            let trends = match csv_reader.headers() {
-                Ok(headers) => headers.iter().map(String::from).collect(),
-                Err(_) => Vec::new(),
-            };
-
-            (
-                trends,
-                from_header.entity_column.clone(),
-                from_header.timestamp_column.clone(),
-            )
-        }
+        TrendsFrom::Header(from_header) => (
+            headers,
+            from_header.entity_column.clone(),
+            from_header.timestamp_column.clone(),
+        ),
         TrendsFrom::List(list) => (
             list.clone(),
             String::from("entity"),
@@ -92,33 +111,36 @@ pub async fn load_data<P: AsRef<Path>>(
         }
     };
 
-    let job_id = start_job(client, &description).await?;
-
-    debug!("Started job with Id {job_id}");
-
-    let raw_data_package: Vec<(String, DateTime<chrono::Utc>, Vec<String>)> = csv_reader
-        .records()
-        .map(|record| {
-            let record = record.unwrap();
-
-            let entity: String = String::from(record.get(entity_column_index).unwrap());
-            let timestamp_txt: &str = record.get(timestamp_column_index).unwrap();
+    let raw_data_package: Vec<(String, DateTime<chrono::Utc>, Vec<String>)> = records
+        .into_iter()
+        .map(|values| {
+            let entity: String = values[entity_column_index].clone();
+            let timestamp_txt: &str = &values[timestamp_column_index];
 
             let timestamp: DateTime<chrono::Utc> = DateTime::parse_from_rfc3339(timestamp_txt)
                 .unwrap()
                 .with_timezone(&chrono::offset::Utc);
 
-            let values: Vec<String> = record.iter().map(String::from).collect();
-
             let record: (String, DateTime<chrono::Utc>, Vec<String>) = (entity, timestamp, values);
 
             record
         })
         .collect();
 
-    let granularity = parse_interval(&parser_config.granularity).unwrap();
+    let granularity = Granularity::from_str(&parser_config.granularity)
+        .map_err(|e| format!("Invalid granularity '{}': {e}", parser_config.granularity))?;
 
-    let trend_store: TrendStore = load_trend_store(client, data_source, &parser_config.entity_type, &granularity)
+    for (entity, timestamp, _) in &raw_data_package {
+        if granularity.truncate(&timestamp.with_timezone(timezone))? != *timestamp {
+            return Err(Error::Runtime(RuntimeError::from_msg(format!(
+                "Timestamp {} of '{entity}' is not aligned to granularity '{}' in timezone '{timezone}'",
+                timestamp.to_rfc3339(),
+                parser_config.granularity
+            ))));
+        }
+    }
+
+    let trend_store: TrendStore = load_trend_store(client, data_source, &parser_config.entity_type, &granularity.duration())
         .await
         .map_err(|e| format!("Error loading trend store for data source '{data_source}', entity type '{}' and granularity '{}': {e}", parser_config.entity_type, parser_config.granularity))?;
 
@@ -138,6 +160,10 @@ pub async fn load_data<P: AsRef<Path>>(
         }
     }
 
+    let job_id = start_job(client, &description).await?;
+
+    debug!("Started job with Id {job_id}");
+
     let entity_mapping = CachingEntityMapping::new(100);
 
     trend_store
@@ -147,7 +173,11 @@ pub async fn load_data<P: AsRef<Path>>(
             job_id,
             &trends,
             &raw_data_package,
-            parser_config.null_value.clone(),
+            &RawLoadOptions {
+                null_value: parser_config.null_value.clone(),
+                value_parsers: parser_config.value_parsers.clone(),
+                load_mode: parser_config.load_mode,
+            },
         )
         .await?;
 
@@ -157,3 +187,126 @@ pub async fn load_data<P: AsRef<Path>>(
 
     Ok(())
 }
+
+/// Read the header and all records of a CSV file
+fn read_csv_records<R: Read>(reader: R) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+
+    let headers = match csv_reader.headers() {
+        Ok(headers) => headers.iter().map(String::from).collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let records = csv_reader
+        .records()
+        .map(|record| record.map(|record| record.iter().map(String::from).collect()))
+        .collect::<Result<Vec<Vec<String>>, csv::Error>>()
+        .map_err(|e| format!("Error reading CSV record: {e}"))?;
+
+    Ok((headers, records))
+}
+
+/// Read the column names and all rows of a Parquet file as text values, in
+/// the same format as CSV values, so that they can be parsed the same way
+pub(crate) fn read_parquet_records<R: ChunkReader + 'static>(
+    reader: R,
+    null_value: &str,
+) -> Result<(Vec<String>, Vec<Vec<String>>), Error> {
+    let builder = ParquetRecordBatchReaderBuilder::try_new(reader)
+        .map_err(|e| format!("Error opening Parquet file: {e}"))?;
+
+    let headers: Vec<String> = builder
+        .schema()
+        .fields()
+        .iter()
+        .map(|field| field.name().clone())
+        .collect();
+
+    let reader = builder
+        .build()
+        .map_err(|e| format!("Error reading Parquet file: {e}"))?;
+
+    let mut records: Vec<Vec<String>> = Vec::new();
+
+    for batch in reader {
+        let batch = batch.map_err(|e| format!("Error reading Parquet record batch: {e}"))?;
+
+        let columns = batch
+            .columns()
+            .iter()
+            .map(|column| ParquetColumn::new(column, null_value))
+            .collect::<Result<Vec<ParquetColumn>, Error>>()?;
+
+        for row in 0..batch.num_rows() {
+            records.push(columns.iter().map(|column| column.value(row)).collect());
+        }
+    }
+
+    Ok((headers, records))
+}
+
+/// Text formatting of a Parquet column, with arrays formatted like `{1,2,3}`
+enum ParquetColumn<'a> {
+    Scalar {
+        array: &'a ArrayRef,
+        formatter: ArrayFormatter<'a>,
+        null_value: &'a str,
+    },
+    List {
+        array: &'a ListArray,
+        formatter: ArrayFormatter<'a>,
+        null_value: &'a str,
+    },
+}
+
+impl<'a> ParquetColumn<'a> {
+    fn new(array: &'a ArrayRef, null_value: &'a str) -> Result<ParquetColumn<'a>, Error> {
+        let options = FormatOptions::default().with_null(null_value);
+
+        match array.as_list_opt::<i32>() {
+            Some(list_array) => Ok(ParquetColumn::List {
+                array: list_array,
+                formatter: ArrayFormatter::try_new(list_array.values().as_ref(), &options)
+                    .map_err(|e| format!("Unsupported Parquet column type: {e}"))?,
+                null_value,
+            }),
+            None => Ok(ParquetColumn::Scalar {
+                array,
+                formatter: ArrayFormatter::try_new(array.as_ref(), &options)
+                    .map_err(|e| format!("Unsupported Parquet column type: {e}"))?,
+                null_value,
+            }),
+        }
+    }
+
+    fn value(&self, row: usize) -> String {
+        match self {
+            ParquetColumn::Scalar {
+                array,
+                formatter,
+                null_value,
+            } => match array.is_null(row) {
+                true => null_value.to_string(),
+                false => formatter.value(row).to_string(),
+            },
+            ParquetColumn::List {
+                array,
+                formatter,
+                null_value,
+            } => match array.is_null(row) {
+                true => null_value.to_string(),
+                false => {
+                    let offsets = array.value_offsets();
+                    let start = offsets[row] as usize;
+                    let end = offsets[row + 1] as usize;
+
+                    let values: Vec<String> = (start..end)
+                        .map(|index| formatter.value(index).to_string())
+                        .collect();
+
+                    array_literal(&values)
+                }
+            },
+        }
+    }
+}
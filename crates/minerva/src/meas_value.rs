@@ -1,5 +1,7 @@
 use std::fmt::{self};
+use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
 use rust_decimal::prelude::*;
 
 use lazy_static::lazy_static;
@@ -7,7 +9,7 @@ use postgres_types::{to_sql_checked, Type};
 use serde::{Deserialize, Serialize};
 use tokio_postgres::types::ToSql;
 
-use crate::error::Error;
+use crate::error::{Error, RuntimeError};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
 pub enum DataType {
@@ -90,70 +92,272 @@ impl fmt::Display for DataType {
 impl From<&str> for DataType {
     fn from(value: &str) -> DataType {
         match value {
+            "boolean" | "bool" => DataType::Boolean,
             "smallint" => DataType::Int2,
             "integer" => DataType::Integer,
             "bigint" => DataType::Int8,
             "numeric" => DataType::Numeric,
+            "numeric[]" => DataType::NumericArray,
             "real" => DataType::Real,
             "double precision" => DataType::Double,
             "text" => DataType::Text,
             "text[]" => DataType::TextArray,
-            "timestamptz" => DataType::Timestamp,
+            "timestamp" | "timestamptz" | "timestamp with time zone" => DataType::Timestamp,
             &_ => DataType::Text,
         }
     }
 }
 
+impl DataType {
+    /// Postgres type used to store values of this type
+    pub fn sql_type(&self) -> Type {
+        match self {
+            DataType::Boolean => Type::BOOL,
+            DataType::Int2 => Type::INT2,
+            DataType::Integer => Type::INT4,
+            DataType::Int8 => Type::INT8,
+            DataType::Numeric => Type::NUMERIC,
+            DataType::NumericArray => Type::NUMERIC_ARRAY,
+            DataType::Real => Type::FLOAT4,
+            DataType::Double => Type::FLOAT8,
+            DataType::Text => Type::TEXT,
+            DataType::TextArray => Type::TEXT_ARRAY,
+            DataType::Timestamp => Type::TIMESTAMPTZ,
+        }
+    }
+}
+
+fn default_separator() -> String {
+    String::from(",")
+}
+
+fn default_prefix() -> String {
+    String::from("{")
+}
+
+fn default_postfix() -> String {
+    String::from("}")
+}
+
+/// Format of array values in raw data, like `{1,2,3}` for the default
+/// configuration.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ValueParserConfig {
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    /// Stripped from the start of the value when present
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    /// Stripped from the end of the value when present
+    #[serde(default = "default_postfix")]
+    pub postfix: String,
+}
+
+impl Default for ValueParserConfig {
+    fn default() -> Self {
+        ValueParserConfig {
+            separator: default_separator(),
+            prefix: default_prefix(),
+            postfix: default_postfix(),
+        }
+    }
+}
+
+impl ValueParserConfig {
+    /// Split an array value into its trimmed elements. Elements can be quoted
+    /// with double quotes like in Postgres array literals, with a backslash
+    /// escaping the next character.
+    pub fn split_array(&self, value: &str) -> Vec<String> {
+        let value = value.trim();
+        let value = value.strip_prefix(&self.prefix).unwrap_or(value);
+        let value = value.strip_suffix(&self.postfix).unwrap_or(value);
+
+        if value.trim().is_empty() {
+            return Vec::new();
+        }
+
+        if self.separator.is_empty() {
+            return vec![value.trim().to_string()];
+        }
+
+        let mut elements: Vec<String> = Vec::new();
+        let mut rest = value.trim_start();
+
+        loop {
+            let (element, remainder) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let mut element = String::new();
+                    let mut end = quoted.len();
+                    let mut chars = quoted.char_indices();
+
+                    while let Some((index, c)) = chars.next() {
+                        match c {
+                            '\\' => element.extend(chars.next().map(|(_, escaped)| escaped)),
+                            '"' => {
+                                end = index + 1;
+                                break;
+                            }
+                            c => element.push(c),
+                        }
+                    }
+
+                    (element, &quoted[end..])
+                }
+                None => {
+                    let end = rest.find(self.separator.as_str()).unwrap_or(rest.len());
+
+                    (rest[..end].trim().to_string(), &rest[end..])
+                }
+            };
+
+            elements.push(element);
+
+            match remainder.find(self.separator.as_str()) {
+                Some(index) => rest = remainder[index + self.separator.len()..].trim_start(),
+                None => return elements,
+            }
+        }
+    }
+}
+
+/// Format elements as a Postgres array literal, quoting the elements that
+/// would otherwise not be read back unchanged
+pub fn array_literal<T: fmt::Display>(elements: &[T]) -> String {
+    let elements: Vec<String> = elements
+        .iter()
+        .map(|element| {
+            let element = element.to_string();
+
+            let needs_quotes = element.is_empty()
+                || element.eq_ignore_ascii_case("null")
+                || element
+                    .chars()
+                    .any(|c| matches!(c, ',' | '{' | '}' | '"' | '\\') || c.is_whitespace());
+
+            match needs_quotes {
+                true => format!("\"{}\"", element.replace('\\', "\\\\").replace('"', "\\\"")),
+                false => element,
+            }
+        })
+        .collect();
+
+    format!("{{{}}}", elements.join(","))
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "t" | "yes" | "y" | "1" => Some(true),
+        "false" | "f" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Parse an RFC 3339 timestamp or a timestamp as formatted by Postgres
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z"))
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum MeasValue {
+    Boolean(Option<bool>),
     Int2(Option<i16>),
     Integer(Option<i32>),
     Int8(Option<i64>),
     Real(Option<f32>),
     Double(Option<f64>),
-    Text(String),
-    TextArray(Vec<String>),
-    Timestamp(chrono::DateTime<chrono::Utc>),
+    Text(Option<String>),
+    TextArray(Option<Vec<String>>),
+    Timestamp(Option<DateTime<Utc>>),
     Numeric(Option<Decimal>),
+    NumericArray(Option<Vec<Decimal>>),
 }
 
+/// Parse a value using the default array format, resulting in a NULL value
+/// when it can not be parsed
 pub fn parse_meas_value(data_type: DataType, value: &str) -> MeasValue {
-    match data_type {
-        DataType::Int2 => {
-            let value: Option<i16> = value.parse().ok();
+    parse_meas_value_with(data_type, value, &ValueParserConfig::default())
+        .unwrap_or_else(|_| MeasValue::null_value_of_type(data_type))
+}
 
-            MeasValue::Int2(value)
-        }
-        DataType::Integer => {
-            let value: Option<i32> = value.parse().ok();
+/// Parse a value of the specified type, with arrays in the format of
+/// `parser_config`
+pub fn parse_meas_value_with(
+    data_type: DataType,
+    value: &str,
+    parser_config: &ValueParserConfig,
+) -> Result<MeasValue, Error> {
+    let parse_error = |description: &str, e: String| {
+        Error::Runtime(RuntimeError {
+            msg: format!("Could not parse {description} measurement value '{value}': {e}"),
+        })
+    };
 
-            MeasValue::Integer(value)
+    let meas_value = match data_type {
+        DataType::Boolean => {
+            MeasValue::Boolean(Some(parse_bool(value).ok_or_else(|| {
+                parse_error("boolean", "expected true or false".to_string())
+            })?))
         }
-        DataType::Numeric => {
-            let value: Option<Decimal> = value.parse().ok();
-
-            MeasValue::Numeric(value)
+        DataType::Int2 => MeasValue::Int2(Some(
+            i16::from_str(value).map_err(|e| parse_error("smallint", e.to_string()))?,
+        )),
+        DataType::Integer => MeasValue::Integer(Some(
+            i32::from_str(value).map_err(|e| parse_error("integer", e.to_string()))?,
+        )),
+        DataType::Int8 => MeasValue::Int8(Some(
+            i64::from_str(value).map_err(|e| parse_error("bigint", e.to_string()))?,
+        )),
+        DataType::Numeric => MeasValue::Numeric(Some(
+            Decimal::from_str(value).map_err(|e| parse_error("numeric", e.to_string()))?,
+        )),
+        DataType::NumericArray => MeasValue::NumericArray(Some(
+            parser_config
+                .split_array(value)
+                .iter()
+                .map(|element| element.parse())
+                .collect::<Result<Vec<Decimal>, _>>()
+                .map_err(|e| parse_error("numeric array", e.to_string()))?,
+        )),
+        DataType::Real => MeasValue::Real(Some(
+            f32::from_str(value).map_err(|e| parse_error("floating point", e.to_string()))?,
+        )),
+        DataType::Double => MeasValue::Double(Some(
+            f64::from_str(value).map_err(|e| parse_error("floating point", e.to_string()))?,
+        )),
+        DataType::Text => MeasValue::Text(Some(value.to_string())),
+        DataType::TextArray => MeasValue::TextArray(Some(parser_config.split_array(value))),
+        DataType::Timestamp => {
+            MeasValue::Timestamp(Some(parse_timestamp(value).ok_or_else(|| {
+                parse_error("timestamp", "expected an RFC 3339 timestamp".to_string())
+            })?))
         }
-        DataType::Int8 => {
-            let value: Option<i64> = value.parse().ok();
+    };
 
-            MeasValue::Int8(value)
-        }
-        DataType::Real => {
-            let value: Option<f32> = value.parse().ok();
-
-            MeasValue::Real(value)
-        }
-        DataType::Double => {
-            let value: Option<f64> = value.parse().ok();
+    Ok(meas_value)
+}
 
-            MeasValue::Double(value)
-        }
-        _ => {
-            let value: String = value.to_string();
+fn no_mapping<T: fmt::Debug>(value: &T, target_data_type: DataType) -> Error {
+    Error::Runtime(RuntimeError {
+        msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
+    })
+}
 
-            MeasValue::Text(value)
-        }
+pub fn map_bool(value: &Option<bool>, target_data_type: DataType) -> Result<MeasValue, Error> {
+    match target_data_type {
+        DataType::Boolean => Ok(MeasValue::Boolean(*value)),
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(i16::from))),
+        DataType::Integer => Ok(MeasValue::Integer(value.map(i32::from))),
+        DataType::Int8 => Ok(MeasValue::Int8(value.map(i64::from))),
+        DataType::Numeric => Ok(MeasValue::Numeric(value.map(|x| Decimal::from(x as i32)))),
+        DataType::Real => Ok(MeasValue::Real(value.map(|x| f32::from(u8::from(x))))),
+        DataType::Double => Ok(MeasValue::Double(value.map(|x| f64::from(u8::from(x))))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
@@ -165,35 +369,37 @@ pub fn map_int2(value: &Option<i16>, target_data_type: DataType) -> Result<MeasV
         DataType::Numeric => Ok(MeasValue::Numeric(value.map(Decimal::from_i16).flatten())),
         DataType::Double => Ok(MeasValue::Double(value.map(f64::from_i16).flatten())),
         DataType::Real => Ok(MeasValue::Real(value.map(f32::from_i16).flatten())),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| x != 0))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
 pub fn map_int4(value: &Option<i32>, target_data_type: DataType) -> Result<MeasValue, Error> {
     match target_data_type {
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(i16::from_i32).flatten())),
         DataType::Integer => Ok(MeasValue::Integer(*value)),
         DataType::Int8 => Ok(MeasValue::Int8(value.map(|x| x as i64))),
         DataType::Numeric => Ok(MeasValue::Numeric(value.map(Decimal::from_i32).flatten())),
         DataType::Double => Ok(MeasValue::Double(value.map(f64::from_i32).flatten())),
         DataType::Real => Ok(MeasValue::Real(value.map(f32::from_i32).flatten())),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| x != 0))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
 pub fn map_int8(value: &Option<i64>, target_data_type: DataType) -> Result<MeasValue, Error> {
     match target_data_type {
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(i16::from_i64).flatten())),
         DataType::Integer => Ok(MeasValue::Integer(value.map(|x| x as i32))),
         DataType::Int8 => Ok(MeasValue::Int8(*value)),
         DataType::Numeric => Ok(MeasValue::Numeric(value.map(Decimal::from_i64).flatten())),
         DataType::Double => Ok(MeasValue::Double(value.map(f64::from_i64).flatten())),
         DataType::Real => Ok(MeasValue::Real(value.map(f32::from_i64).flatten())),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| x != 0))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
@@ -202,11 +408,12 @@ pub fn map_real(value: &Option<f32>, target_data_type: DataType) -> Result<MeasV
         DataType::Numeric => Ok(MeasValue::Numeric(value.map(Decimal::from_f32).flatten())),
         DataType::Real => Ok(MeasValue::Real(*value)),
         DataType::Double => Ok(MeasValue::Double(value.map(f64::from_f32).flatten())),
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(i16::from_f32).flatten())),
         DataType::Int8 => Ok(MeasValue::Int8(value.map(i64::from_f32).flatten())),
         DataType::Integer => Ok(MeasValue::Integer(value.map(i32::from_f32).flatten())),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| x != 0.0))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
@@ -214,11 +421,13 @@ pub fn map_double(value: &Option<f64>, target_data_type: DataType) -> Result<Mea
     match target_data_type {
         DataType::Numeric => Ok(MeasValue::Numeric(value.map(Decimal::from_f64).flatten())),
         DataType::Double => Ok(MeasValue::Double(*value)),
+        DataType::Real => Ok(MeasValue::Real(value.map(f32::from_f64).flatten())),
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(i16::from_f64).flatten())),
         DataType::Int8 => Ok(MeasValue::Int8(value.map(i64::from_f64).flatten())),
         DataType::Integer => Ok(MeasValue::Integer(value.map(i32::from_f64).flatten())),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| x != 0.0))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
@@ -227,90 +436,190 @@ pub fn map_numeric(
     target_data_type: DataType,
 ) -> Result<MeasValue, Error> {
     match target_data_type {
+        DataType::Int2 => Ok(MeasValue::Int2(value.map(|x| x.to_i16()).flatten())),
         DataType::Integer => Ok(MeasValue::Integer(value.map(|x| x.to_i32()).flatten())),
         DataType::Int8 => Ok(MeasValue::Int8(value.map(|x| x.to_i64()).flatten())),
         DataType::Real => Ok(MeasValue::Real(value.map(|x| x.to_f32()).flatten())),
         DataType::Double => Ok(MeasValue::Double(value.map(|x| x.to_f64()).flatten())),
         DataType::Numeric => Ok(MeasValue::Numeric(*value)),
-        _ => Err(Error::Runtime(crate::error::RuntimeError {
-            msg: format!("No mapping defined for {:?} -> {}", value, target_data_type),
-        })),
+        DataType::Boolean => Ok(MeasValue::Boolean(value.map(|x| !x.is_zero()))),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_string()))),
+        _ => Err(no_mapping(value, target_data_type)),
+    }
+}
+
+/// Parse text into the target type, where text that can not be parsed is an
+/// error instead of a NULL value
+pub fn map_text(value: &Option<String>, target_data_type: DataType) -> Result<MeasValue, Error> {
+    match value {
+        None => Ok(MeasValue::null_value_of_type(target_data_type)),
+        Some(text) => parse_meas_value_with(target_data_type, text, &ValueParserConfig::default()),
+    }
+}
+
+pub fn map_text_array(
+    value: &Option<Vec<String>>,
+    target_data_type: DataType,
+) -> Result<MeasValue, Error> {
+    match target_data_type {
+        DataType::TextArray => Ok(MeasValue::TextArray(value.clone())),
+        DataType::NumericArray => match value {
+            None => Ok(MeasValue::NumericArray(None)),
+            Some(elements) => elements
+                .iter()
+                .map(|element| element.trim().parse::<Decimal>())
+                .collect::<Result<Vec<Decimal>, _>>()
+                .map(|elements| MeasValue::NumericArray(Some(elements)))
+                .map_err(|_| no_mapping(value, target_data_type)),
+        },
+        DataType::Text => Ok(MeasValue::Text(value.as_ref().map(|x| x.join(",")))),
+        _ => Err(no_mapping(value, target_data_type)),
+    }
+}
+
+pub fn map_numeric_array(
+    value: &Option<Vec<Decimal>>,
+    target_data_type: DataType,
+) -> Result<MeasValue, Error> {
+    match target_data_type {
+        DataType::NumericArray => Ok(MeasValue::NumericArray(value.clone())),
+        DataType::TextArray => Ok(MeasValue::TextArray(
+            value
+                .as_ref()
+                .map(|x| x.iter().map(Decimal::to_string).collect()),
+        )),
+        DataType::Text => Ok(MeasValue::Text(value.as_ref().map(|x| {
+            x.iter()
+                .map(Decimal::to_string)
+                .collect::<Vec<String>>()
+                .join(",")
+        }))),
+        _ => Err(no_mapping(value, target_data_type)),
+    }
+}
+
+pub fn map_timestamp(
+    value: &Option<DateTime<Utc>>,
+    target_data_type: DataType,
+) -> Result<MeasValue, Error> {
+    match target_data_type {
+        DataType::Timestamp => Ok(MeasValue::Timestamp(*value)),
+        DataType::Text => Ok(MeasValue::Text(value.map(|x| x.to_rfc3339()))),
+        _ => Err(no_mapping(value, target_data_type)),
     }
 }
 
 impl MeasValue {
     pub fn null_value_of_type(data_type: DataType) -> MeasValue {
         match data_type {
+            DataType::Boolean => MeasValue::Boolean(None),
             DataType::Int2 => MeasValue::Int2(None),
             DataType::Integer => MeasValue::Integer(None),
             DataType::Numeric => MeasValue::Numeric(None),
+            DataType::NumericArray => MeasValue::NumericArray(None),
             DataType::Int8 => MeasValue::Int8(None),
             DataType::Real => MeasValue::Real(None),
             DataType::Double => MeasValue::Double(None),
-            _ => MeasValue::Text("".to_string()),
+            DataType::Text => MeasValue::Text(None),
+            DataType::TextArray => MeasValue::TextArray(None),
+            DataType::Timestamp => MeasValue::Timestamp(None),
+        }
+    }
+
+    /// Shared NULL value of a type, for use where a reference is required
+    pub fn null_value_ref(data_type: DataType) -> &'static MeasValue {
+        match data_type {
+            DataType::Boolean => &BOOLEAN_NONE_VALUE,
+            DataType::Int2 => &INT2_NONE_VALUE,
+            DataType::Integer => &INTEGER_NONE_VALUE,
+            DataType::Numeric => &NUMERIC_NONE_VALUE,
+            DataType::NumericArray => &NUMERIC_ARRAY_NONE_VALUE,
+            DataType::Int8 => &INT8_NONE_VALUE,
+            DataType::Real => &REAL_NONE_VALUE,
+            DataType::Double => &DOUBLE_NONE_VALUE,
+            DataType::Text => &TEXT_NONE_VALUE,
+            DataType::TextArray => &TEXT_ARRAY_NONE_VALUE,
+            DataType::Timestamp => &TIMESTAMP_NONE_VALUE,
+        }
+    }
+
+    pub fn data_type(&self) -> DataType {
+        match self {
+            MeasValue::Boolean(_) => DataType::Boolean,
+            MeasValue::Int2(_) => DataType::Int2,
+            MeasValue::Integer(_) => DataType::Integer,
+            MeasValue::Int8(_) => DataType::Int8,
+            MeasValue::Real(_) => DataType::Real,
+            MeasValue::Double(_) => DataType::Double,
+            MeasValue::Text(_) => DataType::Text,
+            MeasValue::TextArray(_) => DataType::TextArray,
+            MeasValue::Timestamp(_) => DataType::Timestamp,
+            MeasValue::Numeric(_) => DataType::Numeric,
+            MeasValue::NumericArray(_) => DataType::NumericArray,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        match self {
+            MeasValue::Boolean(v) => v.is_none(),
+            MeasValue::Int2(v) => v.is_none(),
+            MeasValue::Integer(v) => v.is_none(),
+            MeasValue::Int8(v) => v.is_none(),
+            MeasValue::Real(v) => v.is_none(),
+            MeasValue::Double(v) => v.is_none(),
+            MeasValue::Text(v) => v.is_none(),
+            MeasValue::TextArray(v) => v.is_none(),
+            MeasValue::Timestamp(v) => v.is_none(),
+            MeasValue::Numeric(v) => v.is_none(),
+            MeasValue::NumericArray(v) => v.is_none(),
         }
     }
 
     pub fn to_value_of(&self, data_type: DataType) -> Result<MeasValue, Error> {
         match self {
+            MeasValue::Boolean(v) => map_bool(v, data_type),
             MeasValue::Int2(v) => map_int2(v, data_type),
             MeasValue::Integer(v) => map_int4(v, data_type),
             MeasValue::Int8(v) => map_int8(v, data_type),
             MeasValue::Real(v) => map_real(v, data_type),
             MeasValue::Double(v) => map_double(v, data_type),
-            MeasValue::Text(v) => match data_type {
-                DataType::Text => Ok(MeasValue::Text(v.to_string())),
-                _ => Err(Error::Runtime(crate::error::RuntimeError {
-                    msg: format!("No mapping defined for {:?} -> {}", v, data_type),
-                })),
-            },
-            MeasValue::TextArray(v) => match data_type {
-                DataType::Text => Ok(MeasValue::Text(v.join(","))),
-                _ => Err(Error::Runtime(crate::error::RuntimeError {
-                    msg: format!("No mapping defined for {:?} -> {}", v, data_type),
-                })),
-            },
-            MeasValue::Timestamp(v) => match data_type {
-                DataType::Timestamp => Ok(MeasValue::Timestamp(*v)),
-                _ => Err(Error::Runtime(crate::error::RuntimeError {
-                    msg: format!("No mapping defined for {:?} -> {}", v, data_type),
-                })),
-            },
+            MeasValue::Text(v) => map_text(v, data_type),
+            MeasValue::TextArray(v) => map_text_array(v, data_type),
+            MeasValue::Timestamp(v) => map_timestamp(v, data_type),
             MeasValue::Numeric(v) => map_numeric(v, data_type),
+            MeasValue::NumericArray(v) => map_numeric_array(v, data_type),
         }
     }
 }
 
+fn write_option<T: fmt::Display>(f: &mut fmt::Formatter, value: &Option<T>) -> fmt::Result {
+    match value {
+        Some(v) => write!(f, "{}", v),
+        None => write!(f, "NULL"),
+    }
+}
+
+fn write_array<T: fmt::Display>(f: &mut fmt::Formatter, value: &Option<Vec<T>>) -> fmt::Result {
+    match value {
+        Some(v) => write!(f, "{}", array_literal(v)),
+        None => write!(f, "NULL"),
+    }
+}
+
 impl fmt::Display for MeasValue {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            MeasValue::Int2(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
-            MeasValue::Integer(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
-            MeasValue::Int8(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
-            MeasValue::Real(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
-            MeasValue::Double(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
-            MeasValue::Text(v) => write!(f, "{}", v),
-            MeasValue::TextArray(_) => write!(f, "ARRAY(text)"),
-            MeasValue::Timestamp(v) => write!(f, "{}", v),
-            MeasValue::Numeric(v) => match v {
-                Some(i) => write!(f, "{}", i),
-                None => write!(f, "NULL"),
-            },
+            MeasValue::Boolean(v) => write_option(f, v),
+            MeasValue::Int2(v) => write_option(f, v),
+            MeasValue::Integer(v) => write_option(f, v),
+            MeasValue::Int8(v) => write_option(f, v),
+            MeasValue::Real(v) => write_option(f, v),
+            MeasValue::Double(v) => write_option(f, v),
+            MeasValue::Text(v) => write_option(f, v),
+            MeasValue::TextArray(v) => write_array(f, v),
+            MeasValue::Timestamp(v) => write_option(f, v),
+            MeasValue::Numeric(v) => write_option(f, v),
+            MeasValue::NumericArray(v) => write_array(f, v),
         }
     }
 }
@@ -325,6 +634,7 @@ impl ToSql for MeasValue {
         Self: Sized,
     {
         match self {
+            MeasValue::Boolean(x) => x.to_sql(ty, out),
             MeasValue::Int2(x) => x.to_sql(ty, out),
             MeasValue::Integer(x) => x.to_sql(ty, out),
             MeasValue::Int8(x) => x.to_sql(ty, out),
@@ -334,6 +644,7 @@ impl ToSql for MeasValue {
             MeasValue::TextArray(x) => x.to_sql(ty, out),
             MeasValue::Timestamp(x) => x.to_sql(ty, out),
             MeasValue::Numeric(x) => x.to_sql(ty, out),
+            MeasValue::NumericArray(x) => x.to_sql(ty, out),
         }
     }
 
@@ -350,6 +661,7 @@ impl ToSql for MeasValue {
         out: &mut bytes::BytesMut,
     ) -> Result<postgres_types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
         match self {
+            MeasValue::Boolean(x) => x.to_sql_checked(ty, out),
             MeasValue::Int2(x) => x.to_sql_checked(ty, out),
             MeasValue::Integer(x) => x.to_sql_checked(ty, out),
             MeasValue::Int8(x) => x.to_sql_checked(ty, out),
@@ -359,14 +671,151 @@ impl ToSql for MeasValue {
             MeasValue::TextArray(x) => x.to_sql_checked(ty, out),
             MeasValue::Timestamp(x) => x.to_sql_checked(ty, out),
             MeasValue::Numeric(x) => x.to_sql_checked(ty, out),
+            MeasValue::NumericArray(x) => x.to_sql_checked(ty, out),
         }
     }
 }
 
 lazy_static! {
+    pub static ref BOOLEAN_NONE_VALUE: MeasValue = MeasValue::Boolean(None);
     pub static ref INT2_NONE_VALUE: MeasValue = MeasValue::Int2(None);
     pub static ref INTEGER_NONE_VALUE: MeasValue = MeasValue::Integer(None);
     pub static ref INT8_NONE_VALUE: MeasValue = MeasValue::Int8(None);
+    pub static ref REAL_NONE_VALUE: MeasValue = MeasValue::Real(None);
+    pub static ref DOUBLE_NONE_VALUE: MeasValue = MeasValue::Double(None);
     pub static ref NUMERIC_NONE_VALUE: MeasValue = MeasValue::Numeric(None);
-    pub static ref TEXT_NONE_VALUE: MeasValue = MeasValue::Text("".to_string());
+    pub static ref NUMERIC_ARRAY_NONE_VALUE: MeasValue = MeasValue::NumericArray(None);
+    pub static ref TEXT_NONE_VALUE: MeasValue = MeasValue::Text(None);
+    pub static ref TEXT_ARRAY_NONE_VALUE: MeasValue = MeasValue::TextArray(None);
+    pub static ref TIMESTAMP_NONE_VALUE: MeasValue = MeasValue::Timestamp(None);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse_arrays_with_configured_format() {
+        let parser_config = ValueParserConfig {
+            separator: ";".to_string(),
+            prefix: "[".to_string(),
+            postfix: "]".to_string(),
+        };
+
+        assert_eq!(
+            parse_meas_value_with(DataType::NumericArray, "[1.5; 2;3]", &parser_config).unwrap(),
+            MeasValue::NumericArray(Some(vec![
+                Decimal::new(15, 1),
+                Decimal::from(2),
+                Decimal::from(3)
+            ]))
+        );
+        assert_eq!(
+            parse_meas_value(DataType::TextArray, "{a,b}"),
+            MeasValue::TextArray(Some(vec!["a".to_string(), "b".to_string()]))
+        );
+        assert_eq!(
+            parse_meas_value(DataType::TextArray, "{}"),
+            MeasValue::TextArray(Some(vec![]))
+        );
+
+        let elements = vec![
+            "a,b".to_string(),
+            r#"say "hi""#.to_string(),
+            r"back\slash".to_string(),
+            "".to_string(),
+            "NULL".to_string(),
+            "plain".to_string(),
+        ];
+
+        assert_eq!(
+            parse_meas_value(DataType::TextArray, &array_literal(&elements)),
+            MeasValue::TextArray(Some(elements))
+        );
+        assert_eq!(
+            parse_meas_value(DataType::NumericArray, "{1,x}"),
+            MeasValue::NumericArray(None)
+        );
+    }
+
+    #[test]
+    fn parse_booleans_and_timestamps() {
+        assert_eq!(
+            parse_meas_value(DataType::Boolean, "True"),
+            MeasValue::Boolean(Some(true))
+        );
+        assert_eq!(
+            parse_meas_value(DataType::Boolean, "0"),
+            MeasValue::Boolean(Some(false))
+        );
+        assert_eq!(
+            parse_meas_value(DataType::Boolean, "maybe"),
+            MeasValue::Boolean(None)
+        );
+
+        let expected = Some(Utc.with_ymd_and_hms(2024, 7, 1, 10, 15, 0).unwrap());
+
+        assert_eq!(
+            parse_meas_value(DataType::Timestamp, "2024-07-01T12:15:00+02:00"),
+            MeasValue::Timestamp(expected)
+        );
+        assert_eq!(
+            parse_meas_value(DataType::Timestamp, "2024-07-01 10:15:00+00"),
+            MeasValue::Timestamp(expected)
+        );
+    }
+
+    #[test]
+    fn convert_between_types() {
+        assert_eq!(
+            MeasValue::Boolean(Some(true))
+                .to_value_of(DataType::Integer)
+                .unwrap(),
+            MeasValue::Integer(Some(1))
+        );
+        assert_eq!(
+            MeasValue::Text(Some("42".to_string()))
+                .to_value_of(DataType::Int2)
+                .unwrap(),
+            MeasValue::Int2(Some(42))
+        );
+        assert!(MeasValue::Text(Some("abc".to_string()))
+            .to_value_of(DataType::Integer)
+            .is_err());
+        assert_eq!(
+            MeasValue::TextArray(Some(vec!["1".to_string(), "2.5".to_string()]))
+                .to_value_of(DataType::NumericArray)
+                .unwrap(),
+            MeasValue::NumericArray(Some(vec![Decimal::from(1), Decimal::new(25, 1)]))
+        );
+        assert_eq!(
+            MeasValue::Int8(Some(100000))
+                .to_value_of(DataType::Int2)
+                .unwrap(),
+            MeasValue::Int2(None)
+        );
+        assert_eq!(
+            MeasValue::Timestamp(None)
+                .to_value_of(DataType::Text)
+                .unwrap(),
+            MeasValue::Text(None)
+        );
+        assert_eq!(
+            MeasValue::Boolean(Some(true))
+                .to_value_of(DataType::Double)
+                .unwrap(),
+            MeasValue::Double(Some(1.0))
+        );
+        assert_eq!(
+            MeasValue::Numeric(Some(Decimal::ZERO))
+                .to_value_of(DataType::Boolean)
+                .unwrap(),
+            MeasValue::Boolean(Some(false))
+        );
+        assert!(MeasValue::Timestamp(None)
+            .to_value_of(DataType::Int8)
+            .is_err());
+    }
 }
@@ -1,6 +1,10 @@
+use chrono::{DateTime, Utc};
+use postgres_protocol::escape::escape_identifier;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
 use tokio_postgres::types::ToSql;
 use tokio_postgres::{Client, GenericClient, Transaction};
 
@@ -9,10 +13,11 @@ use async_trait::async_trait;
 type PostgresName = String;
 
 use super::change::{Change, ChangeResult};
+use super::entity::{load_entity_names, lookup_entity_ids};
 use super::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
 
 #[derive(Debug, Serialize, Deserialize, Clone, ToSql)]
-#[postgres(name = "attribute_descr")]
+#[postgres(name = "attr_def")]
 pub struct Attribute {
     pub name: PostgresName,
     pub data_type: String,
@@ -24,6 +29,51 @@ fn default_empty_string() -> String {
     String::new()
 }
 
+/// Differences between the current attributes of a store and the attributes
+/// it should have
+struct AttributeDiff {
+    new: Vec<Attribute>,
+    changed: Vec<Attribute>,
+    removed: Vec<String>,
+}
+
+fn diff_attributes(mine: &[Attribute], other: &[Attribute]) -> AttributeDiff {
+    let mut new: Vec<Attribute> = Vec::new();
+    let mut changed: Vec<Attribute> = Vec::new();
+
+    for other_attribute in other {
+        match mine
+            .iter()
+            .find(|my_attribute| my_attribute.name == other_attribute.name)
+        {
+            Some(my_attribute) => {
+                if my_attribute.data_type != other_attribute.data_type {
+                    changed.push(other_attribute.clone());
+                }
+            }
+            None => {
+                new.push(other_attribute.clone());
+            }
+        }
+    }
+
+    let removed: Vec<String> = mine
+        .iter()
+        .filter(|my_attribute| {
+            !other
+                .iter()
+                .any(|other_attribute| other_attribute.name == my_attribute.name)
+        })
+        .map(|my_attribute| my_attribute.name.clone())
+        .collect();
+
+    AttributeDiff {
+        new,
+        changed,
+        removed,
+    }
+}
+
 pub struct AddAttributes {
     pub notification_store: NotificationStore,
     pub attributes: Vec<Attribute>,
@@ -43,35 +93,387 @@ impl fmt::Display for AddAttributes {
 impl Change for AddAttributes {
     async fn apply(&self, client: &mut Transaction) -> ChangeResult {
         let query = concat!(
-            "with a as (",
-            "insert into notification_directory.attribute(notification_store_id, name, data_type, description) ",
-            "select ns.id, $1, $2, $3 from notification_directory.notification_store ns join directory.data_source ds on ds.id = ns.data_source_id where ds.name = $4 returning attribute",
-            ") ",
-            "select notification_directory.create_attribute_column(a.attribute) from a;"
+            "SELECT notification_directory.add_attributes(notification_store, $1::notification_directory.attr_def[]) ",
+            "FROM notification_directory.notification_store ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2"
+        );
+
+        client
+            .query_one(
+                query,
+                &[&self.attributes, &self.notification_store.data_source],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error adding attributes to notification store: {e}"
+                ))
+            })?;
+
+        Ok(format!(
+            "Added attributes to notification store '{}'",
+            &self.notification_store
+        ))
+    }
+}
+
+pub struct RemoveAttributes {
+    pub notification_store: NotificationStore,
+    pub attributes: Vec<String>,
+}
+
+impl fmt::Display for RemoveAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RemoveAttributes({}, {:?})",
+            &self.notification_store, &self.attributes
+        )
+    }
+}
+
+#[async_trait]
+impl Change for RemoveAttributes {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT notification_directory.remove_attribute(notification_store, $1::name) ",
+            "FROM notification_directory.notification_store ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2"
+        );
+
+        for attribute in &self.attributes {
+            client
+                .query_one(query, &[attribute, &self.notification_store.data_source])
+                .await
+                .map_err(|e| {
+                    DatabaseError::from_msg(format!(
+                        "Error removing attribute '{attribute}' from notification store: {e}"
+                    ))
+                })?;
+        }
+
+        Ok(format!(
+            "Removed {} attributes from notification store '{}'",
+            &self.attributes.len(),
+            &self.notification_store
+        ))
+    }
+}
+
+pub struct ChangeAttribute {
+    pub notification_store: NotificationStore,
+    pub attribute: Attribute,
+}
+
+impl fmt::Display for ChangeAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ChangeAttribute({}, {}: {})",
+            &self.notification_store, &self.attribute.name, &self.attribute.data_type
+        )
+    }
+}
+
+#[async_trait]
+impl Change for ChangeAttribute {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT notification_directory.change_attribute_type(notification_store, $1::notification_directory.attr_def) ",
+            "FROM notification_directory.notification_store ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2"
+        );
+
+        client
+            .query_one(
+                query,
+                &[&self.attribute, &self.notification_store.data_source],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error changing type of attribute '{}' in notification store: {e}",
+                    &self.attribute.name
+                ))
+            })?;
+
+        Ok(format!(
+            "Changed type of attribute '{}' in notification store '{}' to {}",
+            &self.attribute.name, &self.notification_store, &self.attribute.data_type
+        ))
+    }
+}
+
+/// Store for information on sets of related notifications, linked to the
+/// notifications of its notification store
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationSetStore {
+    pub name: PostgresName,
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+}
+
+impl NotificationSetStore {
+    pub fn diff(
+        &self,
+        notification_store: &NotificationStore,
+        other: &NotificationSetStore,
+    ) -> Vec<Box<dyn Change + Send>> {
+        let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
+
+        let attribute_diff = diff_attributes(&self.attributes, &other.attributes);
+
+        for attribute in attribute_diff.changed {
+            changes.push(Box::new(ChangeSetAttribute {
+                notification_store: notification_store.clone(),
+                notification_set_store: self.clone(),
+                attribute,
+            }));
+        }
+
+        if !attribute_diff.new.is_empty() {
+            changes.push(Box::new(AddSetAttributes {
+                notification_store: notification_store.clone(),
+                notification_set_store: self.clone(),
+                attributes: attribute_diff.new,
+            }));
+        }
+
+        if !attribute_diff.removed.is_empty() {
+            changes.push(Box::new(RemoveSetAttributes {
+                notification_store: notification_store.clone(),
+                notification_set_store: self.clone(),
+                attributes: attribute_diff.removed,
+            }));
+        }
+
+        changes
+    }
+}
+
+impl fmt::Display for NotificationSetStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NotificationSetStore({})", &self.name)
+    }
+}
+
+async fn create_notification_set_store(
+    client: &mut Transaction<'_>,
+    data_source: &str,
+    notification_set_store: &NotificationSetStore,
+) -> Result<(), Error> {
+    let query = concat!(
+        "SELECT notification_directory.create_notification_set_store($1::name, notification_store.id, $2::notification_directory.attr_def[]) ",
+        "FROM notification_directory.notification_store ",
+        "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+        "WHERE data_source.name = $3"
+    );
+
+    client
+        .query_one(
+            query,
+            &[
+                &notification_set_store.name,
+                &notification_set_store.attributes,
+                &data_source,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Error creating notification set store '{}': {e}",
+                &notification_set_store.name
+            ))
+        })?;
+
+    Ok(())
+}
+
+pub struct AddNotificationSetStore {
+    pub notification_store: NotificationStore,
+    pub notification_set_store: NotificationSetStore,
+}
+
+impl fmt::Display for AddNotificationSetStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AddNotificationSetStore({}, {})",
+            &self.notification_store, &self.notification_set_store
+        )
+    }
+}
+
+#[async_trait]
+impl Change for AddNotificationSetStore {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        create_notification_set_store(
+            client,
+            &self.notification_store.data_source,
+            &self.notification_set_store,
+        )
+        .await?;
+
+        Ok(format!(
+            "Created notification set store '{}' for '{}'",
+            &self.notification_set_store, &self.notification_store
+        ))
+    }
+}
+
+pub struct AddSetAttributes {
+    pub notification_store: NotificationStore,
+    pub notification_set_store: NotificationSetStore,
+    pub attributes: Vec<Attribute>,
+}
+
+impl fmt::Display for AddSetAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "AddSetAttributes({}, {:?})",
+            &self.notification_set_store, &self.attributes
+        )
+    }
+}
+
+#[async_trait]
+impl Change for AddSetAttributes {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT notification_directory.add_set_attributes(notification_set_store, $1::notification_directory.attr_def[]) ",
+            "FROM notification_directory.notification_set_store ",
+            "JOIN notification_directory.notification_store ON notification_store.id = notification_set_store.notification_store_id ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2 AND notification_set_store.name = $3"
+        );
+
+        client
+            .query_one(
+                query,
+                &[
+                    &self.attributes,
+                    &self.notification_store.data_source,
+                    &self.notification_set_store.name,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error adding attributes to notification set store: {e}"
+                ))
+            })?;
+
+        Ok(format!(
+            "Added attributes to notification set store '{}'",
+            &self.notification_set_store
+        ))
+    }
+}
+
+pub struct RemoveSetAttributes {
+    pub notification_store: NotificationStore,
+    pub notification_set_store: NotificationSetStore,
+    pub attributes: Vec<String>,
+}
+
+impl fmt::Display for RemoveSetAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RemoveSetAttributes({}, {:?})",
+            &self.notification_set_store, &self.attributes
+        )
+    }
+}
+
+#[async_trait]
+impl Change for RemoveSetAttributes {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT notification_directory.remove_set_attribute(notification_set_store, $1::name) ",
+            "FROM notification_directory.notification_set_store ",
+            "JOIN notification_directory.notification_store ON notification_store.id = notification_set_store.notification_store_id ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2 AND notification_set_store.name = $3"
         );
 
         for attribute in &self.attributes {
             client
-                .execute(
+                .query_one(
                     query,
                     &[
-                        &attribute.name,
-                        &attribute.data_type,
-                        &attribute.description,
+                        attribute,
                         &self.notification_store.data_source,
+                        &self.notification_set_store.name,
                     ],
                 )
                 .await
                 .map_err(|e| {
                     DatabaseError::from_msg(format!(
-                        "Error adding attribute to notification store: {e}"
+                        "Error removing attribute '{attribute}' from notification set store: {e}"
                     ))
                 })?;
         }
 
         Ok(format!(
-            "Added attributes to notification store '{}'",
-            &self.notification_store
+            "Removed {} attributes from notification set store '{}'",
+            &self.attributes.len(),
+            &self.notification_set_store
+        ))
+    }
+}
+
+pub struct ChangeSetAttribute {
+    pub notification_store: NotificationStore,
+    pub notification_set_store: NotificationSetStore,
+    pub attribute: Attribute,
+}
+
+impl fmt::Display for ChangeSetAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ChangeSetAttribute({}, {}: {})",
+            &self.notification_set_store, &self.attribute.name, &self.attribute.data_type
+        )
+    }
+}
+
+#[async_trait]
+impl Change for ChangeSetAttribute {
+    async fn apply(&self, client: &mut Transaction) -> ChangeResult {
+        let query = concat!(
+            "SELECT notification_directory.change_set_attribute_type(notification_set_store, $1::notification_directory.attr_def) ",
+            "FROM notification_directory.notification_set_store ",
+            "JOIN notification_directory.notification_store ON notification_store.id = notification_set_store.notification_store_id ",
+            "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+            "WHERE data_source.name = $2 AND notification_set_store.name = $3"
+        );
+
+        client
+            .query_one(
+                query,
+                &[
+                    &self.attribute,
+                    &self.notification_store.data_source,
+                    &self.notification_set_store.name,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Error changing type of attribute '{}' in notification set store: {e}",
+                    &self.attribute.name
+                ))
+            })?;
+
+        Ok(format!(
+            "Changed type of attribute '{}' in notification set store '{}' to {}",
+            &self.attribute.name, &self.notification_set_store, &self.attribute.data_type
         ))
     }
 }
@@ -81,36 +483,55 @@ pub struct NotificationStore {
     pub title: Option<String>,
     pub data_source: String,
     pub attributes: Vec<Attribute>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notification_set_stores: Vec<NotificationSetStore>,
 }
 
 impl NotificationStore {
     pub fn diff(&self, other: &NotificationStore) -> Vec<Box<dyn Change + Send>> {
         let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
 
-        let mut new_attributes: Vec<Attribute> = Vec::new();
+        let attribute_diff = diff_attributes(&self.attributes, &other.attributes);
+
+        for attribute in attribute_diff.changed {
+            changes.push(Box::new(ChangeAttribute {
+                notification_store: self.clone(),
+                attribute,
+            }));
+        }
+
+        if !attribute_diff.new.is_empty() {
+            changes.push(Box::new(AddAttributes {
+                notification_store: self.clone(),
+                attributes: attribute_diff.new,
+            }));
+        }
+
+        if !attribute_diff.removed.is_empty() {
+            changes.push(Box::new(RemoveAttributes {
+                notification_store: self.clone(),
+                attributes: attribute_diff.removed,
+            }));
+        }
 
-        for other_attribute in &other.attributes {
+        for other_set_store in &other.notification_set_stores {
             match self
-                .attributes
+                .notification_set_stores
                 .iter()
-                .find(|my_part| my_part.name == other_attribute.name)
+                .find(|my_set_store| my_set_store.name == other_set_store.name)
             {
-                Some(_my_part) => {
-                    // Ok attribute exists
+                Some(my_set_store) => {
+                    changes.append(&mut my_set_store.diff(self, other_set_store));
                 }
                 None => {
-                    new_attributes.push(other_attribute.clone());
+                    changes.push(Box::new(AddNotificationSetStore {
+                        notification_store: self.clone(),
+                        notification_set_store: other_set_store.clone(),
+                    }));
                 }
             }
         }
 
-        if !new_attributes.is_empty() {
-            changes.push(Box::new(AddAttributes {
-                notification_store: self.clone(),
-                attributes: new_attributes,
-            }));
-        }
-
         changes
     }
 }
@@ -134,20 +555,32 @@ impl fmt::Display for AddNotificationStore {
 #[async_trait]
 impl Change for AddNotificationStore {
     async fn apply(&self, client: &mut Transaction) -> ChangeResult {
-        let query = format!(
-            "SELECT notification_directory.create_notification_store($1::text, ARRAY[{}]::notification_directory.attr_def[])",
-            self.notification_store.attributes.iter().map(|att| format!("('{}', '{}', '')", &att.name, &att.data_type)).collect::<Vec<String>>().join(",")
-        );
+        let query = "SELECT notification_directory.create_notification_store($1::text, $2::notification_directory.attr_def[])";
 
         client
-            .query_one(&query, &[&self.notification_store.data_source])
+            .query_one(
+                query,
+                &[
+                    &self.notification_store.data_source,
+                    &self.notification_store.attributes,
+                ],
+            )
             .await
             .map_err(|e| {
                 DatabaseError::from_msg(format!("Error creating notification store: {e}"))
             })?;
 
+        for notification_set_store in &self.notification_store.notification_set_stores {
+            create_notification_set_store(
+                client,
+                &self.notification_store.data_source,
+                notification_set_store,
+            )
+            .await?;
+        }
+
         Ok(format!(
-            "Created attribute store '{}'",
+            "Created notification store '{}'",
             &self.notification_store
         ))
     }
@@ -168,15 +601,18 @@ pub async fn load_notification_stores(conn: &mut Client) -> Result<Vec<Notificat
         .map_err(|e| DatabaseError::from_msg(format!("Error loading notification stores: {e}")))?;
 
     for row in result {
-        let attribute_store_id: i32 = row.get(0);
+        let notification_store_id: i32 = row.get(0);
         let data_source: &str = row.get(1);
 
-        let attributes = load_attributes(conn, attribute_store_id).await;
+        let attributes = load_attributes(conn, notification_store_id).await;
+        let notification_set_stores =
+            load_notification_set_stores(conn, notification_store_id).await?;
 
         notification_stores.push(NotificationStore {
             title: None,
             data_source: String::from(data_source),
             attributes,
+            notification_set_stores,
         });
     }
 
@@ -186,26 +622,29 @@ pub async fn load_notification_stores(conn: &mut Client) -> Result<Vec<Notificat
 pub async fn load_notification_store(
     conn: &mut Client,
     data_source: &str,
-    entity_type: &str,
 ) -> Result<NotificationStore, Error> {
     let query = concat!(
-        "SELECT attribute_store.id ",
-        "FROM attribute_directory.attribute_store ",
-        "JOIN directory.data_source ON data_source.id = attribute_store.data_source_id ",
+        "SELECT notification_store.id ",
+        "FROM notification_directory.notification_store ",
+        "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
         "WHERE data_source.name = $1"
     );
 
     let result = conn
-        .query_one(query, &[&data_source, &entity_type])
+        .query_one(query, &[&data_source])
         .await
-        .map_err(|e| DatabaseError::from_msg(format!("Could not load attribute stores: {e}")))?;
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load notification store: {e}")))?;
+
+    let notification_store_id: i32 = result.get(0);
 
-    let attributes = load_attributes(conn, result.get::<usize, i32>(0)).await;
+    let attributes = load_attributes(conn, notification_store_id).await;
+    let notification_set_stores = load_notification_set_stores(conn, notification_store_id).await?;
 
     Ok(NotificationStore {
         title: None,
         data_source: String::from(data_source),
         attributes,
+        notification_set_stores,
     })
 }
 
@@ -231,6 +670,55 @@ async fn load_attributes(conn: &mut Client, notification_store_id: i32) -> Vec<A
         .collect()
 }
 
+async fn load_notification_set_stores(
+    conn: &mut Client,
+    notification_store_id: i32,
+) -> Result<Vec<NotificationSetStore>, Error> {
+    let query = concat!(
+        "SELECT notification_set_store.name, set_attribute.name, set_attribute.data_type, set_attribute.description ",
+        "FROM notification_directory.notification_set_store ",
+        "LEFT JOIN notification_directory.set_attribute ON set_attribute.notification_set_store_id = notification_set_store.id ",
+        "WHERE notification_set_store.notification_store_id = $1 ",
+        "ORDER BY notification_set_store.name, set_attribute.id"
+    );
+
+    let rows = conn
+        .query(query, &[&notification_store_id])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Error loading notification set stores: {e}"))
+        })?;
+
+    let mut notification_set_stores: Vec<NotificationSetStore> = Vec::new();
+
+    for row in rows {
+        let name: String = row.get(0);
+        let attribute_name: Option<String> = row.get(1);
+
+        if notification_set_stores
+            .last()
+            .is_none_or(|set_store| set_store.name != name)
+        {
+            notification_set_stores.push(NotificationSetStore {
+                name,
+                attributes: Vec::new(),
+            });
+        }
+
+        if let (Some(attribute_name), Some(set_store)) =
+            (attribute_name, notification_set_stores.last_mut())
+        {
+            set_store.attributes.push(Attribute {
+                name: attribute_name,
+                data_type: row.get(2),
+                description: row.get(3),
+            });
+        }
+    }
+
+    Ok(notification_set_stores)
+}
+
 pub fn load_notification_store_from_file(path: &PathBuf) -> Result<NotificationStore, Error> {
     let f = std::fs::File::open(path).map_err(|e| {
         ConfigurationError::from_msg(format!(
@@ -278,3 +766,482 @@ pub async fn notification_store_exists<T: GenericClient + Sync + Send>(
         )))),
     }
 }
+
+/// Lifecycle state of a notification
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationState {
+    New,
+    Acknowledged,
+    Cleared,
+}
+
+impl FromStr for NotificationState {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "new" => Ok(NotificationState::New),
+            "acknowledged" => Ok(NotificationState::Acknowledged),
+            "cleared" => Ok(NotificationState::Cleared),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported notification state '{s}'"
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for NotificationState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotificationState::New => write!(f, "new"),
+            NotificationState::Acknowledged => write!(f, "acknowledged"),
+            NotificationState::Cleared => write!(f, "cleared"),
+        }
+    }
+}
+
+/// Notification with its lifecycle state
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NotificationStatus {
+    pub notification_id: i32,
+    pub entity_id: i32,
+    pub timestamp: DateTime<Utc>,
+    pub state: NotificationState,
+    /// Time of the last state change, not set for new notifications
+    pub modified: Option<DateTime<Utc>>,
+    pub changed_by: Option<String>,
+    pub remark: Option<String>,
+}
+
+async fn notification_store_id<T: GenericClient + Sync + Send>(
+    client: &T,
+    data_source: &str,
+) -> Result<i32, Error> {
+    let query = concat!(
+        "SELECT notification_store.id ",
+        "FROM notification_directory.notification_store ",
+        "JOIN directory.data_source ON data_source.id = notification_store.data_source_id ",
+        "WHERE data_source.name = $1"
+    );
+
+    let row = client
+        .query_opt(query, &[&data_source])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load notification store '{data_source}': {e}"
+            ))
+        })?
+        .ok_or_else(|| {
+            Error::Runtime(RuntimeError::from_msg(format!(
+                "No notification store '{data_source}'"
+            )))
+        })?;
+
+    Ok(row.get(0))
+}
+
+/// Set the state of notifications in a notification store and return the
+/// number of notifications changed. Ids of notifications that do not exist are
+/// ignored.
+pub async fn set_notification_state<T: GenericClient + Sync + Send>(
+    client: &mut T,
+    data_source: &str,
+    notification_ids: &[i32],
+    state: NotificationState,
+    changed_by: Option<&str>,
+    remark: Option<&str>,
+) -> Result<u64, Error> {
+    let notification_store_id = notification_store_id(client, data_source).await?;
+
+    let query = format!(
+        concat!(
+            "INSERT INTO notification_directory.notification_state",
+            "(notification_store_id, notification_id, state, modified, changed_by, remark) ",
+            "SELECT $1, n.id, $2::text::notification_directory.notification_state_enum, now(), $3, $4 ",
+            "FROM notification.{} n WHERE n.id = ANY($5) ",
+            "ON CONFLICT (notification_store_id, notification_id) DO UPDATE ",
+            "SET state = excluded.state, modified = excluded.modified, ",
+            "changed_by = excluded.changed_by, remark = excluded.remark"
+        ),
+        escape_identifier(data_source)
+    );
+
+    client
+        .execute(
+            &query,
+            &[
+                &notification_store_id,
+                &state.to_string(),
+                &changed_by,
+                &remark,
+                &notification_ids,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Could not set state of notifications in '{data_source}': {e}"
+            )))
+        })
+}
+
+/// Load the most recent notifications of a notification store with their
+/// state, optionally only those in the specified state
+pub async fn load_notification_states<T: GenericClient + Sync + Send>(
+    client: &mut T,
+    data_source: &str,
+    state: Option<NotificationState>,
+    limit: i64,
+) -> Result<Vec<NotificationStatus>, Error> {
+    let notification_store_id = notification_store_id(client, data_source).await?;
+
+    let query = format!(
+        concat!(
+            "SELECT n.id, n.entity_id, n.timestamp, COALESCE(s.state::text, 'new'), ",
+            "s.modified, s.changed_by, s.remark ",
+            "FROM notification.{} n ",
+            "LEFT JOIN notification_directory.notification_state s ",
+            "ON s.notification_store_id = $1 AND s.notification_id = n.id ",
+            "WHERE $2::text IS NULL OR COALESCE(s.state::text, 'new') = $2 ",
+            "ORDER BY n.id DESC LIMIT $3"
+        ),
+        escape_identifier(data_source)
+    );
+
+    let rows = client
+        .query(
+            &query,
+            &[
+                &notification_store_id,
+                &state.map(|state| state.to_string()),
+                &limit,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load notifications of '{data_source}': {e}"
+            ))
+        })?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(NotificationStatus {
+                notification_id: row.get(0),
+                entity_id: row.get(1),
+                timestamp: row.get(2),
+                state: row.get::<_, String>(3).parse()?,
+                modified: row.get(4),
+                changed_by: row.get(5),
+                remark: row.get(6),
+            })
+        })
+        .collect()
+}
+
+/// Entity, or set of entities, that is in maintenance
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum MaintenanceTarget {
+    Entity { entity_type: String, name: String },
+    EntitySet { owner: String, name: String },
+}
+
+impl fmt::Display for MaintenanceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaintenanceTarget::Entity { entity_type, name } => {
+                write!(f, "entity {entity_type}:{name}")
+            }
+            MaintenanceTarget::EntitySet { owner, name } => {
+                write!(f, "entity set {owner}:{name}")
+            }
+        }
+    }
+}
+
+/// Period in which no notifications are created for the target
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MaintenanceWindow {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    pub target: MaintenanceTarget,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+/// Add a maintenance window and return its Id
+pub async fn add_maintenance_window<T: GenericClient + Sync + Send>(
+    client: &mut T,
+    window: &MaintenanceWindow,
+) -> Result<i32, Error> {
+    if window.end <= window.start {
+        return Err(Error::Configuration(ConfigurationError::from_msg(format!(
+            "End of maintenance window for {} is not after its start",
+            window.target
+        ))));
+    }
+
+    let (entity_type_id, entity_id, entity_set_id): (i32, Option<i32>, Option<i32>) =
+        match &window.target {
+            MaintenanceTarget::Entity { entity_type, name } => {
+                let row = client
+                    .query_opt(
+                        "SELECT id FROM directory.entity_type WHERE name = $1",
+                        &[&entity_type],
+                    )
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::from_msg(format!(
+                            "Could not load entity type '{entity_type}': {e}"
+                        ))
+                    })?
+                    .ok_or_else(|| {
+                        Error::Runtime(RuntimeError::from_msg(format!(
+                            "No entity type '{entity_type}'"
+                        )))
+                    })?;
+
+                let entity_ids =
+                    lookup_entity_ids(client, entity_type, std::slice::from_ref(name)).await?;
+
+                (row.get(0), Some(entity_ids[0]), None)
+            }
+            MaintenanceTarget::EntitySet { owner, name } => {
+                let query = concat!(
+                    "SELECT entity_type.id, es.entity_id ",
+                    "FROM attribute.minerva_entity_set es ",
+                    "JOIN directory.entity_type ON entity_type.name = es.source_entity_type ",
+                    "WHERE es.owner = $1 AND es.name = $2"
+                );
+
+                let row = client
+                    .query_opt(query, &[&owner, &name])
+                    .await
+                    .map_err(|e| {
+                        DatabaseError::from_msg(format!(
+                            "Could not load entity set {owner}:{name}: {e}"
+                        ))
+                    })?
+                    .ok_or_else(|| {
+                        Error::Runtime(RuntimeError::from_msg(format!(
+                            "No entity set {owner}:{name}"
+                        )))
+                    })?;
+
+                (row.get(0), None, Some(row.get(1)))
+            }
+        };
+
+    let query = concat!(
+        "INSERT INTO notification_directory.maintenance_window",
+        "(entity_type_id, entity_id, entity_set_id, start, \"end\", remark) ",
+        "VALUES ($1, $2, $3, $4, $5, $6) RETURNING id"
+    );
+
+    let row = client
+        .query_one(
+            query,
+            &[
+                &entity_type_id,
+                &entity_id,
+                &entity_set_id,
+                &window.start,
+                &window.end,
+                &window.remark,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not add maintenance window for {}: {e}",
+                window.target
+            ))
+        })?;
+
+    Ok(row.get(0))
+}
+
+/// Load all maintenance windows, or only those active at the specified time
+pub async fn load_maintenance_windows<T: GenericClient + Sync + Send>(
+    client: &mut T,
+    active_at: Option<DateTime<Utc>>,
+) -> Result<Vec<MaintenanceWindow>, Error> {
+    let query = concat!(
+        "SELECT w.id, entity_type.name, w.entity_id, es.owner, es.name, w.start, w.\"end\", w.remark ",
+        "FROM notification_directory.maintenance_window w ",
+        "JOIN directory.entity_type ON entity_type.id = w.entity_type_id ",
+        "LEFT JOIN attribute.minerva_entity_set es ON es.entity_id = w.entity_set_id ",
+        "WHERE $1::timestamptz IS NULL OR (w.start <= $1 AND w.\"end\" > $1) ",
+        "ORDER BY w.id"
+    );
+
+    let rows = client
+        .query(query, &[&active_at])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load maintenance windows: {e}")))?;
+
+    let mut entity_ids: HashMap<String, Vec<i32>> = HashMap::new();
+
+    for row in &rows {
+        if let Some(entity_id) = row.get::<_, Option<i32>>(2) {
+            entity_ids.entry(row.get(1)).or_default().push(entity_id);
+        }
+    }
+
+    let mut entity_names: HashMap<String, HashMap<i32, String>> = HashMap::new();
+
+    for (entity_type, ids) in &entity_ids {
+        let names = load_entity_names(client, entity_type, Some(ids)).await?;
+
+        entity_names.insert(entity_type.clone(), names);
+    }
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let entity_type: String = row.get(1);
+
+            let target = match row.get::<_, Option<i32>>(2) {
+                Some(entity_id) => MaintenanceTarget::Entity {
+                    name: entity_names
+                        .get(&entity_type)
+                        .and_then(|names| names.get(&entity_id))
+                        .cloned()
+                        .unwrap_or_default(),
+                    entity_type,
+                },
+                None => MaintenanceTarget::EntitySet {
+                    owner: row.get::<_, Option<String>>(3).unwrap_or_default(),
+                    name: row.get::<_, Option<String>>(4).unwrap_or_default(),
+                },
+            };
+
+            MaintenanceWindow {
+                id: row.get(0),
+                target,
+                start: row.get(5),
+                end: row.get(6),
+                remark: row.get(7),
+            }
+        })
+        .collect())
+}
+
+/// Remove a maintenance window, returning false if it did not exist
+pub async fn remove_maintenance_window<T: GenericClient + Sync + Send>(
+    client: &mut T,
+    id: i32,
+) -> Result<bool, Error> {
+    let count = client
+        .execute(
+            "DELETE FROM notification_directory.maintenance_window WHERE id = $1",
+            &[&id],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Could not remove maintenance window {id}: {e}"))
+        })?;
+
+    Ok(count == 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notification_state_round_trip() {
+        for state in [
+            NotificationState::New,
+            NotificationState::Acknowledged,
+            NotificationState::Cleared,
+        ] {
+            assert_eq!(
+                state.to_string().parse::<NotificationState>().unwrap(),
+                state
+            );
+        }
+
+        assert!("closed".parse::<NotificationState>().is_err());
+    }
+
+    #[test]
+    fn maintenance_target_from_yaml() {
+        let window: MaintenanceWindow = serde_yaml::from_str(concat!(
+            "target:\n",
+            "  type: entity_set\n",
+            "  owner: ops\n",
+            "  name: upgrade-batch-1\n",
+            "start: 2024-03-01T22:00:00Z\n",
+            "end: 2024-03-02T04:00:00Z\n",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            window.target,
+            MaintenanceTarget::EntitySet {
+                owner: "ops".to_string(),
+                name: "upgrade-batch-1".to_string()
+            }
+        );
+        assert_eq!(window.target.to_string(), "entity set ops:upgrade-batch-1");
+    }
+
+    #[test]
+    fn notification_store_diff() {
+        let current: NotificationStore = serde_yaml::from_str(concat!(
+            "data_source: trigger-notification\n",
+            "attributes:\n",
+            "  - name: rule_id\n",
+            "    data_type: integer\n",
+            "  - name: details\n",
+            "    data_type: text\n",
+            "  - name: data\n",
+            "    data_type: json\n",
+        ))
+        .unwrap();
+
+        let desired: NotificationStore = serde_yaml::from_str(concat!(
+            "data_source: trigger-notification\n",
+            "attributes:\n",
+            "  - name: rule_id\n",
+            "    data_type: integer\n",
+            "  - name: data\n",
+            "    data_type: jsonb\n",
+            "  - name: weight\n",
+            "    data_type: integer\n",
+            "notification_set_stores:\n",
+            "  - name: incident\n",
+            "    attributes:\n",
+            "      - name: owner\n",
+            "        data_type: text\n",
+        ))
+        .unwrap();
+
+        let changes: Vec<String> = current
+            .diff(&desired)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(changes.len(), 4);
+        assert_eq!(
+            changes[0],
+            "ChangeAttribute(NotificationStore(trigger-notification), data: jsonb)"
+        );
+        assert!(changes[1].starts_with("AddAttributes("));
+        assert_eq!(
+            changes[2],
+            "RemoveAttributes(NotificationStore(trigger-notification), [\"details\"])"
+        );
+        assert_eq!(
+            changes[3],
+            "AddNotificationSetStore(NotificationStore(trigger-notification), NotificationSetStore(incident))"
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt;
 use std::path::PathBuf;
 
+use postgres_protocol::escape::escape_identifier;
 use serde::{Deserialize, Serialize};
-use tokio_postgres::Transaction;
+use tokio_postgres::{GenericClient, Transaction};
 
 use async_trait::async_trait;
 
@@ -139,3 +140,84 @@ impl From<Relation> for AddRelation {
         AddRelation { relation }
     }
 }
+
+/// Load the relations registered in `relation_directory.type` together with
+/// the query of their defining view
+pub async fn load_relations<T: GenericClient + Send + Sync>(
+    client: &T,
+) -> Result<Vec<Relation>, Error> {
+    let query = concat!(
+        "SELECT type.name::text, coalesce(pg_views.definition, '') ",
+        "FROM relation_directory.type ",
+        "LEFT JOIN pg_views ON pg_views.schemaname = 'relation_def' AND pg_views.viewname = type.name ",
+        "ORDER BY type.name"
+    );
+
+    let rows = client
+        .query(query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading relations: {e}")))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| Relation {
+            name: row.get(0),
+            query: row.get(1),
+        })
+        .collect())
+}
+
+/// Synchronize the relation table with its defining view and return the number
+/// of (added, removed) source-target pairs
+pub async fn refresh_relation(
+    client: &mut Transaction<'_>,
+    name: &str,
+) -> Result<(u64, u64), Error> {
+    let query = format!(
+        concat!(
+            "DELETE FROM relation.{0} r WHERE NOT EXISTS (",
+            "SELECT 1 FROM relation_def.{0} d WHERE d.source_id = r.source_id AND d.target_id = r.target_id",
+            ")"
+        ),
+        escape_identifier(name)
+    );
+
+    let removed = client.execute(&query, &[]).await.map_err(|e| {
+        DatabaseError::from_msg(format!("Error removing stale relation pairs: {e}"))
+    })?;
+
+    let query = format!(
+        concat!(
+            "INSERT INTO relation.{0}(source_id, target_id) ",
+            "SELECT source_id, target_id FROM relation_def.{0} ",
+            "ON CONFLICT DO NOTHING"
+        ),
+        escape_identifier(name)
+    );
+
+    let added = client
+        .execute(&query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error adding relation pairs: {e}")))?;
+
+    Ok((added, removed))
+}
+
+/// Return the Ids of the target entities related to a source entity
+pub async fn load_relation_targets<T: GenericClient + Send + Sync>(
+    client: &T,
+    name: &str,
+    source_id: i32,
+) -> Result<Vec<i32>, Error> {
+    let query = format!(
+        "SELECT target_id FROM relation.{} WHERE source_id = $1 ORDER BY target_id",
+        escape_identifier(name)
+    );
+
+    let rows = client
+        .query(&query, &[&source_id])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading relation members: {e}")))?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
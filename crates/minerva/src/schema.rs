@@ -1,4 +1,14 @@
-use tokio_postgres::Client;
+use tokio_postgres::{Client, GenericClient};
+
+use crate::error::{DatabaseError, Error};
+
+/// Schemas that are always present in an initialized Minerva database
+const CORE_SCHEMAS: [&str; 4] = [
+    "directory",
+    "trend_directory",
+    "attribute_directory",
+    "notification_directory",
+];
 
 pub fn schema() -> &'static str {
     include_str!("schema.sql")
@@ -11,3 +21,21 @@ pub async fn create_schema(client: &mut Client) -> Result<(), String> {
 
     Ok(())
 }
+
+/// Check if the Minerva schema has been created in the database
+pub async fn schema_exists<T: GenericClient + Send + Sync>(client: &T) -> Result<bool, Error> {
+    let query = "SELECT count(*) FROM pg_catalog.pg_namespace WHERE nspname = ANY($1)";
+
+    let row = client
+        .query_one(query, &[&CORE_SCHEMAS.as_slice()])
+        .await
+        .map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Error checking for Minerva schema: {e}"
+            )))
+        })?;
+
+    let count: i64 = row.get(0);
+
+    Ok(count == CORE_SCHEMAS.len() as i64)
+}
@@ -1193,9 +1193,15 @@ CREATE TABLE "logging"."job"
   "action" jsonb NOT NULL,
   "started" timestamp with time zone NOT NULL,
   "finished" timestamp with time zone,
+  "status" text NOT NULL DEFAULT 'running',
+  "progress" real,
+  "log" text[] NOT NULL DEFAULT '{}',
   PRIMARY KEY (id)
 );
 
+COMMENT ON COLUMN "logging"."job"."status" IS 'One of ``running``, ``finished`` or ``failed``.';
+COMMENT ON COLUMN "logging"."job"."progress" IS 'Fraction of the work that is done, between 0 and 1, if known.';
+
 GRANT SELECT ON TABLE "logging"."job" TO minerva;
 
 GRANT INSERT,UPDATE,DELETE ON TABLE "logging"."job" TO minerva_writer;
@@ -1213,7 +1219,28 @@ $$ LANGUAGE sql VOLATILE;
 CREATE FUNCTION "logging"."end_job"("job_id" bigint)
     RETURNS void
 AS $$
-UPDATE logging.job SET finished=clock_timestamp() WHERE id=$1;
+UPDATE logging.job SET finished=clock_timestamp(), status='finished', progress=1.0 WHERE id=$1;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "logging"."fail_job"("job_id" bigint, "message" text)
+    RETURNS void
+AS $$
+UPDATE logging.job SET finished=clock_timestamp(), status='failed', log=array_append(log, $2) WHERE id=$1;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "logging"."set_job_progress"("job_id" bigint, "progress" real)
+    RETURNS void
+AS $$
+UPDATE logging.job SET progress=$2 WHERE id=$1;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "logging"."append_job_log"("job_id" bigint, "message" text)
+    RETURNS void
+AS $$
+UPDATE logging.job SET log=array_append(log, $2) WHERE id=$1;
 $$ LANGUAGE sql VOLATILE;
 
 
@@ -1471,6 +1498,31 @@ GRANT SELECT ON TABLE "trend_directory"."trend_store_part_stats" TO minerva;
 GRANT INSERT,UPDATE,DELETE ON TABLE "trend_directory"."trend_store_part_stats" TO minerva_writer;
 
 
+CREATE TABLE "trend_directory"."table_trend_stats"
+(
+  "table_trend_id" integer NOT NULL,
+  "analyzed" timestamp with time zone NOT NULL,
+  "start" timestamp with time zone,
+  "end" timestamp with time zone,
+  "sample_percentage" double precision,
+  "row_count" bigint NOT NULL,
+  "min_value" text,
+  "max_value" text,
+  "null_count" bigint NOT NULL,
+  "distinct_count" bigint NOT NULL,
+  "mean" double precision,
+  "first_timestamp" timestamp with time zone,
+  "last_timestamp" timestamp with time zone,
+  PRIMARY KEY (table_trend_id)
+);
+
+COMMENT ON TABLE "trend_directory"."table_trend_stats" IS 'Statistics of the values of table trends, complementing the per-timestamp record counts in trend_store_part_stats. The start, end and sample_percentage columns hold the limits of the analyzed data.';
+
+GRANT SELECT ON TABLE "trend_directory"."table_trend_stats" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "trend_directory"."table_trend_stats" TO minerva_writer;
+
+
 
 CREATE TABLE "trend_directory"."materialization"
 (
@@ -2194,6 +2246,90 @@ END;
 $$ LANGUAGE plpgsql VOLATILE;
 
 
+CREATE FUNCTION "trend_directory"."repartition_trend_store"(trend_directory.trend_store, "partition_size" interval)
+    RETURNS trend_directory.trend_store
+AS $$
+DECLARE
+  result trend_directory.trend_store;
+  part trend_directory.trend_store_part;
+  old_partition trend_directory.partition;
+  old_partitions trend_directory.partition[];
+  old_table_name name;
+  columns text;
+  partition_index integer;
+BEGIN
+  UPDATE trend_directory.trend_store SET partition_size = $2 WHERE id = $1.id RETURNING * INTO result;
+
+  FOR part IN SELECT * FROM trend_directory.trend_store_part WHERE trend_store_id = $1.id LOOP
+    SELECT coalesce(array_agg(p ORDER BY p.index), '{}')
+    FROM trend_directory.partition p
+    WHERE p.trend_store_part_id = part.id
+    INTO old_partitions;
+
+    DELETE FROM trend_directory.partition WHERE trend_store_part_id = part.id;
+
+    -- Detach and rename all existing partitions before creating new ones,
+    -- because new partitions can get the same names as old ones.
+    FOREACH old_partition IN ARRAY old_partitions LOOP
+      EXECUTE format(
+        'ALTER TABLE %I.%I DETACH PARTITION %I.%I',
+        trend_directory.base_table_schema(),
+        trend_directory.base_table_name(part),
+        trend_directory.partition_schema(),
+        old_partition.name
+      );
+      EXECUTE format(
+        'ALTER TABLE %I.%I RENAME TO %I',
+        trend_directory.partition_schema(),
+        old_partition.name,
+        format('repartition_%s', old_partition.id)
+      );
+    END LOOP;
+
+    -- Generated columns can not be inserted into, so only the stored columns
+    -- are copied.
+    SELECT string_agg(quote_ident(c), ', ')
+    FROM (
+      SELECT unnest(ARRAY['entity_id', 'timestamp', 'created', 'job_id']) AS c
+      UNION ALL
+      SELECT name FROM trend_directory.table_trend WHERE trend_store_part_id = part.id
+    ) stored_columns
+    INTO columns;
+
+    FOREACH old_partition IN ARRAY old_partitions LOOP
+      old_table_name = format('repartition_%s', old_partition.id);
+
+      FOR partition_index IN
+        trend_directory.timestamp_to_index($2, old_partition."from")..trend_directory.timestamp_to_index($2, old_partition."to" - interval '1 microsecond')
+      LOOP
+        IF NOT EXISTS (
+          SELECT 1 FROM trend_directory.partition p
+          WHERE p.trend_store_part_id = part.id AND p.index = partition_index
+        ) THEN
+          PERFORM trend_directory.create_partition(part, partition_index);
+        END IF;
+      END LOOP;
+
+      EXECUTE format(
+        'INSERT INTO %I.%I (%s) SELECT %s FROM %I.%I',
+        trend_directory.base_table_schema(),
+        trend_directory.base_table_name(part),
+        columns,
+        columns,
+        trend_directory.partition_schema(),
+        old_table_name
+      );
+      EXECUTE format('DROP TABLE %I.%I', trend_directory.partition_schema(), old_table_name);
+    END LOOP;
+  END LOOP;
+
+  RETURN result;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trend_directory"."repartition_trend_store"(trend_directory.trend_store, "partition_size" interval) IS 'Change the partition size of a trend store and move the data of all its parts into partitions of the new size.';
+
+
 CREATE FUNCTION "trend_directory"."column_spec"(trend_directory.table_trend)
     RETURNS text
 AS $$
@@ -2631,6 +2767,19 @@ BEGIN
       new_name || '_staging'
   );
   PERFORM trend_directory.rename_partitions($1, $2);
+  UPDATE trend_directory.trend_store_part SET name = $2 WHERE id = $1.id;
+  EXECUTE format(
+      'ALTER VIEW IF EXISTS trend.%I RENAME TO %I',
+      '_' || old_name,
+      '_' || new_name
+  );
+  IF to_regprocedure(format('trend.%I(timestamp with time zone)', old_name)) IS NOT NULL THEN
+    EXECUTE format(
+        'ALTER FUNCTION trend.%I(timestamp with time zone) RENAME TO %I',
+        old_name,
+        new_name
+    );
+  END IF;
   EXECUTE format(
       'UPDATE trend_directory.view_materialization '
       'SET src_view = ''%s'' '
@@ -2961,6 +3110,50 @@ END;
 $$ LANGUAGE plpgsql VOLATILE;
 
 
+CREATE FUNCTION "trend_directory"."rename_table_trend"(trend_directory.trend_store_part, "trend_name" name, "new_name" name)
+    RETURNS trend_directory.trend_store_part
+AS $$
+DECLARE
+  src_view text;
+BEGIN
+  PERFORM trend_directory.alter_trend_name($1, $2, $3);
+
+  IF EXISTS(
+    SELECT 1 FROM trend_directory.table_columns(trend_directory.staging_table_schema(), trend_directory.staging_table_name($1)) c
+    WHERE c.name = $2
+  ) THEN
+    EXECUTE format(
+      'ALTER TABLE %I.%I RENAME COLUMN %I TO %I',
+      trend_directory.staging_table_schema(),
+      trend_directory.staging_table_name($1),
+      $2,
+      $3
+    );
+  END IF;
+
+  -- The columns of views used by materializations into this part must match
+  -- the trend names of the part.
+  FOR src_view IN
+    SELECT vm.src_view
+    FROM trend_directory.view_materialization vm
+    JOIN trend_directory.materialization m ON m.id = vm.materialization_id
+    WHERE m.dst_trend_store_part_id = $1.id
+  LOOP
+    IF EXISTS(
+      SELECT 1 FROM pg_catalog.pg_attribute a
+      WHERE a.attrelid = src_view::regclass AND a.attname = $2 AND NOT a.attisdropped
+    ) THEN
+      EXECUTE format('ALTER VIEW %s RENAME COLUMN %I TO %I', src_view, $2, $3);
+    END IF;
+  END LOOP;
+
+  RETURN $1;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trend_directory"."rename_table_trend"(trend_directory.trend_store_part, "trend_name" name, "new_name" name) IS 'Rename a trend, including the columns of the staging table and of the source views of materializations into the trend store part.';
+
+
 CREATE TYPE "trend_directory"."column_info" AS (
   "name" name,
   "data_type" text
@@ -3227,6 +3420,42 @@ END;
 $$ LANGUAGE plpgsql VOLATILE;
 
 
+CREATE FUNCTION "trend_directory"."remove_generated_table_trend"("trend" trend_directory.generated_table_trend)
+    RETURNS trend_directory.generated_table_trend
+AS $$
+BEGIN
+  EXECUTE FORMAT('ALTER TABLE trend.%I DROP COLUMN %I',
+    (SELECT name FROM trend_directory.trend_store_part WHERE id = trend.trend_store_part_id), trend.name);
+  DELETE FROM trend_directory.generated_table_trend WHERE id = trend.id;
+  RETURN trend;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
+CREATE FUNCTION "trend_directory"."change_generated_table_trend"("trend" trend_directory.generated_table_trend, "data_type" text, "expression" text)
+    RETURNS trend_directory.generated_table_trend
+AS $$
+DECLARE
+  part_name name;
+  result trend_directory.generated_table_trend;
+BEGIN
+  SELECT name FROM trend_directory.trend_store_part WHERE id = trend.trend_store_part_id INTO part_name;
+
+  UPDATE trend_directory.generated_table_trend
+  SET data_type = $2, expression = $3
+  WHERE id = trend.id
+  RETURNING * INTO result;
+
+  -- A generated column can not be altered in place, so it is recreated with
+  -- the new definition
+  EXECUTE FORMAT('ALTER TABLE trend.%I DROP COLUMN %I', part_name, trend.name);
+  EXECUTE FORMAT('ALTER TABLE trend.%I %s', part_name, trend_directory.add_generated_column_sql_part(result));
+
+  RETURN result;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
 CREATE FUNCTION "trend_directory"."trend_store_part_name_for_trend"("trend" trend_directory.table_trend)
     RETURNS name
 AS $$
@@ -6677,6 +6906,56 @@ the notification service';
 
 
 
+CREATE TYPE "notification_directory"."notification_state_enum" AS ENUM (
+  'new',
+  'acknowledged',
+  'cleared'
+);
+
+
+
+CREATE TABLE "notification_directory"."notification_state"
+(
+  "notification_store_id" integer NOT NULL,
+  "notification_id" integer NOT NULL,
+  "state" notification_directory.notification_state_enum NOT NULL DEFAULT 'new',
+  "modified" timestamp with time zone NOT NULL DEFAULT now(),
+  "changed_by" text,
+  "remark" text,
+  PRIMARY KEY (notification_store_id, notification_id)
+);
+
+COMMENT ON TABLE "notification_directory"."notification_state" IS 'Lifecycle state of notifications. Notifications without a row in this table are new.';
+
+GRANT SELECT ON TABLE "notification_directory"."notification_state" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "notification_directory"."notification_state" TO minerva_writer;
+
+
+
+CREATE TABLE "notification_directory"."maintenance_window"
+(
+  "id" serial NOT NULL,
+  "entity_type_id" integer NOT NULL,
+  "entity_id" integer,
+  "entity_set_id" integer,
+  "start" timestamp with time zone NOT NULL,
+  "end" timestamp with time zone NOT NULL,
+  "remark" text,
+  PRIMARY KEY (id),
+  CHECK ((entity_id IS NULL) <> (entity_set_id IS NULL))
+);
+
+COMMENT ON TABLE "notification_directory"."maintenance_window" IS 'Periods in which no notifications are created for an entity, or for the members of an entity set identified by the entity Id of the set.';
+
+GRANT SELECT ON TABLE "notification_directory"."maintenance_window" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "notification_directory"."maintenance_window" TO minerva_writer;
+
+GRANT ALL ON SEQUENCE "notification_directory"."maintenance_window_id_seq" TO minerva_writer;
+
+
+
 CREATE FUNCTION "notification_directory"."get_last_notification"("client" text, "notification_store" text)
     RETURNS integer
 AS $$
@@ -6696,6 +6975,34 @@ SELECT notification_directory.get_last_notification($1, $1);
 $$ LANGUAGE sql STABLE;
 
 
+CREATE FUNCTION "notification_directory"."entities_in_maintenance"(integer, timestamp with time zone)
+    RETURNS SETOF integer
+AS $$
+DECLARE
+    set_relation regclass;
+BEGIN
+    RETURN QUERY
+    SELECT w.entity_id
+    FROM notification_directory.maintenance_window w
+    WHERE w.entity_type_id = $1 AND w.entity_id IS NOT NULL AND w.start <= $2 AND w."end" > $2;
+
+    SELECT to_regclass(format('relation.%I', entity_type.name || '->entity_set'))
+    FROM directory.entity_type
+    WHERE entity_type.id = $1
+    INTO set_relation;
+
+    IF set_relation IS NOT NULL THEN
+        RETURN QUERY EXECUTE format(
+            'SELECT m.source_id FROM %s m '
+            'JOIN notification_directory.maintenance_window w ON w.entity_set_id = m.target_id '
+            'WHERE w.entity_type_id = $1 AND w.start <= $2 AND w."end" > $2',
+            set_relation
+        ) USING $1, $2;
+    END IF;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+
 CREATE FUNCTION "notification_directory"."set_last_notification"("client" text, "notification_store" text, "value" integer)
     RETURNS void
 AS $$
@@ -7120,6 +7427,147 @@ NOT pg_attribute.attisdropped;
 $$ LANGUAGE sql STABLE;
 
 
+CREATE FUNCTION "notification_directory"."add_attributes"(notification_directory.notification_store, notification_directory.attr_def[])
+    RETURNS notification_directory.notification_store
+AS $$
+SELECT notification_directory.create_attribute_column(a)
+FROM unnest($2) d, notification_directory.define_attribute($1, d.name, d.data_type, d.description) a;
+
+SELECT $1;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."remove_attribute"(notification_directory.notification_store, name)
+    RETURNS notification_directory.notification_store
+AS $$
+BEGIN
+    EXECUTE format(
+        'ALTER TABLE %I.%I DROP COLUMN IF EXISTS %I',
+        notification_directory.notification_store_schema(),
+        notification_directory.staging_table_name($1),
+        $2
+    );
+
+    EXECUTE format(
+        'ALTER TABLE %I.%I DROP COLUMN IF EXISTS %I',
+        notification_directory.notification_store_schema(),
+        notification_directory.table_name($1),
+        $2
+    );
+
+    DELETE FROM notification_directory.attribute WHERE notification_store_id = $1.id AND name = $2;
+
+    RETURN $1;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."change_attribute_type"(notification_directory.notification_store, notification_directory.attr_def)
+    RETURNS notification_directory.notification_store
+AS $$
+BEGIN
+    UPDATE notification_directory.attribute SET data_type = $2.data_type
+    WHERE notification_store_id = $1.id AND name = $2.name;
+
+    EXECUTE format(
+        'ALTER TABLE %I.%I ALTER COLUMN %I TYPE %s USING %I::%s',
+        notification_directory.notification_store_schema(),
+        notification_directory.staging_table_name($1),
+        $2.name, $2.data_type, $2.name, $2.data_type
+    );
+
+    EXECUTE format(
+        'ALTER TABLE %I.%I ALTER COLUMN %I TYPE %s USING %I::%s',
+        notification_directory.notification_store_schema(),
+        notification_directory.table_name($1),
+        $2.name, $2.data_type, $2.name, $2.data_type
+    );
+
+    RETURN $1;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."define_set_attribute"(notification_directory.notification_set_store, name, name, text)
+    RETURNS SETOF notification_directory.set_attribute
+AS $$
+INSERT INTO notification_directory.set_attribute(notification_set_store_id, name, data_type, description)
+VALUES($1.id, $2, $3, $4) RETURNING *;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."create_set_attribute_column"(notification_directory.set_attribute)
+    RETURNS notification_directory.set_attribute
+AS $$
+SELECT public.action(
+    $1,
+    format(
+        'ALTER TABLE %I.%I ADD COLUMN %I %s',
+        notification_directory.notification_store_schema(),
+        notification_set_store.name, $1.name, $1.data_type
+    )
+)
+FROM notification_directory.notification_set_store WHERE id = $1.notification_set_store_id;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."add_set_attributes"(notification_directory.notification_set_store, notification_directory.attr_def[])
+    RETURNS notification_directory.notification_set_store
+AS $$
+SELECT notification_directory.create_set_attribute_column(a)
+FROM unnest($2) d, notification_directory.define_set_attribute($1, d.name, d.data_type, d.description) a;
+
+SELECT $1;
+$$ LANGUAGE sql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."remove_set_attribute"(notification_directory.notification_set_store, name)
+    RETURNS notification_directory.notification_set_store
+AS $$
+BEGIN
+    EXECUTE format(
+        'ALTER TABLE %I.%I DROP COLUMN IF EXISTS %I',
+        notification_directory.notification_store_schema(),
+        $1.name,
+        $2
+    );
+
+    DELETE FROM notification_directory.set_attribute WHERE notification_set_store_id = $1.id AND name = $2;
+
+    RETURN $1;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."change_set_attribute_type"(notification_directory.notification_set_store, notification_directory.attr_def)
+    RETURNS notification_directory.notification_set_store
+AS $$
+BEGIN
+    UPDATE notification_directory.set_attribute SET data_type = $2.data_type
+    WHERE notification_set_store_id = $1.id AND name = $2.name;
+
+    EXECUTE format(
+        'ALTER TABLE %I.%I ALTER COLUMN %I TYPE %s USING %I::%s',
+        notification_directory.notification_store_schema(),
+        $1.name,
+        $2.name, $2.data_type, $2.name, $2.data_type
+    );
+
+    RETURN $1;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+
+CREATE FUNCTION "notification_directory"."create_notification_set_store"("name" name, "notification_store_id" integer, notification_directory.attr_def[])
+    RETURNS notification_directory.notification_set_store
+AS $$
+SELECT notification_directory.add_set_attributes(
+    notification_directory.create_notification_set_store($1, $2),
+    $3
+);
+$$ LANGUAGE sql VOLATILE;
+
+
 CREATE CAST ("notification_directory"."notification_store" AS text)
   WITH FUNCTION "notification_directory"."to_char"("notification_directory"."notification_store");
 
@@ -7243,6 +7691,13 @@ CREATE TYPE "trigger"."notification" AS (
 
 
 
+CREATE TYPE "trigger"."deduplication_mode_enum" AS ENUM (
+  'window',
+  'weight_increase'
+);
+
+
+
 CREATE TABLE "trigger"."rule"
 (
   "id" serial NOT NULL,
@@ -7252,9 +7707,21 @@ CREATE TABLE "trigger"."rule"
   "default_interval" interval,
   "enabled" bool NOT NULL DEFAULT false,
   "description" text,
+  "deduplication_window" interval,
+  "deduplication_mode" trigger.deduplication_mode_enum NOT NULL DEFAULT 'window',
+  "flapping_window" interval,
+  "flapping_threshold" integer,
   PRIMARY KEY (id)
 );
 
+COMMENT ON COLUMN "trigger"."rule"."deduplication_window" IS 'Period in which repeated notifications for the same entity are suppressed while the previous notification is not cleared. No deduplication is done when not set.';
+
+COMMENT ON COLUMN "trigger"."rule"."deduplication_mode" IS 'With ''window'' all repeats within the deduplication window are suppressed, with ''weight_increase'' only repeats that do not have a higher weight.';
+
+COMMENT ON COLUMN "trigger"."rule"."flapping_window" IS 'Period over which changes between firing and not firing are counted to detect flapping entities. No flap detection is done when not set.';
+
+COMMENT ON COLUMN "trigger"."rule"."flapping_threshold" IS 'Number of changes between firing and not firing within the flapping window from which an entity is marked as flapping.';
+
 CREATE UNIQUE INDEX "rule_name_key" ON "trigger"."rule" USING btree (name);
 
 GRANT SELECT ON TABLE "trigger"."rule" TO minerva;
@@ -7285,6 +7752,57 @@ COMMENT ON COLUMN "trigger"."rule_trend_store_link"."timestamp_mapping_func" IS
 
 
 
+CREATE TABLE "trigger"."rule_evaluation"
+(
+  "rule_id" integer NOT NULL,
+  "timestamp" timestamp with time zone NOT NULL,
+  "fingerprint" text,
+  "evaluated" timestamp with time zone NOT NULL DEFAULT now(),
+  "notification_count" integer NOT NULL DEFAULT 0,
+  PRIMARY KEY (rule_id, timestamp)
+);
+
+COMMENT ON TABLE "trigger"."rule_evaluation" IS 'Stores the timestamps for which notifications of a trigger rule were created by the trigger runner and the fingerprint of the source data at that moment. A timestamp is only evaluated again when its fingerprint changes.';
+
+GRANT SELECT ON TABLE "trigger"."rule_evaluation" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "trigger"."rule_evaluation" TO minerva_writer;
+
+
+
+CREATE TABLE "trigger"."rule_firing"
+(
+  "rule_id" integer NOT NULL,
+  "entity_id" integer NOT NULL,
+  "timestamp" timestamp with time zone NOT NULL,
+  PRIMARY KEY (rule_id, entity_id, timestamp)
+);
+
+COMMENT ON TABLE "trigger"."rule_firing" IS 'Recent entities for which a trigger rule fired, including those of which the notification was suppressed by deduplication or a maintenance window. Used for clearing notifications and for flap detection.';
+
+GRANT SELECT ON TABLE "trigger"."rule_firing" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "trigger"."rule_firing" TO minerva_writer;
+
+
+
+CREATE TABLE "trigger"."flapping_entity"
+(
+  "rule_id" integer NOT NULL,
+  "entity_id" integer NOT NULL,
+  "since" timestamp with time zone NOT NULL,
+  "changes" integer NOT NULL,
+  PRIMARY KEY (rule_id, entity_id)
+);
+
+COMMENT ON TABLE "trigger"."flapping_entity" IS 'Entities that change between firing and not firing of a trigger rule at least the flapping threshold number of times within the flapping window, as of the last evaluation.';
+
+GRANT SELECT ON TABLE "trigger"."flapping_entity" TO minerva;
+
+GRANT INSERT,UPDATE,DELETE ON TABLE "trigger"."flapping_entity" TO minerva_writer;
+
+
+
 CREATE TABLE "trigger"."exception_base"
 (
   "id" serial NOT NULL,
@@ -7699,7 +8217,9 @@ SELECT format(
         created timestamp with time zone not null default now(),
         start timestamp with time zone not null default now(),
         expires timestamp with time zone not null default now() + interval '3 months',
-        weight integer not null
+        weight integer not null,
+        EXCLUDE USING gist (int4range(entity_id, entity_id, '[]') WITH &&, tstzrange(start, expires) WITH &&)
+            WHERE (entity_id IS NOT NULL)
     );$$,
     trigger.exception_weight_table_name($1)
 );
@@ -8100,6 +8620,164 @@ END;
 $$ LANGUAGE plpgsql VOLATILE;
 
 
+CREATE FUNCTION "trigger"."entity_type_id"(trigger.rule)
+    RETURNS integer
+AS $$
+SELECT trend_store.entity_type_id
+FROM trigger.rule_trend_store_link
+JOIN trend_directory.trend_store_part ON trend_store_part.id = rule_trend_store_link.trend_store_part_id
+JOIN trend_directory.trend_store ON trend_store.id = trend_store_part.trend_store_id
+WHERE rule_trend_store_link.rule_id = $1.id
+LIMIT 1;
+$$ LANGUAGE sql STABLE;
+
+
+CREATE FUNCTION "trigger"."clear_notifications"(trigger.rule, notification_directory.notification_store, timestamp with time zone)
+    RETURNS integer
+AS $$
+DECLARE
+    num_rows integer;
+BEGIN
+    EXECUTE format(
+$query$
+INSERT INTO notification_directory.notification_state(notification_store_id, notification_id, state, modified, changed_by)
+SELECT $1, n.id, 'cleared'::notification_directory.notification_state_enum, now(), 'trigger'
+FROM notification.%I n
+LEFT JOIN notification_directory.notification_state s ON s.notification_store_id = $1 AND s.notification_id = n.id
+WHERE n.rule_id = $2 AND n.timestamp = $3 - $5 AND s.state IS DISTINCT FROM 'cleared'
+AND NOT EXISTS (
+    SELECT 1 FROM trigger.rule_firing f
+    WHERE f.rule_id = n.rule_id AND f.entity_id = n.entity_id AND f.timestamp = $3
+)
+AND n.entity_id NOT IN (SELECT notification_directory.entities_in_maintenance($4, $3))
+ON CONFLICT (notification_store_id, notification_id) DO UPDATE
+SET state = excluded.state, modified = excluded.modified, changed_by = excluded.changed_by, remark = excluded.remark
+$query$,
+        notification_directory.table_name($2)
+    )
+    USING $2.id, $1.id, $3, trigger.entity_type_id($1), $1.granularity;
+
+    GET DIAGNOSTICS num_rows = ROW_COUNT;
+
+    RETURN num_rows;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trigger"."clear_notifications"(trigger.rule, notification_directory.notification_store, timestamp with time zone) IS 'Clear the notifications of a rule at the previous evaluation step, one granularity before the timestamp, for entities for which the rule did not fire at the timestamp itself, so the condition of the rule no longer holds for them. Older notifications are left as they are, because the evaluation of a timestamp says nothing about other timestamps. Entities in maintenance are skipped, because their notifications are suppressed rather than resolved.';
+
+
+CREATE FUNCTION "trigger"."record_firing"(trigger.rule, notification_directory.notification_store, timestamp with time zone)
+    RETURNS integer
+AS $$
+DECLARE
+    num_rows integer;
+BEGIN
+    EXECUTE format(
+$query$
+INSERT INTO trigger.rule_firing(rule_id, entity_id, timestamp)
+SELECT DISTINCT rule_id, entity_id, timestamp FROM notification.%I WHERE rule_id = $1 AND timestamp = $2
+ON CONFLICT DO NOTHING
+$query$,
+        notification_directory.staging_table_name($2)
+    )
+    USING $1.id, $3;
+
+    GET DIAGNOSTICS num_rows = ROW_COUNT;
+
+    -- Only the history needed for flap detection is kept
+    DELETE FROM trigger.rule_firing
+    WHERE rule_id = $1.id AND timestamp < $3 - greatest(coalesce($1.flapping_window, $1.granularity), $1.granularity);
+
+    RETURN num_rows;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trigger"."record_firing"(trigger.rule, notification_directory.notification_store, timestamp with time zone) IS 'Record the entities for which the rule fired at the timestamp, based on the notifications in the staging table.';
+
+
+CREATE FUNCTION "trigger"."deduplicate_notifications"(trigger.rule, notification_directory.notification_store, timestamp with time zone)
+    RETURNS integer
+AS $$
+DECLARE
+    num_rows integer;
+BEGIN
+    IF $1.deduplication_window IS NULL THEN
+        RETURN 0;
+    END IF;
+
+    EXECUTE format(
+$query$
+DELETE FROM notification.%I staging
+WHERE staging.rule_id = $2 AND staging.timestamp = $3 AND EXISTS (
+    SELECT 1 FROM notification.%I n
+    LEFT JOIN notification_directory.notification_state s ON s.notification_store_id = $1 AND s.notification_id = n.id
+    WHERE n.rule_id = staging.rule_id AND n.entity_id = staging.entity_id
+    AND n.timestamp >= staging.timestamp - $4 AND n.timestamp < staging.timestamp
+    AND s.state IS DISTINCT FROM 'cleared'
+    AND ($5 = 'window' OR n.weight >= staging.weight)
+)
+$query$,
+        notification_directory.staging_table_name($2), notification_directory.table_name($2)
+    )
+    USING $2.id, $1.id, $3, $1.deduplication_window, $1.deduplication_mode;
+
+    GET DIAGNOSTICS num_rows = ROW_COUNT;
+
+    RETURN num_rows;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trigger"."deduplicate_notifications"(trigger.rule, notification_directory.notification_store, timestamp with time zone) IS 'Remove notifications from the staging table that repeat a notification for the same entity within the deduplication window of the rule that is not cleared.';
+
+
+CREATE FUNCTION "trigger"."detect_flapping"(trigger.rule, timestamp with time zone)
+    RETURNS integer
+AS $$
+DECLARE
+    num_rows integer;
+BEGIN
+    IF $1.flapping_window IS NULL OR $1.flapping_threshold IS NULL THEN
+        DELETE FROM trigger.flapping_entity WHERE rule_id = $1.id;
+
+        RETURN 0;
+    END IF;
+
+    WITH flapping AS (
+        SELECT entity_id, changes FROM (
+            SELECT entity_id, count(*) FILTER (WHERE changed)::integer AS changes
+            FROM (
+                SELECT
+                    e.entity_id,
+                    lag(f.entity_id IS NOT NULL) OVER w IS DISTINCT FROM (f.entity_id IS NOT NULL)
+                        AND lag(f.entity_id IS NOT NULL) OVER w IS NOT NULL AS changed
+                FROM (
+                    SELECT DISTINCT entity_id FROM trigger.rule_firing
+                    WHERE rule_id = $1.id AND timestamp > $2 - $1.flapping_window AND timestamp <= $2
+                ) e
+                CROSS JOIN generate_series($2, $2 - $1.flapping_window + $1.granularity, -$1.granularity) t(ts)
+                LEFT JOIN trigger.rule_firing f ON f.rule_id = $1.id AND f.entity_id = e.entity_id AND f.timestamp = t.ts
+                WINDOW w AS (PARTITION BY e.entity_id ORDER BY t.ts)
+            ) evaluation
+            GROUP BY entity_id
+        ) entity_changes
+        WHERE changes >= $1.flapping_threshold
+    ), stopped AS (
+        DELETE FROM trigger.flapping_entity
+        WHERE rule_id = $1.id AND entity_id NOT IN (SELECT entity_id FROM flapping)
+    )
+    INSERT INTO trigger.flapping_entity(rule_id, entity_id, since, changes)
+    SELECT $1.id, entity_id, $2, changes FROM flapping
+    ON CONFLICT (rule_id, entity_id) DO UPDATE SET changes = excluded.changes;
+
+    GET DIAGNOSTICS num_rows = ROW_COUNT;
+
+    RETURN num_rows;
+END;
+$$ LANGUAGE plpgsql VOLATILE;
+
+COMMENT ON FUNCTION "trigger"."detect_flapping"(trigger.rule, timestamp with time zone) IS 'Mark the entities that changed between firing and not firing at least the flapping threshold number of times within the flapping window before the timestamp, and unmark the entities that no longer do.';
+
+
 CREATE FUNCTION "trigger"."create_notifications"(trigger.rule, notification_directory.notification_store, timestamp with time zone)
     RETURNS integer
 AS $$
@@ -8115,8 +8793,22 @@ $query$,
     )
     USING $1.id, $3;
 
+    PERFORM trigger.record_firing($1, $2, $3);
+
+    EXECUTE format(
+        'DELETE FROM notification.%I WHERE entity_id IN (SELECT notification_directory.entities_in_maintenance($1, $2))',
+        notification_directory.staging_table_name($2)
+    )
+    USING trigger.entity_type_id($1), $3;
+
+    PERFORM trigger.deduplicate_notifications($1, $2, $3);
+
     SELECT trigger.transfer_notifications_from_staging($2) INTO num_rows;
 
+    PERFORM trigger.clear_notifications($1, $2, $3);
+
+    PERFORM trigger.detect_flapping($1, $3);
+
     RETURN num_rows;
 END;
 $$ LANGUAGE plpgsql VOLATILE;
@@ -8357,7 +9049,9 @@ FROM (
             'expires timestamp with time zone',
             'remark text'
         ]::text[] ||
-        array_agg(quote_ident(threshold.name) || ' ' || threshold.data_type) AS col_def
+        array_agg(quote_ident(threshold.name) || ' ' || threshold.data_type) ||
+        -- Validity periods of exceptions for the same entity may not overlap
+        $e$EXCLUDE USING gist (int4range(entity_id, entity_id, '[]') WITH &&, tstzrange(start, expires) WITH &&) WHERE (entity_id IS NOT NULL)$e$::text AS col_def
     FROM unnest($2) threshold
 ) c;
 $$ LANGUAGE sql STABLE;
@@ -9347,6 +10041,16 @@ ALTER TABLE "notification_directory"."set_attribute"
   FOREIGN KEY (notification_set_store_id)
   REFERENCES "notification_directory"."notification_set_store" (id) ON DELETE CASCADE;
 
+ALTER TABLE "notification_directory"."notification_state"
+  ADD CONSTRAINT "notification_state_notification_store_id_fkey"
+  FOREIGN KEY (notification_store_id)
+  REFERENCES "notification_directory"."notification_store" (id) ON DELETE CASCADE;
+
+ALTER TABLE "notification_directory"."maintenance_window"
+  ADD CONSTRAINT "maintenance_window_entity_type_id_fkey"
+  FOREIGN KEY (entity_type_id)
+  REFERENCES "directory"."entity_type" (id) ON DELETE CASCADE;
+
 ALTER TABLE "trend_directory"."trend_store"
   ADD CONSTRAINT "trend_store_entity_type_id_fkey"
   FOREIGN KEY (entity_type_id)
@@ -9422,6 +10126,11 @@ ALTER TABLE "trend_directory"."trend_store_part_stats"
   FOREIGN KEY (trend_store_part_id)
   REFERENCES "trend_directory"."trend_store_part" (id) ON DELETE CASCADE;
 
+ALTER TABLE "trend_directory"."table_trend_stats"
+  ADD CONSTRAINT "table_trend_stats_table_trend_id_fkey"
+  FOREIGN KEY (table_trend_id)
+  REFERENCES "trend_directory"."table_trend" (id) ON DELETE CASCADE;
+
 ALTER TABLE "trend_directory"."materialization"
   ADD CONSTRAINT "materialization_dst_trend_store_id_fkey"
   FOREIGN KEY (dst_trend_store_part_id)
@@ -9487,6 +10196,21 @@ ALTER TABLE "trigger"."rule_trend_store_link"
   FOREIGN KEY (trend_store_part_id)
   REFERENCES "trend_directory"."trend_store_part" (id) ON DELETE CASCADE;
 
+ALTER TABLE "trigger"."rule_evaluation"
+  ADD CONSTRAINT "rule_evaluation_rule_id_fkey"
+  FOREIGN KEY (rule_id)
+  REFERENCES "trigger"."rule" (id) ON DELETE CASCADE;
+
+ALTER TABLE "trigger"."rule_firing"
+  ADD CONSTRAINT "rule_firing_rule_id_fkey"
+  FOREIGN KEY (rule_id)
+  REFERENCES "trigger"."rule" (id) ON DELETE CASCADE;
+
+ALTER TABLE "trigger"."flapping_entity"
+  ADD CONSTRAINT "flapping_entity_rule_id_fkey"
+  FOREIGN KEY (rule_id)
+  REFERENCES "trigger"."rule" (id) ON DELETE CASCADE;
+
 ALTER TABLE "trigger"."rule_tag_link"
   ADD CONSTRAINT "rule_tag_link_rule_id_fkey"
   FOREIGN KEY (rule_id)
@@ -0,0 +1,302 @@
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use postgres_protocol::escape::escape_identifier;
+use serde::Serialize;
+use tokio_postgres::GenericClient;
+
+use crate::error::{ConfigurationError, DatabaseError, Error};
+use crate::granularity::Granularity;
+use crate::trend_store::TrendStore;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletenessStatus {
+    Complete,
+    /// Fewer entities than expected from the baseline
+    Partial,
+    Missing,
+}
+
+impl fmt::Display for CompletenessStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletenessStatus::Complete => write!(f, "complete"),
+            CompletenessStatus::Partial => write!(f, "partial"),
+            CompletenessStatus::Missing => write!(f, "missing"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimestampCompleteness {
+    pub timestamp: DateTime<Utc>,
+    /// Number of entities with data, not determined for quick checks
+    pub entity_count: Option<i64>,
+    /// Median entity count of the preceding baseline period
+    pub baseline: Option<f64>,
+    /// Last time data was loaded for the timestamp
+    pub modified: Option<DateTime<Utc>>,
+    pub status: CompletenessStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletenessReport {
+    pub trend_store_part: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub timestamps: Vec<TimestampCompleteness>,
+}
+
+impl CompletenessReport {
+    /// Timestamps that are missing or partial
+    pub fn gaps(&self) -> impl Iterator<Item = &TimestampCompleteness> {
+        self.timestamps
+            .iter()
+            .filter(|t| t.status != CompletenessStatus::Complete)
+    }
+}
+
+pub struct CompletenessOptions {
+    /// Start of the time range to check (inclusive)
+    pub start: DateTime<Utc>,
+    /// End of the time range to check (exclusive)
+    pub end: DateTime<Utc>,
+    /// Period before each timestamp of which the median entity count is the
+    /// baseline
+    pub baseline_period: Duration,
+    /// Fraction of the baseline below which a timestamp is partial
+    pub partial_threshold: f64,
+    /// Only use `trend_directory.modified` to find missing timestamps, without
+    /// counting entities in the trend data
+    pub quick: bool,
+    /// Timezone in which the timestamps of the trend store part are aligned
+    pub timezone: chrono_tz::Tz,
+}
+
+fn completeness_query(trend_store_part: &str, quick: bool) -> String {
+    let count_expression = match quick {
+        true => "NULL::bigint",
+        false => "coalesce(c.entity_count, 0)",
+    };
+
+    let counts = match quick {
+        true => String::new(),
+        false => format!(
+            concat!(
+                "LEFT JOIN (",
+                "SELECT timestamp, count(*) AS entity_count FROM trend.{} ",
+                "WHERE timestamp >= $1 AND timestamp < $2 GROUP BY timestamp",
+                ") c ON c.timestamp = e.timestamp "
+            ),
+            escape_identifier(trend_store_part)
+        ),
+    };
+
+    format!(
+        concat!(
+            "SELECT e.timestamp, {}, m.last ",
+            "FROM unnest($3::timestamptz[]) AS e(timestamp) ",
+            "{}",
+            "LEFT JOIN trend_directory.modified m ",
+            "ON m.trend_store_part_id = $4 AND m.timestamp = e.timestamp ",
+            "WHERE e.timestamp >= $1 AND e.timestamp < $2 ",
+            "ORDER BY e.timestamp"
+        ),
+        count_expression, counts
+    )
+}
+
+/// Timestamp, entity count and last modification of a timestamp
+type CompletenessRow = (DateTime<Utc>, Option<i64>, Option<DateTime<Utc>>);
+
+fn median(values: &mut [i64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    values.sort_unstable();
+
+    let middle = values.len() / 2;
+
+    match values.len() % 2 {
+        0 => Some((values[middle - 1] + values[middle]) as f64 / 2.0),
+        _ => Some(values[middle] as f64),
+    }
+}
+
+fn baseline_period(options: &CompletenessOptions) -> Result<chrono::Duration, Error> {
+    chrono::Duration::from_std(options.baseline_period).map_err(|e| {
+        Error::Configuration(ConfigurationError::from_msg(format!(
+            "Invalid baseline period '{}': {e}",
+            humantime::format_duration(options.baseline_period)
+        )))
+    })
+}
+
+/// Determine the status of timestamps from `options.start`, using the
+/// preceding timestamps in `rows` for the baseline.
+fn evaluate_completeness(
+    rows: &[CompletenessRow],
+    options: &CompletenessOptions,
+    baseline_period: chrono::Duration,
+) -> Vec<TimestampCompleteness> {
+    rows.iter()
+        .filter(|(timestamp, _, _)| *timestamp >= options.start)
+        .map(|(timestamp, entity_count, modified)| {
+            // Timestamps without data are left out of the baseline, so that a
+            // feed that stopped does not lower the expectation
+            let mut baseline_counts: Vec<i64> = rows
+                .iter()
+                .filter(|(t, _, _)| *t >= *timestamp - baseline_period && t < timestamp)
+                .filter_map(|(_, count, _)| *count)
+                .filter(|count| *count > 0)
+                .collect();
+
+            let baseline = median(&mut baseline_counts);
+
+            let status = match (entity_count, modified) {
+                (None, None) => CompletenessStatus::Missing,
+                (None, Some(_)) => CompletenessStatus::Complete,
+                (Some(0), _) => CompletenessStatus::Missing,
+                (Some(count), _) => match baseline {
+                    Some(b) if (*count as f64) < b * options.partial_threshold => {
+                        CompletenessStatus::Partial
+                    }
+                    _ => CompletenessStatus::Complete,
+                },
+            };
+
+            TimestampCompleteness {
+                timestamp: *timestamp,
+                entity_count: *entity_count,
+                baseline,
+                modified: *modified,
+                status,
+            }
+        })
+        .collect()
+}
+
+/// Check which timestamps of a trend store part in a time range have data
+/// and how many entities compared to the preceding baseline period.
+pub async fn trend_store_part_completeness<T: GenericClient + Send + Sync>(
+    client: &T,
+    trend_store: &TrendStore,
+    trend_store_part: &str,
+    options: &CompletenessOptions,
+) -> Result<CompletenessReport, Error> {
+    let row = client
+        .query_one(
+            "SELECT id FROM trend_directory.trend_store_part WHERE name = $1",
+            &[&trend_store_part],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not find trend store part '{trend_store_part}': {e}"
+            ))
+        })?;
+
+    let trend_store_part_id: i32 = row.get(0);
+
+    let granularity = Granularity::try_from(trend_store.granularity)?;
+
+    let query = completeness_query(trend_store_part, options.quick);
+
+    let baseline_period = baseline_period(options)?;
+    let baseline_start = options.start - baseline_period;
+    let expected_timestamps =
+        granularity.timestamps_in_range(&baseline_start, &options.end, &options.timezone)?;
+
+    let rows: Vec<CompletenessRow> = client
+        .query(
+            &query,
+            &[
+                &baseline_start,
+                &options.end,
+                &expected_timestamps,
+                &trend_store_part_id,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not check completeness of '{trend_store_part}': {e}"
+            ))
+        })?
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect();
+
+    Ok(CompletenessReport {
+        trend_store_part: trend_store_part.to_string(),
+        start: options.start,
+        end: options.end,
+        timestamps: evaluate_completeness(&rows, options, baseline_period),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn flag_missing_and_partial_timestamps() {
+        let timestamp = |hour: u32| Utc.with_ymd_and_hms(2024, 7, 1, hour, 0, 0).unwrap();
+
+        let rows = vec![
+            (timestamp(0), Some(100), Some(timestamp(1))),
+            (timestamp(1), Some(98), Some(timestamp(2))),
+            (timestamp(2), Some(0), None),
+            (timestamp(3), Some(102), Some(timestamp(4))),
+            (timestamp(4), Some(50), Some(timestamp(5))),
+            (timestamp(5), Some(0), None),
+        ];
+
+        let options = CompletenessOptions {
+            start: timestamp(3),
+            end: timestamp(6),
+            baseline_period: Duration::from_secs(3 * 3600),
+            partial_threshold: 0.9,
+            quick: false,
+            timezone: chrono_tz::UTC,
+        };
+
+        let result = evaluate_completeness(&rows, &options, baseline_period(&options).unwrap());
+
+        let statuses: Vec<(CompletenessStatus, Option<f64>)> =
+            result.iter().map(|t| (t.status, t.baseline)).collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                (CompletenessStatus::Complete, Some(99.0)),
+                (CompletenessStatus::Partial, Some(100.0)),
+                (CompletenessStatus::Missing, Some(76.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_out_of_range_baseline_period() {
+        let timestamp = Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap();
+
+        let options = CompletenessOptions {
+            start: timestamp,
+            end: timestamp,
+            baseline_period: Duration::MAX,
+            partial_threshold: 0.9,
+            quick: false,
+            timezone: chrono_tz::UTC,
+        };
+
+        assert!(matches!(
+            baseline_period(&options),
+            Err(Error::Configuration(_))
+        ));
+    }
+}
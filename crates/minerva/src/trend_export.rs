@@ -0,0 +1,560 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::array::{
+    ArrayRef, BooleanBuilder, Float32Builder, Float64Builder, Int16Builder, Int32Builder,
+    Int64Builder, ListBuilder, StringBuilder, TimestampMicrosecondBuilder,
+};
+use arrow::datatypes::{DataType as ArrowDataType, Field, Schema, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use futures_util::{pin_mut, StreamExt};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use tokio_postgres::Client;
+
+use crate::entity::load_entity_names;
+use crate::error::{ConfigurationError, Error, RuntimeError};
+use crate::meas_value::{array_literal, DataType, MeasValue};
+use crate::trend_store::{TrendDataFilter, TrendStore, TrendStorePart, ValueRow};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    /// CSV with a header line, as read by `load_data`
+    Csv,
+    /// Parquet file with typed columns, written in batches of rows
+    Parquet,
+}
+
+/// Number of rows per record batch in Parquet output
+const PARQUET_BATCH_SIZE: usize = 8192;
+
+/// Timezone of timestamp columns in Parquet output, as an offset so that it can
+/// be read back without timezone database
+const PARQUET_TIMEZONE: &str = "+00:00";
+
+impl FromStr for ExportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "parquet" => Ok(ExportFormat::Parquet),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported export format '{s}'"
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::Parquet => write!(f, "parquet"),
+        }
+    }
+}
+
+/// Definition of the data to export from a trend store part
+pub struct TrendExport<'a> {
+    pub trend_store: &'a TrendStore,
+    pub trend_store_part: &'a TrendStorePart,
+    pub trend_names: Vec<String>,
+    /// Name of the column holding the entity names
+    pub entity_column: String,
+}
+
+impl<'a> TrendExport<'a> {
+    /// Export all trends of the part
+    pub fn new(trend_store: &'a TrendStore, trend_store_part: &'a TrendStorePart) -> Self {
+        TrendExport {
+            trend_store,
+            trend_store_part,
+            trend_names: trend_store_part
+                .trends
+                .iter()
+                .map(|trend| trend.name.clone())
+                .collect(),
+            entity_column: trend_store.entity_type.clone(),
+        }
+    }
+}
+
+/// Text representation of a value in CSV output, `None` for NULL values
+fn csv_value(value: &MeasValue) -> Option<String> {
+    match value {
+        MeasValue::Int2(v) => v.map(|v| v.to_string()),
+        MeasValue::Integer(v) => v.map(|v| v.to_string()),
+        MeasValue::Int8(v) => v.map(|v| v.to_string()),
+        MeasValue::Real(v) => v.map(|v| v.to_string()),
+        MeasValue::Double(v) => v.map(|v| v.to_string()),
+        MeasValue::Numeric(v) => v.map(|v| v.to_string()),
+        MeasValue::Boolean(v) => v.map(|v| v.to_string()),
+        MeasValue::Text(v) => v.clone(),
+        MeasValue::TextArray(v) => v.as_deref().map(array_literal),
+        MeasValue::Timestamp(v) => v.map(|v| v.to_rfc3339()),
+        MeasValue::NumericArray(v) => v.as_deref().map(array_literal),
+    }
+}
+
+fn timestamp_type() -> ArrowDataType {
+    ArrowDataType::Timestamp(TimeUnit::Microsecond, Some(PARQUET_TIMEZONE.into()))
+}
+
+fn arrow_type(data_type: DataType) -> ArrowDataType {
+    match data_type {
+        DataType::Boolean => ArrowDataType::Boolean,
+        DataType::Int2 => ArrowDataType::Int16,
+        DataType::Integer => ArrowDataType::Int32,
+        DataType::Int8 => ArrowDataType::Int64,
+        DataType::Real => ArrowDataType::Float32,
+        DataType::Double => ArrowDataType::Float64,
+        // Numeric values are written as text to keep their precision
+        DataType::Numeric | DataType::Text => ArrowDataType::Utf8,
+        DataType::TextArray => {
+            ArrowDataType::List(Arc::new(Field::new_list_field(ArrowDataType::Utf8, true)))
+        }
+        DataType::Timestamp => timestamp_type(),
+        DataType::NumericArray => {
+            ArrowDataType::List(Arc::new(Field::new_list_field(ArrowDataType::Utf8, true)))
+        }
+    }
+}
+
+/// Builder for the values of one trend column in a Parquet record batch
+enum ColumnBuilder {
+    Boolean(BooleanBuilder),
+    Int2(Int16Builder),
+    Integer(Int32Builder),
+    Int8(Int64Builder),
+    Real(Float32Builder),
+    Double(Float64Builder),
+    Text(StringBuilder),
+    TextArray(ListBuilder<StringBuilder>),
+    Timestamp(TimestampMicrosecondBuilder),
+    NumericArray(ListBuilder<StringBuilder>),
+}
+
+impl ColumnBuilder {
+    fn new(data_type: DataType) -> ColumnBuilder {
+        match data_type {
+            DataType::Boolean => ColumnBuilder::Boolean(BooleanBuilder::new()),
+            DataType::Int2 => ColumnBuilder::Int2(Int16Builder::new()),
+            DataType::Integer => ColumnBuilder::Integer(Int32Builder::new()),
+            DataType::Int8 => ColumnBuilder::Int8(Int64Builder::new()),
+            DataType::Real => ColumnBuilder::Real(Float32Builder::new()),
+            DataType::Double => ColumnBuilder::Double(Float64Builder::new()),
+            DataType::Numeric | DataType::Text => ColumnBuilder::Text(StringBuilder::new()),
+            DataType::TextArray => ColumnBuilder::TextArray(ListBuilder::new(StringBuilder::new())),
+            DataType::Timestamp => ColumnBuilder::Timestamp(
+                TimestampMicrosecondBuilder::new().with_timezone(PARQUET_TIMEZONE),
+            ),
+            DataType::NumericArray => {
+                ColumnBuilder::NumericArray(ListBuilder::new(StringBuilder::new()))
+            }
+        }
+    }
+
+    fn append(&mut self, value: &MeasValue) -> Result<(), Error> {
+        match (self, value) {
+            (ColumnBuilder::Boolean(builder), MeasValue::Boolean(v)) => builder.append_option(*v),
+            (ColumnBuilder::Int2(builder), MeasValue::Int2(v)) => builder.append_option(*v),
+            (ColumnBuilder::Integer(builder), MeasValue::Integer(v)) => builder.append_option(*v),
+            (ColumnBuilder::Int8(builder), MeasValue::Int8(v)) => builder.append_option(*v),
+            (ColumnBuilder::Real(builder), MeasValue::Real(v)) => builder.append_option(*v),
+            (ColumnBuilder::Double(builder), MeasValue::Double(v)) => builder.append_option(*v),
+            (ColumnBuilder::Text(builder), MeasValue::Numeric(v)) => {
+                builder.append_option(v.map(|v| v.to_string()))
+            }
+            (ColumnBuilder::Text(builder), MeasValue::Text(v)) => builder.append_option(v.as_ref()),
+            (ColumnBuilder::TextArray(builder), MeasValue::TextArray(v)) => {
+                builder.append_option(v.as_ref().map(|v| v.iter().map(Some)))
+            }
+            (ColumnBuilder::Timestamp(builder), MeasValue::Timestamp(v)) => {
+                builder.append_option(v.map(|v| v.timestamp_micros()))
+            }
+            (ColumnBuilder::NumericArray(builder), MeasValue::NumericArray(v)) => {
+                builder.append_option(v.as_ref().map(|v| v.iter().map(|v| Some(v.to_string()))))
+            }
+            (_, value) => {
+                return Err(write_error(format!(
+                    "Unexpected value {value:?} for Parquet column"
+                )))
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self) -> ArrayRef {
+        match self {
+            ColumnBuilder::Boolean(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Int2(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Integer(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Int8(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Real(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Double(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Text(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::TextArray(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::Timestamp(builder) => Arc::new(builder.finish()),
+            ColumnBuilder::NumericArray(builder) => Arc::new(builder.finish()),
+        }
+    }
+}
+
+/// Parquet output that collects rows in builders and writes them as a record
+/// batch every `PARQUET_BATCH_SIZE` rows
+struct ParquetExport<W: Write + Send> {
+    writer: ArrowWriter<W>,
+    schema: SchemaRef,
+    entities: StringBuilder,
+    timestamps: TimestampMicrosecondBuilder,
+    values: Vec<ColumnBuilder>,
+    row_count: usize,
+}
+
+impl<W: Write + Send> ParquetExport<W> {
+    fn new(columns: &[String], data_types: &[DataType], writer: W) -> Result<Self, Error> {
+        let mut fields = vec![
+            Field::new(&columns[0], ArrowDataType::Utf8, false),
+            Field::new(&columns[1], timestamp_type(), false),
+        ];
+
+        fields.extend(
+            columns[2..]
+                .iter()
+                .zip(data_types)
+                .map(|(column, data_type)| Field::new(column, arrow_type(*data_type), true)),
+        );
+
+        let schema: SchemaRef = Arc::new(Schema::new(fields));
+
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+
+        let writer = ArrowWriter::try_new(writer, schema.clone(), Some(properties))
+            .map_err(|e| write_error(e.to_string()))?;
+
+        Ok(ParquetExport {
+            writer,
+            schema,
+            entities: StringBuilder::new(),
+            timestamps: TimestampMicrosecondBuilder::new().with_timezone(PARQUET_TIMEZONE),
+            values: data_types.iter().map(|t| ColumnBuilder::new(*t)).collect(),
+            row_count: 0,
+        })
+    }
+
+    fn write_row(&mut self, entity_name: &str, value_row: &ValueRow) -> Result<(), Error> {
+        self.entities.append_value(entity_name);
+        self.timestamps
+            .append_value(value_row.timestamp.timestamp_micros());
+
+        for (builder, value) in self.values.iter_mut().zip(value_row.values.iter()) {
+            builder.append(value)?;
+        }
+
+        self.row_count += 1;
+
+        if self.row_count == PARQUET_BATCH_SIZE {
+            self.write_batch()?;
+        }
+
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<(), Error> {
+        if self.row_count == 0 {
+            return Ok(());
+        }
+
+        let mut arrays: Vec<ArrayRef> = vec![
+            Arc::new(self.entities.finish()),
+            Arc::new(self.timestamps.finish()),
+        ];
+
+        arrays.extend(self.values.iter_mut().map(|builder| builder.finish()));
+
+        let batch = RecordBatch::try_new(self.schema.clone(), arrays)
+            .map_err(|e| write_error(e.to_string()))?;
+
+        self.writer
+            .write(&batch)
+            .map_err(|e| write_error(e.to_string()))?;
+
+        self.row_count = 0;
+
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Error> {
+        self.write_batch()?;
+
+        self.writer
+            .close()
+            .map(|_| ())
+            .map_err(|e| write_error(e.to_string()))
+    }
+}
+
+enum ExportWriter<W: Write + Send> {
+    Csv(Box<csv::Writer<W>>),
+    Parquet(Box<ParquetExport<W>>),
+}
+
+impl<W: Write + Send> ExportWriter<W> {
+    /// Create a writer for the entity and timestamp columns followed by trend
+    /// columns with the specified data types
+    fn new(
+        format: ExportFormat,
+        columns: Vec<String>,
+        data_types: &[DataType],
+        writer: W,
+    ) -> Result<Self, Error> {
+        match format {
+            ExportFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+
+                csv_writer
+                    .write_record(&columns)
+                    .map_err(|e| write_error(e.to_string()))?;
+
+                Ok(ExportWriter::Csv(Box::new(csv_writer)))
+            }
+            ExportFormat::Parquet => Ok(ExportWriter::Parquet(Box::new(ParquetExport::new(
+                &columns, data_types, writer,
+            )?))),
+        }
+    }
+
+    fn write_row(&mut self, entity_name: &str, value_row: &ValueRow) -> Result<(), Error> {
+        match self {
+            ExportWriter::Csv(csv_writer) => {
+                let mut record: Vec<String> =
+                    vec![entity_name.to_string(), value_row.timestamp.to_rfc3339()];
+
+                record.extend(
+                    value_row
+                        .values
+                        .iter()
+                        .map(|value| csv_value(value).unwrap_or_default()),
+                );
+
+                csv_writer
+                    .write_record(&record)
+                    .map_err(|e| write_error(e.to_string()))
+            }
+            ExportWriter::Parquet(parquet_export) => {
+                parquet_export.write_row(entity_name, value_row)
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self {
+            ExportWriter::Csv(mut csv_writer) => {
+                csv_writer.flush().map_err(|e| write_error(e.to_string()))
+            }
+            ExportWriter::Parquet(parquet_export) => parquet_export.finish(),
+        }
+    }
+}
+
+fn write_error(msg: String) -> Error {
+    Error::Runtime(RuntimeError::from_msg(format!(
+        "Could not write exported trend data: {msg}"
+    )))
+}
+
+/// Stream trend data from a trend store part to `writer` in the specified
+/// format and return the number of exported rows.
+///
+/// The first column holds the entity name and the second column the timestamp,
+/// so that CSV and Parquet output can be loaded again using `load_data`.
+pub async fn export_trend_data<W: Write + Send>(
+    client: &Client,
+    export: &TrendExport<'_>,
+    filter: &TrendDataFilter<'_>,
+    format: ExportFormat,
+    writer: W,
+) -> Result<u64, Error> {
+    // Entity names are loaded up front because no other queries can be run on
+    // the connection while the data is streamed.
+    let entity_names: HashMap<i32, String> =
+        load_entity_names(client, &export.trend_store.entity_type, filter.entity_ids).await?;
+
+    let mut columns: Vec<String> = vec![export.entity_column.clone(), "timestamp".to_string()];
+    columns.extend(export.trend_names.iter().cloned());
+
+    let data_types: Vec<DataType> = export
+        .trend_names
+        .iter()
+        .map(|trend_name| {
+            export
+                .trend_store_part
+                .trends
+                .iter()
+                .find(|trend| &trend.name == trend_name)
+                .map(|trend| trend.data_type)
+                .ok_or_else(|| {
+                    Error::Runtime(RuntimeError::from_msg(format!(
+                        "No trend '{}' in trend store part '{}'",
+                        trend_name, export.trend_store_part.name
+                    )))
+                })
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut export_writer = ExportWriter::new(format, columns, &data_types, writer)?;
+
+    let rows = export
+        .trend_store_part
+        .fetch_stream(client, &export.trend_names, filter)
+        .await?;
+
+    pin_mut!(rows);
+
+    let mut row_count: u64 = 0;
+
+    while let Some(row) = rows.next().await {
+        let value_row = row?;
+
+        let entity_name = entity_names.get(&value_row.entity_id).ok_or_else(|| {
+            Error::Runtime(RuntimeError::from_msg(format!(
+                "No name found for entity with Id {}",
+                value_row.entity_id
+            )))
+        })?;
+
+        export_writer.write_row(entity_name, &value_row)?;
+
+        row_count += 1;
+    }
+
+    export_writer.finish()?;
+
+    Ok(row_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn value_row() -> ValueRow {
+        ValueRow {
+            entity_id: 1,
+            timestamp: Utc.with_ymd_and_hms(2024, 7, 1, 10, 15, 0).unwrap(),
+            values: vec![MeasValue::Integer(Some(42)), MeasValue::Double(None)],
+        }
+    }
+
+    fn columns() -> Vec<String> {
+        vec![
+            "node".to_string(),
+            "timestamp".to_string(),
+            "power".to_string(),
+            "temperature".to_string(),
+        ]
+    }
+
+    fn data_types() -> Vec<DataType> {
+        vec![DataType::Integer, DataType::Double]
+    }
+
+    #[test]
+    fn export_csv_row() {
+        let mut buffer: Vec<u8> = Vec::new();
+
+        let mut writer =
+            ExportWriter::new(ExportFormat::Csv, columns(), &data_types(), &mut buffer).unwrap();
+        writer.write_row("node_1", &value_row()).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "node,timestamp,power,temperature\nnode_1,2024-07-01T10:15:00+00:00,42,\n"
+        );
+    }
+
+    #[test]
+    fn export_parquet_round_trip() {
+        let mut buffer: Vec<u8> = Vec::new();
+
+        let columns = vec![
+            "node".to_string(),
+            "timestamp".to_string(),
+            "power".to_string(),
+            "temperature".to_string(),
+            "samples".to_string(),
+            "energy".to_string(),
+            "labels".to_string(),
+        ];
+
+        let data_types = vec![
+            DataType::Integer,
+            DataType::Double,
+            DataType::NumericArray,
+            DataType::Numeric,
+            DataType::TextArray,
+        ];
+
+        let mut writer = ExportWriter::new(
+            ExportFormat::Parquet,
+            columns.clone(),
+            &data_types,
+            &mut buffer,
+        )
+        .unwrap();
+
+        writer
+            .write_row(
+                "node_1",
+                &ValueRow {
+                    entity_id: 1,
+                    timestamp: Utc.with_ymd_and_hms(2024, 7, 1, 10, 15, 0).unwrap(),
+                    values: vec![
+                        MeasValue::Integer(Some(42)),
+                        MeasValue::Double(None),
+                        MeasValue::NumericArray(Some(vec![
+                            rust_decimal::Decimal::new(15, 1),
+                            rust_decimal::Decimal::new(3, 0),
+                        ])),
+                        MeasValue::Numeric(Some(
+                            rust_decimal::Decimal::from_str("12345678901234567.891").unwrap(),
+                        )),
+                        MeasValue::TextArray(Some(vec![
+                            "a,b".to_string(),
+                            "say \"hi\"".to_string(),
+                            "c".to_string(),
+                        ])),
+                    ],
+                },
+            )
+            .unwrap();
+        writer.finish().unwrap();
+
+        let (headers, records) =
+            crate::loading::read_parquet_records(bytes::Bytes::from(buffer), "").unwrap();
+
+        assert_eq!(headers, columns);
+        assert_eq!(
+            records,
+            vec![vec![
+                "node_1".to_string(),
+                "2024-07-01T10:15:00Z".to_string(),
+                "42".to_string(),
+                "".to_string(),
+                "{1.5,3}".to_string(),
+                "12345678901234567.891".to_string(),
+                r#"{"a,b","say \"hi\"",c}"#.to_string(),
+            ]]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use glob::glob;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -5,6 +6,7 @@ use serde_yaml;
 use std::fmt;
 use std::marker::{Send, Sync};
 use std::path::Path;
+use std::str::FromStr;
 use std::time::Duration;
 use tokio_postgres::Transaction;
 
@@ -17,6 +19,7 @@ use async_trait::async_trait;
 
 use super::change::{Change, ChangeResult};
 use super::error::{DatabaseError, Error, RuntimeError};
+use super::granularity::Granularity;
 use super::interval::parse_interval;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -1257,3 +1260,109 @@ pub async fn reset_source_fingerprint<T: GenericClient + Send + Sync>(
 
     Ok(())
 }
+
+/// State of a materialization for one timestamp as registered in
+/// `trend_directory.materialization_state`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MaterializationState {
+    pub timestamp: DateTime<Utc>,
+    pub source_fingerprint: Option<Value>,
+    pub processed_fingerprint: Option<Value>,
+    /// True when the sources changed after the last materialization
+    pub stale: bool,
+    pub max_modified: Option<DateTime<Utc>>,
+    /// Time at which the job that last materialized this timestamp finished
+    pub last_processed: Option<DateTime<Utc>>,
+}
+
+pub async fn load_materialization_state<T: GenericClient + Send + Sync>(
+    client: &T,
+    materialization_id: i32,
+    start: Option<DateTime<Utc>>,
+    end: Option<DateTime<Utc>>,
+) -> Result<Vec<MaterializationState>, Error> {
+    let query = concat!(
+        "SELECT ms.timestamp, ms.source_fingerprint, ms.processed_fingerprint, ",
+        "ms.processed_fingerprint IS DISTINCT FROM ms.source_fingerprint, ",
+        "ms.max_modified, job.finished ",
+        "FROM trend_directory.materialization_state ms ",
+        "LEFT JOIN logging.job ON job.id = ms.job_id ",
+        "WHERE ms.materialization_id = $1 ",
+        "AND ($2::timestamptz IS NULL OR ms.timestamp >= $2) ",
+        "AND ($3::timestamptz IS NULL OR ms.timestamp <= $3) ",
+        "ORDER BY ms.timestamp"
+    );
+
+    let rows = client
+        .query(query, &[&materialization_id, &start, &end])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Error loading materialization state: {e}"))
+        })?;
+
+    Ok(rows
+        .iter()
+        .map(|row| MaterializationState {
+            timestamp: row.get(0),
+            source_fingerprint: row.get(1),
+            processed_fingerprint: row.get(2),
+            stale: row.get(3),
+            max_modified: row.get(4),
+            last_processed: row.get(5),
+        })
+        .collect())
+}
+
+/// Return the timestamps of the target trend store part of a materialization
+/// within the range `start` - `end` (inclusive), aligned in `timezone`
+pub async fn materialization_timestamps<T: GenericClient + Send + Sync>(
+    client: &T,
+    materialization_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    timezone: &chrono_tz::Tz,
+) -> Result<Vec<DateTime<Utc>>, Error> {
+    let query = concat!(
+        "SELECT ts.granularity::text FROM trend_directory.materialization m ",
+        "JOIN trend_directory.trend_store_part tsp ON tsp.id = m.dst_trend_store_part_id ",
+        "JOIN trend_directory.trend_store ts ON ts.id = tsp.trend_store_id ",
+        "WHERE m.id = $1"
+    );
+
+    let row = client
+        .query_one(query, &[&materialization_id])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Error loading materialization granularity: {e}"))
+        })?;
+
+    let granularity = Granularity::from_str(row.get(0))?;
+
+    let timestamps = granularity
+        .timestamps(&start.with_timezone(timezone), &end.with_timezone(timezone))?
+        .iter()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .collect();
+
+    Ok(timestamps)
+}
+
+/// Materialize one timestamp and return the number of records written
+pub async fn materialize<T: GenericClient + Send + Sync>(
+    client: &T,
+    materialization_id: i32,
+    timestamp: DateTime<Utc>,
+) -> Result<i32, Error> {
+    let query = "SELECT (trend_directory.materialize($1, $2)).row_count";
+
+    let row = client
+        .query_one(query, &[&materialization_id, &timestamp])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Could not materialize timestamp {timestamp}: {e}"))
+        })?;
+
+    let row_count: Option<i32> = row.get(0);
+
+    Ok(row_count.unwrap_or(0))
+}
@@ -0,0 +1,679 @@
+use std::fmt;
+
+use postgres_protocol::escape::escape_identifier;
+use rust_decimal::prelude::*;
+use rust_decimal::RoundingStrategy;
+use tokio_postgres::Client;
+
+use crate::changes::trend_store::ModifyTrendDataType;
+use crate::error::{DatabaseError, Error, RuntimeError};
+use crate::meas_value::DataType;
+use crate::trend_store::{trend_value_ranges, TrendStorePart};
+
+/// Values of a trend that do not fit in the new data type
+pub struct RangeViolation {
+    pub trend_name: String,
+    pub to_type: DataType,
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+    /// Number of values that would overflow
+    pub count: i64,
+}
+
+impl fmt::Display for RangeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} values of '{}' do not fit in {} (min: {}, max: {})",
+            self.count,
+            self.trend_name,
+            self.to_type,
+            self.min_value.as_deref().unwrap_or("N/A"),
+            self.max_value.as_deref().unwrap_or("N/A"),
+        )
+    }
+}
+
+/// Plan for changing the data types of trends in a trend store part without
+/// blocking data loading for the duration of the change.
+///
+/// Instead of one `ALTER TABLE ... TYPE` that rewrites the complete table
+/// under an exclusive lock, the migration:
+///
+/// 1. adds a column with the new data type for each trend,
+/// 2. fills the new columns one partition at a time, each in its own
+///    transaction,
+/// 3. copies values that were inserted or updated since step 1 and swaps the
+///    columns in one final transaction. These rows are recorded by a temporary
+///    trigger, so the time the table is locked does not depend on its size.
+///
+/// The migrated trends move to the end of the columns of the table. Trends
+/// that are used by views or generated columns cannot be migrated, because
+/// their columns are replaced.
+pub struct DataTypeMigrationPlan {
+    pub trend_store_part: String,
+    pub modifications: Vec<ModifyTrendDataType>,
+    pub partitions: Vec<String>,
+    /// Trends for which the range of values could not be checked up front
+    pub unchecked: Vec<String>,
+    pub violations: Vec<RangeViolation>,
+    /// Trends with the views or generated columns that use them
+    pub dependents: Vec<(String, String)>,
+}
+
+impl DataTypeMigrationPlan {
+    /// Return true if all existing values fit in the new data types
+    pub fn is_safe(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl fmt::Display for DataTypeMigrationPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Data type migration of trend store part '{}':",
+            self.trend_store_part
+        )?;
+
+        for modification in &self.modifications {
+            writeln!(
+                f,
+                "  {}: {} -> {}",
+                modification.trend_name, modification.from_type, modification.to_type
+            )?;
+        }
+
+        writeln!(
+            f,
+            "1. Add {} column(s) with the new data types",
+            self.modifications.len()
+        )?;
+
+        for (index, partition) in self.partitions.iter().enumerate() {
+            writeln!(f, "{}. Copy values of partition '{}'", index + 2, partition)?;
+        }
+
+        writeln!(
+            f,
+            "{}. Copy values inserted or updated during the migration and swap the columns",
+            self.partitions.len() + 2
+        )?;
+
+        for (trend_name, dependent) in &self.dependents {
+            writeln!(f, "'{trend_name}' is used by {dependent}")?;
+        }
+
+        for trend_name in &self.unchecked {
+            writeln!(f, "Range of values of '{trend_name}' could not be checked")?;
+        }
+
+        for violation in &self.violations {
+            writeln!(f, "{violation}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_numeric(data_type: DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int2
+            | DataType::Integer
+            | DataType::Int8
+            | DataType::Real
+            | DataType::Double
+            | DataType::Numeric
+    )
+}
+
+/// Return true if every value of type `from` can be cast to `to` without
+/// overflowing
+fn always_fits(from: DataType, to: DataType) -> bool {
+    match (from, to) {
+        (_, DataType::Text) => true,
+        (DataType::Int2, DataType::Integer | DataType::Int8) => true,
+        (DataType::Integer, DataType::Int8) => true,
+        (DataType::Int2 | DataType::Integer | DataType::Int8, DataType::Real) => true,
+        (
+            DataType::Int2 | DataType::Integer | DataType::Int8 | DataType::Real,
+            DataType::Double,
+        ) => true,
+        (from, DataType::Numeric) => is_numeric(from),
+        (from, to) => from == to,
+    }
+}
+
+/// Range of the integer data types
+fn integer_range(data_type: DataType) -> Option<(i64, i64)> {
+    match data_type {
+        DataType::Int2 => Some((i16::MIN as i64, i16::MAX as i64)),
+        DataType::Integer => Some((i32::MIN as i64, i32::MAX as i64)),
+        DataType::Int8 => Some((i64::MIN, i64::MAX)),
+        _ => None,
+    }
+}
+
+/// Return true if the text representation of a numeric value can be cast to
+/// `data_type`, `None` if this cannot be determined
+fn value_fits(value: &str, data_type: DataType) -> Option<bool> {
+    if let Some((min, max)) = integer_range(data_type) {
+        let decimal = Decimal::from_str(value)
+            .or_else(|_| Decimal::from_scientific(value))
+            .ok();
+
+        return Some(match decimal {
+            // Casting to an integer type rounds half away from zero
+            Some(d) => {
+                let rounded = d.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero);
+
+                rounded >= Decimal::from(min) && rounded <= Decimal::from(max)
+            }
+            // NaN, Infinity or out of the range of Decimal
+            None => false,
+        });
+    }
+
+    match data_type {
+        DataType::Real => value
+            .parse::<f64>()
+            .ok()
+            .map(|v| !v.is_finite() || v.abs() <= f32::MAX as f64),
+        DataType::Double | DataType::Numeric | DataType::Text => Some(true),
+        _ => None,
+    }
+}
+
+/// SQL condition that matches the values of `trend_name` that do not fit in
+/// `data_type`
+fn overflow_condition(trend_name: &str, data_type: DataType) -> Option<String> {
+    let column = escape_identifier(trend_name);
+
+    if let Some((min, max)) = integer_range(data_type) {
+        return Some(format!(
+            "(round({column}::numeric) < {min} OR round({column}::numeric) > {max})"
+        ));
+    }
+
+    match data_type {
+        DataType::Real => Some(format!(
+            "(abs({column}::double precision) > {})",
+            f32::MAX as f64
+        )),
+        _ => None,
+    }
+}
+
+/// Check the range of values of the trends against their new data types and
+/// return a plan for the migration.
+pub async fn plan_data_type_migration(
+    client: &mut Client,
+    trend_store_part_name: &str,
+    modifications: Vec<ModifyTrendDataType>,
+) -> Result<DataTypeMigrationPlan, Error> {
+    let partitions: Vec<String> = client
+        .query(
+            concat!(
+                "SELECT p.name::text FROM trend_directory.partition p ",
+                "JOIN trend_directory.trend_store_part tsp ON tsp.id = p.trend_store_part_id ",
+                "WHERE tsp.name = $1 ORDER BY p.index"
+            ),
+            &[&trend_store_part_name],
+        )
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load partitions: {e}")))?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let mut unchecked: Vec<String> = Vec::new();
+    let mut checked: Vec<String> = Vec::new();
+
+    for modification in &modifications {
+        if always_fits(modification.from_type, modification.to_type) {
+            continue;
+        }
+
+        if is_numeric(modification.from_type)
+            && overflow_condition(&modification.trend_name, modification.to_type).is_some()
+        {
+            checked.push(modification.trend_name.clone());
+        } else {
+            unchecked.push(modification.trend_name.clone());
+        }
+    }
+
+    let mut violations: Vec<RangeViolation> = Vec::new();
+
+    if !checked.is_empty() {
+        let ranges = trend_value_ranges(client, trend_store_part_name, &checked).await?;
+
+        for range in ranges {
+            let modification = modifications
+                .iter()
+                .find(|m| m.trend_name == range.name)
+                .unwrap();
+
+            let fits = |value: &Option<String>| {
+                value
+                    .as_deref()
+                    .is_none_or(|v| value_fits(v, modification.to_type) != Some(false))
+            };
+
+            if fits(&range.min_value) && fits(&range.max_value) {
+                continue;
+            }
+
+            // The condition exists, otherwise the trend would not be checked
+            let condition = overflow_condition(&range.name, modification.to_type).unwrap();
+
+            let query = format!(
+                "SELECT count(*) FROM trend.{} WHERE {}",
+                escape_identifier(trend_store_part_name),
+                condition
+            );
+
+            let row = client.query_one(&query, &[]).await.map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Could not count overflowing values of '{}': {e}",
+                    &range.name
+                ))
+            })?;
+
+            violations.push(RangeViolation {
+                trend_name: range.name.clone(),
+                to_type: modification.to_type,
+                min_value: range.min_value,
+                max_value: range.max_value,
+                count: row.get(0),
+            });
+        }
+    }
+
+    let trend_names: Vec<&str> = modifications
+        .iter()
+        .map(|m| m.trend_name.as_str())
+        .collect();
+
+    let dependents: Vec<(String, String)> = client
+        .query(
+            concat!(
+                "SELECT DISTINCT a.attname::text, CASE WHEN r.oid IS NOT NULL ",
+                "THEN format('view %s', r.ev_class::regclass) ",
+                "ELSE format('generated column %I', g.attname) END ",
+                "FROM pg_depend d ",
+                "JOIN pg_attribute a ON a.attrelid = d.refobjid AND a.attnum = d.refobjsubid ",
+                "LEFT JOIN pg_rewrite r ON d.classid = 'pg_rewrite'::regclass AND r.oid = d.objid ",
+                "LEFT JOIN pg_attrdef ad ON d.classid = 'pg_attrdef'::regclass AND ad.oid = d.objid ",
+                "LEFT JOIN pg_attribute g ON g.attrelid = ad.adrelid AND g.attnum = ad.adnum ",
+                "WHERE d.refclassid = 'pg_class'::regclass AND d.refobjid = $1::text::regclass ",
+                "AND a.attname = ANY($2::text[]) ",
+                "AND (r.ev_class <> d.refobjid OR g.attnum <> a.attnum) ",
+                "ORDER BY 1, 2"
+            ),
+            &[
+                &format!("trend.{}", escape_identifier(trend_store_part_name)),
+                &trend_names,
+            ],
+        )
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load dependent objects: {e}")))?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    Ok(DataTypeMigrationPlan {
+        trend_store_part: trend_store_part_name.to_string(),
+        modifications,
+        partitions,
+        unchecked,
+        violations,
+        dependents,
+    })
+}
+
+/// Name of the temporary column holding the converted values of a trend
+fn migration_column_name(index: usize) -> String {
+    format!("_migrate_{index}")
+}
+
+/// Name of the table recording the rows inserted or updated during the
+/// migration of a trend store part
+fn changed_rows_table_name(trend_store_part_id: i32) -> String {
+    format!("_migrate_{trend_store_part_id}_changed")
+}
+
+/// Name of the trigger function recording the changed rows
+fn track_function_name(trend_store_part_id: i32) -> String {
+    format!("_migrate_{trend_store_part_id}_track")
+}
+
+fn migration_error(msg: String) -> Error {
+    Error::Database(DatabaseError::from_msg(msg))
+}
+
+/// Execute a data type migration plan, reporting each completed step to
+/// `progress`. The plan is refused if existing values do not fit in the new
+/// data types.
+pub async fn migrate_data_types<F>(
+    client: &mut Client,
+    plan: &DataTypeMigrationPlan,
+    mut progress: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&str),
+{
+    if !plan.is_safe() {
+        return Err(Error::Runtime(RuntimeError::from_msg(format!(
+            "Values of {} trend(s) of '{}' do not fit in the new data types",
+            plan.violations.len(),
+            plan.trend_store_part
+        ))));
+    }
+
+    if !plan.dependents.is_empty() {
+        let dependents: Vec<String> = plan
+            .dependents
+            .iter()
+            .map(|(trend_name, dependent)| format!("'{trend_name}' is used by {dependent}"))
+            .collect();
+
+        return Err(Error::Runtime(RuntimeError::from_msg(format!(
+            "Trends of '{}' cannot be migrated while other objects use them, remove these first: {}",
+            plan.trend_store_part,
+            dependents.join(", ")
+        ))));
+    }
+
+    let table = format!("trend.{}", escape_identifier(&plan.trend_store_part));
+
+    let copy_assignments: Vec<String> = plan
+        .modifications
+        .iter()
+        .enumerate()
+        .map(|(index, m)| {
+            format!(
+                "{} = CAST({} AS {})",
+                escape_identifier(&migration_column_name(index)),
+                escape_identifier(&m.trend_name),
+                m.to_type
+            )
+        })
+        .collect();
+
+    let copy_assignments = copy_assignments.join(", ");
+
+    // Updates that only fill the new columns are not recorded
+    let changed_conditions: Vec<String> = plan
+        .modifications
+        .iter()
+        .map(|m| {
+            let column = escape_identifier(&m.trend_name);
+
+            format!("OLD.{column} IS DISTINCT FROM NEW.{column}")
+        })
+        .collect();
+
+    let changed_condition = changed_conditions.join(" OR ");
+
+    // Step 1: add the columns with the new data types
+    let tx = client.transaction().await?;
+
+    tx.execute("SET LOCAL lock_timeout = '10min'", &[])
+        .await
+        .map_err(|e| migration_error(format!("Error setting lock timeout: {e}")))?;
+
+    for (index, m) in plan.modifications.iter().enumerate() {
+        let column = escape_identifier(&migration_column_name(index));
+
+        tx.batch_execute(&format!(
+            "ALTER TABLE {table} DROP COLUMN IF EXISTS {column}; ALTER TABLE {table} ADD COLUMN {column} {}",
+            m.to_type
+        ))
+        .await
+        .map_err(|e| migration_error(format!("Error adding column for '{}': {e}", m.trend_name)))?;
+    }
+
+    let row = tx
+        .query_one(
+            "SELECT id FROM trend_directory.trend_store_part WHERE name = $1",
+            &[&plan.trend_store_part],
+        )
+        .await
+        .map_err(|e| migration_error(format!("Could not find trend store part: {e}")))?;
+
+    let trend_store_part_id: i32 = row.get(0);
+
+    let changed_rows_table = format!(
+        "trend_directory.{}",
+        escape_identifier(&changed_rows_table_name(trend_store_part_id))
+    );
+    let track_function = format!(
+        "trend_directory.{}",
+        escape_identifier(&track_function_name(trend_store_part_id))
+    );
+
+    // Record the rows that are inserted or updated from here on, also in
+    // partitions created during the migration. Dropping the function also
+    // drops the triggers of an earlier attempt.
+    tx.batch_execute(&format!(
+        concat!(
+            "DROP FUNCTION IF EXISTS {track_function}() CASCADE; ",
+            "DROP TABLE IF EXISTS {changed_rows_table}; ",
+            "CREATE TABLE {changed_rows_table}(entity_id integer NOT NULL, timestamp timestamptz NOT NULL); ",
+            "CREATE FUNCTION {track_function}() RETURNS trigger AS $track$ BEGIN ",
+            "INSERT INTO {changed_rows_table}(entity_id, timestamp) VALUES (NEW.entity_id, NEW.timestamp); ",
+            "RETURN NULL; ",
+            "END; $track$ LANGUAGE plpgsql; ",
+            "CREATE TRIGGER _migrate_track_insert AFTER INSERT ON {table} ",
+            "FOR EACH ROW EXECUTE FUNCTION {track_function}(); ",
+            "CREATE TRIGGER _migrate_track_update AFTER UPDATE ON {table} ",
+            "FOR EACH ROW WHEN ({changed_condition}) EXECUTE FUNCTION {track_function}()"
+        ),
+        track_function = track_function,
+        changed_rows_table = changed_rows_table,
+        table = table,
+        changed_condition = changed_condition,
+    ))
+    .await
+    .map_err(|e| migration_error(format!("Error creating trigger to record changed rows: {e}")))?;
+
+    tx.commit().await?;
+
+    progress(&format!(
+        "Added {} column(s) to '{}'",
+        plan.modifications.len(),
+        plan.trend_store_part
+    ));
+
+    // Step 2: fill the new columns one partition at a time. Rows that are
+    // inserted or updated from here on are copied again in the final step.
+    for partition in &plan.partitions {
+        let tx = client.transaction().await?;
+
+        tx.execute("SET LOCAL statement_timeout = 0", &[])
+            .await
+            .map_err(|e| migration_error(format!("Error setting statement timeout: {e}")))?;
+
+        let query = format!(
+            "UPDATE trend_partition.{} SET {}",
+            escape_identifier(partition),
+            copy_assignments
+        );
+
+        let count = tx.execute(&query, &[]).await.map_err(|e| {
+            migration_error(format!(
+                "Error copying values of partition '{partition}': {e}"
+            ))
+        })?;
+
+        tx.commit().await?;
+
+        progress(&format!("Copied {count} rows of partition '{partition}'"));
+    }
+
+    // Step 3: copy values of the recorded rows and swap the columns
+    let tx = client.transaction().await?;
+
+    tx.batch_execute(&format!(
+        "SET LOCAL lock_timeout = '10min'; LOCK TABLE {table} IN ACCESS EXCLUSIVE MODE"
+    ))
+    .await
+    .map_err(|e| migration_error(format!("Error locking '{}': {e}", plan.trend_store_part)))?;
+
+    tx.execute("SET LOCAL statement_timeout = 0", &[])
+        .await
+        .map_err(|e| migration_error(format!("Error setting statement timeout: {e}")))?;
+
+    let query = format!(
+        concat!(
+            "UPDATE {} t SET {} ",
+            "FROM (SELECT DISTINCT entity_id, timestamp FROM {}) changed ",
+            "WHERE t.entity_id = changed.entity_id AND t.timestamp = changed.timestamp"
+        ),
+        table, copy_assignments, changed_rows_table
+    );
+
+    let count = tx
+        .execute(&query, &[])
+        .await
+        .map_err(|e| migration_error(format!("Error copying recently loaded values: {e}")))?;
+
+    tx.batch_execute(&format!(
+        "DROP FUNCTION {track_function}() CASCADE; DROP TABLE {changed_rows_table}"
+    ))
+    .await
+    .map_err(|e| {
+        migration_error(format!(
+            "Error removing trigger to record changed rows: {e}"
+        ))
+    })?;
+
+    let row = tx
+        .query_one(
+            concat!(
+                "SELECT trend_directory.staging_table_schema()::text, trend_directory.staging_table_name(tsp)::text ",
+                "FROM trend_directory.trend_store_part tsp WHERE id = $1"
+            ),
+            &[&trend_store_part_id],
+        )
+        .await
+        .map_err(|e| migration_error(format!("Could not determine staging table: {e}")))?;
+
+    let staging_table_schema: String = row.get(0);
+    let staging_table_name: String = row.get(1);
+
+    let staging_table_exists: bool = tx
+        .query_one(
+            "SELECT to_regclass($1) IS NOT NULL",
+            &[&format!(
+                "{}.{}",
+                escape_identifier(&staging_table_schema),
+                escape_identifier(&staging_table_name)
+            )],
+        )
+        .await
+        .map_err(|e| migration_error(format!("Could not check staging table: {e}")))?
+        .get(0);
+
+    for (index, m) in plan.modifications.iter().enumerate() {
+        let trend_column = escape_identifier(&m.trend_name);
+
+        tx.batch_execute(&format!(
+            "ALTER TABLE {table} DROP COLUMN {trend_column}; ALTER TABLE {table} RENAME COLUMN {} TO {trend_column}",
+            escape_identifier(&migration_column_name(index)),
+        ))
+        .await
+        .map_err(|e| migration_error(format!("Error swapping column of '{}': {e}", m.trend_name)))?;
+
+        if staging_table_exists {
+            tx.batch_execute(&format!(
+                "ALTER TABLE {}.{} ALTER {trend_column} TYPE {} USING CAST({trend_column} AS {})",
+                escape_identifier(&staging_table_schema),
+                escape_identifier(&staging_table_name),
+                m.to_type,
+                m.to_type
+            ))
+            .await
+            .map_err(|e| {
+                migration_error(format!(
+                    "Error changing staging table column of '{}': {e}",
+                    m.trend_name
+                ))
+            })?;
+        }
+
+        tx.execute(
+            concat!(
+                "UPDATE trend_directory.table_trend SET data_type = $3 ",
+                "WHERE trend_store_part_id = $1 AND name = $2"
+            ),
+            &[&trend_store_part_id, &m.trend_name, &m.to_type],
+        )
+        .await
+        .map_err(|e| {
+            migration_error(format!(
+                "Error updating data type of '{}': {e}",
+                m.trend_name
+            ))
+        })?;
+    }
+
+    tx.commit().await?;
+
+    progress(&format!(
+        "Copied {count} recently loaded rows and changed data types of '{}'",
+        plan.trend_store_part
+    ));
+
+    Ok(())
+}
+
+/// Data type changes of trends that exist in both trend store parts
+pub fn data_type_modifications(
+    from: &TrendStorePart,
+    to: &TrendStorePart,
+) -> Vec<ModifyTrendDataType> {
+    to.trends
+        .iter()
+        .filter_map(|to_trend| {
+            from.trends
+                .iter()
+                .find(|from_trend| from_trend.name == to_trend.name)
+                .filter(|from_trend| from_trend.data_type != to_trend.data_type)
+                .map(|from_trend| ModifyTrendDataType {
+                    trend_name: to_trend.name.clone(),
+                    from_type: from_trend.data_type,
+                    to_type: to_trend.data_type,
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_integer_ranges() {
+        assert_eq!(value_fits("32767", DataType::Int2), Some(true));
+        assert_eq!(value_fits("32767.5", DataType::Int2), Some(false));
+        assert_eq!(value_fits("-32768.4", DataType::Int2), Some(true));
+        assert_eq!(value_fits("2147483648", DataType::Integer), Some(false));
+        assert_eq!(value_fits("1e+20", DataType::Int8), Some(false));
+        assert_eq!(value_fits("NaN", DataType::Int8), Some(false));
+        assert_eq!(value_fits("1e+39", DataType::Real), Some(false));
+        assert_eq!(value_fits("1e+39", DataType::Double), Some(true));
+    }
+
+    #[test]
+    fn widening_conversions_always_fit() {
+        assert!(always_fits(DataType::Int2, DataType::Int8));
+        assert!(always_fits(DataType::Integer, DataType::Numeric));
+        assert!(always_fits(DataType::Numeric, DataType::Text));
+        assert!(!always_fits(DataType::Int8, DataType::Integer));
+        assert!(!always_fits(DataType::Double, DataType::Real));
+        assert!(!always_fits(DataType::Text, DataType::Integer));
+    }
+}
@@ -1,4 +1,4 @@
-use futures_util::pin_mut;
+use futures_util::{pin_mut, Stream, StreamExt};
 use humantime::format_duration;
 use postgres_protocol::escape::escape_identifier;
 use postgres_types::Type;
@@ -10,26 +10,25 @@ use std::fmt;
 use std::iter::zip;
 use std::path::PathBuf;
 use std::time::Duration;
+use tokio_postgres::binary_copy::BinaryCopyInWriter;
 use tokio_postgres::error::SqlState;
 use tokio_postgres::types::ToSql;
-use tokio_postgres::{binary_copy::BinaryCopyInWriter, Client, GenericClient, Row, Transaction};
+use tokio_postgres::{Client, GenericClient, Row, Transaction};
 use log::debug;
 
 use chrono::{DateTime, Utc};
 use rust_decimal::prelude::*;
-use rust_decimal::Decimal;
 
 use async_trait::async_trait;
 
 use crate::changes::trend_store::{
-    AddTrendStorePart, AddTrends, ModifyTrendDataType, ModifyTrendDataTypes, ModifyTrendExtraData,
-    RemoveTrends,
+    AddGeneratedTrends, AddTrendStorePart, AddTrends, ModifyGeneratedTrend, ModifyTrendDataType,
+    ModifyTrendDataTypes, ModifyTrendExtraData, ModifyTrendStoreRetentionPeriod,
+    RemoveGeneratedTrends, RemoveTrendStorePart, RemoveTrends, RenameTrend, RenameTrendStorePart,
 };
 use crate::entity::EntityMapping;
 use crate::error::DatabaseErrorKind;
-use crate::meas_value::{
-    DataType, MeasValue, INT8_NONE_VALUE, INTEGER_NONE_VALUE, NUMERIC_NONE_VALUE, TEXT_NONE_VALUE,
-};
+use crate::meas_value::{parse_meas_value_with, DataType, MeasValue, ValueParserConfig};
 
 use super::change::Change;
 use super::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
@@ -37,6 +36,59 @@ use super::interval::parse_interval;
 
 type PostgresName = String;
 
+/// How loaded trend data is combined with existing data of the same entity
+/// and timestamp
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum LoadMode {
+    /// Fail when data already exists
+    InsertOnly,
+    /// Replace existing values
+    #[default]
+    Overwrite,
+    /// Only set values that are NULL in the existing data
+    FillNullsOnly,
+    /// Leave existing rows untouched
+    SkipExisting,
+}
+
+impl fmt::Display for LoadMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadMode::InsertOnly => write!(f, "insert-only"),
+            LoadMode::Overwrite => write!(f, "overwrite"),
+            LoadMode::FillNullsOnly => write!(f, "fill-nulls-only"),
+            LoadMode::SkipExisting => write!(f, "skip-existing"),
+        }
+    }
+}
+
+impl FromStr for LoadMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insert-only" => Ok(LoadMode::InsertOnly),
+            "overwrite" => Ok(LoadMode::Overwrite),
+            "fill-nulls-only" => Ok(LoadMode::FillNullsOnly),
+            "skip-existing" => Ok(LoadMode::SkipExisting),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported load mode '{s}'"
+            )))),
+        }
+    }
+}
+
+/// How raw text values are parsed and combined with existing data
+#[derive(Debug, Clone, Default)]
+pub struct RawLoadOptions {
+    /// Text that represents a NULL value
+    pub null_value: String,
+    /// Array format per trend, trends without an entry use the default format
+    pub value_parsers: HashMap<String, ValueParserConfig>,
+    pub load_mode: LoadMode,
+}
+
 #[async_trait]
 pub trait RawMeasurementStore {
     async fn store_raw<E: EntityMapping + Sync>(
@@ -46,7 +98,7 @@ pub trait RawMeasurementStore {
         job_id: i64,
         trends: &[String],
         data_package: &[(String, DateTime<chrono::Utc>, Vec<String>)],
-        null_value: String,
+        options: &RawLoadOptions,
     ) -> Result<(), Error>;
 }
 
@@ -58,6 +110,7 @@ pub trait MeasurementStore {
         job_id: i64,
         trends: &[String],
         data_package: &[ValueRow],
+        load_mode: LoadMode,
     ) -> Result<(), Error>;
 
     async fn store_package<U>(
@@ -113,8 +166,7 @@ enum DeleteTrendStoreErrorKind {
     DatabaseError,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, ToSql)]
-#[postgres(name = "trend_descr")]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Trend {
     pub name: PostgresName,
     pub data_type: DataType,
@@ -126,6 +178,22 @@ pub struct Trend {
     pub entity_aggregation: String,
     #[serde(default = "default_extra_data")]
     pub extra_data: Value,
+    /// Previous names of the trend, so that a diff renames the trend instead of
+    /// removing it and adding a new one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub old_names: Vec<PostgresName>,
+}
+
+/// Representation of a trend as the `trend_directory.trend_descr` type
+#[derive(Debug, ToSql)]
+#[postgres(name = "trend_descr")]
+pub struct TrendDescr<'a> {
+    pub name: &'a str,
+    pub data_type: DataType,
+    pub description: &'a str,
+    pub time_aggregation: &'a str,
+    pub entity_aggregation: &'a str,
+    pub extra_data: &'a Value,
 }
 
 fn default_time_aggregation() -> String {
@@ -147,95 +215,43 @@ impl fmt::Display for Trend {
 }
 
 impl Trend {
-    pub fn sql_type(&self) -> Type {
-        match self.data_type {
-            DataType::Int2 => Type::INT2,
-            DataType::Integer => Type::INT4,
-            DataType::Int8 => Type::INT8,
-            DataType::Numeric => Type::NUMERIC,
-            DataType::Real => Type::FLOAT4,
-            DataType::Double => Type::FLOAT8,
-            DataType::Timestamp => Type::TIMESTAMPTZ,
-            _ => Type::TEXT,
+    pub fn descr(&self) -> TrendDescr<'_> {
+        TrendDescr {
+            name: &self.name,
+            data_type: self.data_type,
+            description: &self.description,
+            time_aggregation: &self.time_aggregation,
+            entity_aggregation: &self.entity_aggregation,
+            extra_data: &self.extra_data,
         }
     }
 
+    /// Return true if this trend is a renamed version of `trend`
+    pub fn is_renamed_from(&self, trend: &Trend) -> bool {
+        self.old_names
+            .iter()
+            .any(|old_name| old_name == &trend.name)
+    }
+
+    pub fn sql_type(&self) -> Type {
+        self.data_type.sql_type()
+    }
+
     pub fn none_value(&self) -> MeasValue {
-        match self.data_type {
-            DataType::Int2 => MeasValue::Int2(None),
-            DataType::Integer => MeasValue::Integer(None),
-            DataType::Numeric => MeasValue::Numeric(None),
-            DataType::Int8 => MeasValue::Int8(None),
-            DataType::Real => MeasValue::Real(None),
-            DataType::Double => MeasValue::Double(None),
-            DataType::Timestamp => MeasValue::Timestamp(DateTime::default()),
-            _ => MeasValue::Text("".to_string()),
-        }
+        MeasValue::null_value_of_type(self.data_type)
     }
 
-    pub fn meas_value_from_str(&self, value: &str, null_value: &str) -> Result<MeasValue, Error> {
-        match self.data_type {
-            DataType::Integer => {
-                if value == null_value {
-                    Ok(MeasValue::Integer(None))
-                } else {
-                    Ok(MeasValue::Integer(Some(i32::from_str(value).map_err(
-                        |e| {
-                            Error::Runtime(RuntimeError {
-                                msg: format!(
-                                    "Could not parse integer measurement value '{value}': {e}"
-                                ),
-                            })
-                        },
-                    )?)))
-                }
-            }
-            DataType::Numeric => {
-                if value == null_value {
-                    Ok(MeasValue::Numeric(None))
-                } else {
-                    Ok(MeasValue::Numeric(Some(Decimal::from_str(value).map_err(
-                        |e| {
-                            Error::Runtime(RuntimeError {
-                                msg: format!(
-                                    "Could not parse numeric measurement value '{value}': {e}"
-                                ),
-                            })
-                        },
-                    )?)))
-                }
-            }
-            DataType::Int8 => {
-                if value == null_value {
-                    Ok(MeasValue::Int8(None))
-                } else {
-                    Ok(MeasValue::Int8(Some(i64::from_str(value).map_err(
-                        |e| {
-                            Error::Runtime(RuntimeError {
-                                msg: format!(
-                                    "Could not parse bigint measurement value '{value}': {e}"
-                                ),
-                            })
-                        },
-                    )?)))
-                }
-            }
-            DataType::Real => {
-                if value == null_value {
-                    Ok(MeasValue::Real(None))
-                } else {
-                    Ok(MeasValue::Real(Some(f32::from_str(value).map_err(|e| Error::Runtime(RuntimeError { msg: format!("Could not parse floating point measurement value '{value}': {e}") }))?)))
-                }
-            }
-            DataType::Double => {
-                if value == null_value {
-                    Ok(MeasValue::Double(None))
-                } else {
-                    Ok(MeasValue::Double(Some(f64::from_str(value).map_err(|e| Error::Runtime(RuntimeError { msg: format!("Could not parse floating point measurement value '{value}': {e}") }))?)))
-                }
-            }
-            _ => Ok(MeasValue::Text("".to_string())),
+    pub fn meas_value_from_str(
+        &self,
+        value: &str,
+        null_value: &str,
+        parser_config: &ValueParserConfig,
+    ) -> Result<MeasValue, Error> {
+        if value == null_value {
+            return Ok(self.none_value());
         }
+
+        parse_meas_value_with(self.data_type, value, parser_config)
     }
 }
 
@@ -257,56 +273,113 @@ fn default_empty_string() -> String {
     String::new()
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, ToSql)]
-#[postgres(name = "trend_store_part_descr")]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrendStorePart {
     pub name: PostgresName,
     pub trends: Vec<Trend>,
 
     #[serde(default = "default_generated_trends")]
     pub generated_trends: Vec<GeneratedTrend>,
+
+    /// Previous names of the part, so that a diff renames the part instead of
+    /// removing it and adding a new one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub old_names: Vec<PostgresName>,
+}
+
+/// Representation of a trend store part as the
+/// `trend_directory.trend_store_part_descr` type
+#[derive(Debug, ToSql)]
+#[postgres(name = "trend_store_part_descr")]
+pub struct TrendStorePartDescr<'a> {
+    pub name: &'a str,
+    pub trends: Vec<TrendDescr<'a>>,
+    pub generated_trends: &'a [GeneratedTrend],
 }
 
 fn default_generated_trends() -> Vec<GeneratedTrend> {
     Vec::new()
 }
 
-fn insert_query(trend_store_part: &TrendStorePart, trends: &[&Trend]) -> String {
-    let update_part = trends
-        .iter()
-        .map(|trend| {
-            format!(
-                "{} = excluded.{}",
-                escape_identifier(&trend.name),
-                escape_identifier(&trend.name)
-            )
-        })
+/// Query that moves staged rows with a specific `created` timestamp to the
+/// trend table, handling existing rows as specified by `load_mode`. Of staged
+/// rows for the same entity and timestamp only the last one is used, because
+/// an upsert cannot affect the same row twice.
+fn transfer_staged_query(
+    trend_store_part: &TrendStorePart,
+    staging_table_name: &str,
+    trends: &[&Trend],
+    load_mode: LoadMode,
+) -> String {
+    let columns_part = ["entity_id", "timestamp", "created", "job_id"]
+        .into_iter()
+        .map(String::from)
+        .chain(trends.iter().map(|t| escape_identifier(&t.name)))
         .collect::<Vec<_>>()
         .join(", ");
 
-    let trend_names_part = trends
-        .iter()
-        .map(|t| escape_identifier(&t.name))
-        .collect::<Vec<_>>()
-        .join(", ");
+    let set_part = |expression: &dyn Fn(&str) -> String| {
+        trends
+            .iter()
+            .map(|t| {
+                let name = escape_identifier(&t.name);
 
-    let values_placeholders = (1..(trends.len() + 5))
-        .map(|i| format!("${}", i))
-        .collect::<Vec<_>>()
-        .join(", ");
+                format!("{name} = {}", expression(&name))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
 
-    let insert_query = format!(
-        "INSERT INTO trend.{}(entity_id, timestamp, created, job_id, {}) VALUES ({}) ON CONFLICT (entity_id, timestamp) DO UPDATE SET {}",
+    let conflict_part = match load_mode {
+        LoadMode::InsertOnly => String::new(),
+        LoadMode::Overwrite => format!(
+            " ON CONFLICT (entity_id, timestamp) DO UPDATE SET {}",
+            set_part(&|name| format!("excluded.{name}"))
+        ),
+        LoadMode::FillNullsOnly => format!(
+            " ON CONFLICT (entity_id, timestamp) DO UPDATE SET {} WHERE {}",
+            set_part(&|name| format!("coalesce(t.{name}, excluded.{name})")),
+            trends
+                .iter()
+                .map(|t| format!("t.{} IS NULL", escape_identifier(&t.name)))
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        ),
+        LoadMode::SkipExisting => " ON CONFLICT (entity_id, timestamp) DO NOTHING".to_string(),
+    };
+
+    format!(
+        concat!(
+            "INSERT INTO trend.{} AS t({}) ",
+            "SELECT DISTINCT ON (entity_id, timestamp) {} FROM trend.{} WHERE created = $1 ",
+            "ORDER BY entity_id, timestamp, ctid DESC{}"
+        ),
         escape_identifier(&trend_store_part.name),
-        &trend_names_part,
-        &values_placeholders,
-        update_part,
-    );
+        columns_part,
+        columns_part,
+        escape_identifier(staging_table_name),
+        conflict_part,
+    )
+}
+
+/// Rows with a unique entity and timestamp, keeping the last of duplicate rows
+/// so that later values replace earlier values, as they would when loaded
+/// separately.
+fn last_unique_rows(rows: &[ValueRow]) -> Vec<&ValueRow> {
+    let mut keys: HashSet<(i32, DateTime<Utc>)> = HashSet::new();
+
+    let mut unique_rows: Vec<&ValueRow> = rows
+        .iter()
+        .rev()
+        .filter(|row| keys.insert((row.entity_id, row.timestamp)))
+        .collect();
+
+    unique_rows.reverse();
 
-    insert_query
+    unique_rows
 }
 
-fn copy_from_query(trend_store_part: &TrendStorePart, trends: &[&Trend]) -> String {
+fn copy_from_query(table_name: &str, trends: &[&Trend]) -> String {
     let trend_names_part = trends
         .iter()
         .map(|t| escape_identifier(&t.name))
@@ -315,23 +388,124 @@ fn copy_from_query(trend_store_part: &TrendStorePart, trends: &[&Trend]) -> Stri
 
     let query = format!(
         "COPY trend.{}(entity_id, timestamp, created, job_id, {}) FROM STDIN BINARY",
-        escape_identifier(&trend_store_part.name),
+        escape_identifier(table_name),
         &trend_names_part
     );
 
     query
 }
 
+/// Selection of trend data from a trend store part, used by the read API
+pub struct TrendDataFilter<'a> {
+    /// Only return rows for these entities, or for all entities when `None`
+    pub entity_ids: Option<&'a [i32]>,
+    /// Start of the time range (inclusive)
+    pub start: DateTime<Utc>,
+    /// End of the time range (exclusive)
+    pub end: DateTime<Utc>,
+}
+
+fn fetch_query(
+    trend_store_part: &TrendStorePart,
+    trends: &[Trend],
+    timestamp_condition: &str,
+    entity_condition: Option<String>,
+) -> String {
+    let columns_part = trends
+        .iter()
+        .map(|trend| escape_identifier(&trend.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let select_part = match columns_part.is_empty() {
+        true => "entity_id, timestamp".to_string(),
+        false => format!("entity_id, timestamp, {columns_part}"),
+    };
+
+    let where_part = match entity_condition {
+        Some(condition) => format!("{timestamp_condition} AND {condition}"),
+        None => timestamp_condition.to_string(),
+    };
+
+    format!(
+        "SELECT {} FROM trend.{} WHERE {} ORDER BY timestamp, entity_id",
+        select_part,
+        escape_identifier(&trend_store_part.name),
+        where_part,
+    )
+}
+
+/// Source of column values for a fetched row
+trait FetchedRow {
+    fn value<'a, T: postgres_types::FromSql<'a>>(&'a self, index: usize) -> Result<T, String>;
+}
+
+impl FetchedRow for Row {
+    fn value<'a, T: postgres_types::FromSql<'a>>(&'a self, index: usize) -> Result<T, String> {
+        self.try_get(index).map_err(|e| e.to_string())
+    }
+}
+
+fn meas_value_from_row<R: FetchedRow>(
+    row: &R,
+    index: usize,
+    trend: &Trend,
+) -> Result<MeasValue, String> {
+    let value = match trend.data_type {
+        DataType::Boolean => MeasValue::Boolean(row.value(index)?),
+        DataType::Int2 => MeasValue::Int2(row.value(index)?),
+        DataType::Integer => MeasValue::Integer(row.value(index)?),
+        DataType::Int8 => MeasValue::Int8(row.value(index)?),
+        DataType::Real => MeasValue::Real(row.value(index)?),
+        DataType::Double => MeasValue::Double(row.value(index)?),
+        DataType::Numeric => MeasValue::Numeric(row.value(index)?),
+        DataType::NumericArray => MeasValue::NumericArray(row.value(index)?),
+        DataType::Timestamp => MeasValue::Timestamp(row.value(index)?),
+        DataType::Text => MeasValue::Text(row.value(index)?),
+        DataType::TextArray => MeasValue::TextArray(
+            row.value::<Option<Vec<Option<String>>>>(index)?
+                .map(|values| values.into_iter().map(Option::unwrap_or_default).collect()),
+        ),
+    };
+
+    Ok(value)
+}
+
+fn value_row_from<R: FetchedRow>(row: &R, trends: &[Trend]) -> Result<ValueRow, Error> {
+    let to_error = |e: String| {
+        Error::Database(DatabaseError::from_msg(format!(
+            "Could not read trend data row: {e}"
+        )))
+    };
+
+    let values = trends
+        .iter()
+        .enumerate()
+        .map(|(index, trend)| meas_value_from_row(row, index + 2, trend))
+        .collect::<Result<Vec<MeasValue>, String>>()
+        .map_err(to_error)?;
+
+    Ok(ValueRow {
+        entity_id: row.value(0).map_err(to_error)?,
+        timestamp: row.value(1).map_err(to_error)?,
+        values,
+    })
+}
+
 struct ValueExtractor<'a> {
     pub trend: &'a Trend,
     pub value_index: usize,
+    pub parser_config: &'a ValueParserConfig,
 }
 
 impl<'a> ValueExtractor<'a> {
     fn extract(&self, values: &[String], null_value: &str) -> Result<MeasValue, Error> {
         values
             .get(self.value_index)
-            .map(|v| self.trend.meas_value_from_str(v, null_value))
+            .map(|v| {
+                self.trend
+                    .meas_value_from_str(v, null_value, self.parser_config)
+            })
             .ok_or(Error::Runtime(RuntimeError::from(format!(
                 "Could not find value at index {}",
                 self.value_index
@@ -398,7 +572,7 @@ impl RawMeasurementStore for TrendStore {
         job_id: i64,
         trend_names: &[String],
         records: &[(String, DateTime<chrono::Utc>, Vec<String>)],
-        null_value: String,
+        options: &RawLoadOptions,
     ) -> Result<(), Error> {
         let entity_ids: Vec<i32> = entity_mapping.names_to_entity_ids(
             client,
@@ -411,6 +585,8 @@ impl RawMeasurementStore for TrendStore {
         .await
         .map_err(|e| Error::Runtime(RuntimeError::from_msg(e.to_string())))?;
 
+        let default_parser_config = ValueParserConfig::default();
+
         let mut extractors: HashMap<&str, SubPackageExtractor> = HashMap::new();
 
         for (value_index, trend_name) in trend_names.iter().enumerate() {
@@ -419,12 +595,20 @@ impl RawMeasurementStore for TrendStore {
                     if trend.name == *trend_name {
                         let extractor =
                             extractors.entry(&trend_store_part.name).or_insert_with(|| {
-                                SubPackageExtractor::new(trend_store_part, null_value.clone())
+                                SubPackageExtractor::new(
+                                    trend_store_part,
+                                    options.null_value.clone(),
+                                )
                             });
 
-                        extractor
-                            .value_extractors
-                            .push(ValueExtractor { trend, value_index });
+                        extractor.value_extractors.push(ValueExtractor {
+                            trend,
+                            value_index,
+                            parser_config: options
+                                .value_parsers
+                                .get(trend_name)
+                                .unwrap_or(&default_parser_config),
+                        });
                     }
                 }
             }
@@ -436,7 +620,13 @@ impl RawMeasurementStore for TrendStore {
 
             extractor
                 .trend_store_part
-                .store(client, job_id, &extractor.trend_names(), &sub_data_package)
+                .store(
+                    client,
+                    job_id,
+                    &extractor.trend_names(),
+                    &sub_data_package,
+                    options.load_mode,
+                )
                 .await
                 .map_err(|e| {
                     Error::Runtime(RuntimeError::from(format!(
@@ -464,28 +654,32 @@ impl MeasurementStore for TrendStorePart {
         job_id: i64,
         trends: &[String],
         data_package: &[ValueRow],
+        load_mode: LoadMode,
     ) -> Result<(), Error> {
         if trends.is_empty() {
             return Ok(());
         };
 
+        // Loading directly into the trend table is the fastest and the result
+        // is the same for all load modes when none of the rows exist yet.
         match self
             .store_copy_from(client, job_id, trends, data_package)
             .await
         {
-            Ok(_) => {
-                Ok(())
-            },
+            Ok(_) => Ok(()),
             Err(e) => {
-                debug!("ERROR!!!: {e}");
+                debug!("Could not COPY directly into '{}': {e}", self.name);
                 match e {
-                    Error::Database(dbe) => match dbe.kind {
-                        DatabaseErrorKind::UniqueViolation => {
-                            self.store_insert(client, job_id, trends, data_package)
+                    Error::Database(dbe) => match (&dbe.kind, load_mode) {
+                        (DatabaseErrorKind::UniqueViolation, LoadMode::InsertOnly) => {
+                            Err(Error::Database(dbe))
+                        }
+                        (DatabaseErrorKind::UniqueViolation, _) => {
+                            self.store_staged(client, job_id, trends, data_package, load_mode)
                                 .await?;
 
                             Ok(())
-                        },
+                        }
                         _ => Err(Error::Database(dbe)),
                     },
                     _ => Err(e),
@@ -502,13 +696,19 @@ impl MeasurementStore for TrendStorePart {
     where
         U: DataPackage + std::marker::Sync,
     {
+        let load_mode = data_package.load_mode();
+
         match self.store_copy_from_package(client, data_package).await {
             Ok(_) => Ok(()),
             Err(e) => match e {
-                Error::Database(dbe) => match dbe.kind {
-                    DatabaseErrorKind::UniqueViolation => {
-                        self.store_insert_package(client, data_package).await
+                Error::Database(dbe) => match (&dbe.kind, load_mode) {
+                    (DatabaseErrorKind::UniqueViolation, LoadMode::InsertOnly) => {
+                        Err(Error::Database(dbe))
                     }
+                    (DatabaseErrorKind::UniqueViolation, _) => self
+                        .store_staged_package(client, data_package, load_mode)
+                        .await
+                        .map(|_| ()),
                     _ => Err(Error::Database(dbe)),
                 },
                 _ => Err(e),
@@ -563,21 +763,11 @@ impl<'a> ValueMapper<'a> {
                     Some(v) => v,
                     None => {
                         // This should not be possible
-                        match self.data_type {
-                            DataType::Integer => &(*INTEGER_NONE_VALUE),
-                            DataType::Int8 => &(*INT8_NONE_VALUE),
-                            DataType::Numeric => &(*NUMERIC_NONE_VALUE),
-                            _ => &(*TEXT_NONE_VALUE),
-                        }
+                        MeasValue::null_value_ref(*self.data_type)
                     }
                 }
             }
-            None => match self.data_type {
-                DataType::Integer => &(*INTEGER_NONE_VALUE),
-                DataType::Int8 => &(*INT8_NONE_VALUE),
-                DataType::Numeric => &(*NUMERIC_NONE_VALUE),
-                _ => &(*TEXT_NONE_VALUE),
-            },
+            None => MeasValue::null_value_ref(*self.data_type),
         }
     }
 }
@@ -587,20 +777,17 @@ pub trait DataPackage {
     fn timestamp(&self) -> &DateTime<Utc>;
     fn trends(&self) -> &Vec<String>;
 
+    /// How the data is combined with data that already exists
+    fn load_mode(&self) -> LoadMode {
+        LoadMode::Overwrite
+    }
+
     async fn write(
         &self,
         writer: std::pin::Pin<&mut BinaryCopyInWriter>,
         values: &[(usize, DataType)],
         created_timestamp: &DateTime<chrono::Utc>,
     ) -> Result<usize, Error>;
-
-    async fn insert<C: GenericClient + std::marker::Sync + std::marker::Send>(
-        &self,
-        client: &mut C,
-        query: &str,
-        values: &[(usize, DataType)],
-        created_timestamp: &DateTime<chrono::Utc>,
-    ) -> Result<usize, Error>;
 }
 
 pub struct ValueRow {
@@ -610,6 +797,34 @@ pub struct ValueRow {
 }
 
 impl TrendStorePart {
+    pub fn descr(&self) -> TrendStorePartDescr<'_> {
+        TrendStorePartDescr {
+            name: &self.name,
+            trends: self.trends.iter().map(Trend::descr).collect(),
+            generated_trends: &self.generated_trends,
+        }
+    }
+
+    /// Name of the staging table created by `trend_directory.create_staging_table`
+    pub async fn staging_table_name<T: GenericClient + Send + Sync>(
+        &self,
+        client: &T,
+    ) -> Result<String, Error> {
+        let query = concat!(
+            "SELECT trend_directory.staging_table_name(tsp)::text ",
+            "FROM trend_directory.trend_store_part tsp WHERE name = $1"
+        );
+
+        let row = client.query_one(query, &[&self.name]).await.map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Could not determine staging table of '{}': {e}",
+                self.name
+            )))
+        })?;
+
+        Ok(row.get(0))
+    }
+
     pub async fn store_copy_from<'a, I>(
         &self,
         client: &mut Client,
@@ -617,6 +832,33 @@ impl TrendStorePart {
         trends: &[String],
         data_rows: I,
     ) -> Result<u64, Error>
+    where
+        I: IntoIterator<Item = &'a ValueRow>,
+    {
+        // We cannot use the database now() function for COPY FROM queries, so the 'created'
+        // timestamp for the trend data records is generated here.
+        let created_timestamp = Utc::now();
+
+        self.copy_rows(
+            client,
+            &self.name,
+            job_id,
+            trends,
+            data_rows,
+            &created_timestamp,
+        )
+        .await
+    }
+
+    async fn copy_rows<'a, I>(
+        &self,
+        client: &Client,
+        table_name: &str,
+        job_id: i64,
+        trends: &[String],
+        data_rows: I,
+        created_timestamp: &DateTime<Utc>,
+    ) -> Result<u64, Error>
     where
         I: IntoIterator<Item = &'a ValueRow>,
     {
@@ -653,7 +895,7 @@ impl TrendStorePart {
             return Ok(0);
         }
 
-        let query = copy_from_query(self, &matched_trends);
+        let query = copy_from_query(table_name, &matched_trends);
 
         let copy_in_sink = client.copy_in(&query).await.map_err(|e| {
             Error::Database(DatabaseError::from_msg(format!(
@@ -665,15 +907,11 @@ impl TrendStorePart {
         let binary_copy_writer = BinaryCopyInWriter::new(copy_in_sink, &value_types);
         pin_mut!(binary_copy_writer);
 
-        // We cannot use the database now() function for COPY FROM queries, so the 'created'
-        // timestamp for the trend data records is generated here.
-        let created_timestamp = Utc::now();
-
         for value_row in data_rows {
             let mut values: Vec<&(dyn ToSql + Sync)> = vec![
                 &value_row.entity_id,
                 &value_row.timestamp,
-                &created_timestamp,
+                created_timestamp,
                 &job_id,
             ];
 
@@ -711,11 +949,27 @@ impl TrendStorePart {
         Ok(row_count)
     }
 
-    pub async fn store_copy_from_package<'a, 'b, U>(
+    pub async fn store_copy_from_package<U>(
         &self,
         client: &mut Client,
         data_package: &U,
     ) -> Result<(), Error>
+    where
+        U: DataPackage,
+    {
+        let created_timestamp = Utc::now();
+
+        self.copy_package(client, &self.name, data_package, &created_timestamp)
+            .await
+    }
+
+    async fn copy_package<U>(
+        &self,
+        client: &Client,
+        table_name: &str,
+        data_package: &U,
+        created_timestamp: &DateTime<Utc>,
+    ) -> Result<(), Error>
     where
         U: DataPackage,
     {
@@ -752,7 +1006,7 @@ impl TrendStorePart {
             return Ok(());
         }
 
-        let query = copy_from_query(self, &matched_trends);
+        let query = copy_from_query(table_name, &matched_trends);
 
         let copy_in_sink = client.copy_in(&query).await.map_err(|e| {
             Error::Database(DatabaseError::from_msg(format!(
@@ -764,15 +1018,11 @@ impl TrendStorePart {
         let binary_copy_writer = BinaryCopyInWriter::new(copy_in_sink, &value_types);
         pin_mut!(binary_copy_writer);
 
-        // We cannot use the database now() function for COPY FROM queries, so the 'created'
-        // timestamp for the trend data records is generated here.
-        let created_timestamp = Utc::now();
-
         data_package
             .write(
                 binary_copy_writer.as_mut(),
                 &index_trend_map,
-                &created_timestamp,
+                created_timestamp,
             )
             .await?;
 
@@ -793,135 +1043,291 @@ impl TrendStorePart {
         Ok(())
     }
 
-    async fn store_insert<T: GenericClient>(
+    /// Load rows through the staging table, so that rows that already exist
+    /// are combined with the new data as specified by `load_mode` in a
+    /// single statement.
+    async fn store_staged(
         &self,
-        tx: &mut T,
+        client: &mut Client,
         job_id: i64,
         trends: &[String],
         data_package: &[ValueRow],
-    ) -> Result<(), Error> {
-        // List of indexes of matched trends to extract corresponding values
-        let mut matched_trend_indexes: Vec<Option<usize>> = Vec::new();
-        let mut matched_trends: Vec<&Trend> = Vec::new();
-
-        // Filter trends that match the trend store parts trends
-        for t in self.trends.iter() {
-            let index = trends.iter().position(|trend_name| trend_name == &t.name);
+        load_mode: LoadMode,
+    ) -> Result<u64, Error> {
+        let matched_trends: Vec<&Trend> = self
+            .trends
+            .iter()
+            .filter(|t| trends.contains(&t.name))
+            .collect();
 
-            if index.is_some() {
-                matched_trend_indexes.push(index);
-                matched_trends.push(t);
-            }
+        if matched_trends.is_empty() {
+            return Ok(0);
         }
 
+        let tx = client.transaction().await?;
+
         let created_timestamp = Utc::now();
 
-        let query = insert_query(self, &matched_trends);
+        let staging_table_name = self.staging_table_name(&tx).await?;
 
-        for value_row in data_package {
-            let mut values: Vec<&(dyn ToSql + Sync)> = vec![
-                &value_row.entity_id,
-                &value_row.timestamp,
-                &created_timestamp,
-                &job_id,
-            ];
+        self.copy_rows(
+            tx.client(),
+            &staging_table_name,
+            job_id,
+            trends,
+            last_unique_rows(data_package),
+            &created_timestamp,
+        )
+        .await?;
 
-            for (i, t) in matched_trend_indexes.iter().zip(matched_trends.iter()) {
-                match i {
-                    Some(index) => {
-                        match value_row.values.get(*index) {
-                            Some(v) => values.push(v),
-                            None => {
-                                // This should not be possible
-                                return Err(Error::Runtime(RuntimeError::from_msg(format!(
-                                    "Expected value not found at index {}",
-                                    index
-                                ))));
-                            }
-                        };
-                    }
-                    None => {
-                        match t.data_type {
-                            DataType::Integer => values.push(&(*INTEGER_NONE_VALUE)),
-                            DataType::Int8 => values.push(&(*INT8_NONE_VALUE)),
-                            DataType::Numeric => values.push(&(*NUMERIC_NONE_VALUE)),
-                            _ => values.push(&(*TEXT_NONE_VALUE)),
-                        };
-                    }
-                }
-            }
+        let row_count = self
+            .transfer_staged(
+                &tx,
+                &staging_table_name,
+                &matched_trends,
+                &created_timestamp,
+                load_mode,
+            )
+            .await?;
 
-            tx.execute(&query, &values).await?;
-        }
+        tx.commit().await?;
 
-        Ok(())
+        Ok(row_count)
     }
 
-    async fn store_insert_package<U, T>(
+    async fn store_staged_package<U>(
         &self,
-        client: &mut T,
+        client: &mut Client,
         data_package: &U,
-    ) -> Result<(), Error>
+        load_mode: LoadMode,
+    ) -> Result<u64, Error>
     where
         U: DataPackage,
-        T: GenericClient,
     {
-        // List of indexes of matched trends to extract corresponding values
-        let mut matched_trend_indexes: Vec<Option<usize>> = Vec::new();
-        let mut matched_trends: Vec<&Trend> = Vec::new();
-        let mut values: Vec<(usize, DataType)> = Vec::new();
-
-        // Filter trends that match the trend store parts trends
-        for t in self.trends.iter() {
-            let index = data_package
-                .trends()
-                .iter()
-                .position(|trend_name| trend_name == &t.name);
+        let matched_trends: Vec<&Trend> = self
+            .trends
+            .iter()
+            .filter(|t| data_package.trends().contains(&t.name))
+            .collect();
 
-            if let Some(i) = index {
-                matched_trend_indexes.push(index);
-                matched_trends.push(t);
-                values.push((i, t.data_type));
-            }
+        if matched_trends.is_empty() {
+            return Ok(0);
         }
 
+        let tx = client.transaction().await?;
+
         let created_timestamp = Utc::now();
 
-        let mut tx = client.transaction().await?;
+        let staging_table_name = self.staging_table_name(&tx).await?;
 
-        let query = insert_query(self, &matched_trends);
+        self.copy_package(
+            tx.client(),
+            &staging_table_name,
+            data_package,
+            &created_timestamp,
+        )
+        .await?;
 
-        data_package
-            .insert(&mut tx, &query, &values, &created_timestamp)
+        let row_count = self
+            .transfer_staged(
+                &tx,
+                &staging_table_name,
+                &matched_trends,
+                &created_timestamp,
+                load_mode,
+            )
             .await?;
 
-        tx.commit().await.map_err(|e| {
-            Error::Database(DatabaseError::from_msg(format!(
-                "Could commit data load using COPY command: {e}"
-            )))
-        })?;
+        tx.commit().await?;
 
-        Ok(())
+        Ok(row_count)
     }
 
-    pub fn diff(&self, other: &TrendStorePart) -> Vec<Box<dyn Change + Send>> {
-        let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
+    /// Move rows that were copied to the staging table with `created_timestamp`
+    /// to the trend table and return the number of inserted or updated rows.
+    async fn transfer_staged(
+        &self,
+        tx: &Transaction<'_>,
+        staging_table_name: &str,
+        trends: &[&Trend],
+        created_timestamp: &DateTime<Utc>,
+        load_mode: LoadMode,
+    ) -> Result<u64, Error> {
+        let query = transfer_staged_query(self, staging_table_name, trends, load_mode);
+
+        let row_count = tx
+            .execute(&query, &[created_timestamp])
+            .await
+            .map_err(|e| {
+                Error::Database(DatabaseError::from_msg(format!(
+                    "Could not transfer staged data to '{}': {e}",
+                    self.name
+                )))
+            })?;
 
-        let mut new_trends: Vec<Trend> = Vec::new();
-        let mut removed_trends: Vec<String> = Vec::new();
-        let mut alter_trend_data_types: Vec<ModifyTrendDataType> = Vec::new();
+        let query = format!(
+            "DELETE FROM trend.{} WHERE created = $1",
+            escape_identifier(staging_table_name)
+        );
 
-        for other_trend in &other.trends {
-            match self
+        tx.execute(&query, &[created_timestamp])
+            .await
+            .map_err(|e| {
+                Error::Database(DatabaseError::from_msg(format!(
+                    "Could not clean up staging table of '{}': {e}",
+                    self.name
+                )))
+            })?;
+
+        Ok(row_count)
+    }
+
+    /// Return the trends of this part with the specified names, in the order of
+    /// `trend_names`.
+    fn select_trends(&self, trend_names: &[String]) -> Result<Vec<Trend>, Error> {
+        trend_names
+            .iter()
+            .map(|trend_name| {
+                self.trends
+                    .iter()
+                    .find(|trend| &trend.name == trend_name)
+                    .cloned()
+                    .ok_or_else(|| {
+                        Error::Runtime(RuntimeError::from_msg(format!(
+                            "No trend '{}' in trend store part '{}'",
+                            trend_name, self.name
+                        )))
+                    })
+            })
+            .collect()
+    }
+
+    /// Read trend data from this part. The values of each returned row are in
+    /// the order of `trend_names` and typed according to the trend data types.
+    pub async fn fetch<T: GenericClient + Send + Sync>(
+        &self,
+        client: &T,
+        trend_names: &[String],
+        filter: &TrendDataFilter<'_>,
+    ) -> Result<Vec<ValueRow>, Error> {
+        let trends = self.select_trends(trend_names)?;
+
+        let query = fetch_query(
+            self,
+            &trends,
+            "timestamp >= $1 AND timestamp < $2",
+            filter.entity_ids.map(|_| "entity_id = ANY($3)".to_string()),
+        );
+
+        let entity_ids: Vec<i32> = filter
+            .entity_ids
+            .map(|ids| ids.to_vec())
+            .unwrap_or_default();
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&filter.start, &filter.end];
+
+        if filter.entity_ids.is_some() {
+            params.push(&entity_ids);
+        }
+
+        let rows = client.query(&query, &params).await.map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Could not read trend data from '{}': {e}",
+                self.name
+            )))
+        })?;
+
+        rows.iter()
+            .map(|row| value_row_from(row, &trends))
+            .collect()
+    }
+
+    /// Read trend data from this part as a stream of rows, so that large time
+    /// ranges can be processed without loading them in memory.
+    pub async fn fetch_stream(
+        &self,
+        client: &Client,
+        trend_names: &[String],
+        filter: &TrendDataFilter<'_>,
+    ) -> Result<impl Stream<Item = Result<ValueRow, Error>>, Error> {
+        let trends = self.select_trends(trend_names)?;
+
+        let query = fetch_query(
+            self,
+            &trends,
+            "timestamp >= $1 AND timestamp < $2",
+            filter.entity_ids.map(|_| "entity_id = ANY($3)".to_string()),
+        );
+
+        let entity_ids: Vec<i32> = filter
+            .entity_ids
+            .map(|ids| ids.to_vec())
+            .unwrap_or_default();
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&filter.start, &filter.end];
+
+        if filter.entity_ids.is_some() {
+            params.push(&entity_ids);
+        }
+
+        let row_stream = client.query_raw(&query, params).await.map_err(|e| {
+            Error::Database(DatabaseError::from_msg(format!(
+                "Could not read trend data from '{}': {e}",
+                self.name
+            )))
+        })?;
+
+        Ok(row_stream.map(move |row| match row {
+            Ok(row) => value_row_from(&row, &trends),
+            Err(e) => Err(Error::Database(DatabaseError::from_msg(format!(
+                "Could not read trend data row: {e}"
+            )))),
+        }))
+    }
+
+    pub fn diff(&self, other: &TrendStorePart) -> Vec<Box<dyn Change + Send>> {
+        let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
+
+        let mut new_trends: Vec<Trend> = Vec::new();
+        let mut removed_trends: Vec<String> = Vec::new();
+        let mut renamed_trends: Vec<String> = Vec::new();
+        let mut alter_trend_data_types: Vec<ModifyTrendDataType> = Vec::new();
+
+        for other_trend in &other.trends {
+            let my_trend = match self
                 .trends
                 .iter()
                 .find(|my_trend| my_trend.name == other_trend.name)
             {
+                Some(my_trend) => Some(my_trend),
+                None => {
+                    // A trend that no longer exists under its own name and is
+                    // listed as an old name of the new trend is renamed.
+                    let renamed_trend = self.trends.iter().find(|my_trend| {
+                        other_trend.is_renamed_from(my_trend)
+                            && !other.trends.iter().any(|t| t.name == my_trend.name)
+                    });
+
+                    if let Some(my_trend) = renamed_trend {
+                        renamed_trends.push(my_trend.name.clone());
+
+                        changes.push(Box::new(RenameTrend {
+                            trend_store_part_name: self.name.clone(),
+                            from_name: my_trend.name.clone(),
+                            to_name: other_trend.name.clone(),
+                        }));
+                    }
+
+                    renamed_trend
+                }
+            };
+
+            match my_trend {
                 Some(my_trend) => {
                     // The trend already exists, check for changes
                     if my_trend.data_type != other_trend.data_type {
                         alter_trend_data_types.push(ModifyTrendDataType {
-                            trend_name: my_trend.name.clone(),
+                            trend_name: other_trend.name.clone(),
                             from_type: my_trend.data_type,
                             to_type: other_trend.data_type,
                         });
@@ -930,7 +1336,7 @@ impl TrendStorePart {
                     if my_trend.extra_data != other_trend.extra_data {
                         changes.push(Box::new(ModifyTrendExtraData {
                             trend_store_part_name: self.name.clone(),
-                            trend_name: my_trend.name.clone(),
+                            trend_name: other_trend.name.clone(),
                             from_extra_data: my_trend.extra_data.clone(),
                             to_extra_data: other_trend.extra_data.clone(),
                         }));
@@ -959,7 +1365,9 @@ impl TrendStorePart {
                     // Ok, the trend still exists
                 }
                 None => {
-                    removed_trends.push(my_trend.name.clone());
+                    if !renamed_trends.contains(&my_trend.name) {
+                        removed_trends.push(my_trend.name.clone());
+                    }
                 }
             }
         }
@@ -978,6 +1386,65 @@ impl TrendStorePart {
             }));
         }
 
+        changes.extend(self.diff_generated_trends(other));
+
+        changes
+    }
+
+    fn diff_generated_trends(&self, other: &TrendStorePart) -> Vec<Box<dyn Change + Send>> {
+        let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
+
+        let removed_generated_trends: Vec<String> = self
+            .generated_trends
+            .iter()
+            .filter(|my_trend| {
+                !other
+                    .generated_trends
+                    .iter()
+                    .any(|other_trend| other_trend.name == my_trend.name)
+            })
+            .map(|my_trend| my_trend.name.clone())
+            .collect();
+
+        if !removed_generated_trends.is_empty() {
+            changes.push(Box::new(RemoveGeneratedTrends {
+                trend_store_part: self.clone(),
+                generated_trends: removed_generated_trends,
+            }));
+        }
+
+        let mut new_generated_trends: Vec<GeneratedTrend> = Vec::new();
+
+        for other_trend in &other.generated_trends {
+            match self
+                .generated_trends
+                .iter()
+                .find(|my_trend| my_trend.name == other_trend.name)
+            {
+                Some(my_trend) => {
+                    if my_trend.expression != other_trend.expression
+                        || my_trend.data_type != other_trend.data_type
+                    {
+                        changes.push(Box::new(ModifyGeneratedTrend {
+                            trend_store_part_name: self.name.clone(),
+                            from: my_trend.clone(),
+                            to: other_trend.clone(),
+                        }));
+                    }
+                }
+                None => {
+                    new_generated_trends.push(other_trend.clone());
+                }
+            }
+        }
+
+        if !new_generated_trends.is_empty() {
+            changes.push(Box::new(AddGeneratedTrends {
+                trend_store_part: self.clone(),
+                generated_trends: new_generated_trends,
+            }));
+        }
+
         changes
     }
 }
@@ -996,13 +1463,31 @@ pub struct TrendStore {
     pub granularity: Duration,
     #[serde(with = "humantime_serde")]
     pub partition_size: Duration,
+    #[serde(with = "humantime_serde", default = "default_retention_period")]
+    pub retention_period: Duration,
     pub parts: Vec<TrendStorePart>,
 }
 
+/// The retention period of trend stores for which none is specified, matching
+/// the default in the database.
+pub fn default_retention_period() -> Duration {
+    parse_interval("1 mon").unwrap()
+}
+
 impl TrendStore {
     pub fn diff(&self, other: &TrendStore) -> Vec<Box<dyn Change + Send>> {
         let mut changes: Vec<Box<dyn Change + Send>> = Vec::new();
 
+        if self.retention_period != other.retention_period {
+            changes.push(Box::new(ModifyTrendStoreRetentionPeriod {
+                trend_store: self.clone(),
+                from_retention_period: self.retention_period,
+                to_retention_period: other.retention_period,
+            }));
+        }
+
+        let mut renamed_parts: Vec<&str> = Vec::new();
+
         for other_part in &other.parts {
             match self
                 .parts
@@ -1013,14 +1498,51 @@ impl TrendStore {
                     changes.append(&mut my_part.diff(other_part));
                 }
                 None => {
-                    changes.push(Box::new(AddTrendStorePart {
-                        trend_store: self.clone(),
-                        trend_store_part: other_part.clone(),
-                    }));
+                    // A part that no longer exists under its own name and is
+                    // listed as an old name of the new part is renamed.
+                    let renamed_part = self.parts.iter().find(|my_part| {
+                        other_part.old_names.contains(&my_part.name)
+                            && !other.parts.iter().any(|p| p.name == my_part.name)
+                    });
+
+                    match renamed_part {
+                        Some(my_part) => {
+                            renamed_parts.push(&my_part.name);
+
+                            changes.push(Box::new(RenameTrendStorePart {
+                                trend_store_part: my_part.clone(),
+                                new_name: other_part.name.clone(),
+                            }));
+
+                            let renamed = TrendStorePart {
+                                name: other_part.name.clone(),
+                                ..my_part.clone()
+                            };
+
+                            changes.append(&mut renamed.diff(other_part));
+                        }
+                        None => {
+                            changes.push(Box::new(AddTrendStorePart {
+                                trend_store: self.clone(),
+                                trend_store_part: other_part.clone(),
+                            }));
+                        }
+                    }
                 }
             }
         }
 
+        for my_part in &self.parts {
+            let removed = !other.parts.iter().any(|p| p.name == my_part.name)
+                && !renamed_parts.contains(&my_part.name.as_str());
+
+            if removed {
+                changes.push(Box::new(RemoveTrendStorePart {
+                    trend_store_part: my_part.clone(),
+                }));
+            }
+        }
+
         changes
     }
 
@@ -1124,7 +1646,7 @@ pub async fn load_trend_store<T: GenericClient>(
     granularity: &Duration,
 ) -> Result<TrendStore, Error> {
     let query = concat!(
-        "SELECT trend_store.id, partition_size::text ",
+        "SELECT trend_store.id, partition_size::text, retention_period::text ",
         "FROM trend_directory.trend_store ",
         "JOIN directory.data_source ON data_source.id = trend_store.data_source_id ",
         "JOIN directory.entity_type ON entity_type.id = trend_store.entity_type_id ",
@@ -1142,15 +1664,65 @@ pub async fn load_trend_store<T: GenericClient>(
     let partition_size_str = result.get::<usize, String>(1);
     let partition_size = parse_interval(&partition_size_str).unwrap();
 
+    let retention_period_str = result.get::<usize, String>(2);
+    let retention_period = parse_interval(&retention_period_str).map_err(|e| {
+        RuntimeError::from_msg(format!(
+            "Error parsing retention period '{}': {}",
+            &retention_period_str, e
+        ))
+    })?;
+
     Ok(TrendStore {
         data_source: String::from(data_source),
         entity_type: String::from(entity_type),
         granularity: *granularity,
         partition_size,
+        retention_period,
         parts,
     })
 }
 
+/// Load the trend store that contains the trend store part with the specified
+/// name.
+pub async fn load_trend_store_for_part<T: GenericClient>(
+    conn: &T,
+    trend_store_part_name: &str,
+) -> Result<TrendStore, Error> {
+    let query = concat!(
+        "SELECT data_source.name, entity_type.name, granularity::text ",
+        "FROM trend_directory.trend_store_part ",
+        "JOIN trend_directory.trend_store ON trend_store.id = trend_store_part.trend_store_id ",
+        "JOIN directory.data_source ON data_source.id = trend_store.data_source_id ",
+        "JOIN directory.entity_type ON entity_type.id = trend_store.entity_type_id ",
+        "WHERE trend_store_part.name = $1"
+    );
+
+    let row = conn
+        .query_opt(query, &[&trend_store_part_name])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load trend store of part '{trend_store_part_name}': {e}"
+            ))
+        })?
+        .ok_or_else(|| {
+            RuntimeError::from_msg(format!("No trend store part '{trend_store_part_name}'"))
+        })?;
+
+    let data_source: &str = row.get(0);
+    let entity_type: &str = row.get(1);
+    let granularity_str: String = row.get(2);
+
+    let granularity = parse_interval(&granularity_str).map_err(|e| {
+        RuntimeError::from_msg(format!(
+            "Error parsing granularity '{}': {}",
+            &granularity_str, e
+        ))
+    })?;
+
+    load_trend_store(conn, data_source, entity_type, &granularity).await
+}
+
 async fn load_trend_store_parts<T: GenericClient>(
     conn: &T,
     trend_store_id: i32,
@@ -1197,13 +1769,37 @@ async fn load_trend_store_parts<T: GenericClient>(
                 entity_aggregation: String::from(trend_entity_aggregation),
                 time_aggregation: String::from(trend_time_aggregation),
                 extra_data: trend_extra_data,
+                old_names: Vec::new(),
             })
         }
 
+        let generated_trend_query = concat!(
+            "SELECT name, data_type, description, expression, extra_data ",
+            "FROM trend_directory.generated_table_trend ",
+            "WHERE trend_store_part_id = $1",
+        );
+
+        let generated_trend_result = conn
+            .query(generated_trend_query, &[&trend_store_part_id])
+            .await
+            .unwrap();
+
+        let generated_trends = generated_trend_result
+            .iter()
+            .map(|row| GeneratedTrend {
+                name: row.get(0),
+                data_type: row.get(1),
+                description: row.get(2),
+                expression: row.get(3),
+                extra_data: row.get(4),
+            })
+            .collect();
+
         parts.push(TrendStorePart {
             name: String::from(trend_store_part_name),
             trends,
-            generated_trends: Vec::new(),
+            generated_trends,
+            old_names: Vec::new(),
         });
     }
 
@@ -1214,7 +1810,7 @@ pub async fn load_trend_stores(conn: &mut Client) -> Result<Vec<TrendStore>, Err
     let mut trend_stores: Vec<TrendStore> = Vec::new();
 
     let query = concat!(
-        "SELECT trend_store.id, data_source.name, entity_type.name, granularity::text, partition_size::text ",
+        "SELECT trend_store.id, data_source.name, entity_type.name, granularity::text, partition_size::text, retention_period::text ",
         "FROM trend_directory.trend_store ",
         "JOIN directory.data_source ON data_source.id = trend_store.data_source_id ",
         "JOIN directory.entity_type ON entity_type.id = trend_store.entity_type_id"
@@ -1228,6 +1824,7 @@ pub async fn load_trend_stores(conn: &mut Client) -> Result<Vec<TrendStore>, Err
         let entity_type: &str = row.get(2);
         let granularity_str: String = row.get(3);
         let partition_size_str: String = row.get(4);
+        let retention_period_str: String = row.get(5);
         let parts = load_trend_store_parts(conn, trend_store_id).await;
 
         // Hack for humankind parsing compatibility with PostgreSQL interval
@@ -1246,11 +1843,19 @@ pub async fn load_trend_stores(conn: &mut Client) -> Result<Vec<TrendStore>, Err
             ))
         })?;
 
+        let retention_period = parse_interval(&retention_period_str).map_err(|e| {
+            RuntimeError::from_msg(format!(
+                "Error parsing retention period '{}': {}",
+                &retention_period_str, e
+            ))
+        })?;
+
         trend_stores.push(TrendStore {
             data_source: String::from(data_source),
             entity_type: String::from(entity_type),
             granularity,
             partition_size,
+            retention_period,
             parts,
         });
     }
@@ -1476,21 +2081,159 @@ async fn create_partition_for_trend_store_part(
     Ok(partition_name)
 }
 
+/// Statistics of the values of a trend
+#[derive(Debug, Clone)]
 pub struct TrendStat {
     pub name: String,
     pub max_value: Option<String>,
     pub min_value: Option<String>,
+    pub null_count: i64,
+    /// Number of distinct non-NULL values
+    pub distinct_count: i64,
+    /// Mean value, only for numeric data types
+    pub mean: Option<f64>,
+    /// First timestamp with a non-NULL value
+    pub first_timestamp: Option<DateTime<Utc>>,
+    /// Last timestamp with a non-NULL value
+    pub last_timestamp: Option<DateTime<Utc>>,
 }
 
 pub struct AnalyzeResult {
+    /// Number of analyzed rows
+    pub row_count: i64,
     pub trend_stats: Vec<TrendStat>,
 }
 
+/// Limits of the data analyzed by `analyze_trend_store_part_with`
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    /// Trends to analyze, all trends when not specified
+    pub trend_names: Option<Vec<String>>,
+    /// Start of the time range to analyze (inclusive)
+    pub start: Option<DateTime<Utc>>,
+    /// End of the time range to analyze (exclusive)
+    pub end: Option<DateTime<Utc>>,
+    /// Percentage of the table blocks to sample
+    pub sample_percentage: Option<f64>,
+}
+
+/// Aggregate expressions for the minimum and maximum value of a trend column
+fn min_max_expressions(trend_name: &str, data_type: &str) -> (String, String) {
+    let column = escape_identifier(trend_name);
+
+    match data_type {
+        // There are no min and max aggregates for booleans
+        "boolean" => (
+            format!("bool_and({column})::text"),
+            format!("bool_or({column})::text"),
+        ),
+        _ => (
+            format!("min({column})::text"),
+            format!("max({column})::text"),
+        ),
+    }
+}
+
+const NUMERIC_DATA_TYPES: [&str; 6] = [
+    "smallint",
+    "integer",
+    "bigint",
+    "real",
+    "double precision",
+    "numeric",
+];
+
+/// Number of aggregate expressions per trend in the analyze query
+const TREND_STAT_COLUMNS: usize = 7;
+
+fn trend_stat_expressions(trend_name: &str, data_type: &str) -> Vec<String> {
+    let column = escape_identifier(trend_name);
+    let (min_expression, max_expression) = min_max_expressions(trend_name, data_type);
+
+    let mean_expression = match NUMERIC_DATA_TYPES.contains(&data_type) {
+        true => format!("avg({column})::double precision"),
+        false => "NULL::double precision".to_string(),
+    };
+
+    vec![
+        min_expression,
+        max_expression,
+        format!("count(*) FILTER (WHERE {column} IS NULL)"),
+        format!("count(DISTINCT {column})"),
+        mean_expression,
+        format!("min(timestamp) FILTER (WHERE {column} IS NOT NULL)"),
+        format!("max(timestamp) FILTER (WHERE {column} IS NOT NULL)"),
+    ]
+}
+
+fn analyze_query(name: &str, trends: &[(String, String)], options: &AnalyzeOptions) -> String {
+    let mut expressions: Vec<String> = vec!["count(*)".to_string()];
+
+    expressions.extend(
+        trends
+            .iter()
+            .flat_map(|(trend_name, data_type)| trend_stat_expressions(trend_name, data_type)),
+    );
+
+    let mut query = format!(
+        "SELECT {} FROM trend.{} p",
+        expressions.join(", "),
+        escape_identifier(name)
+    );
+
+    if let Some(percentage) = options.sample_percentage {
+        query.push_str(&format!(" TABLESAMPLE SYSTEM ({percentage})"));
+    }
+
+    let mut conditions: Vec<String> = Vec::new();
+
+    // The time range is passed as query parameters, in the order of
+    // `analyze_params`
+    if options.start.is_some() {
+        conditions.push(format!("timestamp >= ${}", conditions.len() + 1));
+    }
+
+    if options.end.is_some() {
+        conditions.push(format!("timestamp < ${}", conditions.len() + 1));
+    }
+
+    if !conditions.is_empty() {
+        query.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+    }
+
+    query
+}
+
+/// Parameters of the query built by `analyze_query`
+fn analyze_params(options: &AnalyzeOptions) -> Vec<&(dyn ToSql + Sync)> {
+    [&options.start, &options.end]
+        .into_iter()
+        .flatten()
+        .map(|timestamp| timestamp as &(dyn ToSql + Sync))
+        .collect()
+}
+
 pub async fn analyze_trend_store_part(
     client: &mut Client,
     name: &str,
 ) -> Result<AnalyzeResult, Error> {
-    let query = "SELECT tt.name FROM trend_directory.trend_store_part tsp JOIN trend_directory.table_trend tt ON tt.trend_store_part_id = tsp.id WHERE tsp.name = $1";
+    analyze_trend_store_part_with(client, name, &AnalyzeOptions::default()).await
+}
+
+/// Names and data types of the trends of a trend store part, limited to
+/// `trend_names` when specified
+async fn load_trend_data_types(
+    client: &mut Client,
+    name: &str,
+    trend_names: Option<&[String]>,
+) -> Result<Vec<(String, String)>, Error> {
+    let query = concat!(
+        "SELECT tt.name::text, tt.data_type ",
+        "FROM trend_directory.trend_store_part tsp ",
+        "JOIN trend_directory.table_trend tt ON tt.trend_store_part_id = tsp.id ",
+        "WHERE tsp.name = $1 ",
+        "ORDER BY tt.id"
+    );
 
     let result = client.query(query, &[&name]).await.map_err(|e| {
         DatabaseError::from_msg(format!(
@@ -1498,43 +2241,621 @@ pub async fn analyze_trend_store_part(
         ))
     })?;
 
-    let trend_names: Vec<String> = result.iter().map(|row| row.get(0)).collect();
+    let trends = result
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .filter(|(trend_name, _): &(String, String)| {
+            trend_names.is_none_or(|names| names.contains(trend_name))
+        })
+        .collect();
+
+    Ok(trends)
+}
+
+/// Determine statistics of the values of trends in a trend store part, limited
+/// to the trends, time range and sample specified in `options`.
+pub async fn analyze_trend_store_part_with(
+    client: &mut Client,
+    name: &str,
+    options: &AnalyzeOptions,
+) -> Result<AnalyzeResult, Error> {
+    let trends = load_trend_data_types(client, name, options.trend_names.as_deref()).await?;
+
+    let query = analyze_query(name, &trends, options);
+
+    let row = client
+        .query_one(&query, &analyze_params(options))
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Could not analyze trend store part '{name}': {e}"))
+        })?;
+
+    let row_count: i64 = row.get(0);
+
+    let trend_stats = trends
+        .iter()
+        .enumerate()
+        .map(|(i, (trend_name, _))| {
+            let offset = 1 + i * TREND_STAT_COLUMNS;
+
+            TrendStat {
+                name: trend_name.clone(),
+                min_value: row.get(offset),
+                max_value: row.get(offset + 1),
+                null_count: row.get(offset + 2),
+                distinct_count: row.get(offset + 3),
+                mean: row.get(offset + 4),
+                first_timestamp: row.get(offset + 5),
+                last_timestamp: row.get(offset + 6),
+            }
+        })
+        .collect();
+
+    let result = AnalyzeResult {
+        row_count,
+        trend_stats,
+    };
+
+    Ok(result)
+}
+
+/// Minimum and maximum value of a trend
+#[derive(Debug, Clone)]
+pub struct TrendValueRange {
+    pub name: String,
+    pub min_value: Option<String>,
+    pub max_value: Option<String>,
+}
 
-    let max_expressions: Vec<String> = trend_names
+fn value_range_query(name: &str, trends: &[(String, String)]) -> String {
+    let expressions: Vec<String> = trends
         .iter()
-        .map(|name| format!("max(\"{name}\")::text"))
+        .flat_map(|(trend_name, data_type)| {
+            let (min_expression, max_expression) = min_max_expressions(trend_name, data_type);
+
+            [min_expression, max_expression]
+        })
         .collect();
 
-    let max_expressions_part = max_expressions.join(", ");
+    format!(
+        "SELECT {} FROM trend.{}",
+        expressions.join(", "),
+        escape_identifier(name)
+    )
+}
 
-    let query = format!(
-        "SELECT {} FROM trend.\"{}\" p ",
-        &max_expressions_part, name
-    );
+/// Determine the minimum and maximum values of trends in a trend store part,
+/// without the other, more expensive, statistics of an analysis.
+pub async fn trend_value_ranges(
+    client: &mut Client,
+    name: &str,
+    trend_names: &[String],
+) -> Result<Vec<TrendValueRange>, Error> {
+    let trends = load_trend_data_types(client, name, Some(trend_names)).await?;
+
+    let query = value_range_query(name, &trends);
 
     let row = client.query_one(&query, &[]).await.map_err(|e| {
-        DatabaseError::from_msg(format!("Could not analyze trend store part '{name}': {e}"))
+        DatabaseError::from_msg(format!(
+            "Could not determine value ranges of trend store part '{name}': {e}"
+        ))
     })?;
 
-    let trend_stats = trend_names
+    let ranges = trends
         .iter()
         .enumerate()
-        .map(|(i, name)| TrendStat {
-            name: name.clone(),
-            max_value: row.get(i),
-            min_value: None,
+        .map(|(i, (trend_name, _))| TrendValueRange {
+            name: trend_name.clone(),
+            min_value: row.get(i * 2),
+            max_value: row.get(i * 2 + 1),
         })
         .collect();
 
-    let result = AnalyzeResult { trend_stats };
+    Ok(ranges)
+}
 
-    Ok(result)
+/// Analysis results of a trend as stored in `trend_directory.table_trend_stats`
+#[derive(Debug, Clone)]
+pub struct StoredTrendStat {
+    pub analyzed: DateTime<Utc>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub sample_percentage: Option<f64>,
+    pub row_count: i64,
+    pub stat: TrendStat,
+}
+
+/// Store the result of an analysis, replacing earlier results for the same
+/// trends
+pub async fn store_analyze_result<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    name: &str,
+    options: &AnalyzeOptions,
+    result: &AnalyzeResult,
+) -> Result<(), Error> {
+    let query = concat!(
+        "INSERT INTO trend_directory.table_trend_stats(",
+        "table_trend_id, analyzed, start, \"end\", sample_percentage, row_count, ",
+        "min_value, max_value, null_count, distinct_count, mean, first_timestamp, last_timestamp",
+        ") ",
+        "SELECT tt.id, now(), $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13 ",
+        "FROM trend_directory.table_trend tt ",
+        "JOIN trend_directory.trend_store_part tsp ON tsp.id = tt.trend_store_part_id ",
+        "WHERE tsp.name = $1 AND tt.name = $2 ",
+        "ON CONFLICT (table_trend_id) DO UPDATE SET ",
+        "analyzed = excluded.analyzed, start = excluded.start, \"end\" = excluded.\"end\", ",
+        "sample_percentage = excluded.sample_percentage, row_count = excluded.row_count, ",
+        "min_value = excluded.min_value, max_value = excluded.max_value, ",
+        "null_count = excluded.null_count, distinct_count = excluded.distinct_count, ",
+        "mean = excluded.mean, first_timestamp = excluded.first_timestamp, ",
+        "last_timestamp = excluded.last_timestamp"
+    );
+
+    for stat in &result.trend_stats {
+        client
+            .execute(
+                query,
+                &[
+                    &name,
+                    &stat.name,
+                    &options.start,
+                    &options.end,
+                    &options.sample_percentage,
+                    &result.row_count,
+                    &stat.min_value,
+                    &stat.max_value,
+                    &stat.null_count,
+                    &stat.distinct_count,
+                    &stat.mean,
+                    &stat.first_timestamp,
+                    &stat.last_timestamp,
+                ],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Could not store statistics of trend '{}': {e}",
+                    &stat.name
+                ))
+            })?;
+    }
+
+    Ok(())
+}
+
+/// Load the stored analysis results of the trends in a trend store part
+pub async fn load_trend_stats<T: GenericClient + Send + Sync>(
+    client: &T,
+    trend_store_part_id: i32,
+) -> Result<Vec<StoredTrendStat>, Error> {
+    let query = concat!(
+        "SELECT tt.name::text, s.analyzed, s.start, s.\"end\", s.sample_percentage, s.row_count, ",
+        "s.min_value, s.max_value, s.null_count, s.distinct_count, s.mean, s.first_timestamp, s.last_timestamp ",
+        "FROM trend_directory.table_trend_stats s ",
+        "JOIN trend_directory.table_trend tt ON tt.id = s.table_trend_id ",
+        "WHERE tt.trend_store_part_id = $1 ",
+        "ORDER BY tt.id"
+    );
+
+    let rows = client
+        .query(query, &[&trend_store_part_id])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load trend statistics: {e}")))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| StoredTrendStat {
+            analyzed: row.get(1),
+            start: row.get(2),
+            end: row.get(3),
+            sample_percentage: row.get(4),
+            row_count: row.get(5),
+            stat: TrendStat {
+                name: row.get(0),
+                min_value: row.get(6),
+                max_value: row.get(7),
+                null_count: row.get(8),
+                distinct_count: row.get(9),
+                mean: row.get(10),
+                first_timestamp: row.get(11),
+                last_timestamp: row.get(12),
+            },
+        })
+        .collect())
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::TimeZone;
+
     use super::*;
 
+    #[test]
+    fn transfer_staged_query_per_load_mode() {
+        let trend = |name: &str| Trend {
+            name: name.to_string(),
+            data_type: DataType::Numeric,
+            description: String::new(),
+            time_aggregation: "SUM".to_string(),
+            entity_aggregation: "SUM".to_string(),
+            extra_data: json!("{}"),
+            old_names: vec![],
+        };
+
+        let trend_store_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![trend("power"), trend("temp")],
+            generated_trends: vec![],
+            old_names: vec![],
+        };
+
+        let trends: Vec<&Trend> = trend_store_part.trends.iter().collect();
+
+        let query = |load_mode: LoadMode| {
+            transfer_staged_query(
+                &trend_store_part,
+                "hub_node_main_15m_staging",
+                &trends,
+                load_mode,
+            )
+        };
+
+        let insert_part = concat!(
+            "INSERT INTO trend.\"hub_node_main_15m\" AS t(entity_id, timestamp, created, job_id, \"power\", \"temp\") ",
+            "SELECT DISTINCT ON (entity_id, timestamp) entity_id, timestamp, created, job_id, \"power\", \"temp\" ",
+            "FROM trend.\"hub_node_main_15m_staging\" WHERE created = $1 ",
+            "ORDER BY entity_id, timestamp, ctid DESC"
+        );
+
+        assert_eq!(query(LoadMode::InsertOnly), insert_part);
+        assert_eq!(
+            query(LoadMode::Overwrite),
+            format!(
+                "{insert_part} ON CONFLICT (entity_id, timestamp) DO UPDATE SET {}",
+                "\"power\" = excluded.\"power\", \"temp\" = excluded.\"temp\""
+            )
+        );
+        assert_eq!(
+            query(LoadMode::FillNullsOnly),
+            format!(
+                "{insert_part} ON CONFLICT (entity_id, timestamp) DO UPDATE SET {} WHERE {}",
+                concat!(
+                    "\"power\" = coalesce(t.\"power\", excluded.\"power\"), ",
+                    "\"temp\" = coalesce(t.\"temp\", excluded.\"temp\")"
+                ),
+                "t.\"power\" IS NULL OR t.\"temp\" IS NULL"
+            )
+        );
+        assert_eq!(
+            query(LoadMode::SkipExisting),
+            format!("{insert_part} ON CONFLICT (entity_id, timestamp) DO NOTHING")
+        );
+    }
+
+    #[test]
+    fn keep_last_of_duplicate_rows() {
+        let timestamp = |minute: u32| Utc.with_ymd_and_hms(2024, 7, 1, 10, minute, 0).unwrap();
+
+        let row = |entity_id: i32, minute: u32, value: i32| ValueRow {
+            entity_id,
+            timestamp: timestamp(minute),
+            values: vec![MeasValue::Integer(Some(value))],
+        };
+
+        let rows = vec![row(1, 0, 1), row(2, 0, 2), row(1, 0, 3), row(1, 15, 4)];
+
+        let unique_rows: Vec<(i32, DateTime<Utc>, &MeasValue)> = last_unique_rows(&rows)
+            .iter()
+            .map(|row| (row.entity_id, row.timestamp, &row.values[0]))
+            .collect();
+
+        assert_eq!(
+            unique_rows,
+            vec![
+                (2, timestamp(0), &MeasValue::Integer(Some(2))),
+                (1, timestamp(0), &MeasValue::Integer(Some(3))),
+                (1, timestamp(15), &MeasValue::Integer(Some(4))),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_load_mode() {
+        assert_eq!(
+            "fill-nulls-only".parse::<LoadMode>().unwrap(),
+            LoadMode::FillNullsOnly
+        );
+        assert_eq!(
+            serde_json::from_str::<LoadMode>("\"skip-existing\"").unwrap(),
+            LoadMode::SkipExisting
+        );
+        assert!("upsert".parse::<LoadMode>().is_err());
+    }
+
+    #[test]
+    fn fetch_query_with_entity_filter() {
+        let trend_store_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![],
+            generated_trends: vec![],
+            old_names: vec![],
+        };
+
+        let trends = vec![
+            Trend {
+                name: "outside_temp".to_string(),
+                data_type: DataType::Numeric,
+                description: String::new(),
+                time_aggregation: "SUM".to_string(),
+                entity_aggregation: "SUM".to_string(),
+                extra_data: json!("{}"),
+                old_names: vec![],
+            },
+            Trend {
+                name: "online".to_string(),
+                data_type: DataType::Boolean,
+                description: String::new(),
+                time_aggregation: "SUM".to_string(),
+                entity_aggregation: "SUM".to_string(),
+                extra_data: json!("{}"),
+                old_names: vec![],
+            },
+        ];
+
+        let query = fetch_query(
+            &trend_store_part,
+            &trends,
+            "timestamp >= $1 AND timestamp < $2",
+            Some("entity_id = ANY($3)".to_string()),
+        );
+
+        assert_eq!(
+            query,
+            concat!(
+                "SELECT entity_id, timestamp, \"outside_temp\", \"online\" ",
+                "FROM trend.\"hub_node_main_15m\" ",
+                "WHERE timestamp >= $1 AND timestamp < $2 AND entity_id = ANY($3) ",
+                "ORDER BY timestamp, entity_id"
+            )
+        );
+    }
+
+    #[test]
+    fn analyze_query_with_time_range_and_sample() {
+        let trends = vec![
+            ("power".to_string(), "integer".to_string()),
+            ("online".to_string(), "boolean".to_string()),
+        ];
+
+        let options = AnalyzeOptions {
+            trend_names: None,
+            start: Some(Utc.with_ymd_and_hms(2024, 7, 1, 0, 0, 0).unwrap()),
+            end: None,
+            sample_percentage: Some(2.5),
+        };
+
+        assert_eq!(
+            analyze_query("hub_node_main_15m", &trends, &options),
+            concat!(
+                "SELECT count(*), ",
+                "min(\"power\")::text, max(\"power\")::text, ",
+                "count(*) FILTER (WHERE \"power\" IS NULL), ",
+                "count(DISTINCT \"power\"), ",
+                "avg(\"power\")::double precision, ",
+                "min(timestamp) FILTER (WHERE \"power\" IS NOT NULL), ",
+                "max(timestamp) FILTER (WHERE \"power\" IS NOT NULL), ",
+                "bool_and(\"online\")::text, bool_or(\"online\")::text, ",
+                "count(*) FILTER (WHERE \"online\" IS NULL), ",
+                "count(DISTINCT \"online\"), ",
+                "NULL::double precision, ",
+                "min(timestamp) FILTER (WHERE \"online\" IS NOT NULL), ",
+                "max(timestamp) FILTER (WHERE \"online\" IS NOT NULL) ",
+                "FROM trend.\"hub_node_main_15m\" p TABLESAMPLE SYSTEM (2.5) ",
+                "WHERE timestamp >= $1"
+            )
+        );
+    }
+
+    #[test]
+    fn value_range_query_only_min_max() {
+        let trends = vec![
+            ("power".to_string(), "integer".to_string()),
+            ("online".to_string(), "boolean".to_string()),
+        ];
+
+        assert_eq!(
+            value_range_query("hub_node_main_15m", &trends),
+            concat!(
+                "SELECT min(\"power\")::text, max(\"power\")::text, ",
+                "bool_and(\"online\")::text, bool_or(\"online\")::text ",
+                "FROM trend.\"hub_node_main_15m\""
+            )
+        );
+    }
+
+    fn generated_trend(name: &str, data_type: &str, expression: &str) -> GeneratedTrend {
+        GeneratedTrend {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            description: String::new(),
+            expression: expression.to_string(),
+            extra_data: json!("{}"),
+        }
+    }
+
+    #[test]
+    fn diff_generated_trends() {
+        let my_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![],
+            generated_trends: vec![
+                generated_trend("power_kwh", "numeric", "power_kw * 0.25"),
+                generated_trend("power_mwh", "numeric", "power_kw / 4000"),
+                generated_trend("obsolete", "integer", "1"),
+            ],
+            old_names: vec![],
+        };
+
+        let other_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![],
+            generated_trends: vec![
+                generated_trend("power_kwh", "numeric", "power_kw * 0.25"),
+                generated_trend("power_mwh", "double precision", "power_kw / 4000"),
+                generated_trend("power_wh", "numeric", "power_kw * 250"),
+            ],
+            old_names: vec![],
+        };
+
+        let changes: Vec<String> = my_part
+            .diff(&other_part)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                "RemoveGeneratedTrends(TrendStorePart(hub_node_main_15m), 1)",
+                "ModifyGeneratedTrend(hub_node_main_15m.power_mwh, numeric power_kw / 4000->double precision power_kw / 4000)",
+                "AddGeneratedTrends(TrendStorePart(hub_node_main_15m), 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_trend_store_settings() {
+        let my_trend_store = TrendStore {
+            data_source: "hub".to_string(),
+            entity_type: "node".to_string(),
+            granularity: Duration::from_secs(900),
+            partition_size: Duration::from_secs(86400),
+            retention_period: default_retention_period(),
+            parts: vec![],
+        };
+
+        let other_trend_store = TrendStore {
+            partition_size: Duration::from_secs(4 * 86400),
+            retention_period: parse_interval("3 mons").unwrap(),
+            ..my_trend_store.clone()
+        };
+
+        let changes: Vec<String> = my_trend_store
+            .diff(&other_trend_store)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec!["ModifyTrendStoreRetentionPeriod(TrendStore(hub, node, 15m), 1month->3months)"]
+        );
+    }
+
+    fn trend(name: &str, data_type: DataType, old_names: &[&str]) -> Trend {
+        Trend {
+            name: name.to_string(),
+            data_type,
+            description: String::new(),
+            time_aggregation: "SUM".to_string(),
+            entity_aggregation: "SUM".to_string(),
+            extra_data: json!("{}"),
+            old_names: old_names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn diff_renamed_trends() {
+        let my_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![
+                trend("power", DataType::Integer, &[]),
+                trend("temp", DataType::Numeric, &[]),
+            ],
+            generated_trends: vec![],
+            old_names: vec![],
+        };
+
+        let other_part = TrendStorePart {
+            name: "hub_node_main_15m".to_string(),
+            trends: vec![
+                trend("power_kw", DataType::Int8, &["power"]),
+                trend("temp", DataType::Numeric, &["power"]),
+            ],
+            generated_trends: vec![],
+            old_names: vec![],
+        };
+
+        let changes: Vec<String> = my_part
+            .diff(&other_part)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                "RenameTrend(hub_node_main_15m, power->power_kw)",
+                "ModifyTrendDataTypes(TrendStorePart(hub_node_main_15m), 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn diff_renamed_and_removed_parts() {
+        let part = |name: &str, old_names: &[&str]| TrendStorePart {
+            name: name.to_string(),
+            trends: vec![trend("power", DataType::Integer, &[])],
+            generated_trends: vec![],
+            old_names: old_names.iter().map(|name| name.to_string()).collect(),
+        };
+
+        let my_trend_store = TrendStore {
+            data_source: "hub".to_string(),
+            entity_type: "node".to_string(),
+            granularity: Duration::from_secs(900),
+            partition_size: Duration::from_secs(86400),
+            retention_period: default_retention_period(),
+            parts: vec![
+                part("hub_node_main_15m", &[]),
+                part("hub_node_old_15m", &[]),
+            ],
+        };
+
+        let other_trend_store = TrendStore {
+            parts: vec![part("hub_node_power_15m", &["hub_node_main_15m"])],
+            ..my_trend_store.clone()
+        };
+
+        let changes: Vec<String> = my_trend_store
+            .diff(&other_trend_store)
+            .iter()
+            .map(|change| change.to_string())
+            .collect();
+
+        assert_eq!(
+            changes,
+            vec![
+                "RenameTrendStorePart(TrendStorePart(hub_node_main_15m)->hub_node_power_15m)",
+                "RemoveTrendStorePart(TrendStorePart(hub_node_old_15m))",
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_trend_store_without_retention_period() {
+        let trend_store: TrendStore = serde_yaml::from_str(concat!(
+            "data_source: hub\n",
+            "entity_type: node\n",
+            "granularity: 15m\n",
+            "partition_size: 1d\n",
+            "parts: []\n",
+        ))
+        .unwrap();
+
+        assert_eq!(trend_store.retention_period, default_retention_period());
+    }
+
     #[test]
     fn deserialize_trend_with_defaults() {
         let trend_def = concat!(
@@ -1559,6 +2880,7 @@ mod tests {
             entity_aggregation: "SUM".to_string(),
             time_aggregation: "SUM".to_string(),
             extra_data: json!("{}"),
+            old_names: vec![],
         };
 
         let trend_def: String = serde_json::to_string(&trend).unwrap();
@@ -1588,4 +2910,45 @@ mod tests {
             _ => assert!(false),
         }
     }
+
+    #[test]
+    fn parse_raw_text_and_array_values() {
+        let trend = |data_type: DataType| Trend {
+            name: "state".to_string(),
+            data_type,
+            description: String::new(),
+            time_aggregation: "SUM".to_string(),
+            entity_aggregation: "SUM".to_string(),
+            extra_data: json!("{}"),
+            old_names: vec![],
+        };
+
+        let parser_config = ValueParserConfig {
+            separator: "|".to_string(),
+            prefix: String::new(),
+            postfix: String::new(),
+        };
+
+        assert_eq!(
+            trend(DataType::Text)
+                .meas_value_from_str("active", "", &parser_config)
+                .unwrap(),
+            MeasValue::Text(Some("active".to_string()))
+        );
+        assert_eq!(
+            trend(DataType::Text)
+                .meas_value_from_str("", "", &parser_config)
+                .unwrap(),
+            MeasValue::Text(None)
+        );
+        assert_eq!(
+            trend(DataType::NumericArray)
+                .meas_value_from_str("1|2", "", &parser_config)
+                .unwrap(),
+            MeasValue::NumericArray(Some(vec![Decimal::from(1), Decimal::from(2)]))
+        );
+        assert!(trend(DataType::Boolean)
+            .meas_value_from_str("maybe", "", &parser_config)
+            .is_err());
+    }
 }
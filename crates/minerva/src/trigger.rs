@@ -6,17 +6,22 @@ use std::time::Duration;
 use postgres_types::ToSql;
 use serde::{Deserialize, Serialize};
 
-use chrono::{DateTime, TimeZone, Timelike};
+use chrono::{DateTime, TimeZone, Utc};
 use postgres_protocol::escape::{escape_identifier, escape_literal};
 use tokio_postgres::{Client, GenericClient, Row, Transaction};
 
 use async_trait::async_trait;
 
+use crate::granularity::{configured_timezone, Granularity};
 use crate::interval::parse_interval;
 
 use super::change::{Change, ChangeResult};
+use super::entity::load_entity_names;
 use super::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
 use super::notification_store::notification_store_exists;
+use super::trigger_exception::{
+    add_trigger_exceptions, load_trigger_exceptions, trigger_entity_type, TriggerExceptions,
+};
 
 type PostgresName = String;
 
@@ -45,6 +50,59 @@ pub struct MappingFunction {
     pub source: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeduplicationMode {
+    /// Suppress all repeats within the window
+    #[default]
+    Window,
+    /// Suppress repeats within the window unless their weight is higher
+    WeightIncrease,
+}
+
+impl FromStr for DeduplicationMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "window" => Ok(DeduplicationMode::Window),
+            "weight_increase" => Ok(DeduplicationMode::WeightIncrease),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported deduplication mode '{s}'"
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for DeduplicationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeduplicationMode::Window => write!(f, "window"),
+            DeduplicationMode::WeightIncrease => write!(f, "weight_increase"),
+        }
+    }
+}
+
+/// Suppression of repeated notifications for the same entity while the
+/// previous notification is not cleared
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Deduplication {
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+    #[serde(default)]
+    pub mode: DeduplicationMode,
+}
+
+/// Detection of entities that alternate between firing and not firing
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FlapDetection {
+    /// Period over which changes are counted
+    #[serde(with = "humantime_serde")]
+    pub window: Duration,
+    /// Number of changes within the window from which an entity is flapping
+    pub threshold: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Trigger {
     pub name: PostgresName,
@@ -64,6 +122,13 @@ pub struct Trigger {
     #[serde(with = "humantime_serde")]
     pub granularity: Duration,
     pub enabled: bool,
+    /// Entity specific threshold and weight overrides
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exceptions: Option<TriggerExceptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplication: Option<Deduplication>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flapping: Option<FlapDetection>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -85,6 +150,12 @@ pub struct FileTrigger {
     #[serde(with = "humantime_serde")]
     pub granularity: Duration,
     pub enabled: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exceptions: Option<TriggerExceptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplication: Option<Deduplication>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flapping: Option<FlapDetection>,
 }
 
 impl fmt::Display for Trigger {
@@ -94,7 +165,7 @@ impl fmt::Display for Trigger {
 }
 
 impl FileTrigger {
-    fn full_trigger(&self) -> Trigger {
+    pub(crate) fn full_trigger(&self) -> Trigger {
         Trigger {
             name: self.name.clone(),
             kpi_data: self.kpi_data.clone(),
@@ -112,6 +183,9 @@ impl FileTrigger {
             description: self.description.clone(),
             granularity: self.granularity,
             enabled: self.enabled.unwrap_or(true),
+            exceptions: self.exceptions.clone(),
+            deduplication: self.deduplication.clone(),
+            flapping: self.flapping.clone(),
         }
     }
 }
@@ -183,7 +257,7 @@ impl fmt::Display for AddTrigger {
     }
 }
 
-const MAX_TRIGGER_NAME_LENGTH: usize = 45;
+pub const MAX_TRIGGER_NAME_LENGTH: usize = 45;
 
 #[async_trait]
 impl Change for AddTrigger {
@@ -220,8 +294,14 @@ impl Change for AddTrigger {
 
         set_description(&self.trigger, &mut transaction).await?;
 
+        set_notification_control(&self.trigger, &mut transaction).await?;
+
         set_enabled(&mut transaction, &self.trigger.name, self.trigger.enabled).await?;
 
+        if let Some(exceptions) = &self.trigger.exceptions {
+            add_trigger_exceptions(&mut transaction, &self.trigger.name, exceptions, false).await?;
+        }
+
         let mut check_result: String = "No check has run".to_string();
 
         if self.verify {
@@ -627,6 +707,63 @@ async fn set_description<T: GenericClient + Sync + Send>(
     ))
 }
 
+async fn set_notification_control<T: GenericClient + Sync + Send>(
+    trigger: &Trigger,
+    client: &mut T,
+) -> ChangeResult {
+    let query = concat!(
+        "UPDATE trigger.rule SET ",
+        "deduplication_window = $1::text::interval, ",
+        "deduplication_mode = $2::text::trigger.deduplication_mode_enum, ",
+        "flapping_window = $3::text::interval, ",
+        "flapping_threshold = $4 ",
+        "WHERE name = $5"
+    );
+
+    let deduplication_window = trigger
+        .deduplication
+        .as_ref()
+        .map(|deduplication| humantime::format_duration(deduplication.window).to_string());
+
+    let deduplication_mode = trigger
+        .deduplication
+        .as_ref()
+        .map(|deduplication| deduplication.mode)
+        .unwrap_or_default()
+        .to_string();
+
+    let flapping_window = trigger
+        .flapping
+        .as_ref()
+        .map(|flapping| humantime::format_duration(flapping.window).to_string());
+
+    let flapping_threshold = trigger.flapping.as_ref().map(|flapping| flapping.threshold);
+
+    client
+        .execute(
+            query,
+            &[
+                &deduplication_window,
+                &deduplication_mode,
+                &flapping_window,
+                &flapping_threshold,
+                &trigger.name,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Error setting deduplication and flap detection of trigger '{}': {}",
+                trigger.name, e
+            ))
+        })?;
+
+    Ok(format!(
+        "Set deduplication and flap detection of trigger '{}'",
+        trigger.name
+    ))
+}
+
 pub async fn set_enabled<T: GenericClient + Sync + Send>(
     client: &mut T,
     trigger_name: &str,
@@ -649,75 +786,6 @@ pub async fn set_enabled<T: GenericClient + Sync + Send>(
 }
 
 /// Truncate a reference timestamp to the nearest timestamp for a specified granularity.
-fn truncate_timestamp_for_granularity<Tz>(
-    granularity: Duration,
-    ref_timestamp: &DateTime<Tz>,
-) -> Result<DateTime<Tz>, Error>
-where
-    Tz: chrono::TimeZone,
-{
-    let granularity_text: String = humantime::format_duration(granularity).to_string();
-
-    match granularity_text.as_str() {
-        "15m" => {
-            let date = ref_timestamp.date_naive();
-
-            let gran_minutes: u32 = 15;
-
-            let time = ref_timestamp.time();
-
-            let remainder = time.minute() % gran_minutes;
-
-            let minutes = time.minute() - remainder;
-
-            let timestamp = date
-                .and_hms_opt(ref_timestamp.time().hour(), minutes, 0)
-                .unwrap()
-                .and_local_timezone(ref_timestamp.timezone())
-                .unwrap();
-
-            Ok(timestamp)
-        }
-        "1h" => {
-            let date = ref_timestamp.date_naive();
-
-            let timestamp = date
-                .and_hms_opt(ref_timestamp.time().hour(), 0, 0)
-                .unwrap()
-                .and_local_timezone(ref_timestamp.timezone())
-                .unwrap();
-
-            Ok(timestamp)
-        }
-        "1day" => {
-            let date = ref_timestamp.date_naive();
-
-            let timestamp = date
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(ref_timestamp.timezone())
-                .unwrap();
-
-            Ok(timestamp)
-        }
-        "7days" => {
-            let date = ref_timestamp.date_naive();
-
-            let timestamp = date
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(ref_timestamp.timezone())
-                .unwrap();
-
-            Ok(timestamp)
-        }
-        _ => Err(Error::Runtime(RuntimeError::from_msg(format!(
-            "Unsupported granularity: {}",
-            &granularity_text
-        )))),
-    }
-}
-
 async fn trigger_exists<T: GenericClient + Sync + Send>(
     trigger_name: &str,
     client: &mut T,
@@ -744,10 +812,13 @@ async fn run_checks<T: GenericClient + Sync + Send>(
         escape_identifier(trigger_name)
     );
 
-    let reference_timestamp = chrono::offset::Local::now();
+    // Checks are run as part of changes, so the timezone is taken from the
+    // configuration instead of being passed along
+    let reference_timestamp = chrono::Utc::now().with_timezone(&configured_timezone()?);
 
-    let check_timestamp =
-        truncate_timestamp_for_granularity(trigger.granularity, &reference_timestamp)?;
+    let check_timestamp = Granularity::try_from(trigger.granularity)?
+        .truncate(&reference_timestamp)?
+        .with_timezone(&chrono::Utc);
 
     client
         .execute(&query, &[&check_timestamp])
@@ -871,6 +942,13 @@ impl Change for UpdateTrigger {
     async fn apply(&self, client: &mut Transaction) -> ChangeResult {
         let mut transaction = client.transaction().await?;
 
+        // The exception tables are dropped with the rule, so existing exceptions
+        // are kept unless the definition specifies its own
+        let exceptions = match &self.trigger.exceptions {
+            Some(exceptions) => exceptions.clone(),
+            None => load_trigger_exceptions(&mut transaction, &self.trigger.name).await?,
+        };
+
         // Tear down
         drop_notification_data_function(&self.trigger, &mut transaction).await?;
 
@@ -902,8 +980,19 @@ impl Change for UpdateTrigger {
 
         set_description(&self.trigger, &mut transaction).await?;
 
+        set_notification_control(&self.trigger, &mut transaction).await?;
+
         set_enabled(&mut transaction, &self.trigger.name, self.trigger.enabled).await?;
 
+        // Overrides of thresholds that were removed from the trigger are dropped
+        add_trigger_exceptions(
+            &mut transaction,
+            &self.trigger.name,
+            &exceptions,
+            self.trigger.exceptions.is_none(),
+        )
+        .await?;
+
         let mut check_result: String = "No check has run".to_string();
 
         if self.verify {
@@ -957,6 +1046,11 @@ impl Change for RenameTrigger {
 
         old_trigger.name = self.old_name.clone();
 
+        let exceptions = match &self.trigger.exceptions {
+            Some(exceptions) => exceptions.clone(),
+            None => load_trigger_exceptions(&mut transaction, &self.old_name).await?,
+        };
+
         // Tear down
         drop_notification_data_function(&old_trigger, &mut transaction).await?;
 
@@ -990,6 +1084,16 @@ impl Change for RenameTrigger {
 
         link_trend_stores(&self.trigger, &mut transaction).await?;
 
+        set_notification_control(&self.trigger, &mut transaction).await?;
+
+        add_trigger_exceptions(
+            &mut transaction,
+            &self.trigger.name,
+            &exceptions,
+            self.trigger.exceptions.is_none(),
+        )
+        .await?;
+
         let mut check_result: String = "No check has run".to_string();
 
         if self.verify {
@@ -1102,7 +1206,9 @@ pub async fn load_trigger<T: GenericClient + Send + Sync>(
     name: &str,
 ) -> Result<Trigger, Error> {
     let query = concat!(
-        "SELECT name, granularity::text, ns::text, rule.description, enabled ",
+        "SELECT name, granularity::text, ns::text, rule.description, enabled, ",
+        "deduplication_window::text, deduplication_mode::text, ",
+        "flapping_window::text, flapping_threshold ",
         "FROM trigger.rule ",
         "LEFT JOIN notification_directory.notification_store ns ON ns.id = notification_store_id ",
         "WHERE name = $1"
@@ -1127,6 +1233,25 @@ pub async fn load_trigger<T: GenericClient + Send + Sync>(
 
     let enabled: bool = row.get(4);
 
+    let deduplication = match row.get::<_, Option<String>>(5) {
+        Some(window) => Some(Deduplication {
+            window: parse_interval(&window)?,
+            mode: row.get::<_, String>(6).parse()?,
+        }),
+        None => None,
+    };
+
+    let flapping = match (
+        row.get::<_, Option<String>>(7),
+        row.get::<_, Option<i32>>(8),
+    ) {
+        (Some(window), Some(threshold)) => Some(FlapDetection {
+            window: parse_interval(&window)?,
+            threshold,
+        }),
+        _ => None,
+    };
+
     let kpi_data_columns = load_kpi_data_columns(conn, name).await?;
 
     let kpi_function_source =
@@ -1179,6 +1304,9 @@ pub async fn load_trigger<T: GenericClient + Send + Sync>(
         weight: weight_function_source,
         description: description.unwrap_or("".to_string()),
         enabled,
+        exceptions: None,
+        deduplication,
+        flapping,
     })
 }
 
@@ -1235,6 +1363,62 @@ where
     Ok(notifications)
 }
 
+/// Entity that alternates between firing and not firing of a trigger
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FlappingEntity {
+    pub entity: String,
+    /// Evaluation at which the entity was first marked as flapping
+    pub since: DateTime<Utc>,
+    /// Number of changes within the flapping window as of the last evaluation
+    pub changes: i32,
+}
+
+/// Load the entities marked as flapping by the last evaluation of a trigger
+pub async fn load_flapping_entities<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+) -> Result<Vec<FlappingEntity>, Error> {
+    let query = concat!(
+        "SELECT f.entity_id, f.since, f.changes ",
+        "FROM trigger.flapping_entity f ",
+        "JOIN trigger.rule ON rule.id = f.rule_id ",
+        "WHERE rule.name = $1 ",
+        "ORDER BY f.changes DESC, f.entity_id"
+    );
+
+    let rows = client.query(query, &[&trigger_name]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not load flapping entities of trigger '{trigger_name}': {e}"
+        ))
+    })?;
+
+    if rows.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let entity_type = trigger_entity_type(client, trigger_name).await?;
+
+    let entity_ids: Vec<i32> = rows.iter().map(|row| row.get(0)).collect();
+
+    let entity_names = load_entity_names(client, &entity_type, Some(&entity_ids)).await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let entity_id: i32 = row.get(0);
+
+            FlappingEntity {
+                entity: entity_names
+                    .get(&entity_id)
+                    .cloned()
+                    .unwrap_or_else(|| entity_id.to_string()),
+                since: row.get(1),
+                changes: row.get(2),
+            }
+        })
+        .collect())
+}
+
 pub struct CreateNotifications<Tz: TimeZone> {
     pub trigger_name: String,
     pub timestamp: Option<DateTime<Tz>>,
@@ -0,0 +1,209 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use postgres_protocol::escape::escape_identifier;
+use serde::Serialize;
+use tokio_postgres::GenericClient;
+
+use crate::entity::load_entity_names;
+use crate::error::{DatabaseError, Error};
+use crate::granularity::Granularity;
+use crate::trigger_exception::trigger_entity_type;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestTimestamp {
+    pub timestamp: DateTime<Utc>,
+    pub notification_count: u64,
+    pub total_weight: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestEntity {
+    pub entity_id: i32,
+    pub entity: Option<String>,
+    pub notification_count: u64,
+    pub max_weight: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    pub trigger: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub notification_count: u64,
+    pub timestamps: Vec<BacktestTimestamp>,
+    /// Entities with the most notifications, most notifications first
+    pub top_entities: Vec<BacktestEntity>,
+    /// Number of notifications per weight
+    pub weight_distribution: BTreeMap<i32, u64>,
+}
+
+/// Timestamp and the entity and weight of a notification, if any
+type BacktestRow = (DateTime<Utc>, Option<i32>, Option<i32>);
+
+fn backtest_query(trigger_name: &str) -> String {
+    format!(
+        concat!(
+            "SELECT t.timestamp, n.entity_id, n.weight ",
+            "FROM unnest($1::timestamptz[]) AS t(timestamp) ",
+            "LEFT JOIN LATERAL trigger_rule.{}(t.timestamp) n ON true ",
+            "ORDER BY t.timestamp"
+        ),
+        escape_identifier(&format!("{trigger_name}_create_notification"))
+    )
+}
+
+fn summarize_backtest(
+    rows: &[BacktestRow],
+    top: usize,
+) -> (
+    Vec<BacktestTimestamp>,
+    Vec<BacktestEntity>,
+    BTreeMap<i32, u64>,
+) {
+    let mut timestamps: Vec<BacktestTimestamp> = Vec::new();
+    let mut entities: HashMap<i32, BacktestEntity> = HashMap::new();
+    let mut weight_distribution: BTreeMap<i32, u64> = BTreeMap::new();
+
+    for (timestamp, entity_id, weight) in rows {
+        if timestamps.last().is_none_or(|t| t.timestamp != *timestamp) {
+            timestamps.push(BacktestTimestamp {
+                timestamp: *timestamp,
+                notification_count: 0,
+                total_weight: 0,
+            });
+        }
+
+        let Some(entity_id) = entity_id else {
+            continue;
+        };
+
+        let weight = weight.unwrap_or_default();
+
+        if let Some(t) = timestamps.last_mut() {
+            t.notification_count += 1;
+            t.total_weight += weight as i64;
+        }
+
+        let entity = entities.entry(*entity_id).or_insert(BacktestEntity {
+            entity_id: *entity_id,
+            entity: None,
+            notification_count: 0,
+            max_weight: weight,
+        });
+
+        entity.notification_count += 1;
+        entity.max_weight = entity.max_weight.max(weight);
+
+        *weight_distribution.entry(weight).or_default() += 1;
+    }
+
+    let mut top_entities: Vec<BacktestEntity> = entities.into_values().collect();
+
+    top_entities.sort_by(|a, b| {
+        b.notification_count
+            .cmp(&a.notification_count)
+            .then(a.entity_id.cmp(&b.entity_id))
+    });
+    top_entities.truncate(top);
+
+    (timestamps, top_entities, weight_distribution)
+}
+
+/// Evaluate a trigger at every granularity step from `start` (inclusive) to
+/// `end` (exclusive), aligned in `timezone`, without writing notifications.
+pub async fn backtest_trigger<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger_name: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    timezone: &chrono_tz::Tz,
+    top: usize,
+) -> Result<BacktestReport, Error> {
+    let row = client
+        .query_one(
+            "SELECT granularity::text FROM trigger.rule WHERE name = $1",
+            &[&trigger_name],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Could not find trigger '{trigger_name}': {e}"))
+        })?;
+
+    let granularity_str: String = row.get(0);
+
+    let granularity = Granularity::from_str(&granularity_str)?;
+
+    let timestamps = granularity.timestamps_in_range(&start, &end, timezone)?;
+
+    let rows: Vec<BacktestRow> = client
+        .query(&backtest_query(trigger_name), &[&timestamps])
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!("Could not evaluate trigger '{trigger_name}': {e}"))
+        })?
+        .iter()
+        .map(|row| (row.get(0), row.get(1), row.get(2)))
+        .collect();
+
+    let (timestamps, mut top_entities, weight_distribution) = summarize_backtest(&rows, top);
+
+    if !top_entities.is_empty() {
+        let entity_type = trigger_entity_type(client, trigger_name).await?;
+
+        let entity_ids: Vec<i32> = top_entities.iter().map(|e| e.entity_id).collect();
+
+        let entity_names = load_entity_names(client, &entity_type, Some(&entity_ids)).await?;
+
+        for entity in top_entities.iter_mut() {
+            entity.entity = entity_names.get(&entity.entity_id).cloned();
+        }
+    }
+
+    Ok(BacktestReport {
+        trigger: trigger_name.to_string(),
+        start,
+        end,
+        notification_count: weight_distribution.values().sum(),
+        timestamps,
+        top_entities,
+        weight_distribution,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn summarize_notifications_per_timestamp_and_entity() {
+        let timestamp = |hour: u32| Utc.with_ymd_and_hms(2024, 7, 1, hour, 0, 0).unwrap();
+
+        let rows = vec![
+            (timestamp(0), Some(1), Some(10)),
+            (timestamp(0), Some(2), Some(5)),
+            (timestamp(1), None, None),
+            (timestamp(2), Some(1), Some(20)),
+        ];
+
+        let (timestamps, top_entities, weight_distribution) = summarize_backtest(&rows, 1);
+
+        let counts: Vec<(u64, i64)> = timestamps
+            .iter()
+            .map(|t| (t.notification_count, t.total_weight))
+            .collect();
+
+        assert_eq!(counts, vec![(2, 15), (0, 0), (1, 20)]);
+        assert_eq!(top_entities.len(), 1);
+        assert_eq!(top_entities[0].entity_id, 1);
+        assert_eq!(top_entities[0].notification_count, 2);
+        assert_eq!(top_entities[0].max_weight, 20);
+        assert_eq!(
+            weight_distribution,
+            BTreeMap::from([(5, 1), (10, 1), (20, 1)])
+        );
+    }
+}
@@ -0,0 +1,493 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use postgres_protocol::escape::escape_identifier;
+use postgres_types::ToSql;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::error::SqlState;
+use tokio_postgres::GenericClient;
+
+use crate::entity::{load_entity_names, lookup_entity_ids};
+use crate::error::{ConfigurationError, DatabaseError, Error, RuntimeError};
+use crate::trigger::{load_thresholds, Threshold};
+
+/// Entity specific override of one or more thresholds of a trigger
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ThresholdException {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    pub entity: String,
+    /// Start of the validity period, unbounded if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    /// End of the validity period, unbounded if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+    /// Overridden thresholds by name, thresholds that are not specified keep
+    /// their global value
+    pub thresholds: BTreeMap<String, String>,
+}
+
+/// Entity specific override of the weight of notifications of a trigger
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WeightException {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i32>,
+    pub entity: String,
+    /// Start of the validity period, unbounded if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<DateTime<Utc>>,
+    /// End of the validity period, unbounded if not specified
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    pub weight: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct TriggerExceptions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub threshold: Vec<ThresholdException>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weight: Vec<WeightException>,
+}
+
+impl TriggerExceptions {
+    pub fn is_empty(&self) -> bool {
+        self.threshold.is_empty() && self.weight.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExceptionKind {
+    Threshold,
+    Weight,
+}
+
+impl FromStr for ExceptionKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threshold" => Ok(ExceptionKind::Threshold),
+            "weight" => Ok(ExceptionKind::Weight),
+            _ => Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Unsupported exception kind '{s}'"
+            )))),
+        }
+    }
+}
+
+impl fmt::Display for ExceptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExceptionKind::Threshold => write!(f, "threshold"),
+            ExceptionKind::Weight => write!(f, "weight"),
+        }
+    }
+}
+
+impl ExceptionKind {
+    fn table_name(&self, trigger_name: &str) -> String {
+        format!("{trigger_name}_exception_{self}")
+    }
+}
+
+/// Name of the entity type of the trend store parts a trigger is linked to
+pub async fn trigger_entity_type<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger_name: &str,
+) -> Result<String, Error> {
+    let query = concat!(
+        "SELECT DISTINCT et.name ",
+        "FROM trigger.rule r ",
+        "JOIN trigger.rule_trend_store_link l ON l.rule_id = r.id ",
+        "JOIN trend_directory.trend_store_part tsp ON tsp.id = l.trend_store_part_id ",
+        "JOIN trend_directory.trend_store ts ON ts.id = tsp.trend_store_id ",
+        "JOIN directory.entity_type et ON et.id = ts.entity_type_id ",
+        "WHERE r.name = $1"
+    );
+
+    let rows = client.query(query, &[&trigger_name]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not determine entity type of trigger '{trigger_name}': {e}"
+        ))
+    })?;
+
+    match rows.as_slice() {
+        [row] => Ok(row.get(0)),
+        [] => Err(Error::Runtime(RuntimeError::from_msg(format!(
+            "No trend store linked to trigger '{trigger_name}'"
+        )))),
+        _ => Err(Error::Runtime(RuntimeError::from_msg(format!(
+            "Trigger '{trigger_name}' is linked to trend stores of multiple entity types"
+        )))),
+    }
+}
+
+async fn lookup_entity_id<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger_name: &str,
+    entity: &str,
+) -> Result<i32, Error> {
+    let entity_type = trigger_entity_type(client, trigger_name).await?;
+
+    let entity_ids = lookup_entity_ids(client, &entity_type, &[entity.to_string()]).await?;
+
+    Ok(entity_ids[0])
+}
+
+fn threshold_exception_insert_query(
+    trigger_name: &str,
+    exception: &ThresholdException,
+    thresholds: &[Threshold],
+) -> Result<String, Error> {
+    let mut columns: Vec<String> = ["entity_id", "start", "expires", "remark"]
+        .iter()
+        .map(|column| column.to_string())
+        .collect();
+
+    let mut values: Vec<String> = vec![
+        "$1".to_string(),
+        "coalesce($2::timestamptz, '-infinity')".to_string(),
+        "coalesce($3::timestamptz, 'infinity')".to_string(),
+        "$4".to_string(),
+    ];
+
+    for (index, name) in exception.thresholds.keys().enumerate() {
+        let threshold = thresholds.iter().find(|t| &t.name == name).ok_or_else(|| {
+            Error::Configuration(ConfigurationError::from_msg(format!(
+                "Trigger '{trigger_name}' has no threshold '{name}'"
+            )))
+        })?;
+
+        columns.push(escape_identifier(name));
+        values.push(format!(
+            "${}::text::{}",
+            index + 5,
+            escape_identifier(&threshold.data_type)
+        ));
+    }
+
+    Ok(format!(
+        "INSERT INTO trigger_rule.{}({}) VALUES ({}) RETURNING id",
+        escape_identifier(&ExceptionKind::Threshold.table_name(trigger_name)),
+        columns.join(", "),
+        values.join(", ")
+    ))
+}
+
+/// Map an insert error of an exception, reporting a validity period that
+/// overlaps with an existing exception of the same entity as such
+fn exception_insert_error(
+    kind: ExceptionKind,
+    trigger_name: &str,
+    entity: &str,
+    e: tokio_postgres::Error,
+) -> Error {
+    if e.code() == Some(&SqlState::EXCLUSION_VIOLATION) {
+        return Error::Configuration(ConfigurationError::from_msg(format!(
+            "The validity period of the {kind} exception for '{entity}' overlaps with an existing exception of trigger '{trigger_name}'"
+        )));
+    }
+
+    Error::Database(DatabaseError::from_msg(format!(
+        "Could not add {kind} exception to trigger '{trigger_name}': {e}"
+    )))
+}
+
+/// Add an entity specific threshold exception to a trigger and return its Id
+pub async fn add_threshold_exception<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+    exception: &ThresholdException,
+) -> Result<i32, Error> {
+    if exception.thresholds.is_empty() {
+        return Err(Error::Configuration(ConfigurationError::from_msg(
+            "No thresholds specified for exception".to_string(),
+        )));
+    }
+
+    let thresholds = load_thresholds(client, trigger_name).await?;
+
+    let query = threshold_exception_insert_query(trigger_name, exception, &thresholds)?;
+
+    let entity_id = lookup_entity_id(client, trigger_name, &exception.entity).await?;
+
+    let mut params: Vec<&(dyn ToSql + Sync)> = vec![
+        &entity_id,
+        &exception.start,
+        &exception.expires,
+        &exception.remark,
+    ];
+
+    for value in exception.thresholds.values() {
+        params.push(value);
+    }
+
+    let row = client.query_one(&query, &params).await.map_err(|e| {
+        exception_insert_error(ExceptionKind::Threshold, trigger_name, &exception.entity, e)
+    })?;
+
+    Ok(row.get(0))
+}
+
+/// Add an entity specific weight exception to a trigger and return its Id
+pub async fn add_weight_exception<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+    exception: &WeightException,
+) -> Result<i32, Error> {
+    let entity_id = lookup_entity_id(client, trigger_name, &exception.entity).await?;
+
+    let query = format!(
+        concat!(
+            "INSERT INTO trigger_rule.{}(entity_id, start, expires, weight) ",
+            "VALUES ($1, coalesce($2::timestamptz, '-infinity'), coalesce($3::timestamptz, 'infinity'), $4) ",
+            "RETURNING id"
+        ),
+        escape_identifier(&ExceptionKind::Weight.table_name(trigger_name))
+    );
+
+    let row = client
+        .query_one(
+            &query,
+            &[
+                &entity_id,
+                &exception.start,
+                &exception.expires,
+                &exception.weight,
+            ],
+        )
+        .await
+        .map_err(|e| {
+            exception_insert_error(ExceptionKind::Weight, trigger_name, &exception.entity, e)
+        })?;
+
+    Ok(row.get(0))
+}
+
+/// Add all exceptions to a trigger, skipping threshold overrides of thresholds
+/// that the trigger does not have when `skip_unknown` is set.
+pub async fn add_trigger_exceptions<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+    exceptions: &TriggerExceptions,
+    skip_unknown: bool,
+) -> Result<(), Error> {
+    let threshold_names: Vec<String> = load_thresholds(client, trigger_name)
+        .await?
+        .into_iter()
+        .map(|threshold| threshold.name)
+        .collect();
+
+    for exception in &exceptions.threshold {
+        let mut exception = exception.clone();
+
+        if skip_unknown {
+            exception
+                .thresholds
+                .retain(|name, _| threshold_names.contains(name));
+
+            if exception.thresholds.is_empty() {
+                continue;
+            }
+        }
+
+        add_threshold_exception(client, trigger_name, &exception).await?;
+    }
+
+    for exception in &exceptions.weight {
+        add_weight_exception(client, trigger_name, exception).await?;
+    }
+
+    Ok(())
+}
+
+/// Validity bound as stored, with infinite bounds mapped to `None`
+fn bound_expression(column: &str) -> String {
+    format!("CASE WHEN isfinite({column}) THEN {column} END")
+}
+
+/// Load all threshold and weight exceptions of a trigger
+pub async fn load_trigger_exceptions<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+) -> Result<TriggerExceptions, Error> {
+    let thresholds = load_thresholds(client, trigger_name).await?;
+
+    let threshold_query = format!(
+        "SELECT id, entity_id, {}, {}, remark{} FROM trigger_rule.{} ORDER BY id",
+        bound_expression("start"),
+        bound_expression("expires"),
+        thresholds
+            .iter()
+            .map(|threshold| format!(", {}::text", escape_identifier(&threshold.name)))
+            .collect::<String>(),
+        escape_identifier(&ExceptionKind::Threshold.table_name(trigger_name)),
+    );
+
+    let threshold_rows = client.query(&threshold_query, &[]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not load threshold exceptions of trigger '{trigger_name}': {e}"
+        ))
+    })?;
+
+    let weight_query = format!(
+        "SELECT id, entity_id, {}, {}, weight FROM trigger_rule.{} ORDER BY id",
+        bound_expression("start"),
+        bound_expression("expires"),
+        escape_identifier(&ExceptionKind::Weight.table_name(trigger_name)),
+    );
+
+    let weight_rows = client.query(&weight_query, &[]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not load weight exceptions of trigger '{trigger_name}': {e}"
+        ))
+    })?;
+
+    if threshold_rows.is_empty() && weight_rows.is_empty() {
+        return Ok(TriggerExceptions::default());
+    }
+
+    // The entity type is only required to resolve names, so that triggers
+    // without exceptions do not need to be linked to a trend store
+    let entity_type = trigger_entity_type(client, trigger_name).await?;
+
+    let entity_ids: Vec<i32> = threshold_rows
+        .iter()
+        .chain(weight_rows.iter())
+        .filter_map(|row| row.get::<_, Option<i32>>(1))
+        .collect();
+
+    let entity_names = load_entity_names(client, &entity_type, Some(&entity_ids)).await?;
+
+    let entity_name = |entity_id: Option<i32>| -> String {
+        entity_id
+            .and_then(|entity_id| entity_names.get(&entity_id).cloned())
+            .unwrap_or_default()
+    };
+
+    let threshold = threshold_rows
+        .iter()
+        .map(|row| ThresholdException {
+            id: row.get(0),
+            entity: entity_name(row.get(1)),
+            start: row.get(2),
+            expires: row.get(3),
+            remark: row.get(4),
+            thresholds: thresholds
+                .iter()
+                .enumerate()
+                .filter_map(|(index, threshold)| {
+                    row.get::<_, Option<String>>(index + 5)
+                        .map(|value| (threshold.name.clone(), value))
+                })
+                .collect(),
+        })
+        .collect();
+
+    let weight = weight_rows
+        .iter()
+        .map(|row| WeightException {
+            id: row.get(0),
+            entity: entity_name(row.get(1)),
+            start: row.get(2),
+            expires: row.get(3),
+            weight: row.get(4),
+        })
+        .collect();
+
+    Ok(TriggerExceptions { threshold, weight })
+}
+
+/// Remove an exception from a trigger, returning false if it did not exist
+pub async fn remove_exception<T: GenericClient + Send + Sync>(
+    client: &mut T,
+    trigger_name: &str,
+    kind: ExceptionKind,
+    id: i32,
+) -> Result<bool, Error> {
+    let query = format!(
+        "DELETE FROM trigger_rule.{} WHERE id = $1",
+        escape_identifier(&kind.table_name(trigger_name))
+    );
+
+    let count = client.execute(&query, &[&id]).await.map_err(|e| {
+        DatabaseError::from_msg(format!(
+            "Could not remove {kind} exception {id} of trigger '{trigger_name}': {e}"
+        ))
+    })?;
+
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_threshold_exception_insert_query() {
+        let thresholds = vec![
+            Threshold {
+                name: "max_drop_rate".to_string(),
+                data_type: "numeric".to_string(),
+                value: "0.1".to_string(),
+            },
+            Threshold {
+                name: "min_attempts".to_string(),
+                data_type: "int4".to_string(),
+                value: "100".to_string(),
+            },
+        ];
+
+        let exception = ThresholdException {
+            id: None,
+            entity: "cell_1".to_string(),
+            start: None,
+            expires: None,
+            remark: Some("Known noisy cell".to_string()),
+            thresholds: BTreeMap::from([("min_attempts".to_string(), "500".to_string())]),
+        };
+
+        let query = threshold_exception_insert_query("drop-rate", &exception, &thresholds).unwrap();
+
+        assert_eq!(
+            query,
+            concat!(
+                r#"INSERT INTO trigger_rule."drop-rate_exception_threshold"(entity_id, start, expires, remark, "min_attempts") "#,
+                r#"VALUES ($1, coalesce($2::timestamptz, '-infinity'), coalesce($3::timestamptz, 'infinity'), $4, $5::text::"int4") RETURNING id"#
+            )
+        );
+
+        let unknown = ThresholdException {
+            thresholds: BTreeMap::from([("max_volume".to_string(), "5".to_string())]),
+            ..exception
+        };
+
+        assert!(threshold_exception_insert_query("drop-rate", &unknown, &thresholds).is_err());
+    }
+
+    #[test]
+    fn parse_exceptions_without_validity_period() {
+        let exceptions: TriggerExceptions = serde_yaml::from_str(concat!(
+            "threshold:\n",
+            "- entity: cell_1\n",
+            "  thresholds:\n",
+            "    min_attempts: '500'\n",
+            "weight:\n",
+            "- entity: cell_2\n",
+            "  expires: 2024-08-01T00:00:00Z\n",
+            "  weight: 10\n",
+        ))
+        .unwrap();
+
+        assert_eq!(exceptions.threshold[0].start, None);
+        assert_eq!(exceptions.threshold[0].expires, None);
+        assert_eq!(exceptions.weight[0].weight, 10);
+        assert!(exceptions.weight[0].expires.is_some());
+    }
+}
@@ -0,0 +1,864 @@
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::error::{ConfigurationError, Error};
+use crate::granularity::Granularity;
+use crate::meas_value::{parse_meas_value_with, DataType, ValueParserConfig};
+use crate::schema::schema;
+use crate::trend_store::TrendStore;
+use crate::trigger::{FileTrigger, Trigger, MAX_TRIGGER_NAME_LENGTH};
+
+/// Columns that are always present in the KPI and details types of a trigger
+const BUILTIN_COLUMNS: [&str; 2] = ["entity_id", "timestamp"];
+
+/// Unquoted words in a condition that are not column references
+const SQL_KEYWORDS: &[&str] = &[
+    "all",
+    "and",
+    "any",
+    "array",
+    "as",
+    "between",
+    "bigint",
+    "boolean",
+    "case",
+    "cast",
+    "current_date",
+    "current_timestamp",
+    "date",
+    "day",
+    "distinct",
+    "double",
+    "else",
+    "end",
+    "escape",
+    "exists",
+    "false",
+    "from",
+    "hour",
+    "ilike",
+    "in",
+    "integer",
+    "interval",
+    "is",
+    "isnull",
+    "like",
+    "minute",
+    "month",
+    "not",
+    "notnull",
+    "null",
+    "numeric",
+    "or",
+    "precision",
+    "real",
+    "second",
+    "similar",
+    "smallint",
+    "some",
+    "symmetric",
+    "text",
+    "then",
+    "time",
+    "to",
+    "true",
+    "unknown",
+    "varchar",
+    "week",
+    "when",
+    "with",
+    "without",
+    "year",
+    "zone",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Position in a definition file, 1-based
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    /// Field of the trigger definition the issue applies to
+    pub path: Vec<PathSegment>,
+    pub message: String,
+    pub location: Option<Location>,
+    /// Line and column of the offending text in the value of the field,
+    /// 0-based
+    position: Option<(usize, usize)>,
+}
+
+impl LintIssue {
+    fn new(path: Vec<PathSegment>, message: String) -> LintIssue {
+        LintIssue {
+            path,
+            message,
+            location: None,
+            position: None,
+        }
+    }
+
+    pub fn path_str(&self) -> String {
+        let mut result = String::new();
+
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) if result.is_empty() => result.push_str(key),
+                PathSegment::Key(key) => {
+                    result.push('.');
+                    result.push_str(key);
+                }
+                PathSegment::Index(index) => result.push_str(&format!("[{index}]")),
+            }
+        }
+
+        result
+    }
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = &self.location {
+            write!(f, "{location}: ")?;
+        }
+
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{}: {}", self.path_str(), self.message),
+        }
+    }
+}
+
+fn key(name: &str) -> PathSegment {
+    PathSegment::Key(name.to_string())
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Identifier { name: String, quoted: bool },
+    Parameter(String),
+    Symbol(char),
+    Literal,
+}
+
+#[derive(Debug)]
+struct PositionedToken {
+    token: Token,
+    line: usize,
+    column: usize,
+}
+
+/// Index just past the string literal starting at the quote at `start`
+fn skip_string(chars: &[char], start: usize, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if backslash_escapes => i += 2,
+            '\'' if chars.get(i + 1) == Some(&'\'') => i += 2,
+            '\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+
+    i
+}
+
+/// Split an SQL expression into the tokens relevant for finding column
+/// references, skipping comments and the contents of literals
+fn tokenize(sql: &str) -> Vec<PositionedToken> {
+    let chars: Vec<char> = sql.chars().collect();
+
+    let mut positions = Vec::with_capacity(chars.len());
+    let (mut line, mut column) = (0, 0);
+
+    for c in &chars {
+        positions.push((line, column));
+
+        if *c == '\n' {
+            line += 1;
+            column = 0;
+        } else {
+            column += 1;
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let (line, column) = positions[i];
+
+        let token = if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == '-' && chars.get(i + 1) == Some(&'-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        } else if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        } else if c == '\'' {
+            i = skip_string(&chars, i, false);
+            Token::Literal
+        } else if c == '"' {
+            let mut name = String::new();
+            i += 1;
+
+            while i < chars.len() {
+                if chars[i] == '"' {
+                    if chars.get(i + 1) == Some(&'"') {
+                        name.push('"');
+                        i += 2;
+                        continue;
+                    }
+
+                    i += 1;
+                    break;
+                }
+
+                name.push(chars[i]);
+                i += 1;
+            }
+
+            Token::Identifier { name, quoted: true }
+        } else if c == '$' && chars.get(i + 1).is_some_and(|c| c.is_ascii_digit()) {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+
+            Token::Parameter(chars[start..i].iter().collect())
+        } else if c == '$' {
+            // Dollar quoted string
+            let tag_end = chars[i + 1..]
+                .iter()
+                .position(|c| *c == '$')
+                .map_or(chars.len(), |p| i + 1 + p + 1);
+            let tag: Vec<char> = chars[i..tag_end].to_vec();
+
+            i = tag_end;
+            while i < chars.len() && !chars[i..].starts_with(&tag) {
+                i += 1;
+            }
+            i += tag.len();
+
+            Token::Literal
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+            {
+                i += 1;
+            }
+
+            let name: String = chars[start..i].iter().collect::<String>().to_lowercase();
+
+            if name == "e" && chars.get(i) == Some(&'\'') {
+                i = skip_string(&chars, i, true);
+                Token::Literal
+            } else {
+                Token::Identifier {
+                    name,
+                    quoted: false,
+                }
+            }
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+
+            Token::Literal
+        } else {
+            i += 1;
+            Token::Symbol(c)
+        };
+
+        tokens.push(PositionedToken {
+            token,
+            line,
+            column,
+        });
+    }
+
+    tokens
+}
+
+/// Identifiers in a condition that refer to columns: words that are not
+/// keywords, function names, qualified names or type names
+fn condition_references(tokens: &[PositionedToken]) -> Vec<&PositionedToken> {
+    let mut references = Vec::new();
+
+    for (index, t) in tokens.iter().enumerate() {
+        let Token::Identifier { name, quoted } = &t.token else {
+            continue;
+        };
+
+        let previous = index
+            .checked_sub(1)
+            .and_then(|i| tokens.get(i))
+            .map(|t| &t.token);
+        let next = tokens.get(index + 1).map(|t| &t.token);
+
+        if !quoted && SQL_KEYWORDS.contains(&name.as_str()) {
+            continue;
+        }
+
+        if matches!(previous, Some(Token::Symbol('.' | ':')))
+            || matches!(next, Some(Token::Symbol('(' | '.')))
+        {
+            continue;
+        }
+
+        // Type name in a CAST expression
+        if matches!(previous, Some(Token::Identifier { name, quoted: false }) if name == "as") {
+            continue;
+        }
+
+        references.push(t);
+    }
+
+    references
+}
+
+/// Fields of the `$1` record referenced in a weight, notification or data
+/// expression
+fn record_references(tokens: &[PositionedToken]) -> Vec<&PositionedToken> {
+    tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [PositionedToken {
+                token: Token::Parameter(parameter),
+                ..
+            }, PositionedToken {
+                token: Token::Symbol('.'),
+                ..
+            }, reference @ PositionedToken {
+                token: Token::Identifier { .. },
+                ..
+            }] if parameter == "1" => Some(reference),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Timestamp mapping functions created by the Minerva schema
+fn builtin_mapping_functions() -> Vec<&'static str> {
+    schema()
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("CREATE FUNCTION \"trend\".\"")?
+                .strip_suffix("\"(timestamp with time zone)")
+        })
+        .collect()
+}
+
+/// Source and target granularity of a built-in mapping function, such as
+/// `mapping_15m->1h`, applied to a trend store part of granularity `part`
+fn mapping_granularities(name: &str, part: Granularity) -> Option<(Granularity, Granularity)> {
+    match name {
+        "mapping_id" => Some((part, part)),
+        _ => {
+            let (from, to) = name.strip_prefix("mapping_")?.split_once("->")?;
+
+            Some((
+                Granularity::from_str(from).ok()?,
+                Granularity::from_str(to).ok()?,
+            ))
+        }
+    }
+}
+
+fn lint_references(
+    issues: &mut Vec<LintIssue>,
+    field: &str,
+    references: Vec<&PositionedToken>,
+    columns: &[&str],
+) {
+    for reference in references {
+        let Token::Identifier { name, .. } = &reference.token else {
+            continue;
+        };
+
+        if !columns.contains(&name.as_str()) {
+            issues.push(LintIssue {
+                position: Some((reference.line, reference.column)),
+                ..LintIssue::new(
+                    vec![key(field)],
+                    format!("Unknown column '{name}', expected a KPI column or threshold"),
+                )
+            });
+        }
+    }
+}
+
+/// Check a trigger definition for errors that would otherwise only surface
+/// when it is created in the database. Trend store links are only checked
+/// against the parts of `trend_stores` if these are provided.
+pub fn lint_trigger(trigger: &Trigger, trend_stores: Option<&[TrendStore]>) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    if trigger.name.len() > MAX_TRIGGER_NAME_LENGTH {
+        issues.push(LintIssue::new(
+            vec![key("name")],
+            format!(
+                "Trigger name too long ({} > {})",
+                trigger.name.len(),
+                MAX_TRIGGER_NAME_LENGTH
+            ),
+        ));
+    }
+
+    for (index, threshold) in trigger.thresholds.iter().enumerate() {
+        let data_type = DataType::from(threshold.data_type.as_str());
+
+        // Unknown types are mapped to text, for which any value is valid
+        if parse_meas_value_with(data_type, &threshold.value, &ValueParserConfig::default())
+            .is_err()
+        {
+            issues.push(LintIssue::new(
+                vec![key("thresholds"), PathSegment::Index(index), key("value")],
+                format!(
+                    "Value '{}' of threshold '{}' is not a valid {}",
+                    threshold.value, threshold.name, threshold.data_type
+                ),
+            ));
+        }
+    }
+
+    let columns: Vec<&str> = BUILTIN_COLUMNS
+        .into_iter()
+        .chain(trigger.kpi_data.iter().map(|column| column.name.as_str()))
+        .chain(
+            trigger
+                .thresholds
+                .iter()
+                .map(|threshold| threshold.name.as_str()),
+        )
+        .collect();
+
+    lint_references(
+        &mut issues,
+        "condition",
+        condition_references(&tokenize(&trigger.condition)),
+        &columns,
+    );
+
+    for (field, expression) in [
+        ("weight", &trigger.weight),
+        ("notification", &trigger.notification),
+        ("data", &trigger.data),
+    ] {
+        lint_references(
+            &mut issues,
+            field,
+            record_references(&tokenize(expression)),
+            &columns,
+        );
+    }
+
+    let granularity = match Granularity::try_from(trigger.granularity) {
+        Ok(granularity) => Some(granularity),
+        Err(e) => {
+            issues.push(LintIssue::new(vec![key("granularity")], e.to_string()));
+            None
+        }
+    };
+
+    if let Some(deduplication) = &trigger.deduplication {
+        if deduplication.window < trigger.granularity {
+            issues.push(LintIssue::new(
+                vec![key("deduplication"), key("window")],
+                format!(
+                    "Deduplication window {} is shorter than the trigger granularity, so no notifications are suppressed",
+                    humantime::format_duration(deduplication.window)
+                ),
+            ));
+        }
+    }
+
+    if let Some(flapping) = &trigger.flapping {
+        // Each evaluation after the first in the window can be a change
+        let max_changes = match trigger.granularity.as_secs() {
+            0 => 0,
+            secs => flapping.window.as_secs() / secs,
+        };
+
+        if flapping.threshold < 1 {
+            issues.push(LintIssue::new(
+                vec![key("flapping"), key("threshold")],
+                "Flapping threshold must be at least 1".to_string(),
+            ));
+        } else if flapping.threshold as u64 > max_changes {
+            issues.push(LintIssue::new(
+                vec![key("flapping"), key("threshold")],
+                format!(
+                    "Flapping threshold {} can not be reached, the flapping window of {} allows at most {max_changes} change(s)",
+                    flapping.threshold,
+                    humantime::format_duration(flapping.window)
+                ),
+            ));
+        }
+    }
+
+    let builtin_mapping_functions = builtin_mapping_functions();
+
+    for (index, link) in trigger.trend_store_links.iter().enumerate() {
+        let path = |field: &str| {
+            vec![
+                key("trend_store_links"),
+                PathSegment::Index(index),
+                key(field),
+            ]
+        };
+
+        let defined = builtin_mapping_functions.contains(&link.mapping_function.as_str())
+            || trigger
+                .mapping_functions
+                .iter()
+                .any(|mapping_function| mapping_function.name == link.mapping_function);
+
+        if !defined {
+            issues.push(LintIssue::new(
+                path("mapping_function"),
+                format!(
+                    "Mapping function '{}' is not defined in the trigger or the Minerva schema",
+                    link.mapping_function
+                ),
+            ));
+        }
+
+        let Some(trend_stores) = trend_stores else {
+            continue;
+        };
+
+        let Some(trend_store) = trend_stores.iter().find(|trend_store| {
+            trend_store
+                .parts
+                .iter()
+                .any(|part| part.name == link.part_name)
+        }) else {
+            issues.push(LintIssue::new(
+                path("part_name"),
+                format!("No trend store part named '{}'", link.part_name),
+            ));
+            continue;
+        };
+
+        let Ok(part_granularity) = Granularity::try_from(trend_store.granularity) else {
+            continue;
+        };
+
+        let Some((from, to)) = mapping_granularities(&link.mapping_function, part_granularity)
+        else {
+            continue;
+        };
+
+        if from != part_granularity {
+            issues.push(LintIssue::new(
+                path("part_name"),
+                format!(
+                    "Trend store part '{}' has granularity {part_granularity}, but mapping function '{}' maps from {from}",
+                    link.part_name, link.mapping_function
+                ),
+            ));
+        }
+
+        if let Some(granularity) = granularity {
+            if to != granularity {
+                issues.push(LintIssue::new(
+                    path("mapping_function"),
+                    format!(
+                        "Mapping function '{}' maps trend store part '{}' to {to}, but the trigger granularity is {granularity}",
+                        link.mapping_function, link.part_name
+                    ),
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// Column of the content of a line, after the dash of a sequence item
+fn content_column(line: &str) -> usize {
+    let column = indentation(line);
+
+    match line[column..].strip_prefix('-') {
+        Some(rest) if rest.starts_with(' ') => line.len() - rest.trim_start_matches(' ').len(),
+        _ => column,
+    }
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+/// Locate a field in a block style YAML document, and optionally a position
+/// in its value. Returns None for documents in other styles.
+fn locate(
+    source: &str,
+    path: &[PathSegment],
+    position: Option<(usize, usize)>,
+) -> Option<Location> {
+    let lines: Vec<&str> = source.lines().collect();
+
+    let mut start = 0;
+    let mut end = lines.len();
+    let mut found = None;
+
+    for segment in path {
+        let first = (start..end).find(|&i| is_content(lines[i]))?;
+
+        match segment {
+            PathSegment::Key(key) => {
+                let key_column = content_column(lines[first]);
+
+                let line = (start..end).find(|&i| {
+                    is_content(lines[i])
+                        && content_column(lines[i]) == key_column
+                        && lines[i][key_column..]
+                            .strip_prefix(key.as_str())
+                            .is_some_and(|rest| rest.starts_with(':'))
+                })?;
+
+                // The value ends at the next key at the same or a lower level,
+                // allowing for sequences at the same indentation as their key
+                end = (line + 1..end)
+                    .find(|&i| {
+                        is_content(lines[i])
+                            && (indentation(lines[i]) < key_column
+                                || (indentation(lines[i]) == key_column
+                                    && !lines[i].trim_start().starts_with('-')))
+                    })
+                    .unwrap_or(end);
+                start = line + 1;
+                found = Some((line, key_column));
+            }
+            PathSegment::Index(index) => {
+                let dash_column = indentation(lines[first]);
+
+                let line = (start..end)
+                    .filter(|&i| {
+                        is_content(lines[i])
+                            && indentation(lines[i]) == dash_column
+                            && lines[i].trim_start().starts_with('-')
+                    })
+                    .nth(*index)?;
+
+                end = (line + 1..end)
+                    .find(|&i| is_content(lines[i]) && indentation(lines[i]) <= dash_column)
+                    .unwrap_or(end);
+                start = line;
+                found = Some((line, dash_column));
+            }
+        }
+    }
+
+    let (line, column) = found?;
+
+    let location = match (path.last(), position) {
+        (Some(PathSegment::Key(key)), Some((value_line, value_column))) => {
+            let value = lines[line][column + key.len() + 1..].trim_start();
+
+            if value.starts_with('|') {
+                let block_column = (line + 1..end)
+                    .find(|&i| is_content(lines[i]))
+                    .map_or(column, |i| indentation(lines[i]));
+
+                (line + 1 + value_line, block_column + value_column)
+            } else if value_line == 0 && !value.starts_with(['"', '\'', '>']) {
+                let value_start = lines[line].len() - value.len();
+
+                (line, value_start + value_column)
+            } else {
+                (line, column)
+            }
+        }
+        _ => (line, column),
+    };
+
+    Some(Location {
+        line: location.0 + 1,
+        column: location.1 + 1,
+    })
+}
+
+/// Check a trigger definition in YAML format, reporting the locations of the
+/// issues in `source`
+pub fn lint_trigger_definition(
+    source: &str,
+    trend_stores: Option<&[TrendStore]>,
+) -> Vec<LintIssue> {
+    let trigger: FileTrigger = match serde_yaml::from_str(source) {
+        Ok(trigger) => trigger,
+        Err(e) => {
+            return vec![LintIssue {
+                location: e.location().map(|location| Location {
+                    line: location.line(),
+                    column: location.column(),
+                }),
+                ..LintIssue::new(Vec::new(), e.to_string())
+            }];
+        }
+    };
+
+    lint_trigger(&trigger.full_trigger(), trend_stores)
+        .into_iter()
+        .map(|issue| LintIssue {
+            location: locate(source, &issue.path, issue.position),
+            ..issue
+        })
+        .collect()
+}
+
+/// Check a trigger definition file in YAML or JSON format
+pub fn lint_trigger_file(
+    path: &Path,
+    trend_stores: Option<&[TrendStore]>,
+) -> Result<Vec<LintIssue>, Error> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        ConfigurationError::from_msg(format!(
+            "Could not read trigger definition file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    if path.extension() == Some(std::ffi::OsStr::new("json")) {
+        return match serde_json::from_str::<FileTrigger>(&source) {
+            Ok(trigger) => Ok(lint_trigger(&trigger.full_trigger(), trend_stores)),
+            Err(e) => Ok(vec![LintIssue {
+                location: Some(Location {
+                    line: e.line(),
+                    column: e.column(),
+                }),
+                ..LintIssue::new(Vec::new(), e.to_string())
+            }]),
+        };
+    }
+
+    Ok(lint_trigger_definition(&source, trend_stores))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEFINITION: &str = r#"name: node/15m/highload
+kpi_data:
+  - name: load
+    data_type: numeric
+kpi_function: |-
+  BEGIN
+  END;
+thresholds:
+  - name: max_load
+    data_type: integer
+    value: high
+condition: |-
+  load > max_load
+  AND lod IS NOT NULL
+weight: |-
+  SELECT CASE WHEN $1.load > 2 * $1.max_load THEN 500 ELSE 300 END
+notification: |-
+  SELECT format('%s > %s', $1.load, $1.maximum)
+data: |-
+  SELECT json_build_object('load', $1.load)
+tags: []
+fingerprint: |-
+  SELECT '{}'
+notification_store: trigger-notification
+trend_store_links:
+  - part_name: hub_node_main_15m
+    mapping_function: mapping_15m->1h
+  - part_name: hub_node_main_15m
+    mapping_function: mapping_custom
+mapping_functions: []
+granularity: 15m
+description: ''
+deduplication:
+  window: 5m
+flapping:
+  window: 30m
+  threshold: 3
+"#;
+
+    #[test]
+    fn condition_column_references() {
+        let tokens = tokenize(
+            "coalesce(t.load, 0)::numeric > max_load AND status NOT IN ('down', E'it''s') AND \"Load\" IS NOT NULL",
+        );
+
+        let references: Vec<String> = condition_references(&tokens)
+            .iter()
+            .filter_map(|t| match &t.token {
+                Token::Identifier { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(references, vec!["max_load", "status", "Load"]);
+    }
+
+    #[test]
+    fn lint_with_locations() {
+        let trend_store: TrendStore = serde_yaml::from_str(concat!(
+            "data_source: hub\n",
+            "entity_type: node\n",
+            "granularity: 15m\n",
+            "partition_size: 1d\n",
+            "parts:\n",
+            "  - name: hub_node_main_15m\n",
+            "    trends: []\n",
+        ))
+        .unwrap();
+
+        let issues: Vec<String> = lint_trigger_definition(DEFINITION, Some(&[trend_store]))
+            .iter()
+            .map(|issue| issue.to_string())
+            .collect();
+
+        assert_eq!(
+            issues,
+            vec![
+                "11:5: thresholds[0].value: Value 'high' of threshold 'max_load' is not a valid integer",
+                "14:7: condition: Unknown column 'lod', expected a KPI column or threshold",
+                "18:40: notification: Unknown column 'maximum', expected a KPI column or threshold",
+                "34:3: deduplication.window: Deduplication window 5m is shorter than the trigger granularity, so no notifications are suppressed",
+                "37:3: flapping.threshold: Flapping threshold 3 can not be reached, the flapping window of 30m allows at most 2 change(s)",
+                "27:5: trend_store_links[0].mapping_function: Mapping function 'mapping_15m->1h' maps trend store part 'hub_node_main_15m' to 1h, but the trigger granularity is 15m",
+                "29:5: trend_store_links[1].mapping_function: Mapping function 'mapping_custom' is not defined in the trigger or the Minerva schema",
+            ]
+        );
+    }
+}
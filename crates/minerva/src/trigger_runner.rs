@@ -0,0 +1,545 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use postgres_protocol::escape::escape_identifier;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, GenericClient};
+
+use crate::error::{DatabaseError, Error, RuntimeError};
+use crate::granularity::Granularity;
+use crate::interval::parse_interval;
+
+/// Enabled trigger with the settings that determine when it is evaluated
+#[derive(Debug, Clone)]
+pub struct ScheduledTrigger {
+    pub id: i32,
+    pub name: String,
+    pub granularity: Granularity,
+    /// Period before now in which timestamps are evaluated, so that timestamps
+    /// missed during downtime are caught up
+    pub default_interval: Duration,
+}
+
+/// Settings of a trigger run
+#[derive(Debug, Clone)]
+pub struct TriggerRunOptions {
+    /// Maximum period before now in which timestamps are caught up after the
+    /// last evaluation of a trigger, when that is longer ago than its default
+    /// interval
+    pub max_catch_up: Duration,
+    /// Timezone in which the timestamps of triggers are aligned
+    pub timezone: chrono_tz::Tz,
+}
+
+impl Default for TriggerRunOptions {
+    fn default() -> Self {
+        TriggerRunOptions {
+            max_catch_up: Duration::from_secs(7 * 86400),
+            timezone: chrono_tz::UTC,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TriggerEvaluation {
+    pub trigger: String,
+    pub timestamp: DateTime<Utc>,
+    pub notification_count: i32,
+    /// True if the timestamp was evaluated before with a different fingerprint
+    pub reevaluated: bool,
+}
+
+fn parse_rule_interval(value: &str) -> Result<Duration, Error> {
+    parse_interval(value).map_err(|e| {
+        Error::Runtime(RuntimeError::from_msg(format!(
+            "Could not parse interval '{value}': {e}"
+        )))
+    })
+}
+
+/// Load the enabled triggers, or only the specified ones if `names` is not
+/// empty
+pub async fn load_scheduled_triggers<T: GenericClient + Send + Sync>(
+    client: &T,
+    names: &[String],
+) -> Result<Vec<ScheduledTrigger>, Error> {
+    let query = concat!(
+        "SELECT id, name::text, granularity::text, default_interval::text ",
+        "FROM trigger.rule ",
+        "WHERE enabled AND (cardinality($1::text[]) = 0 OR name = ANY($1)) ",
+        "ORDER BY name"
+    );
+
+    let rows = client
+        .query(query, &[&names])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not load triggers: {e}")))?;
+
+    rows.iter()
+        .map(|row| {
+            let granularity_str: String = row.get(2);
+            let default_interval_str: Option<String> = row.get(3);
+
+            let granularity = Granularity::from_str(&granularity_str)?;
+
+            let default_interval = match default_interval_str {
+                Some(value) => parse_rule_interval(&value)?,
+                None => granularity.duration(),
+            };
+
+            Ok(ScheduledTrigger {
+                id: row.get(0),
+                name: row.get(1),
+                granularity,
+                default_interval,
+            })
+        })
+        .collect()
+}
+
+/// Aligned timestamps of the trigger granularity from the last evaluated
+/// timestamp, or the default interval before `now` if that is later, oldest
+/// first. Catching up is limited to `max_catch_up` before `now`.
+fn candidate_timestamps<Tz: TimeZone>(
+    trigger: &ScheduledTrigger,
+    now: &DateTime<Tz>,
+    last_evaluated: Option<DateTime<Utc>>,
+    max_catch_up: Duration,
+) -> Result<Vec<DateTime<Utc>>, Error> {
+    let default_start = now.clone() - trigger.default_interval;
+
+    let window_start = match last_evaluated {
+        Some(last_evaluated) => {
+            let catch_up_start = std::cmp::max(
+                last_evaluated.with_timezone(&now.timezone()),
+                now.clone() - max_catch_up,
+            );
+
+            std::cmp::min(default_start, catch_up_start)
+        }
+        None => default_start,
+    };
+
+    Ok(trigger
+        .granularity
+        .timestamps(&window_start, now)?
+        .iter()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .collect())
+}
+
+/// Query for the candidate timestamps for which the linked trend store parts
+/// have data and the runnable function of the trigger returns true
+fn ready_timestamps_query(trigger_name: &str, mapping_functions: &[(i32, String)]) -> String {
+    let source_conditions: String = mapping_functions
+        .iter()
+        .map(|(trend_store_part_id, mapping_function)| {
+            format!(
+                concat!(
+                    " AND t.timestamp IN (",
+                    "SELECT {}(m.timestamp) FROM trend_directory.modified m ",
+                    "WHERE m.trend_store_part_id = {} ",
+                    "AND m.timestamp > $1::timestamptz - $2::text::interval ",
+                    "AND m.timestamp <= $3::timestamptz)"
+                ),
+                mapping_function, trend_store_part_id
+            )
+        })
+        .collect();
+
+    format!(
+        concat!(
+            "SELECT t.timestamp FROM unnest($4::timestamptz[]) AS t(timestamp) ",
+            "WHERE trigger_rule.{}(t.timestamp){} ",
+            "ORDER BY t.timestamp"
+        ),
+        escape_identifier(&format!("{trigger_name}_runnable")),
+        source_conditions
+    )
+}
+
+/// Last timestamp for which the trigger was evaluated
+async fn last_evaluated_timestamp<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger: &ScheduledTrigger,
+) -> Result<Option<DateTime<Utc>>, Error> {
+    let row = client
+        .query_one(
+            "SELECT max(timestamp) FROM trigger.rule_evaluation WHERE rule_id = $1",
+            &[&trigger.id],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load last evaluation of trigger '{}': {e}",
+                trigger.name
+            ))
+        })?;
+
+    Ok(row.get(0))
+}
+
+/// Fingerprint queries that do not depend on the source data, like the
+/// default `SELECT now()::text`, which result in a new fingerprint on every run
+fn is_time_based_fingerprint(source: &str) -> bool {
+    let normalized = source
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase();
+
+    normalized.trim_end_matches(';').trim_end() == "select now()::text"
+}
+
+/// Query for a fingerprint of a timestamp from the last modification of the
+/// source data in the linked trend store parts
+fn modified_fingerprint_query(mapping_functions: &[(i32, String)]) -> String {
+    let source_conditions: Vec<String> = mapping_functions
+        .iter()
+        .map(|(trend_store_part_id, mapping_function)| {
+            format!(
+                "(m.trend_store_part_id = {} AND {}(m.timestamp) = $1::timestamptz)",
+                trend_store_part_id, mapping_function
+            )
+        })
+        .collect();
+
+    format!(
+        concat!(
+            "SELECT max(m.last)::text FROM trend_directory.modified m ",
+            "WHERE m.timestamp > $1::timestamptz - $2::text::interval ",
+            "AND m.timestamp <= $1::timestamptz AND ({})"
+        ),
+        match source_conditions.is_empty() {
+            true => "false".to_string(),
+            false => source_conditions.join(" OR "),
+        }
+    )
+}
+
+/// Fingerprint function source of a trigger
+async fn fingerprint_source<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger: &ScheduledTrigger,
+) -> Result<String, Error> {
+    let row = client
+        .query_one(
+            concat!(
+                "SELECT prosrc FROM pg_proc ",
+                "JOIN pg_namespace ON pg_namespace.oid = pronamespace ",
+                "WHERE nspname = 'trigger_rule' AND proname = $1"
+            ),
+            &[&format!("{}_fingerprint", trigger.name)],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load fingerprint function of trigger '{}': {e}",
+                trigger.name
+            ))
+        })?;
+
+    Ok(row.get(0))
+}
+
+/// Linked trend store parts of a trigger with their timestamp mapping
+/// functions
+async fn load_mapping_functions<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger: &ScheduledTrigger,
+) -> Result<Vec<(i32, String)>, Error> {
+    let mapping_functions = client
+        .query(
+            concat!(
+                "SELECT trend_store_part_id, timestamp_mapping_func::regproc::text ",
+                "FROM trigger.rule_trend_store_link WHERE rule_id = $1"
+            ),
+            &[&trigger.id],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not load trend store links of trigger '{}': {e}",
+                trigger.name
+            ))
+        })?
+        .iter()
+        .map(|row| (row.get(0), row.get(1)))
+        .collect();
+
+    Ok(mapping_functions)
+}
+
+async fn ready_timestamps<T: GenericClient + Send + Sync>(
+    client: &T,
+    trigger: &ScheduledTrigger,
+    mapping_functions: &[(i32, String)],
+    candidates: &[DateTime<Utc>],
+) -> Result<Vec<DateTime<Utc>>, Error> {
+    let (Some(first), Some(last)) = (candidates.first(), candidates.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let granularity_text = trigger.granularity.to_string();
+
+    let rows = client
+        .query(
+            &ready_timestamps_query(&trigger.name, mapping_functions),
+            &[first, &granularity_text, last, &candidates],
+        )
+        .await
+        .map_err(|e| {
+            DatabaseError::from_msg(format!(
+                "Could not check source data of trigger '{}': {e}",
+                trigger.name
+            ))
+        })?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Create notifications of a trigger for each timestamp in its default
+/// interval before `now`, or since its last evaluation, that has source data
+/// and was not evaluated before with the same fingerprint. Triggers with a
+/// fingerprint that only depends on the time, like the default, use the last
+/// modification of the source data as fingerprint instead, so that the same
+/// timestamps are not evaluated again on every run.
+///
+/// Every timestamp is evaluated in its own transaction together with the
+/// registration in `trigger.rule_evaluation`.
+pub async fn run_trigger(
+    client: &mut Client,
+    trigger: &ScheduledTrigger,
+    now: DateTime<Utc>,
+    options: &TriggerRunOptions,
+) -> Result<Vec<TriggerEvaluation>, Error> {
+    let last_evaluated = last_evaluated_timestamp(client, trigger).await?;
+
+    let candidates = candidate_timestamps(
+        trigger,
+        &now.with_timezone(&options.timezone),
+        last_evaluated,
+        options.max_catch_up,
+    )?;
+
+    let mapping_functions = load_mapping_functions(client, trigger).await?;
+
+    let timestamps = ready_timestamps(client, trigger, &mapping_functions, &candidates).await?;
+
+    let time_based_fingerprint =
+        is_time_based_fingerprint(&fingerprint_source(client, trigger).await?);
+
+    let fingerprint_query = match time_based_fingerprint {
+        true => modified_fingerprint_query(&mapping_functions),
+        false => format!(
+            "SELECT trigger_rule.{}($1)",
+            escape_identifier(&format!("{}_fingerprint", trigger.name))
+        ),
+    };
+
+    let granularity_text = trigger.granularity.to_string();
+
+    let mut evaluations = Vec::new();
+
+    for timestamp in timestamps {
+        let tx = client.transaction().await?;
+
+        let fingerprint_params: Vec<&(dyn ToSql + Sync)> = match time_based_fingerprint {
+            true => vec![&timestamp, &granularity_text],
+            false => vec![&timestamp],
+        };
+
+        let fingerprint: Option<String> = tx
+            .query_one(&fingerprint_query, &fingerprint_params)
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Could not determine fingerprint of trigger '{}' for {timestamp}: {e}",
+                    trigger.name
+                ))
+            })?
+            .get(0);
+
+        let previous = tx
+            .query_opt(
+                "SELECT fingerprint FROM trigger.rule_evaluation WHERE rule_id = $1 AND timestamp = $2",
+                &[&trigger.id, &timestamp],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!("Could not load evaluation state: {e}"))
+            })?
+            .map(|row| row.get::<_, Option<String>>(0));
+
+        if let Some(previous_fingerprint) = &previous {
+            if *previous_fingerprint == fingerprint {
+                continue;
+            }
+        }
+
+        let notification_count: i32 = tx
+            .query_one(
+                "SELECT trigger.create_notifications($1::name, $2::timestamptz)",
+                &[&trigger.name, &timestamp],
+            )
+            .await
+            .map_err(|e| {
+                DatabaseError::from_msg(format!(
+                    "Could not create notifications of trigger '{}' for {timestamp}: {e}",
+                    trigger.name
+                ))
+            })?
+            .get(0);
+
+        tx.execute(
+            concat!(
+                "INSERT INTO trigger.rule_evaluation(rule_id, timestamp, fingerprint, evaluated, notification_count) ",
+                "VALUES ($1, $2, $3, now(), $4) ",
+                "ON CONFLICT (rule_id, timestamp) DO UPDATE SET ",
+                "fingerprint = excluded.fingerprint, evaluated = excluded.evaluated, ",
+                "notification_count = excluded.notification_count"
+            ),
+            &[&trigger.id, &timestamp, &fingerprint, &notification_count],
+        )
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Could not record evaluation: {e}")))?;
+
+        tx.commit().await?;
+
+        evaluations.push(TriggerEvaluation {
+            trigger: trigger.name.clone(),
+            timestamp,
+            notification_count,
+            reevaluated: previous.is_some(),
+        });
+    }
+
+    Ok(evaluations)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn scheduled_trigger() -> ScheduledTrigger {
+        ScheduledTrigger {
+            id: 1,
+            name: "high-load".to_string(),
+            granularity: Granularity::Fixed(Duration::from_secs(900)),
+            default_interval: Duration::from_secs(3600),
+        }
+    }
+
+    fn format_timestamps(timestamps: &[DateTime<Utc>]) -> Vec<String> {
+        timestamps
+            .iter()
+            .map(|t| t.format("%H:%M").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn candidate_timestamps_in_default_interval() {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 10, 20, 0).unwrap();
+        let last_evaluated = Utc.with_ymd_and_hms(2024, 7, 1, 10, 0, 0).unwrap();
+        let max_catch_up = Duration::from_secs(86400);
+
+        let timestamps =
+            candidate_timestamps(&scheduled_trigger(), &now, None, max_catch_up).unwrap();
+
+        assert_eq!(
+            format_timestamps(&timestamps),
+            vec!["09:30", "09:45", "10:00", "10:15"]
+        );
+
+        // A recent evaluation does not shorten the default interval
+        let timestamps = candidate_timestamps(
+            &scheduled_trigger(),
+            &now,
+            Some(last_evaluated),
+            max_catch_up,
+        )
+        .unwrap();
+
+        assert_eq!(
+            format_timestamps(&timestamps),
+            vec!["09:30", "09:45", "10:00", "10:15"]
+        );
+    }
+
+    #[test]
+    fn candidate_timestamps_since_last_evaluation() {
+        let now = Utc.with_ymd_and_hms(2024, 7, 1, 10, 20, 0).unwrap();
+        let last_evaluated = Utc.with_ymd_and_hms(2024, 7, 1, 8, 0, 0).unwrap();
+
+        let timestamps = candidate_timestamps(
+            &scheduled_trigger(),
+            &now,
+            Some(last_evaluated),
+            Duration::from_secs(86400),
+        )
+        .unwrap();
+
+        assert_eq!(
+            format_timestamps(&timestamps),
+            vec![
+                "08:00", "08:15", "08:30", "08:45", "09:00", "09:15", "09:30", "09:45", "10:00",
+                "10:15"
+            ]
+        );
+
+        // Catching up is limited to the maximum period before now
+        let timestamps = candidate_timestamps(
+            &scheduled_trigger(),
+            &now,
+            Some(last_evaluated),
+            Duration::from_secs(5400),
+        )
+        .unwrap();
+
+        assert_eq!(
+            format_timestamps(&timestamps),
+            vec!["09:00", "09:15", "09:30", "09:45", "10:00", "10:15"]
+        );
+    }
+
+    #[test]
+    fn ready_timestamps_query_checks_each_source() {
+        let query = ready_timestamps_query("high-load", &[(3, "trigger.mapping_id".to_string())]);
+
+        assert_eq!(
+            query,
+            concat!(
+                r#"SELECT t.timestamp FROM unnest($4::timestamptz[]) AS t(timestamp) WHERE trigger_rule."high-load_runnable"(t.timestamp)"#,
+                " AND t.timestamp IN (SELECT trigger.mapping_id(m.timestamp) FROM trend_directory.modified m ",
+                "WHERE m.trend_store_part_id = 3 AND m.timestamp > $1::timestamptz - $2::text::interval ",
+                "AND m.timestamp <= $3::timestamptz) ORDER BY t.timestamp"
+            )
+        );
+    }
+
+    #[test]
+    fn default_fingerprint_is_time_based() {
+        assert!(is_time_based_fingerprint("SELECT now()::text;"));
+        assert!(is_time_based_fingerprint("\n  select  NOW()::text\n"));
+        assert!(!is_time_based_fingerprint(
+            "SELECT trigger.modified_to_fingerprint(ARRAY[trend.modified(trend.to_trendstore('hub_node_main_15m'), $1)]::timestamptz[])"
+        ));
+
+        assert_eq!(
+            modified_fingerprint_query(&[
+                (3, "trigger.mapping_id".to_string()),
+                (4, "trigger.mapping_id".to_string())
+            ]),
+            concat!(
+                "SELECT max(m.last)::text FROM trend_directory.modified m ",
+                "WHERE m.timestamp > $1::timestamptz - $2::text::interval ",
+                "AND m.timestamp <= $1::timestamptz AND (",
+                "(m.trend_store_part_id = 3 AND trigger.mapping_id(m.timestamp) = $1::timestamptz) OR ",
+                "(m.trend_store_part_id = 4 AND trigger.mapping_id(m.timestamp) = $1::timestamptz))"
+            )
+        );
+    }
+}
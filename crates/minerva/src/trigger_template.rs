@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use glob::glob;
+use serde::Deserialize;
+
+use crate::error::{ConfigurationError, Error, RuntimeError};
+use crate::trigger::{FileTrigger, Trigger};
+
+const GRANULARITY_PLACEHOLDER: &str = "{{granularity}}";
+const PART_PLACEHOLDER: &str = "{{part}}";
+
+#[derive(Debug, Deserialize)]
+struct TemplateHeader {
+    granularities: Vec<String>,
+    part: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TemplateBody {
+    trigger: FileTrigger,
+}
+
+/// Trigger definition with `{{granularity}}` and `{{part}}` placeholders that
+/// is expanded into a trigger for each of its granularities, e.g.:
+///
+/// ```yaml
+/// granularities: [15m, 1h]
+/// part: hub_node_main_{{granularity}}
+/// trigger:
+///   name: node/{{granularity}}/highpowerusage
+///   granularity: '{{granularity}}'
+///   ...
+/// ```
+///
+/// Placeholders are replaced in the text of the definition, so a placeholder
+/// at the start of a plain scalar must be quoted to keep the template valid
+/// YAML.
+#[derive(Debug, Clone)]
+pub struct TriggerTemplate {
+    pub granularities: Vec<String>,
+    /// Name of the trend store part, which may contain the granularity
+    /// placeholder
+    pub part: Option<String>,
+    source: String,
+}
+
+impl TriggerTemplate {
+    pub fn from_source(source: &str) -> Result<TriggerTemplate, Error> {
+        let header: TemplateHeader = serde_yaml::from_str(source)
+            .map_err(|e| RuntimeError::from_msg(format!("Could not read trigger template: {e}")))?;
+
+        if header.part.is_none() && source.contains(PART_PLACEHOLDER) {
+            return Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                "Trigger template uses {PART_PLACEHOLDER} without defining 'part'"
+            ))));
+        }
+
+        Ok(TriggerTemplate {
+            granularities: header.granularities,
+            part: header.part,
+            source: source.to_string(),
+        })
+    }
+
+    /// Generate a trigger for each granularity of the template
+    pub fn expand(&self) -> Result<Vec<Trigger>, Error> {
+        let mut triggers: Vec<Trigger> = Vec::new();
+
+        for granularity in &self.granularities {
+            let part = self
+                .part
+                .as_ref()
+                .map(|part| part.replace(GRANULARITY_PLACEHOLDER, granularity))
+                .unwrap_or_default();
+
+            let source = self
+                .source
+                .replace(GRANULARITY_PLACEHOLDER, granularity)
+                .replace(PART_PLACEHOLDER, &part);
+
+            let body: TemplateBody = serde_yaml::from_str(&source).map_err(|e| {
+                RuntimeError::from_msg(format!(
+                    "Could not read trigger definition for granularity '{granularity}': {e}"
+                ))
+            })?;
+
+            let trigger = body.trigger.full_trigger();
+
+            if triggers.iter().any(|t| t.name == trigger.name) {
+                return Err(Error::Configuration(ConfigurationError::from_msg(format!(
+                    "Trigger template generates trigger '{}' more than once",
+                    trigger.name
+                ))));
+            }
+
+            triggers.push(trigger);
+        }
+
+        Ok(triggers)
+    }
+}
+
+pub fn load_trigger_template_from_file(path: &PathBuf) -> Result<TriggerTemplate, Error> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        ConfigurationError::from_msg(format!(
+            "Could not open trigger template file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    TriggerTemplate::from_source(&source)
+}
+
+/// Load the trigger templates of an instance and expand them into triggers
+pub fn load_template_triggers_from(minerva_instance_root: &Path) -> impl Iterator<Item = Trigger> {
+    let yaml_paths = glob(&format!(
+        "{}/trigger-template/*.yaml",
+        minerva_instance_root.to_string_lossy()
+    ))
+    .expect("Failed to read glob pattern");
+
+    yaml_paths
+        .filter_map(|entry| match entry {
+            Ok(path) => match load_trigger_template_from_file(&path)
+                .and_then(|template| template.expand())
+            {
+                Ok(triggers) => Some(triggers),
+                Err(e) => {
+                    println!("Error loading trigger template '{}': {e}", path.display());
+                    None
+                }
+            },
+            Err(_) => None,
+        })
+        .flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEMPLATE: &str = r#"granularities: [15m, 1h]
+part: hub_node_main_{{granularity}}
+trigger:
+  name: node/{{granularity}}/highpowerusage
+  kpi_data:
+    - name: power_kwh
+      data_type: numeric
+  kpi_function: |-
+    BEGIN
+        RETURN QUERY SELECT t.entity_id, t.timestamp, t.power_kwh
+        FROM trend."{{part}}" AS t WHERE t.timestamp = $1;
+    END;
+  thresholds:
+    - name: max_power
+      data_type: numeric
+      value: 0.05
+  condition: power_kwh > max_power
+  weight: SELECT 300
+  notification: SELECT 'HighPowerUsage'
+  data: SELECT '{}'::json
+  tags: []
+  fingerprint: SELECT trigger.modified_to_fingerprint(ARRAY[]::timestamptz[])
+  notification_store: trigger-notification
+  trend_store_links:
+    - part_name: '{{part}}'
+      mapping_function: mapping_id
+  mapping_functions: []
+  granularity: '{{granularity}}'
+  description: ''
+"#;
+
+    #[test]
+    fn expand_per_granularity() {
+        let triggers = TriggerTemplate::from_source(TEMPLATE)
+            .unwrap()
+            .expand()
+            .unwrap();
+
+        let expanded: Vec<(String, String, u64)> = triggers
+            .iter()
+            .map(|trigger| {
+                (
+                    trigger.name.clone(),
+                    trigger.trend_store_links[0].part_name.clone(),
+                    trigger.granularity.as_secs(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            expanded,
+            vec![
+                (
+                    "node/15m/highpowerusage".to_string(),
+                    "hub_node_main_15m".to_string(),
+                    900
+                ),
+                (
+                    "node/1h/highpowerusage".to_string(),
+                    "hub_node_main_1h".to_string(),
+                    3600
+                ),
+            ]
+        );
+
+        assert!(triggers[1]
+            .kpi_function
+            .contains("trend.\"hub_node_main_1h\""));
+        assert_eq!(triggers[1].thresholds[0].value, "0.05");
+    }
+}
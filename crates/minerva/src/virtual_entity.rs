@@ -2,8 +2,9 @@ use async_trait::async_trait;
 use std::fmt;
 use std::{io::Read, path::PathBuf};
 
+use postgres_protocol::escape::escape_identifier;
 use serde::{Deserialize, Serialize};
-use tokio_postgres::Transaction;
+use tokio_postgres::{GenericClient, Transaction};
 
 use super::change::{Change, ChangeResult};
 use super::error::{ConfigurationError, DatabaseError, Error};
@@ -73,3 +74,66 @@ impl From<VirtualEntity> for AddVirtualEntity {
         AddVirtualEntity { virtual_entity }
     }
 }
+
+/// Load the virtual entities defined as views in the `virtual_entity` schema
+pub async fn load_virtual_entities<T: GenericClient + Send + Sync>(
+    client: &T,
+) -> Result<Vec<VirtualEntity>, Error> {
+    let query = concat!(
+        "SELECT viewname::text, definition FROM pg_views ",
+        "WHERE schemaname = 'virtual_entity' ORDER BY viewname"
+    );
+
+    let rows = client
+        .query(query, &[])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error loading virtual entities: {e}")))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let name: String = row.get(0);
+            let definition: String = row.get(1);
+
+            VirtualEntity {
+                sql: format!(
+                    "CREATE OR REPLACE VIEW virtual_entity.{} AS\n{}",
+                    escape_identifier(&name),
+                    definition
+                ),
+                name,
+            }
+        })
+        .collect())
+}
+
+/// Create the entities of a virtual entity type that do not exist yet and
+/// return the number of created entities
+pub async fn update_virtual_entity<T: GenericClient + Send + Sync>(
+    client: &T,
+    name: &str,
+) -> Result<i32, Error> {
+    let row = client
+        .query_one("SELECT virtual_entity.update($1)", &[&name])
+        .await
+        .map_err(|e| DatabaseError::from_msg(format!("Error updating virtual entity: {e}")))?;
+
+    Ok(row.get(0))
+}
+
+/// Return the names of the entities currently defined by a virtual entity view
+pub async fn load_virtual_entity_members<T: GenericClient + Send + Sync>(
+    client: &T,
+    name: &str,
+) -> Result<Vec<String>, Error> {
+    let query = format!(
+        "SELECT name::text FROM virtual_entity.{} ORDER BY name",
+        escape_identifier(name)
+    );
+
+    let rows = client.query(&query, &[]).await.map_err(|e| {
+        DatabaseError::from_msg(format!("Error loading virtual entity members: {e}"))
+    })?;
+
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}